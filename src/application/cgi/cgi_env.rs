@@ -1,5 +1,6 @@
 use crate::http::request::Request;
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
 
 /// Build CGI environment variables from HTTP request
@@ -12,6 +13,7 @@ impl CgiEnvironment {
         script_path: &PathBuf,
         server_name: &str,
         server_port: u16,
+        remote_addr: Option<IpAddr>,
     ) -> HashMap<String, String> {
         let mut env_vars = HashMap::new();
 
@@ -96,7 +98,8 @@ impl CgiEnvironment {
 
         // Remote address (if available)
         // Note: This would need to be passed from connection
-        env_vars.insert("REMOTE_ADDR".to_string(), "127.0.0.1".to_string());
+        let remote_addr = remote_addr.map_or_else(|| "127.0.0.1".to_string(), |ip| ip.to_string());
+        env_vars.insert("REMOTE_ADDR".to_string(), remote_addr);
         env_vars.insert("REMOTE_HOST".to_string(), String::new());
 
         // Script filename (absolute path)
@@ -132,7 +135,7 @@ mod tests {
             .add("Host".to_string(), "localhost:8080".to_string());
 
         let script_path = PathBuf::from("/var/www/cgi/test.py");
-        let env_vars = CgiEnvironment::build(&request, &script_path, "localhost", 8080);
+        let env_vars = CgiEnvironment::build(&request, &script_path, "localhost", 8080, None);
 
         assert_eq!(env_vars.get("REQUEST_METHOD"), Some(&"GET".to_string()));
         assert_eq!(
@@ -1,22 +1,75 @@
 use crate::application::cgi::cgi_env::CgiEnvironment;
 use crate::application::cgi::cgi_io::CgiIo;
-use crate::application::cgi::cgi_process::CgiProcess;
+use crate::application::cgi::cgi_process::{CgiProcess, CgiWatchdog};
+use crate::common::constants::DEFAULT_CGI_MAX_HEADER_LINE;
 use crate::common::error::{Result, ServerError};
 use crate::http::request::Request;
 use crate::http::response::Response;
+use std::fs::File;
+use std::io::Read;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Executes CGI scripts and returns HTTP responses
+#[derive(Debug, Clone)]
 pub struct CgiExecutor {
-    /// Maximum execution time for CGI scripts (in seconds)
-    #[allow(dead_code)] // Will be used for timeout implementation
+    /// Maximum execution time for CGI scripts in seconds (0 = unlimited)
     timeout_secs: u64,
+
+    /// Resolve scripts without an interpreter through their `#!` line
+    shebang_fallback: bool,
+
+    /// Client address reported to scripts as `REMOTE_ADDR`
+    remote_addr: Option<IpAddr>,
+
+    /// Directory scripts run in; None runs them in their own directory
+    working_dir: Option<PathBuf>,
+
+    /// Longest header line accepted from a script (0 = unlimited)
+    max_header_line: usize,
 }
 
 impl CgiExecutor {
     /// Create a new CGI executor
     pub fn new(timeout_secs: u64) -> Self {
-        Self { timeout_secs }
+        Self {
+            timeout_secs,
+            shebang_fallback: false,
+            remote_addr: None,
+            working_dir: None,
+            max_header_line: DEFAULT_CGI_MAX_HEADER_LINE,
+        }
+    }
+
+    /// Kill scripts running longer than this many seconds (0 = unlimited)
+    pub fn with_timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+
+    /// Run scripts without a configured interpreter through the interpreter on their `#!` line
+    pub fn with_shebang_fallback(mut self, enabled: bool) -> Self {
+        self.shebang_fallback = enabled;
+        self
+    }
+
+    /// Report this client address to scripts (unset falls back to loopback)
+    pub fn with_remote_addr(mut self, remote_addr: Option<IpAddr>) -> Self {
+        self.remote_addr = remote_addr;
+        self
+    }
+
+    /// Run scripts in this directory instead of their own
+    pub fn with_working_dir(mut self, working_dir: Option<PathBuf>) -> Self {
+        self.working_dir = working_dir;
+        self
+    }
+
+    /// Refuse script output with a header line longer than this many bytes (0 = unlimited)
+    pub fn with_max_header_line(mut self, max_header_line: usize) -> Self {
+        self.max_header_line = max_header_line;
+        self
     }
 
     /// Execute a CGI script and return HTTP response
@@ -44,36 +97,58 @@ impl CgiExecutor {
         }
 
         // Build environment variables
-        let env_vars = CgiEnvironment::build(request, &script_path, server_name, server_port);
+        let env_vars = CgiEnvironment::build(
+            request,
+            &script_path,
+            server_name,
+            server_port,
+            self.remote_addr,
+        );
 
-        // Get request body if present
-        let body_data = if !request.body.is_empty() {
-            Some(request.body.as_slice())
-        } else {
-            None
+        // A large body was spooled to disk while it arrived; stream it from there
+        let body: Box<dyn Read + Send> = match request.body_file {
+            Some(ref path) => Box::new(File::open(path).map_err(|e| {
+                ServerError::CgiError(format!("Failed to open spooled request body: {}", e))
+            })?),
+            None => Box::new(request.body.as_slice()),
         };
+        let has_body = request.body_file.is_some() || !request.body.is_empty();
 
         // Spawn CGI process
-        let mut process =
-            CgiProcess::spawn(script_path.clone(), interpreter, &env_vars, body_data)?;
+        let mut process = CgiProcess::spawn(
+            script_path.clone(),
+            interpreter,
+            self.shebang_fallback,
+            &env_vars,
+            has_body,
+            self.working_dir.as_deref(),
+        )?;
 
-        // Write request body to stdin if present
-        if let Some(body) = body_data {
-            CgiIo::write_stdin(process.child_mut(), body)?;
-        }
-
-        // Close stdin to signal end of input
-        drop(process.child_mut().stdin.take());
+        // Killing the script closes its output, which ends `communicate` below
+        let watchdog = (self.timeout_secs > 0)
+            .then(|| CgiWatchdog::start(&process, Duration::from_secs(self.timeout_secs)));
 
-        // Wait for process
-        // Note: In a production system, this should use async waiting with proper timeout handling
-        // For now, we use a simple blocking wait
+        // Write the body and drain the output concurrently, then collect the exit status
+        let child = process.child_mut();
+        let communicated = CgiIo::communicate(
+            child.stdin.take(),
+            child.stdout.take(),
+            child.stderr.take(),
+            body,
+            self.max_header_line,
+        );
+        if watchdog.is_some_and(CgiWatchdog::finish) {
+            return Err(ServerError::TimeoutError(format!(
+                "CGI script '{}' ran longer than {} seconds",
+                script_path.display(),
+                self.timeout_secs
+            )));
+        }
+        let (output, stderr) = communicated?;
         let exit_code = process.wait()?;
 
         // Check exit code
         if exit_code != 0 {
-            // Read stderr for error information
-            let stderr = CgiIo::read_stderr(process.child_mut())?;
             return Err(ServerError::CgiError(format!(
                 "CGI script '{}' exited with code {}: {}",
                 script_path.display(),
@@ -82,8 +157,8 @@ impl CgiExecutor {
             )));
         }
 
-        // Read and parse response from stdout
-        let response = CgiIo::read_stdout(process.child_mut())?;
+        // Parse response from stdout
+        let response = CgiIo::parse_cgi_output_with_limit(&output, self.max_header_line)?;
 
         Ok(response)
     }
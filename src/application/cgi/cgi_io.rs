@@ -1,11 +1,12 @@
-use crate::common::constants::CRLF_BYTES;
+use crate::common::constants::{CRLF_BYTES, DEFAULT_BUFFER_SIZE, DEFAULT_CGI_MAX_HEADER_LINE};
 use crate::common::error::{Result, ServerError};
-use crate::http::headers::Headers;
+use crate::http::headers::{names as header_names, Headers};
 use crate::http::response::Response;
 use crate::http::status::StatusCode;
 use crate::http::version::Version;
-use std::io::{Read, Write};
-use std::process::Child;
+use std::io::{ErrorKind, Read, Write};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout};
+use std::thread;
 
 /// Handle CGI script I/O
 pub struct CgiIo;
@@ -24,6 +25,104 @@ impl CgiIo {
         Ok(())
     }
 
+    /// Feed `body` to the script while collecting its stdout and stderr. The body is
+    /// copied in buffer-sized pieces, so a spooled body is never loaded into memory.
+    /// A header line over `max_header_line` bytes (0 = unlimited) stops the read as soon
+    /// as it arrives.
+    ///
+    /// Writing and reading happen on separate threads so a script that produces output
+    /// before consuming all of its input can't deadlock with us on full pipe buffers.
+    /// A script that exits without reading its input is not an error.
+    pub fn communicate(
+        stdin: Option<ChildStdin>,
+        stdout: Option<ChildStdout>,
+        stderr: Option<ChildStderr>,
+        mut body: impl Read + Send,
+        max_header_line: usize,
+    ) -> Result<(Vec<u8>, String)> {
+        thread::scope(|scope| {
+            let writer = scope.spawn(move || match stdin {
+                // Dropping stdin at the end closes it, signalling end of input
+                Some(mut stdin) => {
+                    match std::io::copy(&mut body, &mut stdin).and_then(|_| stdin.flush()) {
+                        Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e),
+                        _ => Ok(()),
+                    }
+                }
+                None => Ok(()),
+            });
+            let error_reader = scope.spawn(move || {
+                let mut errors = String::new();
+                if let Some(mut stderr) = stderr {
+                    let _ = stderr.read_to_string(&mut errors);
+                }
+                errors
+            });
+
+            // Dropping stdout on a refused header closes the pipe, so the script stops too
+            let output = match stdout {
+                Some(mut stdout) => Self::read_output(&mut stdout, max_header_line)?,
+                None => Vec::new(),
+            };
+
+            writer
+                .join()
+                .map_err(|_| ServerError::CgiError("CGI stdin writer panicked".to_string()))?
+                .map_err(|e| {
+                    ServerError::CgiError(format!("Failed to write to CGI stdin: {}", e))
+                })?;
+            let errors = error_reader.join().unwrap_or_default();
+            Ok((output, errors))
+        })
+    }
+
+    /// Read a script's whole stdout, checking each header line against `max_header_line`
+    /// bytes (0 = unlimited) while the header section is still arriving
+    fn read_output(stdout: &mut impl Read, max_header_line: usize) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        let mut chunk = [0u8; DEFAULT_BUFFER_SIZE];
+        // Bytes of the header line being read, not counting its CR
+        let mut line_len = 0;
+        let mut in_headers = max_header_line > 0;
+        loop {
+            let n = match stdout.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(ServerError::CgiError(format!(
+                        "Failed to read CGI stdout: {}",
+                        e
+                    )))
+                }
+            };
+            output.extend_from_slice(&chunk[..n]);
+
+            if !in_headers {
+                continue;
+            }
+            for &byte in &chunk[..n] {
+                match byte {
+                    // An empty line ends the headers; the body isn't line-limited
+                    b'\n' if line_len == 0 => {
+                        in_headers = false;
+                        break;
+                    }
+                    b'\n' => line_len = 0,
+                    b'\r' => {}
+                    _ => line_len += 1,
+                }
+                if line_len > max_header_line {
+                    return Err(ServerError::CgiHeaderTooLong(format!(
+                        "header line exceeds the {} byte limit",
+                        max_header_line
+                    )));
+                }
+            }
+        }
+        Ok(output)
+    }
+
     /// Read CGI process stdout and parse response
     pub fn read_stdout(child: &mut Child) -> Result<Response> {
         let mut output = Vec::new();
@@ -51,17 +150,36 @@ impl CgiIo {
 
     /// Parse CGI script output according to CGI/1.1 specification
     /// CGI scripts output headers followed by blank line, then body
-    fn parse_cgi_output(output: &[u8]) -> Result<Response> {
+    pub(crate) fn parse_cgi_output(output: &[u8]) -> Result<Response> {
+        Self::parse_cgi_output_with_limit(output, DEFAULT_CGI_MAX_HEADER_LINE)
+    }
+
+    /// Parse CGI script output, refusing header lines longer than `max_header_line`
+    /// bytes (0 = unlimited)
+    pub(crate) fn parse_cgi_output_with_limit(
+        output: &[u8],
+        max_header_line: usize,
+    ) -> Result<Response> {
         // Find double CRLF (end of headers)
         // Look for pattern: CRLF CRLF
-        let crlf_len = CRLF_BYTES.len();
-        let mut header_end = None;
-        for i in 0..output.len().saturating_sub(crlf_len * 2) {
-            if &output[i..i + crlf_len] == CRLF_BYTES
-                && &output[i + crlf_len..i + crlf_len * 2] == CRLF_BYTES
+        // (header-only output may end right after the separator)
+        let separator = [CRLF_BYTES, CRLF_BYTES].concat();
+        let header_end = output
+            .windows(separator.len())
+            .position(|window| window == separator.as_slice());
+
+        // Checked before the separator, so one endless line isn't reported as a missing one
+        if max_header_line > 0 {
+            let head = &output[..header_end.unwrap_or(output.len())];
+            if let Some(line) = head
+                .split(|&b| b == b'\n')
+                .find(|line| line.strip_suffix(b"\r").unwrap_or(line).len() > max_header_line)
             {
-                header_end = Some(i);
-                break;
+                return Err(ServerError::CgiHeaderTooLong(format!(
+                    "{} byte header line exceeds the {} byte limit",
+                    line.len(),
+                    max_header_line
+                )));
             }
         }
 
@@ -100,11 +218,28 @@ impl CgiIo {
         // Build response
         let mut response = Response::new(version, status);
         response.headers = headers;
-        response.body = body;
+
+        // The whole output has been read, so always frame it with the real length
+        response.headers.remove(header_names::TRANSFER_ENCODING);
+        response.headers.remove(header_names::CONTENT_LENGTH);
+        response.set_body(body);
 
         Ok(response)
     }
 
+    /// Take the internal-redirect target (e.g. `X-Accel-Redirect: /files/report.pdf`) out of a
+    /// parsed CGI response. Only absolute paths count; the header is removed either way so
+    /// the target path is never revealed to the client.
+    pub fn take_internal_redirect(response: &mut Response, header: &str) -> Option<String> {
+        let target = response.headers.get(header)?.trim().to_string();
+        response.headers.remove(header);
+        if target.starts_with('/') {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
     /// Parse Status header (format: "200 OK" or just "200")
     fn parse_status_header(status_str: &str) -> Result<StatusCode> {
         let parts: Vec<&str> = status_str.split_whitespace().collect();
@@ -123,3 +258,90 @@ impl CgiIo {
             .ok_or_else(|| ServerError::CgiError(format!("Invalid HTTP status code: {}", code)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cgi_output_always_has_content_length() {
+        let response = CgiIo::parse_cgi_output(
+            b"content-length: 999\r\nTransfer-Encoding: chunked\r\n\r\nhello",
+        )
+        .unwrap();
+        assert_eq!(response.content_length(), Some(5));
+        assert!(response.headers.get("Transfer-Encoding").is_none());
+        assert!(!response.chunked);
+    }
+
+    #[test]
+    fn test_cgi_output_without_content_type_has_none() {
+        let response = CgiIo::parse_cgi_output(b"Status: 200 OK\r\n\r\nbody").unwrap();
+        assert!(response.headers.get("Content-Type").is_none());
+        assert_eq!(response.body, b"body");
+    }
+
+    #[test]
+    fn test_overlong_header_line_is_refused() {
+        let long_value = "x".repeat(100);
+        let output = format!("X-Big: {}\r\n\r\nbody", long_value);
+        assert!(matches!(
+            CgiIo::parse_cgi_output_with_limit(output.as_bytes(), 64),
+            Err(ServerError::CgiHeaderTooLong(_))
+        ));
+        assert!(CgiIo::parse_cgi_output_with_limit(output.as_bytes(), 0).is_ok());
+        assert!(CgiIo::parse_cgi_output_with_limit(output.as_bytes(), 107).is_ok());
+
+        // A line that never ends is refused too, not reported as a missing separator
+        let endless = "X-Big: ".to_string() + &long_value;
+        assert!(matches!(
+            CgiIo::parse_cgi_output_with_limit(endless.as_bytes(), 64),
+            Err(ServerError::CgiHeaderTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_overlong_header_line_stops_the_read() {
+        /// Stdout of a script printing one header line forever
+        struct Endless(usize);
+        impl Read for Endless {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.0 += buf.len();
+                buf.fill(b'x');
+                Ok(buf.len())
+            }
+        }
+
+        let mut stdout = Endless(0);
+        assert!(matches!(
+            CgiIo::read_output(&mut stdout, 64),
+            Err(ServerError::CgiHeaderTooLong(_))
+        ));
+        assert!(stdout.0 <= DEFAULT_BUFFER_SIZE);
+
+        // Only header lines are limited, not the body after them
+        let output = format!("X-Ok: 1\r\n\r\n{}", "b".repeat(500));
+        let read = CgiIo::read_output(&mut output.as_bytes(), 64).unwrap();
+        assert_eq!(read, output.as_bytes());
+    }
+
+    #[test]
+    fn test_take_internal_redirect() {
+        let mut response =
+            CgiIo::parse_cgi_output(b"X-Accel-Redirect: /private/report.pdf\r\n\r\nignored")
+                .unwrap();
+        assert_eq!(
+            CgiIo::take_internal_redirect(&mut response, "x-accel-redirect").as_deref(),
+            Some("/private/report.pdf")
+        );
+        assert!(!response.headers.contains("X-Accel-Redirect"));
+
+        let mut relative =
+            CgiIo::parse_cgi_output(b"X-Accel-Redirect: report.pdf\r\n\r\n").unwrap();
+        assert_eq!(
+            CgiIo::take_internal_redirect(&mut relative, "X-Accel-Redirect"),
+            None
+        );
+        assert!(!relative.headers.contains("X-Accel-Redirect"));
+    }
+}
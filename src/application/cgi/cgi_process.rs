@@ -1,7 +1,16 @@
 use crate::common::error::{Result, ServerError};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Longest `#!` line read when resolving a script's interpreter
+const MAX_SHEBANG_LINE: u64 = 256;
 
 /// Represents a running CGI process
 pub struct CgiProcess {
@@ -14,19 +23,30 @@ impl CgiProcess {
     pub fn spawn(
         script_path: PathBuf,
         interpreter: Option<&str>,
+        shebang_fallback: bool,
         env_vars: &HashMap<String, String>,
-        stdin_data: Option<&[u8]>,
+        pipe_stdin: bool,
+        working_dir: Option<&Path>,
     ) -> Result<Self> {
+        let script = script_path.to_string_lossy().to_string();
+
         // Determine command and arguments
+        let shebang = if interpreter.is_none() && shebang_fallback {
+            Self::read_shebang(&script_path)
+        } else {
+            None
+        };
         let (cmd, args) = if let Some(interpreter) = interpreter {
             // Use specified interpreter
-            (
-                interpreter.to_string(),
-                vec![script_path.to_string_lossy().to_string()],
-            )
+            (interpreter.to_string(), vec![script])
+        } else if let Some((interpreter, arg)) = shebang {
+            // Run the interpreter named on the `#!` line ourselves, like the kernel would
+            let mut args: Vec<String> = arg.into_iter().collect();
+            args.push(script);
+            (interpreter, args)
         } else {
             // Execute script directly (must be executable)
-            (script_path.to_string_lossy().to_string(), Vec::new())
+            (script, Vec::new())
         };
 
         // Build command
@@ -40,7 +60,7 @@ impl CgiProcess {
         }
 
         // Set up stdin/stdout/stderr
-        if stdin_data.is_some() {
+        if pipe_stdin {
             command.stdin(Stdio::piped());
         } else {
             command.stdin(Stdio::null());
@@ -48,14 +68,17 @@ impl CgiProcess {
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
 
-        // Set working directory to script's directory
-        if let Some(parent) = script_path.parent() {
-            command.current_dir(parent);
+        // Run in the configured directory, or else the script's own
+        if let Some(dir) = working_dir.or_else(|| script_path.parent()) {
+            command.current_dir(dir);
         }
 
-        // Spawn process
+        // Own process group, so a timeout also kills anything the script started
+        command.process_group(0);
+
+        // Spawn process - failing here means a missing or non-executable interpreter/script
         let child = command.spawn().map_err(|e| {
-            ServerError::CgiError(format!(
+            ServerError::CgiSpawnError(format!(
                 "Failed to spawn CGI process for '{}': {}",
                 script_path.display(),
                 e
@@ -65,6 +88,26 @@ impl CgiProcess {
         Ok(Self { child, script_path })
     }
 
+    /// Interpreter and optional single argument from a script's `#!` line.
+    /// As with the kernel, everything after the interpreter path is one argument.
+    pub fn read_shebang(script_path: &Path) -> Option<(String, Option<String>)> {
+        let file = File::open(script_path).ok()?;
+        let mut line = Vec::new();
+        BufReader::new(file.take(MAX_SHEBANG_LINE))
+            .read_until(b'\n', &mut line)
+            .ok()?;
+        let line = String::from_utf8_lossy(&line);
+        let rest = line.strip_prefix("#!")?.trim();
+        let (interpreter, arg) = match rest.split_once(char::is_whitespace) {
+            Some((interpreter, arg)) => (interpreter, Some(arg.trim().to_string())),
+            None => (rest, None),
+        };
+        if interpreter.is_empty() {
+            return None;
+        }
+        Some((interpreter.to_string(), arg))
+    }
+
     /// Get mutable reference to child process
     pub fn child_mut(&mut self) -> &mut Child {
         &mut self.child
@@ -113,3 +156,80 @@ impl Drop for CgiProcess {
         let _ = self.child.wait();
     }
 }
+
+/// Kills a CGI process group that runs past its time limit
+pub struct CgiWatchdog {
+    cancel: Option<Sender<()>>,
+    thread: Option<JoinHandle<bool>>,
+}
+
+impl CgiWatchdog {
+    /// Start timing `process`. Stop the watchdog before reaping the process, so the
+    /// group id can't be reused by the time it fires.
+    pub fn start(process: &CgiProcess, timeout: Duration) -> Self {
+        let pgid = process.child.id() as libc::pid_t;
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || match cancelled.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                unsafe {
+                    libc::kill(-pgid, libc::SIGKILL);
+                }
+                true
+            }
+            _ => false,
+        });
+        Self {
+            cancel: Some(cancel),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stop timing; true if the process was killed for running too long
+    pub fn finish(mut self) -> bool {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> bool {
+        // Dropping the sender wakes the thread unless it already fired
+        self.cancel.take();
+        self.thread
+            .take()
+            .is_some_and(|thread| thread.join().unwrap_or(false))
+    }
+}
+
+impl Drop for CgiWatchdog {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_shebang() {
+        let dir = std::env::temp_dir().join(format!("localhost_shebang_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("script");
+        type Expected<'a> = Option<(&'a str, Option<&'a str>)>;
+        let cases: [(&str, Expected); 5] = [
+            ("#!/bin/sh\necho hi\n", Some(("/bin/sh", None))),
+            (
+                "#! /usr/bin/env  python3 -u\r\nprint()\n",
+                Some(("/usr/bin/env", Some("python3 -u"))),
+            ),
+            ("#!\n", None),
+            ("echo no shebang\n", None),
+            ("", None),
+        ];
+        for (content, expected) in cases {
+            std::fs::write(&script, content).unwrap();
+            let expected =
+                expected.map(|(interp, arg)| (interp.to_string(), arg.map(str::to_string)));
+            assert_eq!(CgiProcess::read_shebang(&script), expected, "{:?}", content);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
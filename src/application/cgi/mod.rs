@@ -6,4 +6,4 @@ pub mod cgi_process;
 pub use cgi_env::CgiEnvironment;
 pub use cgi_executor::CgiExecutor;
 pub use cgi_io::CgiIo;
-pub use cgi_process::CgiProcess;
+pub use cgi_process::{CgiProcess, CgiWatchdog};
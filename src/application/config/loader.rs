@@ -52,6 +52,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_load_rejects_max_concurrent_without_workers() {
+        let toml = |worker_threads: usize| {
+            format!(
+                r#"
+                worker_threads = {}
+
+                [[servers]]
+                server_address = "127.0.0.1"
+                ports = [8080]
+                server_name = "localhost"
+                root = "."
+
+                [servers.routes."/report"]
+                methods = ["GET"]
+                max_concurrent = 2
+                "#,
+                worker_threads
+            )
+        };
+
+        let error = ConfigLoader::load_from_str(&toml(0)).unwrap_err();
+        assert!(error.to_string().contains("requires worker_threads"));
+        assert!(ConfigLoader::load_from_str(&toml(2)).is_ok());
+    }
+
+    #[test]
+    fn test_load_rejects_proxy_pass_without_workers() {
+        let toml = |worker_threads: usize| {
+            format!(
+                r#"
+                worker_threads = {}
+
+                [[servers]]
+                server_address = "127.0.0.1"
+                ports = [8080]
+                server_name = "localhost"
+                root = "."
+
+                [servers.routes."/api"]
+                methods = ["GET"]
+                proxy_pass = "http://127.0.0.1:9000"
+                "#,
+                worker_threads
+            )
+        };
+
+        let error = ConfigLoader::load_from_str(&toml(0)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("proxy_pass requires worker_threads"));
+        assert!(ConfigLoader::load_from_str(&toml(2)).is_ok());
+    }
+
     #[test]
     fn test_load_rejects_nonexistent_root() {
         // Validator must reject a config whose root directory does not exist
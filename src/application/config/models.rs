@@ -1,4 +1,14 @@
-use crate::common::constants::{DEFAULT_MAX_BODY_SIZE, DEFAULT_REQUEST_TIMEOUT_SECS};
+use crate::common::constants::{
+    DEFAULT_BANNER, DEFAULT_BIND_RETRY_BACKOFF_MS, DEFAULT_CGI_CONTENT_TYPE,
+    DEFAULT_CGI_MAX_HEADER_LINE, DEFAULT_CGI_TIMEOUT_SECS, DEFAULT_DUMP_TRAFFIC_MAX_BYTES,
+    DEFAULT_EXPECT_CONTINUE_TIMEOUT_SECS, DEFAULT_KEEP_ALIVE_TIMEOUT_SECS,
+    DEFAULT_LINGERING_CLOSE_SECS, DEFAULT_MAINTENANCE_INTERVAL_REQUESTS,
+    DEFAULT_MAX_ACCEPTS_PER_TICK, DEFAULT_MAX_BODY_SIZE, DEFAULT_MAX_COOKIES,
+    DEFAULT_MAX_COOKIE_HEADER_SIZE, DEFAULT_MAX_GENERATED_BODY_SIZE, DEFAULT_MAX_HEADER_COUNT,
+    DEFAULT_MAX_HEADER_SIZE, DEFAULT_MAX_KEEP_ALIVE_REQUESTS, DEFAULT_MAX_PATH_DEPTH,
+    DEFAULT_MAX_PIPELINE_DEPTH, DEFAULT_MAX_ROUTES, DEFAULT_REQUEST_TIMEOUT_SECS,
+    DEFAULT_RESPONSE_CHUNK_SIZE,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
@@ -20,6 +30,157 @@ pub struct Config {
     /// Admin credentials (optional)
     #[serde(default)]
     pub admin: Option<AdminConfig>,
+
+    /// Extra attempts to bind a listener when the port is still in use (0 = fail immediately)
+    #[serde(default)]
+    pub bind_retries: u32,
+
+    /// Delay before the first bind retry in milliseconds; doubles after each attempt
+    #[serde(default = "default_bind_retry_backoff_ms")]
+    pub bind_retry_backoff_ms: u64,
+
+    /// Run session cleanup and memory compaction every N requests (0 = never)
+    #[serde(default = "default_maintenance_interval_requests")]
+    pub maintenance_interval_requests: u64,
+
+    /// Most keep-alive connections allowed to sit idle between requests; beyond it the
+    /// least recently active idle connection is closed (0 = unlimited)
+    #[serde(default)]
+    pub max_idle_connections: usize,
+
+    /// Seconds to wait for the body after sending `100 Continue` before answering 408
+    /// (0 = only the client timeout applies)
+    #[serde(default = "default_expect_continue_timeout_secs")]
+    pub expect_continue_timeout_secs: u64,
+
+    /// Most path segments a request path may have; deeper paths get 400 (0 = unlimited)
+    #[serde(default = "default_max_path_depth")]
+    pub max_path_depth: usize,
+
+    /// Most routes one server may define; configs with more are rejected at load (0 = unlimited)
+    #[serde(default = "default_max_routes")]
+    pub max_routes: usize,
+
+    /// Answer `421 Misdirected Request` when the Host header names no configured server,
+    /// instead of serving the port's default server
+    #[serde(default)]
+    pub strict_host_matching: bool,
+
+    /// Largest chunk, in bytes, when a response body is sent with chunked encoding
+    /// (0 = the whole body as one chunk)
+    #[serde(default = "default_response_chunk_size")]
+    pub response_chunk_size: usize,
+
+    /// Write response header names in canonical casing (`content-type` -> `Content-Type`)
+    #[serde(default)]
+    pub canonical_header_names: bool,
+
+    /// Add a `Server-Timing` header reporting how long routing, file I/O and CGI took
+    #[serde(default)]
+    pub server_timing: bool,
+
+    /// After a keep-alive response, answer a request the client already pipelined
+    /// right away instead of on the next event loop pass
+    #[serde(default = "default_keep_alive_read_ahead")]
+    pub keep_alive_read_ahead: bool,
+
+    /// Log errors with their full cause chain, and caught panics with a backtrace
+    #[serde(default)]
+    pub error_log_verbose: bool,
+
+    /// Log the raw bytes read from and written to every connection (for protocol debugging)
+    #[serde(default)]
+    pub dump_traffic: bool,
+
+    /// Most bytes of each read or write shown in a traffic dump (0 = all)
+    #[serde(default = "default_dump_traffic_max_bytes")]
+    pub dump_traffic_max_bytes: usize,
+
+    /// Answer 400 to request lines and headers ending in a bare LF instead of accepting
+    /// them as if they ended in CRLF
+    #[serde(default)]
+    pub strict_line_endings: bool,
+
+    /// Read and drop a body sent with GET or HEAD instead of answering 400
+    #[serde(default)]
+    pub discard_get_body: bool,
+
+    /// How startup server info is reported: "text" on stdout, "log" through the logger,
+    /// "json" as one line on stdout, or "off"
+    #[serde(default = "default_banner")]
+    pub banner: String,
+
+    /// Answer 400 to requests repeating a header that must appear once (`Content-Length`,
+    /// `Host`, `Content-Type`, ...); repeatable headers like `Accept` are unaffected
+    #[serde(default = "default_reject_duplicate_headers")]
+    pub reject_duplicate_headers: bool,
+
+    /// Collapse repeated slashes in request paths (`//api//x` -> `/api/x`) before routing
+    #[serde(default = "default_merge_slashes")]
+    pub merge_slashes: bool,
+
+    /// Fold case and drop a trailing dot when matching `Host` against `server_name`,
+    /// so `Example.COM.` selects `example.com`; off compares the name exactly
+    #[serde(default = "default_normalize_host")]
+    pub normalize_host: bool,
+
+    /// Most pipelined requests answered in a row on one connection while more are queued;
+    /// the last one gets `Connection: close` and the rest are dropped (0 = unlimited)
+    #[serde(default = "default_max_pipeline_depth")]
+    pub max_pipeline_depth: usize,
+
+    /// Most bytes the request line and headers may take together; larger heads get 431
+    #[serde(default = "default_max_header_size")]
+    pub max_header_size: usize,
+
+    /// Most header lines a request may carry; more get 431
+    #[serde(default = "default_max_header_count")]
+    pub max_header_count: usize,
+
+    /// Most cookies read from a request's Cookie header; the rest are ignored
+    #[serde(default = "default_max_cookies")]
+    pub max_cookies: usize,
+
+    /// Most bytes a request's Cookie headers may take together; larger ones get 400
+    #[serde(default = "default_max_cookie_header_size")]
+    pub max_cookie_header_size: usize,
+
+    /// Answer 400 to a `TE` header asking for a transfer coding other than `trailers`
+    /// (such as `TE: gzip`) instead of ignoring it
+    #[serde(default)]
+    pub reject_unsupported_te: bool,
+
+    /// Seconds to keep reading (and discarding) after the final response on a connection
+    /// has been sent and its write side shut down, so input the server never read can't
+    /// make the close a reset that truncates the response (0 = close right away)
+    #[serde(default = "default_lingering_close_secs")]
+    pub lingering_close_secs: u64,
+
+    /// Seconds a kept-alive connection may sit idle waiting for its next request before
+    /// it is closed (0 = only the client timeout applies)
+    #[serde(default = "default_keep_alive_timeout_secs")]
+    pub keep_alive_timeout_secs: u64,
+
+    /// Most requests answered on one connection; the last gets `Connection: close`
+    /// (0 = unlimited)
+    #[serde(default = "default_max_keep_alive_requests")]
+    pub max_keep_alive_requests: usize,
+
+    /// Threads that run CGI scripts, proxied requests and static file serving (compression,
+    /// range slicing) off the event loop, which keeps all socket I/O (0 = everything on the
+    /// event loop)
+    #[serde(default)]
+    pub worker_threads: usize,
+
+    /// Most new connections accepted per event loop pass, so a burst of connects can't
+    /// starve connections already open (0 = accept everything pending)
+    #[serde(default = "default_max_accepts_per_tick")]
+    pub max_accepts_per_tick: usize,
+
+    /// Most bytes one connection may hold across its read and write buffers (pending
+    /// input plus a queued response); beyond it the connection is closed (0 = unlimited)
+    #[serde(default)]
+    pub max_connection_buffer_bytes: usize,
 }
 
 fn default_timeout() -> u64 {
@@ -30,6 +191,90 @@ fn default_max_body_size() -> usize {
     DEFAULT_MAX_BODY_SIZE
 }
 
+fn default_bind_retry_backoff_ms() -> u64 {
+    DEFAULT_BIND_RETRY_BACKOFF_MS
+}
+
+fn default_maintenance_interval_requests() -> u64 {
+    DEFAULT_MAINTENANCE_INTERVAL_REQUESTS
+}
+
+fn default_expect_continue_timeout_secs() -> u64 {
+    DEFAULT_EXPECT_CONTINUE_TIMEOUT_SECS
+}
+
+fn default_max_path_depth() -> usize {
+    DEFAULT_MAX_PATH_DEPTH
+}
+
+fn default_max_routes() -> usize {
+    DEFAULT_MAX_ROUTES
+}
+
+fn default_response_chunk_size() -> usize {
+    DEFAULT_RESPONSE_CHUNK_SIZE
+}
+
+fn default_keep_alive_read_ahead() -> bool {
+    true
+}
+
+fn default_dump_traffic_max_bytes() -> usize {
+    DEFAULT_DUMP_TRAFFIC_MAX_BYTES
+}
+
+fn default_banner() -> String {
+    DEFAULT_BANNER.to_string()
+}
+
+fn default_reject_duplicate_headers() -> bool {
+    true
+}
+
+fn default_merge_slashes() -> bool {
+    true
+}
+
+fn default_normalize_host() -> bool {
+    true
+}
+
+fn default_max_pipeline_depth() -> usize {
+    DEFAULT_MAX_PIPELINE_DEPTH
+}
+
+fn default_max_header_size() -> usize {
+    DEFAULT_MAX_HEADER_SIZE
+}
+
+fn default_max_header_count() -> usize {
+    DEFAULT_MAX_HEADER_COUNT
+}
+
+fn default_max_cookies() -> usize {
+    DEFAULT_MAX_COOKIES
+}
+
+fn default_max_cookie_header_size() -> usize {
+    DEFAULT_MAX_COOKIE_HEADER_SIZE
+}
+
+fn default_lingering_close_secs() -> u64 {
+    DEFAULT_LINGERING_CLOSE_SECS
+}
+
+fn default_keep_alive_timeout_secs() -> u64 {
+    DEFAULT_KEEP_ALIVE_TIMEOUT_SECS
+}
+
+fn default_max_keep_alive_requests() -> usize {
+    DEFAULT_MAX_KEEP_ALIVE_REQUESTS
+}
+
+fn default_max_accepts_per_tick() -> usize {
+    DEFAULT_MAX_ACCEPTS_PER_TICK
+}
+
 /// Server instance configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ServerConfig {
@@ -60,6 +305,123 @@ pub struct ServerConfig {
     /// CGI handler mappings (extension -> interpreter)
     #[serde(default)]
     pub cgi_handlers: HashMap<String, String>,
+
+    /// Content-Type used when a CGI script doesn't send one
+    #[serde(default = "default_cgi_content_type")]
+    pub cgi_default_content_type: String,
+
+    /// CGI response header naming a file for the server to serve instead of the script's
+    /// body (e.g. "X-Accel-Redirect"). Unset disables internal redirects.
+    #[serde(default)]
+    pub cgi_internal_redirect_header: Option<String>,
+
+    /// Run scripts with no configured interpreter through the interpreter named on
+    /// their `#!` line instead of executing them directly
+    #[serde(default)]
+    pub cgi_shebang_fallback: bool,
+
+    /// Directory holding ACME HTTP-01 tokens, served as text/plain under
+    /// `/.well-known/acme-challenge/` ahead of normal routing. Unset disables it.
+    #[serde(default)]
+    pub acme_challenge_dir: Option<String>,
+
+    /// Byte cap on server-generated HTML (directory listings, default error pages);
+    /// longer output is truncated (0 = unlimited)
+    #[serde(default = "default_max_generated_body_size")]
+    pub max_generated_body_size: usize,
+
+    /// `Cache-Control` value for successful static file responses (e.g. "public, max-age=3600").
+    /// Routes may override it with their own `cache_control`.
+    #[serde(default)]
+    pub static_cache_control: Option<String>,
+
+    /// Expect a PROXY protocol v1 header ahead of each connection on these ports and
+    /// treat the client address it carries as the peer (only behind a trusted proxy)
+    #[serde(default)]
+    pub proxy_protocol: bool,
+
+    /// Most open connections on each of this server's ports; further connections are
+    /// answered with 503 and closed (0 = unlimited). The lowest limit wins on shared ports.
+    #[serde(default)]
+    pub max_connections: usize,
+
+    /// Seconds a CGI script may run before it is killed and the client gets 504 with a
+    /// matching `Retry-After` (0 = unlimited)
+    #[serde(default = "default_cgi_timeout_secs")]
+    pub cgi_timeout_secs: u64,
+
+    /// Refuse POST, PUT, DELETE and PATCH with 405 whatever the routes allow, so nothing
+    /// on this server can be changed through it
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Compress textual static responses of at least 1KB with gzip (or br) when the
+    /// client accepts it
+    #[serde(default = "default_enable_gzip")]
+    pub enable_gzip: bool,
+
+    /// Answer OPTIONS on every route, whether or not the route lists it
+    #[serde(default)]
+    pub auto_options: bool,
+
+    /// Answer HEAD (as GET without the body) on every route that allows GET
+    #[serde(default)]
+    pub auto_head: bool,
+
+    /// Longest header line accepted from a CGI script; longer ones answer 502 (0 = unlimited)
+    #[serde(default = "default_cgi_max_header_line")]
+    pub cgi_max_header_line: usize,
+}
+
+impl Default for ServerConfig {
+    /// A server on 127.0.0.1 serving the current directory, with every optional
+    /// setting at its serde default
+    fn default() -> Self {
+        Self {
+            server_address: IpAddr::from([127, 0, 0, 1]),
+            ports: Vec::new(),
+            server_name: String::new(),
+            root: ".".to_string(),
+            admin_access: false,
+            routes: HashMap::new(),
+            errors: HashMap::new(),
+            cgi_handlers: HashMap::new(),
+            cgi_default_content_type: default_cgi_content_type(),
+            cgi_internal_redirect_header: None,
+            cgi_shebang_fallback: false,
+            acme_challenge_dir: None,
+            max_generated_body_size: default_max_generated_body_size(),
+            static_cache_control: None,
+            proxy_protocol: false,
+            max_connections: 0,
+            cgi_timeout_secs: default_cgi_timeout_secs(),
+            read_only: false,
+            enable_gzip: default_enable_gzip(),
+            auto_options: false,
+            auto_head: false,
+            cgi_max_header_line: default_cgi_max_header_line(),
+        }
+    }
+}
+
+fn default_cgi_content_type() -> String {
+    DEFAULT_CGI_CONTENT_TYPE.to_string()
+}
+
+fn default_cgi_timeout_secs() -> u64 {
+    DEFAULT_CGI_TIMEOUT_SECS
+}
+
+fn default_cgi_max_header_line() -> usize {
+    DEFAULT_CGI_MAX_HEADER_LINE
+}
+
+fn default_enable_gzip() -> bool {
+    true
+}
+
+fn default_max_generated_body_size() -> usize {
+    DEFAULT_MAX_GENERATED_BODY_SIZE
 }
 
 /// Route configuration
@@ -85,21 +447,77 @@ pub struct RouteConfig {
     #[serde(default)]
     pub directory_listing: bool,
 
+    /// Apply `Key: Value` lines from a `<file>.headers` sidecar to static file responses
+    #[serde(default)]
+    pub sidecar_headers: bool,
+
+    /// `Cache-Control` for static files on this route, replacing the server's
+    /// `static_cache_control` (an empty value sends none)
+    #[serde(default)]
+    pub cache_control: Option<String>,
+
     /// Upload directory (for POST requests)
     #[serde(default)]
     pub upload_dir: Option<String>,
 
+    /// Request Content-Types accepted for uploads (e.g. "image/*", "multipart/form-data").
+    /// Empty means any type; others are rejected with 415.
+    #[serde(default)]
+    pub allowed_content_types: Vec<String>,
+
     /// HTTP redirect target
     #[serde(default)]
     pub redirect: Option<String>,
 
-    /// Redirect type: "301" for permanent redirect, "302" for temporary redirect (default: "302")
+    /// Redirect type: "permanent" (or "301") or "temporary" (or "302", the default)
     #[serde(default)]
     pub redirect_type: Option<String>,
 
+    /// Emit redirect Location as an absolute URL (http://<server_name>[:port]<path>) built
+    /// from the answering server's name and port, never the client's Host header
+    #[serde(default)]
+    pub absolute_redirect: bool,
+
+    /// Upstream to forward requests to (e.g. "http://127.0.0.1:9000" or "http://127.0.0.1:9000/v1").
+    /// With a path, the matched route prefix is replaced by it; without one the request target is kept.
+    /// The upstream exchange blocks, so this needs `worker_threads`
+    #[serde(default)]
+    pub proxy_pass: Option<String>,
+
     /// CGI extension for this route
     #[serde(default)]
     pub cgi_extension: Option<String>,
+
+    /// Execute matching CGI files (true) or serve them as static files (false).
+    /// When unset, files are executed if they match `cgi_extension` or a `cgi_handlers` entry.
+    #[serde(default)]
+    pub cgi_enabled: Option<bool>,
+
+    /// Working directory for CGI scripts on this route, resolved against the server root
+    /// (default: the script's own directory)
+    #[serde(default)]
+    pub cgi_working_dir: Option<String>,
+
+    /// Honor `Range` on CGI output: the buffered output is sliced into a 206 (or 416)
+    /// like a static file. Off by default, so scripts always send their full output.
+    #[serde(default)]
+    pub cgi_range: bool,
+
+    /// Most requests this route handles at once; more are answered with 503
+    /// (unset = unlimited). Requests only overlap on workers, so this needs `worker_threads`
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+
+    /// Body size limit for this route, replacing `client_max_body_size` in either
+    /// direction (unset = the global limit). Bodies are read up to the largest limit of
+    /// any route until the request's route is known, then held to its own.
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+
+    /// Fallback language for Accept-Language negotiation. When set, a request for
+    /// `index.html` is served from `index.<lang>.html` and `Content-Language` is added.
+    #[serde(default)]
+    pub default_language: Option<String>,
 }
 
 /// Error page configuration
@@ -131,6 +549,39 @@ impl Default for Config {
             client_max_body_size: default_max_body_size(),
             servers: Vec::new(),
             admin: None,
+            bind_retries: 0,
+            bind_retry_backoff_ms: default_bind_retry_backoff_ms(),
+            maintenance_interval_requests: default_maintenance_interval_requests(),
+            max_idle_connections: 0,
+            expect_continue_timeout_secs: default_expect_continue_timeout_secs(),
+            max_path_depth: default_max_path_depth(),
+            max_routes: default_max_routes(),
+            strict_host_matching: false,
+            response_chunk_size: default_response_chunk_size(),
+            canonical_header_names: false,
+            server_timing: false,
+            keep_alive_read_ahead: default_keep_alive_read_ahead(),
+            error_log_verbose: false,
+            dump_traffic: false,
+            dump_traffic_max_bytes: default_dump_traffic_max_bytes(),
+            strict_line_endings: false,
+            discard_get_body: false,
+            banner: default_banner(),
+            reject_duplicate_headers: default_reject_duplicate_headers(),
+            merge_slashes: default_merge_slashes(),
+            normalize_host: default_normalize_host(),
+            max_pipeline_depth: default_max_pipeline_depth(),
+            max_header_size: default_max_header_size(),
+            max_header_count: default_max_header_count(),
+            max_cookies: default_max_cookies(),
+            max_cookie_header_size: default_max_cookie_header_size(),
+            reject_unsupported_te: false,
+            lingering_close_secs: default_lingering_close_secs(),
+            keep_alive_timeout_secs: default_keep_alive_timeout_secs(),
+            max_keep_alive_requests: default_max_keep_alive_requests(),
+            worker_threads: 0,
+            max_accepts_per_tick: default_max_accepts_per_tick(),
+            max_connection_buffer_bytes: 0,
         }
     }
 }
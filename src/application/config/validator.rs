@@ -1,4 +1,6 @@
 use crate::application::config::models::{Config, RouteConfig, ServerConfig};
+use crate::application::handler::path_resolver::PathResolver;
+use crate::common::constants::{BANNER_MODES, REDIRECT_TYPES, SUPPORTED_METHODS};
 use crate::common::error::{Result, ServerError};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
@@ -23,6 +25,9 @@ pub fn validate_config(config: &Config) -> Result<()> {
         validate_server(server, idx)?;
     }
 
+    validate_route_count(config)?;
+    validate_worker_routes(config)?;
+
     // Validate admin config if present
     if let Some(ref admin) = config.admin {
         validate_admin(admin)?;
@@ -44,6 +49,58 @@ fn validate_global_settings(config: &Config) -> Result<()> {
         ));
     }
 
+    if !BANNER_MODES.contains(&config.banner.as_str()) {
+        return Err(ServerError::ConfigError(format!(
+            "banner must be one of {}, got '{}'",
+            BANNER_MODES.join(", "),
+            config.banner
+        )));
+    }
+
+    Ok(())
+}
+
+/// Every server's routes are compiled into its router at startup, so the table is capped
+fn validate_route_count(config: &Config) -> Result<()> {
+    if config.max_routes == 0 {
+        return Ok(());
+    }
+    for (idx, server) in config.servers.iter().enumerate() {
+        if server.routes.len() > config.max_routes {
+            return Err(ServerError::ConfigError(format!(
+                "Server {}: {} routes exceed max_routes ({})",
+                idx,
+                server.routes.len(),
+                config.max_routes
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Route features that only work on the worker pool: a `max_concurrent` slot is held
+/// while its handler runs, which only overlaps other requests on workers, and a
+/// `proxy_pass` upstream exchange blocks, which must not stall the event loop
+fn validate_worker_routes(config: &Config) -> Result<()> {
+    if config.worker_threads > 0 {
+        return Ok(());
+    }
+    for (idx, server) in config.servers.iter().enumerate() {
+        for (path, route) in &server.routes {
+            if route.max_concurrent.is_some() {
+                return Err(ServerError::ConfigError(format!(
+                    "Server {}: route '{}' max_concurrent requires worker_threads > 0",
+                    idx, path
+                )));
+            }
+            if route.proxy_pass.is_some() {
+                return Err(ServerError::ConfigError(format!(
+                    "Server {}: route '{}' proxy_pass requires worker_threads > 0",
+                    idx, path
+                )));
+            }
+        }
+    }
     Ok(())
 }
 
@@ -168,8 +225,9 @@ fn validate_server(server: &ServerConfig, index: usize) -> Result<()> {
     }
 
     // Validate routes
+    let resolver = PathResolver::new(root_path.to_path_buf());
     for (path, route) in &server.routes {
-        validate_route(route, path, index)?;
+        validate_route(route, path, index, &resolver)?;
     }
 
     // Validate error pages
@@ -213,7 +271,12 @@ fn validate_server(server: &ServerConfig, index: usize) -> Result<()> {
     Ok(())
 }
 
-fn validate_route(route: &RouteConfig, path: &str, server_idx: usize) -> Result<()> {
+fn validate_route(
+    route: &RouteConfig,
+    path: &str,
+    server_idx: usize,
+    resolver: &PathResolver,
+) -> Result<()> {
     // Validate path
     if path.is_empty() {
         return Err(ServerError::ConfigError(format!(
@@ -237,10 +300,7 @@ fn validate_route(route: &RouteConfig, path: &str, server_idx: usize) -> Result<
         )));
     }
 
-    let valid_methods: HashSet<&str> = ["GET", "POST", "DELETE", "PUT", "PATCH", "HEAD", "OPTIONS"]
-        .iter()
-        .copied()
-        .collect();
+    let valid_methods: HashSet<&str> = SUPPORTED_METHODS.iter().copied().collect();
 
     for method in &route.methods {
         if !valid_methods.contains(method.as_str()) {
@@ -257,15 +317,16 @@ fn validate_route(route: &RouteConfig, path: &str, server_idx: usize) -> Result<
     let has_file = route.filename.is_some();
     let has_dir = route.directory.is_some();
     let has_redirect = route.redirect.is_some();
+    let has_proxy = route.proxy_pass.is_some();
 
-    let target_count = [has_file, has_dir, has_redirect]
+    let target_count = [has_file, has_dir, has_redirect, has_proxy]
         .iter()
         .filter(|&&x| x)
         .count();
 
     if target_count > 1 {
         return Err(ServerError::ConfigError(format!(
-            "Server {}: route '{}' cannot specify multiple targets (filename, directory, redirect)",
+            "Server {}: route '{}' cannot specify multiple targets (filename, directory, redirect, proxy_pass)",
             server_idx, path
         )));
     }
@@ -311,6 +372,55 @@ fn validate_route(route: &RouteConfig, path: &str, server_idx: usize) -> Result<
         }
     }
 
+    if let Some(ref redirect_type) = route.redirect_type {
+        if !REDIRECT_TYPES.contains(&redirect_type.as_str()) {
+            return Err(ServerError::ConfigError(format!(
+                "Server {}: route '{}' redirect_type must be one of {}, got '{}'",
+                server_idx,
+                path,
+                REDIRECT_TYPES.join(", "),
+                redirect_type
+            )));
+        }
+    }
+
+    // CGI working directory must exist when the server starts, not when a script runs
+    if let Some(ref working_dir) = route.cgi_working_dir {
+        let dir = resolver.resolve_config_path(working_dir);
+        if !dir.is_dir() {
+            return Err(ServerError::ConfigError(format!(
+                "Server {}: route '{}' cgi_working_dir '{}' is not an existing directory",
+                server_idx,
+                path,
+                dir.display()
+            )));
+        }
+    }
+
+    if route.max_concurrent == Some(0) {
+        return Err(ServerError::ConfigError(format!(
+            "Server {}: route '{}' max_concurrent must be greater than 0",
+            server_idx, path
+        )));
+    }
+
+    if route.max_body_size == Some(0) {
+        return Err(ServerError::ConfigError(format!(
+            "Server {}: route '{}' max_body_size must be greater than 0",
+            server_idx, path
+        )));
+    }
+
+    // Validate proxy upstream (plain HTTP only)
+    if let Some(ref upstream) = route.proxy_pass {
+        if !upstream.starts_with("http://") || upstream.len() == "http://".len() {
+            return Err(ServerError::ConfigError(format!(
+                "Server {}: route '{}' proxy_pass must be an 'http://host:port' URL",
+                server_idx, path
+            )));
+        }
+    }
+
     Ok(())
 }
 
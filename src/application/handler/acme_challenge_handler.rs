@@ -0,0 +1,151 @@
+use crate::application::config::models::ServerConfig;
+use crate::application::handler::request_context::RequestContext;
+use crate::application::handler::request_handler::RequestHandler;
+use crate::common::constants::ACME_CHALLENGE_PREFIX;
+use crate::common::error::{Result, ServerError};
+use crate::http::headers::names as header_names;
+use crate::http::method::Method;
+use crate::http::request::Request;
+use crate::http::response::Response;
+use std::fs;
+
+/// Handler for ACME HTTP-01 challenges (`/.well-known/acme-challenge/<token>`)
+#[derive(Default)]
+pub struct AcmeChallengeHandler;
+
+impl AcmeChallengeHandler {
+    /// Create a new ACME challenge handler
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Whether the request targets a challenge path on a server with a challenge directory
+    pub fn is_challenge_request(request: &Request, config: &ServerConfig) -> bool {
+        config.acme_challenge_dir.is_some() && request.path().starts_with(ACME_CHALLENGE_PREFIX)
+    }
+
+    /// Tokens are base64url, so anything else (including `/` and `..`) is rejected
+    fn is_valid_token(token: &str) -> bool {
+        !token.is_empty()
+            && token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    }
+}
+
+impl RequestHandler for AcmeChallengeHandler {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
+        if request.method != Method::GET && request.method != Method::HEAD {
+            let mut response = Response::method_not_allowed_with_message(
+                request.version,
+                "Only GET and HEAD are allowed for ACME challenges",
+            );
+            response
+                .headers
+                .set(header_names::ALLOW.to_string(), "GET, HEAD".to_string());
+            return Ok(response);
+        }
+
+        let dir = ctx
+            .server_config
+            .acme_challenge_dir
+            .as_deref()
+            .ok_or_else(|| ServerError::HttpError("ACME challenges not configured".to_string()))?;
+
+        let token = request
+            .path()
+            .strip_prefix(ACME_CHALLENGE_PREFIX)
+            .unwrap_or_default();
+        if !Self::is_valid_token(token) {
+            return Ok(Response::not_found_with_message(
+                request.version,
+                "Challenge not found",
+            ));
+        }
+
+        let token_path = ctx.router.resolve_path(dir).join(token);
+        match fs::read(&token_path) {
+            Ok(content) if token_path.is_file() => {
+                let mut response = Response::ok(request.version);
+                response.set_content_type("text/plain");
+                response.set_body(content);
+                Ok(response)
+            }
+            _ => Ok(Response::not_found_with_message(
+                request.version,
+                "Challenge not found",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::handler::router::Router;
+    use crate::http::status::StatusCode;
+    use crate::http::version::Version;
+    use std::path::{Path, PathBuf};
+
+    fn server_config(acme_dir: Option<&Path>) -> ServerConfig {
+        ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: ".".to_string(),
+            acme_challenge_dir: acme_dir.map(|d| d.to_string_lossy().to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn get(path: &str) -> Request {
+        Request::new(Method::GET, path.to_string(), Version::Http11)
+    }
+
+    #[test]
+    fn test_serves_challenge_token_as_text_plain() {
+        let dir = std::env::temp_dir().join(format!("localhost_acme_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("tok-EN_123"), b"tok-EN_123.thumbprint").unwrap();
+
+        let config = server_config(Some(&dir));
+        let router = Router::new(&config, PathBuf::from("."));
+        let ctx = RequestContext::new(&router, &config, 8080);
+        let handler = AcmeChallengeHandler::new();
+
+        let request = get("/.well-known/acme-challenge/tok-EN_123");
+        assert!(AcmeChallengeHandler::is_challenge_request(
+            &request, &config
+        ));
+        let response = handler.handle(&request, &ctx).unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/plain".to_string())
+        );
+        assert_eq!(response.body, b"tok-EN_123.thumbprint");
+
+        for path in [
+            "/.well-known/acme-challenge/missing",
+            "/.well-known/acme-challenge/../secret",
+            "/.well-known/acme-challenge/",
+        ] {
+            let response = handler.handle(&get(path), &ctx).unwrap();
+            assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", path);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disabled_without_challenge_dir() {
+        let request = get("/.well-known/acme-challenge/token");
+        assert!(!AcmeChallengeHandler::is_challenge_request(
+            &request,
+            &server_config(None)
+        ));
+        assert!(!AcmeChallengeHandler::is_challenge_request(
+            &get("/.well-known/other"),
+            &server_config(Some(Path::new("/tmp")))
+        ));
+    }
+}
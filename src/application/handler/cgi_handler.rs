@@ -1,37 +1,38 @@
-use crate::application::cgi::CgiExecutor;
+use crate::application::cgi::{CgiExecutor, CgiIo};
 use crate::application::config::models::ServerConfig;
+use crate::application::handler::request_context::RequestContext;
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
-use crate::common::constants::DEFAULT_REQUEST_TIMEOUT_SECS;
+use crate::application::handler::static_file_handler::StaticFileHandler;
+use crate::common::constants::DEFAULT_CGI_TIMEOUT_SECS;
 use crate::common::error::{Result, ServerError};
+use crate::http::headers::names as header_names;
+use crate::http::method::Method;
+use crate::http::range;
 use crate::http::request::Request;
 use crate::http::response::Response;
+use crate::http::status::StatusCode;
 use std::path::Path;
 
 /// Handler for executing CGI scripts
 pub struct CgiHandler {
-    router: Router,
     executor: CgiExecutor,
-    server_config: ServerConfig,
-    server_port: u16,
 }
 
 impl CgiHandler {
     /// Create a new CGI handler
-    pub fn new(router: Router, server_config: ServerConfig, server_port: u16) -> Self {
-        let executor = CgiExecutor::new(DEFAULT_REQUEST_TIMEOUT_SECS);
+    pub fn new() -> Self {
         Self {
-            router,
-            executor,
-            server_config,
-            server_port,
+            executor: CgiExecutor::new(DEFAULT_CGI_TIMEOUT_SECS),
         }
     }
 
     /// Determine interpreter for script based on extension
-    fn get_interpreter(&self, script_path: &Path) -> Option<&String> {
+    fn get_interpreter<'a>(
+        server_config: &'a ServerConfig,
+        script_path: &Path,
+    ) -> Option<&'a String> {
         if let Some(ext) = script_path.extension().and_then(|e| e.to_str()) {
-            self.server_config.cgi_handlers.get(ext)
+            server_config.cgi_handlers.get(ext)
         } else {
             None
         }
@@ -39,7 +40,7 @@ impl CgiHandler {
 
     /// Check if file is a CGI script based on route configuration
     fn is_cgi_script(
-        &self,
+        server_config: &ServerConfig,
         route: &crate::application::config::models::RouteConfig,
         file_path: &Path,
     ) -> bool {
@@ -54,23 +55,71 @@ impl CgiHandler {
         // CGI handlers in config use format ".py", ".sh", etc.
         if let Some(ext) = file_path.extension().and_then(|e| e.to_str()) {
             let ext_with_dot = format!(".{}", ext);
-            return self.server_config.cgi_handlers.contains_key(&ext_with_dot);
+            return server_config.cgi_handlers.contains_key(&ext_with_dot);
         }
 
         false
     }
+
+    /// Apply a `Range` request to a script's complete 200 output, keeping the script's
+    /// own headers. Output the script already marked partial is left alone.
+    fn apply_range(request: &Request, response: &mut Response) {
+        if response.status != StatusCode::OK
+            || response.headers.contains(header_names::CONTENT_RANGE)
+        {
+            return;
+        }
+        let content_type = response
+            .headers
+            .get(header_names::CONTENT_TYPE)
+            .cloned()
+            .unwrap_or_default();
+        range::apply(request, response, &content_type);
+    }
+
+    /// Serve the static file a script named via the internal-redirect header.
+    /// The target is routed like a GET for that path, so route rules and root confinement apply.
+    fn serve_internal_redirect(
+        request: &Request,
+        ctx: &RequestContext,
+        target: &str,
+    ) -> Result<Response> {
+        crate::common::logger::Logger::info(&format!(
+            "CGI internal redirect: {} -> {}",
+            request.path(),
+            target
+        ));
+        let mut internal = Request::new(Method::GET, target.to_string(), request.version);
+        internal.headers = request.headers.clone();
+        internal.parse_query_params();
+
+        match StaticFileHandler::new().handle(&internal, ctx) {
+            Ok(response) => Ok(response),
+            Err(ServerError::HttpError(_)) => Ok(Response::not_found_with_message(
+                request.version,
+                "Not Found",
+            )),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Default for CgiHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RequestHandler for CgiHandler {
-    fn handle(&self, request: &Request) -> Result<Response> {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
         // Validate route and method
-        let (route, error_response) = self.router.validate_request(request)?;
+        let (route, error_response) = ctx.router.validate_request(request)?;
         if let Some(response) = error_response {
             return Ok(response);
         }
 
         // Resolve script path
-        let script_path = self.router.resolve_file_path(request, route)?;
+        let script_path = ctx.router.resolve_within_root(request, route)?;
 
         // Verify script exists
         if !script_path.exists() {
@@ -81,7 +130,7 @@ impl RequestHandler for CgiHandler {
         }
 
         // Check if this is a CGI script
-        if !self.is_cgi_script(route, &script_path) {
+        if !Self::is_cgi_script(ctx.server_config, route, &script_path) {
             return Ok(Response::forbidden_with_message(
                 request.version,
                 "Not a CGI script",
@@ -89,26 +138,293 @@ impl RequestHandler for CgiHandler {
         }
 
         // Get interpreter for script
-        let interpreter = self.get_interpreter(&script_path);
+        let interpreter = Self::get_interpreter(ctx.server_config, &script_path);
 
         // Execute CGI script
-        match self.executor.execute(
+        let working_dir = route
+            .cgi_working_dir
+            .as_deref()
+            .map(|dir| ctx.router.resolver().resolve_config_path(dir));
+        let executor = self
+            .executor
+            .clone()
+            .with_working_dir(working_dir)
+            .with_timeout_secs(ctx.server_config.cgi_timeout_secs)
+            .with_max_header_line(ctx.server_config.cgi_max_header_line)
+            .with_shebang_fallback(ctx.server_config.cgi_shebang_fallback)
+            .with_remote_addr(ctx.client_addr.map(|addr| addr.ip()));
+        match executor.execute(
             script_path,
             interpreter.map(|s| s.as_str()),
             request,
-            &self.server_config.server_name,
-            self.server_port,
+            &ctx.server_config.server_name,
+            ctx.server_port,
         ) {
-            Ok(response) => Ok(response),
-            Err(ServerError::CgiError(msg)) => Ok(Response::internal_error_with_message(
-                request.version,
-                &format!("CGI Error: {}", msg),
-            )),
-            Err(ServerError::TimeoutError(msg)) => Ok(Response::gateway_timeout_with_message(
-                request.version,
-                &format!("CGI Timeout: {}", msg),
-            )),
-            Err(e) => Err(e),
+            Ok(mut response) => {
+                if let Some(header) = &ctx.server_config.cgi_internal_redirect_header {
+                    if let Some(target) = CgiIo::take_internal_redirect(&mut response, header) {
+                        return Self::serve_internal_redirect(request, ctx, &target);
+                    }
+                }
+                if !response.headers.contains(header_names::CONTENT_TYPE) {
+                    response.set_content_type(&ctx.server_config.cgi_default_content_type);
+                }
+                if route.cgi_range {
+                    Self::apply_range(request, &mut response);
+                }
+                Ok(response)
+            }
+            Err(e) => {
+                crate::common::logger::Logger::error_with(
+                    &format!("CGI {} {} failed", request.method, request.path()),
+                    &e,
+                );
+                match e {
+                    // The logged error names the script; the client only learns it failed
+                    ServerError::CgiSpawnError(_) => Ok(Response::bad_gateway_with_message(
+                        request.version,
+                        "CGI Error: the script could not be started",
+                    )),
+                    ServerError::CgiHeaderTooLong(msg) => Ok(Response::bad_gateway_with_message(
+                        request.version,
+                        &format!("CGI Error: {}", msg),
+                    )),
+                    ServerError::CgiError(msg) => Ok(Response::internal_error_with_message(
+                        request.version,
+                        &format!("CGI Error: {}", msg),
+                    )),
+                    ServerError::TimeoutError(_) => {
+                        // Tell the client the limit it ran into and when to try again
+                        let timeout_secs = ctx.server_config.cgi_timeout_secs;
+                        let mut response = Response::gateway_timeout_with_message(
+                            request.version,
+                            &format!(
+                                "CGI Timeout: script did not finish within {} seconds",
+                                timeout_secs
+                            ),
+                        );
+                        response.headers.set(
+                            header_names::RETRY_AFTER.to_string(),
+                            timeout_secs.to_string(),
+                        );
+                        Ok(response)
+                    }
+                    e => Err(e),
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::config::models::RouteConfig;
+    use crate::application::handler::router::Router;
+    use crate::http::version::Version;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn run_script(
+        name: &str,
+        script: &str,
+        interpreter: Option<&str>,
+        shebang_fallback: bool,
+    ) -> Response {
+        run_script_with(name, script, interpreter, shebang_fallback, |_| {})
+    }
+
+    fn run_script_with(
+        name: &str,
+        script: &str,
+        interpreter: Option<&str>,
+        shebang_fallback: bool,
+        configure: impl FnOnce(&mut ServerConfig),
+    ) -> Response {
+        let request = Request::new(Method::GET, "/script.sh".to_string(), Version::Http11);
+        run_request(
+            name,
+            script,
+            interpreter,
+            shebang_fallback,
+            configure,
+            &request,
+        )
+    }
+
+    fn run_request(
+        name: &str,
+        script: &str,
+        interpreter: Option<&str>,
+        shebang_fallback: bool,
+        configure: impl FnOnce(&mut ServerConfig),
+        request: &Request,
+    ) -> Response {
+        let root =
+            std::env::temp_dir().join(format!("localhost_cgi_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("script.sh"), script).unwrap();
+
+        let mut routes = HashMap::new();
+        routes.insert(
+            "/".to_string(),
+            RouteConfig {
+                methods: vec!["GET".to_string()],
+                cgi_extension: Some("sh".to_string()),
+                ..Default::default()
+            },
+        );
+        let mut config = ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: root.to_string_lossy().to_string(),
+            routes,
+            cgi_handlers: interpreter
+                .map(|i| HashMap::from([("sh".to_string(), i.to_string())]))
+                .unwrap_or_default(),
+            cgi_shebang_fallback: shebang_fallback,
+            ..Default::default()
+        };
+        configure(&mut config);
+        let router = Router::new(&config, PathBuf::from(&root));
+        let ctx = RequestContext::new(&router, &config, 8080);
+        let response = CgiHandler::new().handle(request, &ctx).unwrap();
+        let _ = fs::remove_dir_all(&root);
+        response
+    }
+
+    fn ranged(name: &str, range: &str, cgi_range: bool) -> Response {
+        let mut request = Request::new(Method::GET, "/script.sh".to_string(), Version::Http11);
+        request
+            .headers
+            .set(header_names::RANGE.to_string(), range.to_string());
+        let script = "printf 'Content-Type: application/pdf\\r\\nX-Report: 7\\r\\n\\r\\n'\nprintf '0123456789'\n";
+        run_request(
+            name,
+            script,
+            Some("/bin/sh"),
+            false,
+            |config| config.routes.get_mut("/").unwrap().cgi_range = cgi_range,
+            &request,
+        )
+    }
+
+    #[test]
+    fn test_range_on_cgi_output_is_206_with_the_slice() {
+        let response = ranged("range", "bytes=2-5", true);
+        assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body, b"2345");
+        assert_eq!(
+            response.headers.get(header_names::CONTENT_RANGE),
+            Some(&"bytes 2-5/10".to_string())
+        );
+        assert_eq!(response.content_length(), Some(4));
+        // The script's own headers survive the slicing
+        assert_eq!(
+            response.headers.get(header_names::CONTENT_TYPE),
+            Some(&"application/pdf".to_string())
+        );
+        assert_eq!(response.headers.get("X-Report"), Some(&"7".to_string()));
+
+        let response = ranged("range_416", "bytes=50-", true);
+        assert_eq!(response.status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers.get(header_names::CONTENT_RANGE),
+            Some(&"bytes */10".to_string())
+        );
+
+        let response = ranged("range_multi", "bytes=0-1,8-", true);
+        assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+        let body = String::from_utf8_lossy(&response.body);
+        assert!(body.contains("Content-Range: bytes 0-1/10\r\n\r\n01"));
+        assert!(body.contains("Content-Range: bytes 8-9/10\r\n\r\n89"));
+    }
+
+    #[test]
+    fn test_range_on_cgi_output_is_ignored_by_default() {
+        let response = ranged("range_off", "bytes=2-5", false);
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, b"0123456789");
+        assert!(!response.headers.contains(header_names::ACCEPT_RANGES));
+    }
+
+    #[test]
+    fn test_missing_interpreter_is_502() {
+        let response = run_script(
+            "spawn",
+            "echo hi\n",
+            Some("/nonexistent/interpreter"),
+            false,
+        );
+        assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+        let body = String::from_utf8_lossy(&response.body);
+        assert!(!body.contains("script.sh"));
+        assert!(!body.contains("/nonexistent"));
+    }
+
+    #[test]
+    fn test_failing_script_is_500() {
+        let response = run_script("exit", "exit 3\n", Some("/bin/sh"), false);
+        assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_multi_megabyte_header_line_is_502() {
+        let script = "printf 'X-Huge: '\nhead -c 3000000 /dev/zero | tr '\\0' x\nprintf '\\r\\n\\r\\nbody'\n";
+        let response = run_script("huge_header", script, Some("/bin/sh"), false);
+        assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_slow_script_is_504_with_retry_after() {
+        // The background child must not keep the response open past the limit
+        let started = std::time::Instant::now();
+        let response = run_script_with(
+            "timeout",
+            "sleep 30 &\nsleep 30\n",
+            Some("/bin/sh"),
+            false,
+            |config| config.cgi_timeout_secs = 1,
+        );
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert_eq!(response.status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(
+            response.headers.get(header_names::RETRY_AFTER),
+            Some(&"1".to_string())
+        );
+        assert!(String::from_utf8_lossy(&response.body).contains("within 1 seconds"));
+    }
+
+    #[test]
+    fn test_script_runs_in_configured_working_dir() {
+        let script = "printf 'Content-Type: text/plain\\r\\n\\r\\n'\npwd\n";
+
+        let response = run_script_with("workdir", script, Some("/bin/sh"), false, |config| {
+            fs::create_dir_all(Path::new(&config.root).join("data")).unwrap();
+            config.routes.get_mut("/").unwrap().cgi_working_dir = Some("./data".to_string());
+        });
+        assert_eq!(response.status, StatusCode::OK);
+        let cwd = String::from_utf8_lossy(&response.body);
+        assert!(cwd.trim_end().ends_with("/data"), "{}", cwd);
+
+        // Unset, the script runs in its own directory
+        let response = run_script("own_dir", script, Some("/bin/sh"), false);
+        let cwd = String::from_utf8_lossy(&response.body);
+        assert!(cwd.contains("localhost_cgi_own_dir_"), "{}", cwd);
+    }
+
+    #[test]
+    fn test_shebang_names_interpreter_when_none_configured() {
+        // Written without the execute bit, so only the shebang fallback can run it
+        let script = "#!/bin/sh\nprintf 'Content-Type: text/plain\\r\\n\\r\\nfrom shebang'\n";
+
+        let response = run_script("shebang", script, None, true);
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, b"from shebang");
+
+        let response = run_script("no_shebang", script, None, false);
+        assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+    }
+}
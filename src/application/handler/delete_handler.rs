@@ -1,6 +1,7 @@
+use crate::application::handler::request_context::RequestContext;
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
 use crate::common::error::Result;
+use crate::http::headers::names as header_names;
 use crate::http::method::Method;
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -8,14 +9,13 @@ use std::fs;
 use std::path::Path;
 
 /// Handler for DELETE requests - safely deletes files
-pub struct DeleteHandler {
-    router: Router,
-}
+#[derive(Default)]
+pub struct DeleteHandler;
 
 impl DeleteHandler {
     /// Create a new DELETE handler
-    pub fn new(router: Router) -> Self {
-        Self { router }
+    pub fn new() -> Self {
+        Self
     }
 
     /// Safely delete a file
@@ -74,23 +74,27 @@ impl DeleteHandler {
 }
 
 impl RequestHandler for DeleteHandler {
-    fn handle(&self, request: &Request) -> Result<Response> {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
         // Only DELETE requests are allowed
         if request.method != Method::DELETE {
-            return Ok(Response::method_not_allowed_with_message(
+            let mut response = Response::method_not_allowed_with_message(
                 request.version,
                 "Only DELETE method is allowed",
-            ));
+            );
+            response
+                .headers
+                .set(header_names::ALLOW.to_string(), "DELETE".to_string());
+            return Ok(response);
         }
 
         // Match route (without method validation since DELETE was already checked by server manager)
         // We still need to match the route to get the file path, but we skip method validation
-        let route = self.router.match_route(request).ok_or_else(|| {
+        let route = ctx.router.match_route(request).ok_or_else(|| {
             crate::common::error::ServerError::HttpError("No matching route".to_string())
         })?;
 
         // Resolve file path
-        let file_path = self.router.resolve_file_path(request, route)?;
+        let file_path = ctx.router.resolve_within_root(request, route)?;
 
         // Log the resolved path for debugging
         crate::common::logger::Logger::info(&format!(
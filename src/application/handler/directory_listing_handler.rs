@@ -1,5 +1,5 @@
+use crate::application::handler::request_context::RequestContext;
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
 use crate::common::error::{Result, ServerError};
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -7,18 +7,28 @@ use std::fs;
 use std::path::Path;
 
 /// Handler for generating directory listings
-pub struct DirectoryListingHandler {
-    router: Router,
-}
+#[derive(Default)]
+pub struct DirectoryListingHandler;
 
 impl DirectoryListingHandler {
     /// Create a new directory listing handler
-    pub fn new(router: Router) -> Self {
-        Self { router }
+    pub fn new() -> Self {
+        Self
     }
 
-    /// Generate HTML directory listing
-    fn generate_listing(&self, dir_path: &Path, request_path: &str) -> Result<String> {
+    /// Closing markup of a listing page
+    const FOOTER: &'static str = "</pre><hr></body></html>";
+
+    /// Line standing in for the entries left out of a listing that hit its size cap
+    const TRUNCATED_NOTICE: &'static str = "... listing truncated\n";
+
+    /// Generate HTML directory listing, keeping it within `max_size` bytes (0 = unlimited)
+    fn generate_listing(
+        &self,
+        dir_path: &Path,
+        request_path: &str,
+        max_size: usize,
+    ) -> Result<String> {
         let mut html = String::from("<!DOCTYPE html>\n<html><head><title>Index of ");
         html.push_str(request_path);
         html.push_str("</title></head><body><h1>Index of ");
@@ -59,7 +69,8 @@ impl DirectoryListingHandler {
         });
 
         // Generate listing
-        for entry in entries {
+        let total = entries.len();
+        for (shown, entry) in entries.into_iter().enumerate() {
             let path = entry.path();
             let name = entry.file_name();
             let name_str = name.to_string_lossy();
@@ -72,47 +83,61 @@ impl DirectoryListingHandler {
                 format!("{}/{}", request_path, name_str)
             };
 
-            html.push_str("<a href=\"");
-            html.push_str(&url);
-            html.push_str("\">");
-            html.push_str(&name_str);
+            let mut line = String::from("<a href=\"");
+            line.push_str(&url);
+            line.push_str("\">");
+            line.push_str(&name_str);
             if is_dir {
-                html.push('/');
+                line.push('/');
             }
-            html.push_str("</a>");
+            line.push_str("</a>");
 
             // Add spacing for alignment
             let name_len = name_str.len();
             let padding = 50usize.saturating_sub(name_len);
-            html.push_str(&" ".repeat(padding));
+            line.push_str(&" ".repeat(padding));
 
             if is_dir {
-                html.push('-');
+                line.push('-');
             } else if let Ok(metadata) = path.metadata() {
                 let size = metadata.len();
-                html.push_str(&size.to_string());
+                line.push_str(&size.to_string());
             } else {
-                html.push('-');
+                line.push('-');
             }
 
-            html.push('\n');
+            line.push('\n');
+
+            // Stop before the entry that would push the page (with notice and footer) past the cap
+            if max_size > 0
+                && html.len() + line.len() + Self::TRUNCATED_NOTICE.len() + Self::FOOTER.len()
+                    > max_size
+            {
+                crate::common::logger::Logger::warn(&format!(
+                    "Directory listing for '{}' truncated at {} of {} entries ({} byte cap)",
+                    request_path, shown, total, max_size
+                ));
+                html.push_str(Self::TRUNCATED_NOTICE);
+                break;
+            }
+            html.push_str(&line);
         }
 
-        html.push_str("</pre><hr></body></html>");
+        html.push_str(Self::FOOTER);
         Ok(html)
     }
 }
 
 impl RequestHandler for DirectoryListingHandler {
-    fn handle(&self, request: &Request) -> Result<Response> {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
         // Validate route and method
-        let (route, error_response) = self.router.validate_request(request)?;
+        let (route, error_response) = ctx.router.validate_request(request)?;
         if let Some(response) = error_response {
             return Ok(response);
         }
 
         // Resolve directory path
-        let dir_path = self.router.resolve_file_path(request, route)?;
+        let dir_path = ctx.router.resolve_within_root(request, route)?;
 
         // Verify it's a directory
         if !dir_path.is_dir() {
@@ -120,7 +145,7 @@ impl RequestHandler for DirectoryListingHandler {
         }
 
         // Check if directory listing is enabled
-        if !self.router.is_directory_listing_enabled(route) {
+        if !ctx.router.is_directory_listing_enabled(route) {
             return Ok(Response::forbidden_with_message(
                 request.version,
                 "Directory listing is disabled",
@@ -128,7 +153,11 @@ impl RequestHandler for DirectoryListingHandler {
         }
 
         // Generate listing
-        let html = self.generate_listing(&dir_path, request.path())?;
+        let html = self.generate_listing(
+            &dir_path,
+            request.path(),
+            ctx.server_config.max_generated_body_size,
+        )?;
 
         let mut response = Response::ok(request.version);
         response.set_content_type("text/html");
@@ -137,3 +166,30 @@ impl RequestHandler for DirectoryListingHandler {
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_truncated_at_size_cap() {
+        let dir = std::env::temp_dir().join(format!("localhost_listing_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..200 {
+            fs::write(dir.join(format!("file_{:03}.txt", i)), "x").unwrap();
+        }
+        let handler = DirectoryListingHandler::new();
+
+        let full = handler.generate_listing(&dir, "/files/", 0).unwrap();
+        assert!(full.contains("file_199.txt"));
+        assert!(full.len() > 4096);
+
+        let capped = handler.generate_listing(&dir, "/files/", 4096).unwrap();
+        assert!(capped.len() <= 4096);
+        assert!(capped.contains("file_000.txt"));
+        assert!(!capped.contains("file_199.txt"));
+        assert!(capped.contains("listing truncated"));
+        assert!(capped.ends_with("</pre><hr></body></html>"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,27 +1,21 @@
 use crate::application::config::models::ServerConfig;
+use crate::application::handler::path_resolver::PathResolver;
 use crate::common::error::Result;
 use crate::http::response::Response;
 use crate::http::status::StatusCode;
 use crate::http::version::Version;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 /// Handler for custom error pages
 pub struct ErrorPageHandler {
-    root_path: PathBuf,
+    resolver: PathResolver,
     error_pages: std::collections::HashMap<String, String>,
+    /// Cap on the generated default page (0 = unlimited)
+    max_generated_body_size: usize,
 }
 
 impl ErrorPageHandler {
-    /// Resolve error page path - helper to reduce redundancy with router's resolve_path
-    fn resolve_error_path(root_path: &Path, path: &str) -> PathBuf {
-        if path.starts_with('/') || path.starts_with("./") {
-            PathBuf::from(path)
-        } else {
-            root_path.join(path)
-        }
-    }
-
     /// Create a new error page handler from server configuration
     pub fn new(config: &ServerConfig, root_path: PathBuf) -> Self {
         let error_pages: std::collections::HashMap<String, String> = config
@@ -37,8 +31,9 @@ impl ErrorPageHandler {
             .collect();
 
         Self {
-            root_path,
+            resolver: PathResolver::new(root_path),
             error_pages,
+            max_generated_body_size: config.max_generated_body_size,
         }
     }
 
@@ -53,7 +48,7 @@ impl ErrorPageHandler {
 
         // Try to find custom error page
         if let Some(error_file) = self.error_pages.get(&status_str) {
-            let error_path = Self::resolve_error_path(&self.root_path, error_file);
+            let error_path = self.resolver.resolve_config_path(error_file);
 
             // Try to read custom error page
             if crate::common::path_utils::is_valid_file(&error_path) {
@@ -137,7 +132,24 @@ impl ErrorPageHandler {
             status_code.reason_phrase()
         );
 
-        response.set_body_str(&html);
+        response.set_body_str(Self::cap_generated(&html, self.max_generated_body_size));
         Ok(response)
     }
+
+    /// Cut generated HTML to at most `max_size` bytes on a char boundary (0 = unlimited)
+    fn cap_generated(html: &str, max_size: usize) -> &str {
+        if max_size == 0 || html.len() <= max_size {
+            return html;
+        }
+        let mut end = max_size;
+        while !html.is_char_boundary(end) {
+            end -= 1;
+        }
+        crate::common::logger::Logger::warn(&format!(
+            "Generated error page truncated from {} to {} bytes",
+            html.len(),
+            end
+        ));
+        &html[..end]
+    }
 }
@@ -1,19 +1,29 @@
+pub mod acme_challenge_handler;
 pub mod cgi_handler;
 pub mod delete_handler;
 pub mod directory_listing_handler;
 pub mod error_page_handler;
+pub mod options_handler;
+pub mod path_resolver;
+pub mod proxy_handler;
 pub mod redirection_handler;
+pub mod request_context;
 pub mod request_handler;
 pub mod router;
 pub mod session_manager;
 pub mod static_file_handler;
 pub mod upload_handler;
 
+pub use acme_challenge_handler::AcmeChallengeHandler;
 pub use cgi_handler::CgiHandler;
 pub use delete_handler::DeleteHandler;
 pub use directory_listing_handler::DirectoryListingHandler;
 pub use error_page_handler::ErrorPageHandler;
+pub use options_handler::OptionsHandler;
+pub use path_resolver::PathResolver;
+pub use proxy_handler::ProxyHandler;
 pub use redirection_handler::RedirectionHandler;
+pub use request_context::{RequestContext, SessionHandle};
 pub use request_handler::RequestHandler;
 pub use router::Router;
 pub use session_manager::{Session, SessionData, SessionManager};
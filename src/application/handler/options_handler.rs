@@ -0,0 +1,44 @@
+use crate::application::handler::request_context::RequestContext;
+use crate::application::handler::request_handler::RequestHandler;
+use crate::common::error::Result;
+use crate::http::headers::names;
+use crate::http::request::Request;
+use crate::http::response::Response;
+use crate::http::status::StatusCode;
+
+/// Handler for OPTIONS requests - reports the methods a route accepts
+#[derive(Default)]
+pub struct OptionsHandler;
+
+impl OptionsHandler {
+    /// Create a new OPTIONS handler
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Whether the request asks about the server as a whole (`OPTIONS *`)
+    pub fn is_server_wide(request: &Request) -> bool {
+        request.target == "*"
+    }
+}
+
+impl RequestHandler for OptionsHandler {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
+        let methods = if Self::is_server_wide(request) {
+            ctx.router.all_allowed_methods()
+        } else {
+            // Routes that leave OPTIONS out of their methods refuse it like any other
+            let (route, error_response) = ctx.router.validate_request(request)?;
+            if let Some(response) = error_response {
+                return Ok(response);
+            }
+            ctx.router.allowed_methods(route)
+        };
+
+        let mut response = Response::new(request.version, StatusCode::NO_CONTENT);
+        response
+            .headers
+            .set(names::ALLOW.to_string(), methods.join(", "));
+        Ok(response)
+    }
+}
@@ -0,0 +1,159 @@
+use crate::common::error::{Result, ServerError};
+use crate::common::path_utils::{canonicalize_parent, safe_join};
+use std::path::{Path, PathBuf};
+
+/// Maps configured and request-derived paths onto the filesystem under a server root
+///
+/// Shared by the router (route files, directories and uploads) and the error page
+/// handler, so every configured path is interpreted the same way.
+#[derive(Debug, Clone)]
+pub struct PathResolver {
+    root_path: PathBuf,
+}
+
+impl PathResolver {
+    /// Create a resolver for the given server root
+    pub fn new(root_path: PathBuf) -> Self {
+        Self { root_path }
+    }
+
+    /// Server root that relative paths resolve against
+    pub fn root(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Resolve a configured path: absolute paths are used as-is, "." is the root, and
+    /// anything else (with or without a leading "./") is relative to the root
+    pub fn resolve_config_path(&self, path: &str) -> PathBuf {
+        if path.starts_with('/') {
+            PathBuf::from(path)
+        } else if path == "." {
+            self.root_path.clone()
+        } else if let Some(relative) = path.strip_prefix("./") {
+            self.root_path.join(relative)
+        } else {
+            self.root_path.join(path)
+        }
+    }
+
+    /// Join a request-derived path onto `base`; an empty path is `base` itself.
+    /// Request segments are always relative and can't climb out with `..`.
+    pub fn resolve_request_path(&self, base: &Path, relative: &str) -> Result<PathBuf> {
+        if relative.trim_start_matches('/').is_empty() {
+            Ok(base.to_path_buf())
+        } else {
+            safe_join(base, relative)
+        }
+    }
+
+    /// Confine `path` to `base`: parent directories are canonicalized and the result
+    /// must not escape `base` through `..` or symlinks. `base` should be canonical.
+    pub fn confine(&self, path: &Path, base: &Path) -> Result<PathBuf> {
+        let resolved = if path == base {
+            base.to_path_buf()
+        } else {
+            canonicalize_parent(path)
+        };
+        if !resolved.starts_with(base) {
+            return Err(ServerError::HttpError(
+                "Path escapes the document root".to_string(),
+            ));
+        }
+
+        // A symlink inside the root must not point outside of it
+        if let Ok(target) = std::fs::canonicalize(&resolved) {
+            if !target.starts_with(base) {
+                return Err(ServerError::HttpError(
+                    "Symlink escapes the document root".to_string(),
+                ));
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Canonical form of a configured directory, for use as a confinement base
+    pub fn canonical_dir(&self, directory: &str) -> PathBuf {
+        let dir_path = self.resolve_config_path(directory);
+        std::fs::canonicalize(&dir_path).unwrap_or(dir_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "localhost_resolver_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(&dir).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_config_path() {
+        let resolver = PathResolver::new(PathBuf::from("/srv/www"));
+        assert_eq!(resolver.resolve_config_path("."), PathBuf::from("/srv/www"));
+        assert_eq!(
+            resolver.resolve_config_path("./static"),
+            PathBuf::from("/srv/www/static")
+        );
+        // Error pages and route files are written both ways and must agree
+        assert_eq!(
+            resolver.resolve_config_path("errors/404.html"),
+            resolver.resolve_config_path("./errors/404.html")
+        );
+        assert_eq!(
+            resolver.resolve_config_path("/etc/pages/500.html"),
+            PathBuf::from("/etc/pages/500.html")
+        );
+    }
+
+    #[test]
+    fn test_resolve_request_path_stays_under_base() {
+        let resolver = PathResolver::new(PathBuf::from("/srv/www"));
+        let base = Path::new("/srv/www/static");
+        assert_eq!(resolver.resolve_request_path(base, "").unwrap(), base);
+        assert_eq!(resolver.resolve_request_path(base, "/").unwrap(), base);
+        assert_eq!(
+            resolver
+                .resolve_request_path(base, "//css/./site.css")
+                .unwrap(),
+            PathBuf::from("/srv/www/static/css/site.css")
+        );
+        assert!(resolver.resolve_request_path(base, "/../secret").is_err());
+    }
+
+    #[test]
+    fn test_confine_rejects_escapes() {
+        let root = temp_root("confine");
+        let outside = temp_root("confine_outside");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/page.html"), "ok").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let resolver = PathResolver::new(root.clone());
+
+        assert_eq!(resolver.confine(&root, &root).unwrap(), root);
+        assert_eq!(
+            resolver
+                .confine(&root.join("sub/page.html"), &root)
+                .unwrap(),
+            root.join("sub/page.html")
+        );
+        // Files that don't exist yet (uploads) are still confined by their parents
+        assert_eq!(
+            resolver.confine(&root.join("sub/new.txt"), &root).unwrap(),
+            root.join("sub/new.txt")
+        );
+        assert!(resolver.confine(&root.join("link/file"), &root).is_err());
+        assert!(resolver.confine(&root.join("link"), &root).is_err());
+
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&outside);
+    }
+}
@@ -0,0 +1,708 @@
+use crate::application::handler::request_context::RequestContext;
+use crate::application::handler::request_handler::RequestHandler;
+use crate::common::constants::{DEFAULT_MAX_PROXY_RESPONSE_SIZE, DEFAULT_REQUEST_TIMEOUT_SECS};
+use crate::common::error::{Result, ServerError};
+use crate::common::path_utils::percent_encode_path;
+use crate::http::headers::{names as header_names, Headers};
+use crate::http::method::Method;
+use crate::http::request::Request;
+use crate::http::response::Response;
+use crate::http::status::StatusCode;
+use crate::http::version::Version;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Headers that describe a single connection and must not be forwarded (RFC 9110 §7.6.1)
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Connection",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+/// Handler forwarding requests to an upstream HTTP server (`proxy_pass` routes)
+pub struct ProxyHandler {
+    timeout: Duration,
+    /// Largest upstream response buffered; bigger ones are answered with 502
+    max_response_size: usize,
+}
+
+/// Parsed `proxy_pass` target
+#[derive(Debug, PartialEq, Eq)]
+struct Upstream {
+    /// `host:port` used for the connection and the forwarded Host header
+    authority: String,
+    /// Path replacing the matched route prefix, if the URL has one
+    path: Option<String>,
+}
+
+impl Upstream {
+    fn parse(url: &str) -> Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            ServerError::ConfigError(format!("Unsupported proxy_pass URL '{}'", url))
+        })?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], Some(rest[idx..].to_string())),
+            None => (rest, None),
+        };
+        if authority.is_empty() {
+            return Err(ServerError::ConfigError(format!(
+                "proxy_pass URL '{}' has no host",
+                url
+            )));
+        }
+        let authority = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+        Ok(Self { authority, path })
+    }
+
+    /// Request target sent upstream for `request`, which matched `route_prefix`.
+    /// The parser decoded the path, so it is escaped again before it goes on the wire.
+    fn target_for(&self, request: &Request, route_prefix: &str) -> String {
+        let path = percent_encode_path(request.path());
+        let mut target = match &self.path {
+            Some(base) => Self::join(base, path.strip_prefix(route_prefix).unwrap_or("")),
+            None => path,
+        };
+        if let Some(query) = request.query_string() {
+            target.push('?');
+            target.push_str(query);
+        }
+        target
+    }
+
+    /// Append the part of the path after the route prefix to the upstream base path
+    fn join(base: &str, rest: &str) -> String {
+        if rest.is_empty() {
+            base.to_string()
+        } else if base.ends_with('/') && rest.starts_with('/') {
+            format!("{}{}", base, &rest[1..])
+        } else if !base.ends_with('/') && !rest.starts_with('/') {
+            format!("{}/{}", base, rest)
+        } else {
+            format!("{}{}", base, rest)
+        }
+    }
+}
+
+impl ProxyHandler {
+    /// Create a new proxy handler
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+            max_response_size: DEFAULT_MAX_PROXY_RESPONSE_SIZE,
+        }
+    }
+
+    /// Headers named in `Connection` are hop-by-hop too
+    fn is_hop_by_hop(name: &str, connection_tokens: &[String]) -> bool {
+        HOP_BY_HOP_HEADERS
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(name))
+            || connection_tokens
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(name))
+    }
+
+    fn connection_tokens(headers: &Headers) -> Vec<String> {
+        headers
+            .get_all(header_names::CONNECTION)
+            .map(|values| {
+                values
+                    .iter()
+                    .flat_map(|v| v.split(','))
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Serialize the request to forward upstream. The body was already de-chunked
+    /// by the parser, so it is always sent with a Content-Length.
+    fn build_upstream_request(request: &Request, upstream: &Upstream, target: &str) -> Vec<u8> {
+        let tokens = Self::connection_tokens(&request.headers);
+        let mut head = format!("{} {} HTTP/1.1\r\n", request.method, target);
+        head.push_str(&format!("Host: {}\r\n", upstream.authority));
+        if let Some(host) = request.host() {
+            head.push_str(&format!("X-Forwarded-Host: {}\r\n", host));
+        }
+        for (name, values) in request.headers.iter() {
+            if Self::is_hop_by_hop(name, &tokens)
+                || name.eq_ignore_ascii_case(header_names::HOST)
+                || name.eq_ignore_ascii_case(header_names::CONTENT_LENGTH)
+            {
+                continue;
+            }
+            for value in values {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        if !request.body.is_empty() || request.content_length().is_some() {
+            head.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&request.body);
+        bytes
+    }
+
+    /// Send the request and read the upstream's response until it is fully framed
+    fn exchange(
+        &self,
+        upstream: &Upstream,
+        raw_request: &[u8],
+        head: bool,
+    ) -> Result<UpstreamResponse> {
+        let addr = upstream
+            .authority
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                ServerError::NetworkError(format!("Cannot resolve '{}'", upstream.authority))
+            })?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(raw_request)?;
+
+        let mut response = UpstreamResponse::new(head, self.max_response_size);
+        let mut chunk = [0u8; 8192];
+        loop {
+            match stream.read(&mut chunk) {
+                Ok(0) => {
+                    response.finish()?;
+                    break;
+                }
+                Ok(n) => {
+                    // A complete response is done even if the upstream keeps the socket open
+                    if response.feed(&chunk[..n])? {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Err(ServerError::TimeoutError(format!(
+                        "Upstream {} did not respond in time",
+                        upstream.authority
+                    )))
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(response)
+    }
+}
+
+impl Default for ProxyHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestHandler for ProxyHandler {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
+        let (route, error_response) = ctx.router.validate_request(request)?;
+        if let Some(response) = error_response {
+            return Ok(response);
+        }
+        let upstream_url = route.proxy_pass.as_ref().ok_or_else(|| {
+            ServerError::HttpError("Route does not have proxy_pass configured".to_string())
+        })?;
+        let route_prefix = ctx
+            .router
+            .match_route_with_path(request)
+            .map(|(path, _)| path.as_str())
+            .unwrap_or("/");
+
+        let upstream = Upstream::parse(upstream_url)?;
+        let target = upstream.target_for(request, route_prefix);
+        crate::common::logger::Logger::info(&format!(
+            "Proxying {} {} -> http://{}{}",
+            request.method,
+            request.path(),
+            upstream.authority,
+            target
+        ));
+
+        let raw_request = Self::build_upstream_request(request, &upstream, &target);
+        let head = request.method == Method::HEAD;
+        let upstream_response = match self.exchange(&upstream, &raw_request, head) {
+            Ok(upstream_response) => upstream_response,
+            Err(ServerError::TimeoutError(msg)) => {
+                crate::common::logger::Logger::error(&msg);
+                return Ok(Response::gateway_timeout_with_message(
+                    request.version,
+                    "Gateway Timeout",
+                ));
+            }
+            Err(e) => {
+                crate::common::logger::Logger::error(&format!(
+                    "Upstream {} failed: {}",
+                    upstream.authority, e
+                ));
+                return Ok(Response::bad_gateway_with_message(
+                    request.version,
+                    "Bad Gateway",
+                ));
+            }
+        };
+
+        Ok(upstream_response.into_response(request.version))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// How the body of the upstream's final response is delimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// Answer to HEAD, or a 1xx/204/304: the response ends with its head
+    Bodiless,
+    /// `Content-Length`, with the bytes still to come
+    Length(usize),
+    /// `Transfer-Encoding: chunked`, at the given point of the chunk syntax
+    Chunked(ChunkState),
+    /// Neither: the body runs until the upstream closes
+    UntilClose,
+    /// Fully read
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    /// Expecting a chunk-size line
+    Size,
+    /// Inside a chunk with this many data bytes left
+    Data(usize),
+    /// Expecting the CRLF after a chunk's data
+    DataEnd,
+    /// After the last chunk, skipping trailer fields up to the blank line
+    Trailers,
+}
+
+/// Upstream response read incrementally: the head is parsed once, then each read only
+/// advances the body framing over its own bytes. Everything buffered (head, pending
+/// chunk syntax and body) counts towards `max_size`.
+struct UpstreamResponse {
+    head_request: bool,
+    max_size: usize,
+    /// Bytes of the head, or of chunk syntax, not yet consumed
+    pending: Vec<u8>,
+    /// How far `pending` was already searched for the end of the head
+    scanned: usize,
+    status: Option<StatusCode>,
+    headers: Headers,
+    framing: Option<Framing>,
+    body: Vec<u8>,
+}
+
+impl UpstreamResponse {
+    fn new(head_request: bool, max_size: usize) -> Self {
+        Self {
+            head_request,
+            max_size,
+            pending: Vec::new(),
+            scanned: 0,
+            status: None,
+            headers: Headers::new(),
+            framing: None,
+            body: Vec::new(),
+        }
+    }
+
+    fn malformed() -> ServerError {
+        ServerError::ParseError("Malformed upstream response".to_string())
+    }
+
+    /// Take in the next bytes read from the upstream; `true` once the response is complete
+    fn feed(&mut self, data: &[u8]) -> Result<bool> {
+        self.pending.extend_from_slice(data);
+        if self.framing.is_none() {
+            self.parse_head()?;
+        }
+        match self.framing {
+            None => {}
+            Some(Framing::Bodiless) | Some(Framing::Done) => {
+                // Nothing follows the response; stray bytes are dropped
+                self.pending.clear();
+                self.framing = Some(Framing::Done);
+            }
+            Some(Framing::Length(remaining)) => {
+                let take = remaining.min(self.pending.len());
+                self.body.extend_from_slice(&self.pending[..take]);
+                self.pending.clear();
+                self.framing = Some(match remaining - take {
+                    0 => Framing::Done,
+                    left => Framing::Length(left),
+                });
+            }
+            Some(Framing::UntilClose) => self.body.append(&mut self.pending),
+            Some(Framing::Chunked(state)) => self.decode_chunks(state)?,
+        }
+        if self.pending.len() + self.body.len() > self.max_size {
+            return Err(ServerError::HttpError(format!(
+                "Upstream response exceeds {} bytes",
+                self.max_size
+            )));
+        }
+        Ok(self.framing == Some(Framing::Done))
+    }
+
+    /// The upstream closed the connection: only a body delimited by closing may end here
+    fn finish(&mut self) -> Result<()> {
+        match self.framing {
+            Some(Framing::UntilClose) | Some(Framing::Done) | Some(Framing::Bodiless) => {
+                self.framing = Some(Framing::Done);
+                Ok(())
+            }
+            _ => Err(Self::malformed()),
+        }
+    }
+
+    /// Parse the head of the first non-1xx response (`101` counts as final) once it is
+    /// complete, leaving the bytes after it in `pending`
+    fn parse_head(&mut self) -> Result<()> {
+        loop {
+            let from = self.scanned.saturating_sub(3);
+            let Some(head_end) = find(&self.pending[from..], b"\r\n\r\n").map(|i| i + from) else {
+                self.scanned = self.pending.len();
+                return Ok(());
+            };
+            let head = String::from_utf8_lossy(&self.pending[..head_end]).to_string();
+            self.pending.drain(..head_end + 4);
+            self.scanned = 0;
+
+            let mut lines = head.split("\r\n");
+            let status = lines
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|code| code.parse::<u16>().ok())
+                .and_then(StatusCode::new)
+                .ok_or_else(Self::malformed)?;
+            if status.is_informational() && status.as_u16() != 101 {
+                continue;
+            }
+            let header_lines: Vec<String> = lines.map(str::to_string).collect();
+            self.headers = Headers::from_lines(&header_lines).map_err(|_| Self::malformed())?;
+            self.status = Some(status);
+
+            let framing = if self.head_request || !status.allows_body() {
+                Framing::Bodiless
+            } else if is_chunked(&self.headers) {
+                Framing::Chunked(ChunkState::Size)
+            } else if let Some(len) = content_length(&self.headers) {
+                Framing::Length(len)
+            } else {
+                Framing::UntilClose
+            };
+            self.framing = Some(framing);
+            return Ok(());
+        }
+    }
+
+    /// Move chunk data from `pending` into the body, stopping where the input runs out
+    fn decode_chunks(&mut self, mut state: ChunkState) -> Result<()> {
+        let mut pos = 0;
+        let done = loop {
+            let rest = &self.pending[pos..];
+            match state {
+                ChunkState::Size => {
+                    let Some(line_end) = find(rest, b"\r\n") else {
+                        break false;
+                    };
+                    let size = std::str::from_utf8(&rest[..line_end])
+                        .ok()
+                        .and_then(|line| line.split(';').next())
+                        .and_then(|hex| usize::from_str_radix(hex.trim(), 16).ok())
+                        .ok_or_else(Self::malformed)?;
+                    pos += line_end + 2;
+                    state = match size {
+                        0 => ChunkState::Trailers,
+                        size => ChunkState::Data(size),
+                    };
+                }
+                ChunkState::Data(left) => {
+                    if rest.is_empty() {
+                        break false;
+                    }
+                    let take = left.min(rest.len());
+                    self.body.extend_from_slice(&rest[..take]);
+                    pos += take;
+                    state = match left - take {
+                        0 => ChunkState::DataEnd,
+                        left => ChunkState::Data(left),
+                    };
+                }
+                ChunkState::DataEnd => {
+                    if rest.len() < 2 {
+                        break false;
+                    }
+                    if &rest[..2] != b"\r\n" {
+                        return Err(Self::malformed());
+                    }
+                    pos += 2;
+                    state = ChunkState::Size;
+                }
+                ChunkState::Trailers => {
+                    let Some(line_end) = find(rest, b"\r\n") else {
+                        break false;
+                    };
+                    pos += line_end + 2;
+                    if line_end == 0 {
+                        break true;
+                    }
+                }
+            }
+        };
+        self.pending.drain(..pos);
+        self.framing = Some(if done {
+            Framing::Done
+        } else {
+            Framing::Chunked(state)
+        });
+        Ok(())
+    }
+
+    /// The client response, with hop-by-hop headers dropped. Answers to HEAD and
+    /// bodiless statuses keep the upstream's length; others get their own.
+    fn into_response(self, version: Version) -> Response {
+        let status = self.status.unwrap_or(StatusCode::BAD_GATEWAY);
+        let bodiless = self.head_request || !status.allows_body();
+        let tokens = ProxyHandler::connection_tokens(&self.headers);
+        let mut response = Response::new(version, status);
+        for (name, values) in self.headers.iter() {
+            if ProxyHandler::is_hop_by_hop(name, &tokens)
+                || (!bodiless && name.eq_ignore_ascii_case(header_names::CONTENT_LENGTH))
+            {
+                continue;
+            }
+            response.headers.remove(name);
+            for value in values {
+                response.headers.add(name.clone(), value.clone());
+            }
+        }
+        if !bodiless {
+            response.set_body(self.body);
+        }
+        response
+    }
+}
+
+fn is_chunked(headers: &Headers) -> bool {
+    headers
+        .get(header_names::TRANSFER_ENCODING)
+        .map(|te| te.to_ascii_lowercase().contains("chunked"))
+        .unwrap_or(false)
+}
+
+fn content_length(headers: &Headers) -> Option<usize> {
+    headers
+        .get(header_names::CONTENT_LENGTH)
+        .and_then(|v| v.trim().parse::<usize>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::method::Method;
+
+    fn request(target: &str) -> Request {
+        let mut request = Request::new(Method::GET, target.to_string(), Version::Http11);
+        request
+            .headers
+            .set("Host".to_string(), "front.example".to_string());
+        request
+    }
+
+    #[test]
+    fn test_upstream_parsing() {
+        assert_eq!(
+            Upstream::parse("http://127.0.0.1:9000").unwrap(),
+            Upstream {
+                authority: "127.0.0.1:9000".to_string(),
+                path: None
+            }
+        );
+        assert_eq!(
+            Upstream::parse("http://backend/v1").unwrap(),
+            Upstream {
+                authority: "backend:80".to_string(),
+                path: Some("/v1".to_string())
+            }
+        );
+        assert!(Upstream::parse("https://backend").is_err());
+        assert!(Upstream::parse("http:///path").is_err());
+    }
+
+    #[test]
+    fn test_target_mapping() {
+        let keep = Upstream::parse("http://u:1").unwrap();
+        assert_eq!(
+            keep.target_for(&request("/api/users?x=1"), "/api"),
+            "/api/users?x=1"
+        );
+
+        let replace = Upstream::parse("http://u:1/v1/").unwrap();
+        assert_eq!(
+            replace.target_for(&request("/api/users?x=1"), "/api"),
+            "/v1/users?x=1"
+        );
+        assert_eq!(replace.target_for(&request("/api"), "/api"), "/v1/");
+
+        // A path the parser decoded goes upstream escaped again
+        let mut decoded = request("/api/my%20file.txt?x=%20");
+        decoded.decode_path();
+        assert_eq!(
+            keep.target_for(&decoded, "/api"),
+            "/api/my%20file.txt?x=%20"
+        );
+        assert_eq!(
+            replace.target_for(&decoded, "/api"),
+            "/v1/my%20file.txt?x=%20"
+        );
+    }
+
+    #[test]
+    fn test_upstream_request_drops_hop_by_hop_headers() {
+        let mut req = request("/api");
+        req.headers.set(
+            "Connection".to_string(),
+            "keep-alive, X-Private".to_string(),
+        );
+        req.headers
+            .set("X-Private".to_string(), "secret".to_string());
+        req.headers
+            .set("Keep-Alive".to_string(), "timeout=5".to_string());
+        req.headers
+            .set("Accept".to_string(), "text/plain".to_string());
+        let upstream = Upstream::parse("http://127.0.0.1:9000").unwrap();
+
+        let raw = ProxyHandler::build_upstream_request(&req, &upstream, "/api");
+        let raw = String::from_utf8(raw).unwrap();
+        assert!(raw.starts_with("GET /api HTTP/1.1\r\nHost: 127.0.0.1:9000\r\n"));
+        assert!(raw.contains("X-Forwarded-Host: front.example\r\n"));
+        assert!(raw.contains("Accept: text/plain\r\n"));
+        assert!(raw.ends_with("Connection: close\r\n\r\n"));
+        assert!(!raw.contains("X-Private"));
+        assert!(!raw.contains("Keep-Alive"));
+    }
+
+    /// Feed `raw` to a reader in `step`-byte reads; the reader and whether it completed
+    fn read_in_steps(raw: &[u8], head: bool, step: usize) -> (UpstreamResponse, bool) {
+        let mut response = UpstreamResponse::new(head, DEFAULT_MAX_PROXY_RESPONSE_SIZE);
+        let mut complete = false;
+        for piece in raw.chunks(step) {
+            complete = response.feed(piece).unwrap();
+        }
+        (response, complete)
+    }
+
+    #[test]
+    fn test_parse_chunked_upstream_response() {
+        let raw = b"HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\nConnection: keep-alive\r\nX-Backend: a\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        // Split anywhere, including inside the head and the chunk syntax
+        for step in [1, 3, 7, raw.len()] {
+            let (upstream, complete) = read_in_steps(raw, false, step);
+            assert!(complete, "step {}", step);
+
+            let response = upstream.into_response(Version::Http11);
+            assert_eq!(response.status, StatusCode::CREATED);
+            assert_eq!(response.body, b"hello world");
+            assert_eq!(response.headers.get("Content-Length").unwrap(), "11");
+            assert_eq!(response.headers.get("X-Backend").unwrap(), "a");
+            assert!(response.headers.get("Transfer-Encoding").is_none());
+            assert!(response.headers.get("Connection").is_none());
+        }
+    }
+
+    #[test]
+    fn test_response_complete_with_content_length() {
+        let mut upstream = UpstreamResponse::new(false, DEFAULT_MAX_PROXY_RESPONSE_SIZE);
+        assert!(!upstream
+            .feed(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nab")
+            .unwrap());
+        assert!(upstream.feed(b"cd").unwrap());
+        assert_eq!(upstream.into_response(Version::Http11).body, b"abcd");
+    }
+
+    #[test]
+    fn test_body_until_close_and_truncated_responses() {
+        let mut upstream = UpstreamResponse::new(false, DEFAULT_MAX_PROXY_RESPONSE_SIZE);
+        assert!(!upstream.feed(b"HTTP/1.1 200 OK\r\n\r\nstream").unwrap());
+        upstream.finish().unwrap();
+        assert_eq!(upstream.into_response(Version::Http11).body, b"stream");
+
+        let mut truncated = UpstreamResponse::new(false, DEFAULT_MAX_PROXY_RESPONSE_SIZE);
+        truncated
+            .feed(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nab")
+            .unwrap();
+        assert!(truncated.finish().is_err());
+    }
+
+    #[test]
+    fn test_oversized_upstream_response_is_refused() {
+        let mut upstream = UpstreamResponse::new(false, 64);
+        assert!(!upstream
+            .feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+            .unwrap());
+        assert!(upstream.feed(b"41\r\n").is_ok());
+        let error = upstream.feed(&[b'x'; 65]).unwrap_err();
+        assert!(error.to_string().contains("exceeds 64 bytes"));
+    }
+
+    #[test]
+    fn test_chunked_response_with_trailers() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nX-Checksum: 1\r\n";
+        let (mut upstream, complete) = read_in_steps(raw, false, raw.len());
+        assert!(!complete);
+        assert!(upstream.feed(b"\r\n").unwrap());
+        assert_eq!(upstream.into_response(Version::Http11).body, b"abc");
+    }
+
+    #[test]
+    fn test_head_and_bodiless_responses_end_with_their_head() {
+        // The length describes the GET representation; no body follows
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\nContent-Type: text/plain\r\n\r\n";
+        let (upstream, complete) = read_in_steps(raw, true, raw.len());
+        assert!(complete);
+        let response = upstream.into_response(Version::Http11);
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(response.body.is_empty());
+        assert_eq!(response.headers.get("Content-Length").unwrap(), "1234");
+
+        for raw in [
+            &b"HTTP/1.1 204 No Content\r\n\r\n"[..],
+            &b"HTTP/1.1 304 Not Modified\r\nContent-Length: 10\r\nETag: \"a\"\r\n\r\n"[..],
+        ] {
+            let (upstream, complete) = read_in_steps(raw, false, raw.len());
+            assert!(complete);
+            assert!(upstream.into_response(Version::Http11).body.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_interim_responses_are_skipped() {
+        let mut upstream = UpstreamResponse::new(false, DEFAULT_MAX_PROXY_RESPONSE_SIZE);
+        assert!(!upstream.feed(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap());
+        assert!(upstream
+            .feed(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok")
+            .unwrap());
+
+        let response = upstream.into_response(Version::Http11);
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert_eq!(response.body, b"ok");
+    }
+}
@@ -1,30 +1,42 @@
+use crate::application::handler::request_context::RequestContext;
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
 use crate::common::error::{Result, ServerError};
 use crate::http::request::Request;
 use crate::http::response::Response;
 
 /// Handler for HTTP redirects (301/302)
-pub struct RedirectionHandler {
-    router: Router,
-}
+#[derive(Default)]
+pub struct RedirectionHandler;
 
 impl RedirectionHandler {
     /// Create a new redirection handler
-    pub fn new(router: Router) -> Self {
-        Self { router }
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// `server_name[:port]` of the server answering, the port left out when it is 80
+    fn authority(ctx: &RequestContext) -> String {
+        match ctx.server_port {
+            80 => ctx.server_config.server_name.clone(),
+            port => format!("{}:{}", ctx.server_config.server_name, port),
+        }
+    }
+
+    /// Whether a route's `redirect_type` asks for 301 rather than the default 302
+    fn is_permanent(redirect_type: Option<&str>) -> bool {
+        matches!(redirect_type, Some("permanent" | "301"))
     }
 }
 
 impl RequestHandler for RedirectionHandler {
-    fn handle(&self, request: &Request) -> Result<Response> {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
         crate::common::logger::Logger::info(&format!(
             "🔀 RedirectionHandler.handle() called for path='{}'",
             request.path()
         ));
 
         // Validate route and method - this will match the route again
-        let (route, error_response) = self.router.validate_request(request)?;
+        let (route, error_response) = ctx.router.validate_request(request)?;
         if let Some(response) = error_response {
             crate::common::logger::Logger::warn(
                 "RedirectionHandler: validate_request returned error response",
@@ -33,7 +45,7 @@ impl RequestHandler for RedirectionHandler {
         }
 
         // Get the matched route path for logging BEFORE accessing redirect
-        let matched_path = self
+        let matched_path = ctx
             .router
             .match_route_with_path(request)
             .map(|(path, route_config)| (path.as_str(), route_config.redirect.as_ref()))
@@ -72,8 +84,7 @@ impl RequestHandler for RedirectionHandler {
         }
 
         // Determine redirect type: 301 (permanent) or 302 (temporary, default)
-        let redirect_type = route.redirect_type.as_deref().unwrap_or("302");
-        let mut response = if redirect_type == "301" {
+        let mut response = if Self::is_permanent(route.redirect_type.as_deref()) {
             Response::moved_permanently(request.version)
         } else {
             Response::found(request.version)
@@ -98,13 +109,22 @@ impl RequestHandler for RedirectionHandler {
                 format!("{}{}", base_path, redirect_target)
             };
 
+        // Optionally turn a path-absolute Location into an absolute URI for old clients.
+        // The authority is the server's own name and port, never the client's Host header,
+        // so a request can't point the redirect at another origin.
+        let location = if route.absolute_redirect && location.starts_with('/') {
+            format!("http://{}{}", Self::authority(ctx), location)
+        } else {
+            location
+        };
+
         // Log redirect information for debugging
         crate::common::logger::Logger::info(&format!(
-            "🎯 FINAL REDIRECT: {} {} -> {} (type: {})",
+            "🎯 FINAL REDIRECT: {} {} -> {} (status: {})",
             request.method,
             request.path(),
             location,
-            redirect_type
+            response.status
         ));
 
         response.set_location(&location);
@@ -114,3 +134,112 @@ impl RequestHandler for RedirectionHandler {
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::config::models::{RouteConfig, ServerConfig};
+    use crate::application::handler::router::Router;
+    use crate::http::method::Method;
+    use crate::http::status::StatusCode;
+    use crate::http::version::Version;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn config_with_redirect(absolute_redirect: bool) -> ServerConfig {
+        config_with_redirect_type(absolute_redirect, None)
+    }
+
+    fn config_with_redirect_type(
+        absolute_redirect: bool,
+        redirect_type: Option<&str>,
+    ) -> ServerConfig {
+        let mut routes = HashMap::new();
+        routes.insert(
+            "/old".to_string(),
+            RouteConfig {
+                methods: vec!["GET".to_string()],
+                redirect: Some("/new".to_string()),
+                redirect_type: redirect_type.map(str::to_string),
+                absolute_redirect,
+                ..Default::default()
+            },
+        );
+        ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: ".".to_string(),
+            routes,
+            ..Default::default()
+        }
+    }
+
+    fn redirect(absolute_redirect: bool, request: &Request) -> Response {
+        let config = config_with_redirect(absolute_redirect);
+        let router = Router::new(&config, PathBuf::from("."));
+        let ctx = RequestContext::new(&router, &config, 8080);
+        RedirectionHandler::new().handle(request, &ctx).unwrap()
+    }
+
+    fn get_old() -> Request {
+        let mut request = Request::new(Method::GET, "/old".to_string(), Version::Http11);
+        request
+            .headers
+            .set("Host".to_string(), "test:8080".to_string());
+        request
+    }
+
+    #[test]
+    fn test_redirect_location_is_path_absolute_by_default() {
+        let response = redirect(false, &get_old());
+        assert_eq!(response.headers.get("Location"), Some(&"/new".to_string()));
+    }
+
+    #[test]
+    fn test_absolute_redirect_uses_server_name_and_port() {
+        let response = redirect(true, &get_old());
+        assert_eq!(
+            response.headers.get("Location"),
+            Some(&"http://test:8080/new".to_string())
+        );
+
+        let request = Request::new(Method::GET, "/old".to_string(), Version::Http11);
+        let response = redirect(true, &request);
+        assert_eq!(
+            response.headers.get("Location"),
+            Some(&"http://test:8080/new".to_string())
+        );
+    }
+
+    #[test]
+    fn test_absolute_redirect_ignores_a_spoofed_host() {
+        let mut request = get_old();
+        request
+            .headers
+            .set("Host".to_string(), "evil.example".to_string());
+        let response = redirect(true, &request);
+        assert_eq!(
+            response.headers.get("Location"),
+            Some(&"http://test:8080/new".to_string())
+        );
+    }
+
+    #[test]
+    fn test_redirect_type_selects_301_or_302() {
+        for (redirect_type, status) in [
+            (None, StatusCode::FOUND),
+            (Some("temporary"), StatusCode::FOUND),
+            (Some("302"), StatusCode::FOUND),
+            (Some("permanent"), StatusCode::MOVED_PERMANENTLY),
+            (Some("301"), StatusCode::MOVED_PERMANENTLY),
+        ] {
+            let config = config_with_redirect_type(false, redirect_type);
+            let router = Router::new(&config, PathBuf::from("."));
+            let ctx = RequestContext::new(&router, &config, 8080);
+            let response = RedirectionHandler::new().handle(&get_old(), &ctx).unwrap();
+            assert_eq!(response.status, status, "{:?}", redirect_type);
+            assert_eq!(response.headers.get("Location"), Some(&"/new".to_string()));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::application::config::models::ServerConfig;
+use crate::application::handler::router::Router;
+use crate::application::handler::session_manager::{Session, SessionManager};
+use std::net::SocketAddr;
+
+/// Per-request data shared with every handler: routing, the serving virtual host,
+/// the connection it arrived on and the client's session
+pub struct RequestContext<'a> {
+    /// Routes of the server handling the request
+    pub router: &'a Router,
+    /// Configuration of the server handling the request
+    pub server_config: &'a ServerConfig,
+    /// Local port the request arrived on
+    pub server_port: u16,
+    /// Remote peer address, when known
+    pub client_addr: Option<SocketAddr>,
+    /// Session of the requesting client, when sessions are in use
+    pub session: Option<SessionHandle<'a>>,
+}
+
+impl<'a> RequestContext<'a> {
+    /// Context without client address or session (e.g. for internal requests and tests)
+    pub fn new(router: &'a Router, server_config: &'a ServerConfig, server_port: u16) -> Self {
+        Self {
+            router,
+            server_config,
+            server_port,
+            client_addr: None,
+            session: None,
+        }
+    }
+
+    /// Attach the remote peer address
+    pub fn with_client_addr(mut self, client_addr: SocketAddr) -> Self {
+        self.client_addr = Some(client_addr);
+        self
+    }
+
+    /// Attach the client's session
+    pub fn with_session(mut self, session: SessionHandle<'a>) -> Self {
+        self.session = Some(session);
+        self
+    }
+}
+
+/// A session id bound to the manager that stores it
+pub struct SessionHandle<'a> {
+    manager: &'a SessionManager,
+    id: String,
+}
+
+impl<'a> SessionHandle<'a> {
+    pub fn new(manager: &'a SessionManager, id: String) -> Self {
+        Self { manager, id }
+    }
+
+    /// Session ID (the value of the session cookie)
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Snapshot of the session data
+    pub fn get(&self) -> Option<Session> {
+        self.manager.get_session(&self.id)
+    }
+
+    /// Store a value in the session
+    pub fn set(&self, key: String, value: String) -> Result<(), String> {
+        self.manager.update_session(&self.id, key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::config::models::RouteConfig;
+    use crate::application::handler::delete_handler::DeleteHandler;
+    use crate::application::handler::directory_listing_handler::DirectoryListingHandler;
+    use crate::application::handler::request_handler::RequestHandler;
+    use crate::application::handler::static_file_handler::StaticFileHandler;
+    use crate::http::method::Method;
+    use crate::http::request::Request;
+    use crate::http::status::StatusCode;
+    use crate::http::version::Version;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn server_config(root: &Path) -> ServerConfig {
+        let mut routes = HashMap::new();
+        routes.insert(
+            "/files".to_string(),
+            RouteConfig {
+                methods: vec!["GET".to_string(), "DELETE".to_string()],
+                directory: Some(root.to_string_lossy().to_string()),
+                directory_listing: true,
+                ..Default::default()
+            },
+        );
+        ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: root.to_string_lossy().to_string(),
+            routes,
+            ..Default::default()
+        }
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("localhost_ctx_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("hello.txt"), b"hello").unwrap();
+        fs::canonicalize(&dir).unwrap()
+    }
+
+    fn request(method: Method, target: &str) -> Request {
+        Request::new(method, target.to_string(), Version::Http11)
+    }
+
+    #[test]
+    fn test_handlers_serve_through_context() {
+        let root = temp_root("handlers");
+        let config = server_config(&root);
+        let router = Router::new(&config, root.clone());
+        let ctx = RequestContext::new(&router, &config, 8080);
+
+        let response = StaticFileHandler::new()
+            .handle(&request(Method::GET, "/files/hello.txt"), &ctx)
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, b"hello");
+
+        let response = DirectoryListingHandler::new()
+            .handle(&request(Method::GET, "/files/"), &ctx)
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(String::from_utf8_lossy(&response.body).contains("hello.txt"));
+
+        let response = DeleteHandler::new()
+            .handle(&request(Method::DELETE, "/files/hello.txt"), &ctx)
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(!root.join("hello.txt").exists());
+
+        // Missing files surface as errors so the server can render its error page
+        assert!(StaticFileHandler::new()
+            .handle(&request(Method::GET, "/files/hello.txt"), &ctx)
+            .is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_context_carries_client_and_session() {
+        let root = temp_root("session");
+        let config = server_config(&root);
+        let router = Router::new(&config, root.clone());
+        let sessions = SessionManager::new(60);
+        let id = sessions.create_session();
+
+        let ctx = RequestContext::new(&router, &config, 8080)
+            .with_client_addr("10.0.0.7:51000".parse().unwrap())
+            .with_session(SessionHandle::new(&sessions, id.clone()));
+        assert_eq!(ctx.server_port, 8080);
+        assert_eq!(ctx.client_addr, Some("10.0.0.7:51000".parse().unwrap()));
+
+        let session = ctx.session.as_ref().unwrap();
+        assert_eq!(session.id(), id);
+        session
+            .set("user".to_string(), "alice".to_string())
+            .unwrap();
+        assert_eq!(
+            sessions.get_session(&id).unwrap().data.get("user"),
+            Some(&"alice".to_string())
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::application::handler::request_context::RequestContext;
 use crate::common::error::Result;
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -5,5 +6,5 @@ use crate::http::response::Response;
 /// Trait for handling HTTP requests
 pub trait RequestHandler {
     /// Handle an HTTP request and return a response
-    fn handle(&self, request: &Request) -> Result<Response>;
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response>;
 }
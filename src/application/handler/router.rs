@@ -1,37 +1,62 @@
 use crate::application::config::models::{RouteConfig, ServerConfig};
+use crate::application::handler::path_resolver::PathResolver;
+use crate::common::constants::SUPPORTED_METHODS;
 use crate::common::error::{Result, ServerError};
+use crate::http::headers::names as header_names;
 use crate::http::request::Request;
 use crate::http::response::Response;
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Router matches requests to routes and determines the appropriate handler
+///
+/// Routes are compiled once (sorted longest-first) and shared, so cloning a
+/// router is cheap and it can be built per server instance instead of per request.
+#[derive(Clone)]
 pub struct Router {
-    routes: HashMap<String, RouteConfig>,
-    root_path: PathBuf,
+    routes: Arc<Vec<(String, RouteConfig)>>,
+    resolver: PathResolver,
+    /// OPTIONS is allowed on every route
+    auto_options: bool,
+    /// HEAD is allowed wherever GET is
+    auto_head: bool,
+    /// The server refuses mutating methods, so none are advertised
+    read_only: bool,
 }
 
 impl Router {
     /// Create a new router from server configuration
     pub fn new(config: &ServerConfig, root_path: PathBuf) -> Self {
+        let mut routes: Vec<(String, RouteConfig)> = config
+            .routes
+            .iter()
+            .map(|(path, route)| (path.clone(), route.clone()))
+            .collect();
+        // Longest route first, so the first match is the longest prefix match
+        routes.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
         Self {
-            routes: config.routes.clone(),
-            root_path,
+            routes: Arc::new(routes),
+            resolver: PathResolver::new(root_path),
+            auto_options: config.auto_options,
+            auto_head: config.auto_head,
+            read_only: config.read_only,
         }
     }
 
-    /// Resolve path - if absolute use as-is, if relative (./) resolve relative to root_path, otherwise join with root
+    /// Check whether two routers share the same compiled route table
+    pub fn shares_routes_with(&self, other: &Router) -> bool {
+        Arc::ptr_eq(&self.routes, &other.routes)
+    }
+
+    /// Path resolver for this server's root
+    pub fn resolver(&self) -> &PathResolver {
+        &self.resolver
+    }
+
+    /// Resolve a configured path against the server root (see [`PathResolver::resolve_config_path`])
     pub fn resolve_path(&self, path: &str) -> PathBuf {
-        if path.starts_with('/') {
-            PathBuf::from(path)
-        } else if path == "." {
-            // "." means root directory
-            self.root_path.clone()
-        } else if let Some(relative) = path.strip_prefix("./") {
-            self.root_path.join(relative)
-        } else {
-            self.root_path.join(path)
-        }
+        self.resolver.resolve_config_path(path)
     }
 
     /// Match a request to a route and return the route configuration
@@ -44,59 +69,88 @@ impl Router {
     pub fn match_route_with_path(&self, request: &Request) -> Option<(&String, &RouteConfig)> {
         let path = request.path();
 
-        // Try exact match first
-        for (route_path, route_config) in &self.routes {
-            if route_path == path {
-                return Some((route_path, route_config));
-            }
-        }
-
-        // Try longest prefix match
+        // Routes are sorted longest-first, so the first match is either an exact
+        // match or the longest prefix match.
         // A route matches if:
         // 1. Path exactly equals route path, OR
         // 2. Path starts with route path followed by '/' (for subdirectories/files)
         // Special case: "/" route matches everything
-        let mut best_match: Option<(&String, &RouteConfig)> = None;
-        for (route_path, route_config) in &self.routes {
-            let matches = if path == *route_path {
-                true
-            } else if route_path == "/" {
-                // Root route matches everything
-                path.starts_with("/")
-            } else if path.starts_with(route_path) {
-                // For other routes, check if route path is followed by '/' or is at the end
-                // This prevents "/upload" from matching "/uploads/filename"
-                let remaining = &path[route_path.len()..];
-                remaining.is_empty() || remaining.starts_with('/')
-            } else {
-                false
-            };
-
-            if matches {
-                if let Some((best_path, _)) = &best_match {
-                    if route_path.len() > best_path.len() {
-                        best_match = Some((route_path, route_config));
-                    }
+        self.routes
+            .iter()
+            .find(|(route_path, _)| {
+                if path == *route_path {
+                    true
+                } else if route_path == "/" {
+                    // Root route matches everything
+                    path.starts_with('/')
+                } else if path.starts_with(route_path.as_str()) {
+                    // For other routes, check if route path is followed by '/' or is at the end
+                    // This prevents "/upload" from matching "/uploads/filename"
+                    let remaining = &path[route_path.len()..];
+                    remaining.is_empty() || remaining.starts_with('/')
                 } else {
-                    best_match = Some((route_path, route_config));
+                    false
                 }
-            }
-        }
-
-        best_match
+            })
+            .map(|(route_path, route_config)| (route_path, route_config))
     }
 
     /// Check if method is allowed for the route
     pub fn is_method_allowed(&self, request: &Request, route: &RouteConfig) -> bool {
+        self.route_allows(route, &request.method.to_string())
+    }
+
+    /// Whether the route accepts the method, counting the implicit OPTIONS and HEAD
+    fn route_allows(&self, route: &RouteConfig, method: &str) -> bool {
         if route.methods.is_empty() {
             return true; // No restrictions
         }
 
-        let method_str = request.method.to_string();
-        route
-            .methods
+        let listed = |name: &str| route.methods.iter().any(|m| m.eq_ignore_ascii_case(name));
+        listed(method)
+            || (self.auto_options && method == "OPTIONS")
+            || (self.auto_head && method == "HEAD" && listed("GET"))
+    }
+
+    /// Methods the route accepts, in canonical order; every supported method when the
+    /// route has no restrictions. A read-only server accepts only the safe ones
+    pub fn allowed_methods(&self, route: &RouteConfig) -> Vec<&'static str> {
+        SUPPORTED_METHODS
+            .iter()
+            .copied()
+            .filter(|method| self.route_allows(route, method))
+            .filter(|method| !(self.read_only && Self::is_mutating(method)))
+            .collect()
+    }
+
+    fn is_mutating(method: &str) -> bool {
+        method
+            .parse::<crate::http::method::Method>()
+            .is_ok_and(|method| method.is_mutating())
+    }
+
+    /// Methods accepted by at least one route, for `OPTIONS *`
+    pub fn all_allowed_methods(&self) -> Vec<&'static str> {
+        SUPPORTED_METHODS
             .iter()
-            .any(|m| m.eq_ignore_ascii_case(&method_str))
+            .copied()
+            .filter(|method| {
+                self.routes
+                    .iter()
+                    .any(|(_, route)| self.allowed_methods(route).contains(method))
+            })
+            .collect()
+    }
+
+    /// 405 response for the route, with the `Allow` header naming what it accepts
+    pub fn method_not_allowed(&self, request: &Request, route: &RouteConfig) -> Response {
+        let mut response =
+            Response::method_not_allowed_with_message(request.version, "Method Not Allowed");
+        response.headers.set(
+            header_names::ALLOW.to_string(),
+            self.allowed_methods(route).join(", "),
+        );
+        response
     }
 
     /// Validate route and method, return error response if invalid
@@ -106,13 +160,7 @@ impl Router {
             .ok_or_else(|| ServerError::HttpError("No matching route".to_string()))?;
 
         if !self.is_method_allowed(request, route) {
-            return Ok((
-                route,
-                Some(Response::method_not_allowed_with_message(
-                    request.version,
-                    "Method Not Allowed",
-                )),
-            ));
+            return Ok((route, Some(self.method_not_allowed(request, route))));
         }
 
         Ok((route, None))
@@ -129,10 +177,9 @@ impl Router {
 
         // If route has directory, map path to directory
         if let Some(ref directory) = route.directory {
+            // The prefix to strip is the matched route's, not just any route prefixing the path
             let route_path = self
-                .routes
-                .iter()
-                .find(|(p, _)| path.starts_with(*p))
+                .match_route_with_path(request)
                 .map(|(p, _)| p.as_str())
                 .unwrap_or("/");
 
@@ -143,62 +190,39 @@ impl Router {
                 ""
             };
 
+            // An empty remainder maps to the directory itself, not default_file;
+            // the server manager decides between a listing and the default file
             let dir_path = self.resolve_path(directory);
+            return self.resolver.resolve_request_path(&dir_path, relative_path);
+        }
 
-            let file_path = if relative_path.is_empty() {
-                // Always return the directory path, not default_file
-                // The server manager will decide whether to show directory listing or serve default_file
-                dir_path
-            } else {
-                // Sanitize path to prevent directory traversal
-                let sanitized = self.sanitize_path(relative_path)?;
-                dir_path.join(sanitized)
-            };
+        // Default: map to root directory. The parser already decoded the path, so an
+        // encoded `..` is still caught when the path is joined.
+        self.resolver
+            .resolve_request_path(self.resolver.root(), path)
+    }
 
+    /// Resolve a request to a validated path inside its base directory (the route
+    /// directory, or the server root). Parent directories are canonicalized and the
+    /// final target must not escape the base through `..` or symlinks.
+    /// Files configured explicitly via `filename` are trusted and returned as-is.
+    pub fn resolve_within_root(&self, request: &Request, route: &RouteConfig) -> Result<PathBuf> {
+        let file_path = self.resolve_file_path(request, route)?;
+        if route.filename.is_some() {
             return Ok(file_path);
         }
 
-        // Default: map to root directory
-        let relative_path = if path == "/" {
-            ""
-        } else {
-            &path[1..] // Remove leading /
-        };
-
-        if relative_path.is_empty() {
-            // Always return the root directory path, not default_file
-            // The server manager will decide whether to show directory listing or serve default_file
-            Ok(self.root_path.clone())
-        } else {
-            let sanitized = self.sanitize_path(relative_path)?;
-            Ok(self.root_path.join(sanitized))
-        }
+        self.resolver
+            .confine(&file_path, &self.confinement_base(route))
     }
 
-    /// Sanitize path to prevent directory traversal attacks
-    fn sanitize_path(&self, path: &str) -> Result<String> {
-        let path = Path::new(path);
-
-        // Check for directory traversal attempts
-        for component in path.components() {
-            if let std::path::Component::ParentDir = component {
-                return Err(ServerError::HttpError(
-                    "Path contains '..' - directory traversal attempt".to_string(),
-                ));
-            }
+    /// Directory a route's request paths must stay inside
+    fn confinement_base(&self, route: &RouteConfig) -> PathBuf {
+        // The root is canonicalized once by ServerInstance; route directories resolve against it
+        match route.directory {
+            Some(ref directory) => self.resolver.canonical_dir(directory),
+            None => self.resolver.root().to_path_buf(),
         }
-
-        // Normalize path
-        let normalized = path
-            .components()
-            .filter_map(|c| match c {
-                std::path::Component::Normal(s) => s.to_str(),
-                _ => None,
-            })
-            .collect::<Vec<_>>()
-            .join("/");
-
-        Ok(normalized)
     }
 
     /// Get default file for a directory route
@@ -211,6 +235,90 @@ impl Router {
         route.directory_listing
     }
 
+    /// Check if matching CGI files should be executed for route (defaults to true)
+    pub fn is_cgi_enabled(&self, route: &RouteConfig) -> bool {
+        route.cgi_enabled.unwrap_or(true)
+    }
+
+    /// Pick the `name.<lang>.ext` variant of a file matching the client's
+    /// Accept-Language, falling back to the route's default language.
+    /// Returns the original path and no language when the route doesn't negotiate
+    /// or no variant exists.
+    pub fn select_language_variant(
+        &self,
+        path: &Path,
+        route: &RouteConfig,
+        request: &Request,
+    ) -> (PathBuf, Option<String>) {
+        let default_language = match route.default_language.as_ref() {
+            Some(lang) => lang.to_ascii_lowercase(),
+            None => return (path.to_path_buf(), None),
+        };
+        let stem = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(stem) => stem,
+            None => return (path.to_path_buf(), None),
+        };
+        let ext = path.extension().and_then(|e| e.to_str());
+
+        // Try each requested language, then its primary subtag (fr-ca -> fr), then the fallback
+        let mut candidates = Vec::new();
+        for lang in request.accept_languages() {
+            if let Some((primary, _)) = lang.split_once('-') {
+                let primary = primary.to_string();
+                candidates.push(lang);
+                candidates.push(primary);
+            } else {
+                candidates.push(lang);
+            }
+        }
+        candidates.push(default_language);
+
+        // A variant must stay where the file itself was allowed to be; configured
+        // `filename`s are trusted, so theirs only has to stay next to them
+        let base = if route.filename.is_some() {
+            match path.parent().map(std::fs::canonicalize) {
+                Some(Ok(parent)) => parent,
+                _ => return (path.to_path_buf(), None),
+            }
+        } else {
+            self.confinement_base(route)
+        };
+
+        for lang in candidates {
+            let name = match ext {
+                Some(ext) => format!("{}.{}.{}", stem, lang, ext),
+                None => format!("{}.{}", stem, lang),
+            };
+            let variant = match self.resolver.confine(&path.with_file_name(name), &base) {
+                Ok(variant) => variant,
+                Err(_) => continue,
+            };
+            if crate::common::path_utils::is_valid_file(&variant) {
+                return (variant, Some(lang));
+            }
+        }
+
+        (path.to_path_buf(), None)
+    }
+
+    /// Locate the route's default file inside `dir`, honouring language variants.
+    /// Returns `None` when the route has no default file or it doesn't exist.
+    pub fn directory_index(
+        &self,
+        dir: &Path,
+        route: &RouteConfig,
+        request: &Request,
+    ) -> Option<(PathBuf, Option<String>)> {
+        let default_file = self.get_default_file(route)?;
+        let (path, language) =
+            self.select_language_variant(&dir.join(default_file), route, request);
+        if crate::common::path_utils::is_valid_file(&path) {
+            Some((path, language))
+        } else {
+            None
+        }
+    }
+
     /// Get redirect target for route
     pub fn get_redirect<'a>(&self, route: &'a RouteConfig) -> Option<&'a String> {
         route.redirect.as_ref()
@@ -223,6 +331,7 @@ mod tests {
     use crate::application::config::models::ServerConfig;
     use crate::http::method::Method;
     use crate::http::version::Version;
+    use std::collections::HashMap;
 
     fn empty_server() -> ServerConfig {
         ServerConfig {
@@ -230,10 +339,7 @@ mod tests {
             ports: vec![8080],
             server_name: "test".to_string(),
             root: ".".to_string(),
-            admin_access: false,
-            routes: HashMap::new(),
-            errors: HashMap::new(),
-            cgi_handlers: HashMap::new(),
+            ..Default::default()
         }
     }
 
@@ -400,6 +506,62 @@ mod tests {
         assert!(router.is_method_allowed(&req(Method::GET, "/x"), &route));
     }
 
+    #[test]
+    fn test_allowed_methods_per_route_and_across_routes() {
+        let mut config = empty_server();
+        config
+            .routes
+            .insert("/".to_string(), route_with(&["get", "OPTIONS"], Some(".")));
+        config.routes.insert(
+            "/upload".to_string(),
+            route_with(&["POST", "GET"], Some("uploads")),
+        );
+        let router = Router::new(&config, std::env::current_dir().unwrap());
+
+        assert_eq!(
+            router.allowed_methods(&config.routes["/"]),
+            vec!["GET", "OPTIONS"]
+        );
+        assert_eq!(
+            router.allowed_methods(&RouteConfig::default()),
+            SUPPORTED_METHODS.to_vec()
+        );
+        assert_eq!(router.all_allowed_methods(), vec!["GET", "POST", "OPTIONS"]);
+    }
+
+    #[test]
+    fn test_auto_modes_allow_options_everywhere_and_head_with_get() {
+        let mut config = empty_server();
+        config
+            .routes
+            .insert("/".to_string(), route_with(&["GET"], Some(".")));
+        config.routes.insert(
+            "/upload".to_string(),
+            route_with(&["POST"], Some("uploads")),
+        );
+        let router = Router::new(&config, std::env::current_dir().unwrap());
+        let root = &config.routes["/"];
+        assert!(!router.is_method_allowed(&req(Method::HEAD, "/"), root));
+        assert!(!router.is_method_allowed(&req(Method::OPTIONS, "/"), root));
+
+        config.auto_options = true;
+        config.auto_head = true;
+        let router = Router::new(&config, std::env::current_dir().unwrap());
+        let (root, upload) = (&config.routes["/"], &config.routes["/upload"]);
+        assert!(router.is_method_allowed(&req(Method::HEAD, "/"), root));
+        assert!(router.is_method_allowed(&req(Method::OPTIONS, "/"), root));
+        assert!(!router.is_method_allowed(&req(Method::HEAD, "/upload"), upload));
+        assert!(router.is_method_allowed(&req(Method::OPTIONS, "/upload"), upload));
+        assert_eq!(router.allowed_methods(root), vec!["GET", "HEAD", "OPTIONS"]);
+        assert_eq!(router.allowed_methods(upload), vec!["POST", "OPTIONS"]);
+
+        // A read-only server advertises only the safe methods
+        config.read_only = true;
+        let router = Router::new(&config, std::env::current_dir().unwrap());
+        assert_eq!(router.allowed_methods(upload), vec!["OPTIONS"]);
+        assert_eq!(router.all_allowed_methods(), vec!["GET", "HEAD", "OPTIONS"]);
+    }
+
     #[test]
     fn test_validate_request_returns_405_for_wrong_method() {
         let mut config = empty_server();
@@ -413,6 +575,13 @@ mod tests {
             .expect("route exists");
         let response = response.expect("expected 405 response");
         assert_eq!(response.status.as_u16(), 405);
+        assert_eq!(response.headers.get("Allow"), Some(&"GET".to_string()));
+
+        let (_, response) = router
+            .validate_request(&req(Method::POST, "/only-get"))
+            .expect("route exists");
+        let response = response.expect("expected 405 response");
+        assert_eq!(response.headers.get("Allow"), Some(&"GET".to_string()));
     }
 
     #[test]
@@ -443,4 +612,274 @@ mod tests {
             "directory traversal via '..' must be rejected"
         );
     }
+
+    // -----------------------------------------------------------------------
+    // Compiled route table
+    // -----------------------------------------------------------------------
+
+    /// Reference implementation of the previous per-request HashMap scan
+    fn naive_match<'a>(routes: &'a HashMap<String, RouteConfig>, path: &str) -> Option<&'a str> {
+        routes
+            .keys()
+            .filter(|route_path| {
+                path == route_path.as_str()
+                    || route_path.as_str() == "/"
+                    || (path.starts_with(route_path.as_str())
+                        && path[route_path.len()..].starts_with('/'))
+            })
+            .max_by_key(|route_path| route_path.len())
+            .map(|route_path| route_path.as_str())
+    }
+
+    #[test]
+    fn test_compiled_routes_match_naive_lookup() {
+        let mut config = empty_server();
+        for path in ["/", "/api", "/api/v1", "/upload", "/uploads", "/a/b/c"] {
+            config
+                .routes
+                .insert(path.to_string(), route_with(&["GET"], Some(".")));
+        }
+        let router = Router::new(&config, std::env::current_dir().unwrap());
+
+        for target in [
+            "/",
+            "/api",
+            "/api/",
+            "/api/v1/users",
+            "/api/v2",
+            "/apix",
+            "/upload",
+            "/uploads/file.txt",
+            "/a/b",
+            "/a/b/c/d",
+        ] {
+            let matched = router
+                .match_route_with_path(&req(Method::GET, target))
+                .map(|(p, _)| p.as_str());
+            assert_eq!(
+                matched,
+                naive_match(&config.routes, target),
+                "path {}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_file_path_strips_the_matched_route_prefix() {
+        let mut config = empty_server();
+        config
+            .routes
+            .insert("/".to_string(), route_with(&["GET"], Some("site")));
+        config
+            .routes
+            .insert("/up".to_string(), route_with(&["GET"], Some("uploads")));
+        let router = Router::new(&config, PathBuf::from("/srv/www"));
+
+        // "/up" prefixes the path but doesn't match it, so "/" strips nothing
+        for (target, expected) in [
+            ("/upload.txt", "/srv/www/site/upload.txt"),
+            ("/up/a.txt", "/srv/www/uploads/a.txt"),
+        ] {
+            let request = req(Method::GET, target);
+            let route = router.match_route(&request).unwrap().clone();
+            assert_eq!(
+                router.resolve_file_path(&request, &route).unwrap(),
+                PathBuf::from(expected),
+                "target {}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_cloned_router_shares_compiled_routes() {
+        let (config, root) = create_test_config();
+        let router = Router::new(&config, root.clone());
+        let clone = router.clone();
+        assert!(clone.shares_routes_with(&router));
+
+        let rebuilt = Router::new(&config, root);
+        assert!(!rebuilt.shares_routes_with(&router));
+    }
+
+    #[test]
+    fn test_resolve_file_path_uses_the_decoded_path() {
+        let mut config = empty_server();
+        config
+            .routes
+            .insert("/".to_string(), route_with(&["GET"], None));
+        config
+            .routes
+            .insert("/static".to_string(), route_with(&["GET"], Some("static")));
+        let router = Router::new(&config, PathBuf::from("/srv/www"));
+
+        for (target, expected) in [
+            ("/my%20file.txt", "/srv/www/my file.txt"),
+            ("/docs%2Fguide.html", "/srv/www/docs/guide.html"),
+            ("/static/a%2fb.css?v=%20", "/srv/www/static/a/b.css"),
+        ] {
+            let mut request = req(Method::GET, target);
+            request.decode_path();
+            let route = router.match_route(&request).unwrap().clone();
+            assert_eq!(
+                router.resolve_file_path(&request, &route).unwrap(),
+                PathBuf::from(expected),
+                "target {}",
+                target
+            );
+        }
+
+        for target in [
+            "/%2e%2e%2fetc/passwd",
+            "/static/%2E%2E/%2e%2e/etc/passwd",
+            "/a%00.txt",
+        ] {
+            let mut request = req(Method::GET, target);
+            request.decode_path();
+            let route = router.match_route(&request).unwrap().clone();
+            assert!(
+                router.resolve_file_path(&request, &route).is_err(),
+                "target {}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_file_path_never_goes_absolute() {
+        let mut config = empty_server();
+        config
+            .routes
+            .insert("/".to_string(), route_with(&["GET"], None));
+        config
+            .routes
+            .insert("/static".to_string(), route_with(&["GET"], Some("static")));
+        let router = Router::new(&config, PathBuf::from("/srv/www"));
+
+        for (target, expected) in [
+            ("//etc/passwd", "/srv/www/etc/passwd"),
+            ("/static//etc/passwd", "/srv/www/static/etc/passwd"),
+            ("/static/\\etc\\passwd", "/srv/www/static/etc/passwd"),
+        ] {
+            let request = req(Method::GET, target);
+            let route = router.match_route(&request).unwrap().clone();
+            assert_eq!(
+                router.resolve_file_path(&request, &route).unwrap(),
+                PathBuf::from(expected),
+                "target {}",
+                target
+            );
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Centralized in-root resolution
+    // -----------------------------------------------------------------------
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("localhost_router_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("www/sub")).unwrap();
+        std::fs::write(dir.join("www/sub/page.html"), "ok").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+        std::fs::canonicalize(&dir).unwrap()
+    }
+
+    fn root_router(root: &Path) -> Router {
+        let mut config = empty_server();
+        config
+            .routes
+            .insert("/".to_string(), route_with(&["GET"], None));
+        Router::new(&config, root.to_path_buf())
+    }
+
+    fn resolve(router: &Router, target: &str) -> Result<PathBuf> {
+        let request = req(Method::GET, target);
+        let route = router.match_route(&request).unwrap().clone();
+        router.resolve_within_root(&request, &route)
+    }
+
+    #[test]
+    fn test_resolve_within_root_normal_paths() {
+        let dir = temp_root("normal");
+        let root = dir.join("www");
+        let router = root_router(&root);
+
+        assert_eq!(
+            resolve(&router, "/sub/page.html").unwrap(),
+            root.join("sub/page.html")
+        );
+        assert_eq!(resolve(&router, "/").unwrap(), root);
+        // Missing files still resolve (the handler decides on 404)
+        assert_eq!(
+            resolve(&router, "/sub/missing.html").unwrap(),
+            root.join("sub/missing.html")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_within_root_rejects_traversal() {
+        let dir = temp_root("traversal");
+        let router = root_router(&dir.join("www"));
+
+        assert!(resolve(&router, "/../secret.txt").is_err());
+        assert!(resolve(&router, "/sub/../../secret.txt").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_within_root_rejects_symlink_escape() {
+        let dir = temp_root("symlink");
+        let root = dir.join("www");
+        std::os::unix::fs::symlink(dir.join("secret.txt"), root.join("leak.txt")).unwrap();
+        std::os::unix::fs::symlink(&dir, root.join("up")).unwrap();
+        std::os::unix::fs::symlink(root.join("sub/page.html"), root.join("alias.html")).unwrap();
+        let router = root_router(&root);
+
+        assert!(resolve(&router, "/leak.txt").is_err());
+        assert!(resolve(&router, "/up/secret.txt").is_err());
+        // Symlinks that stay inside the root are fine and keep their own name
+        assert_eq!(
+            resolve(&router, "/alias.html").unwrap(),
+            root.join("alias.html")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_language_variant_cannot_leave_the_root() {
+        let dir = temp_root("language");
+        let root = dir.join("www");
+        std::fs::create_dir_all(root.join("v1.0")).unwrap();
+        std::fs::write(root.join("v1.en"), "en").unwrap();
+        let mut route = route_with(&["GET"], None);
+        route.default_language = Some("en".to_string());
+        let mut config = empty_server();
+        config.routes.insert("/".to_string(), route.clone());
+        let router = Router::new(&config, root.clone());
+
+        let mut request = req(Method::GET, "/v1");
+        request.headers.set(
+            "Accept-Language".to_string(),
+            "0/../../secret.txt".to_string(),
+        );
+        let path = router.resolve_within_root(&request, &route).unwrap();
+        assert_eq!(
+            router.select_language_variant(&path, &route, &request),
+            (root.join("v1.en"), Some("en".to_string()))
+        );
+
+        // Even a configured fallback is held to the root
+        route.default_language = Some("0/../../secret.txt".to_string());
+        request.headers.remove("Accept-Language");
+        assert_eq!(
+            router.select_language_variant(&path, &route, &request),
+            (path.clone(), None)
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
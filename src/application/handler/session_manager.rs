@@ -1,8 +1,13 @@
+use crate::common::constants::SESSION_ID_BYTES;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
-use std::sync::{Arc, RwLock};
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+/// Sequence number keeping fallback session IDs unique within the process
+static SESSION_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 /// Session data storage
 ///
 /// Stores arbitrary key-value pairs for session data
@@ -107,23 +112,53 @@ impl SessionManager {
         }
     }
 
-    /// Generate a unique session ID
+    /// Shared access to the sessions. A handler that panicked while holding the lock
+    /// only fails its own request, so the map is used as it was left.
+    fn read_sessions(&self) -> RwLockReadGuard<'_, HashMap<String, Session>> {
+        self.sessions
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Exclusive access to the sessions, recovering from a poisoned lock like `read_sessions`
+    fn write_sessions(&self) -> RwLockWriteGuard<'_, HashMap<String, Session>> {
+        self.sessions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Generate a unique, unguessable session ID: 128 random bits as 32 hex digits
     fn generate_session_id() -> String {
-        use std::collections::hash_map::DefaultHasher;
+        let mut bytes = [0u8; SESSION_ID_BYTES];
+        let from_os = std::fs::File::open("/dev/urandom")
+            .and_then(|mut urandom| urandom.read_exact(&mut bytes))
+            .is_ok();
+        if !from_os {
+            crate::common::logger::Logger::warn(
+                "/dev/urandom unavailable, deriving session ID from counter, pid and time",
+            );
+            bytes = Self::fallback_id_bytes();
+        }
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
 
-        let timestamp = SystemTime::now()
+    /// Unique (though not unpredictable) bytes for when the OS has no random source
+    fn fallback_id_bytes() -> [u8; SESSION_ID_BYTES] {
+        use std::collections::hash_map::RandomState;
+        use std::hash::BuildHasher;
+
+        let count = SESSION_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs();
-
-        // Generate random component
-        let mut hasher = DefaultHasher::new();
-        timestamp.hash(&mut hasher);
-        let random: u64 = std::ptr::addr_of!(hasher) as usize as u64;
-        random.hash(&mut hasher);
-
-        let hash = hasher.finish();
-        format!("{:x}_{:x}", timestamp, hash)
+            .as_nanos();
+        // The counter alone keeps IDs from this process distinct; the hash hides it
+        let mixed = RandomState::new().hash_one((count, std::process::id(), nanos));
+
+        let mut bytes = [0u8; SESSION_ID_BYTES];
+        bytes[..8].copy_from_slice(&mixed.to_be_bytes());
+        bytes[8..].copy_from_slice(&count.to_be_bytes());
+        bytes
     }
 
     /// Create a new session and return its ID
@@ -131,7 +166,7 @@ impl SessionManager {
         let session_id = Self::generate_session_id();
         let session = Session::new(session_id.clone(), self.timeout_secs);
 
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
         sessions.insert(session_id.clone(), session);
 
         session_id
@@ -144,7 +179,7 @@ impl SessionManager {
     pub fn get_or_create_session(&self, session_id: Option<&str>) -> Option<String> {
         if let Some(id) = session_id {
             // Try to get existing session
-            let sessions = self.sessions.read().unwrap();
+            let sessions = self.read_sessions();
             if let Some(session) = sessions.get(id) {
                 if !session.is_expired() {
                     // Session exists and is valid
@@ -162,7 +197,7 @@ impl SessionManager {
 
     /// Get session by ID (returns a clone of session data)
     pub fn get_session(&self, session_id: &str) -> Option<Session> {
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
 
         if let Some(session) = sessions.get_mut(session_id) {
             if session.is_expired() {
@@ -186,7 +221,7 @@ impl SessionManager {
         key: String,
         value: String,
     ) -> Result<(), String> {
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
 
         if let Some(session) = sessions.get_mut(session_id) {
             if session.is_expired() {
@@ -208,7 +243,7 @@ impl SessionManager {
         session_id: &str,
         key: &str,
     ) -> Result<Option<String>, String> {
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
 
         if let Some(session) = sessions.get_mut(session_id) {
             if session.is_expired() {
@@ -225,13 +260,13 @@ impl SessionManager {
 
     /// Delete a session
     pub fn delete_session(&self, session_id: &str) {
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
         sessions.remove(session_id);
     }
 
     /// Touch (update last access time) a session
     fn touch_session(&self, session_id: &str) {
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
         if let Some(session) = sessions.get_mut(session_id) {
             if !session.is_expired() {
                 session.touch(self.timeout_secs);
@@ -243,7 +278,7 @@ impl SessionManager {
 
     /// Clean up expired sessions
     pub fn cleanup_expired(&self) -> usize {
-        let mut sessions = self.sessions.write().unwrap();
+        let mut sessions = self.write_sessions();
         let initial_count = sessions.len();
 
         sessions.retain(|_, session| !session.is_expired());
@@ -251,6 +286,16 @@ impl SessionManager {
         initial_count - sessions.len()
     }
 
+    /// Release memory left over from removed sessions
+    pub fn compact(&self) {
+        self.write_sessions().shrink_to_fit();
+    }
+
+    /// Number of sessions currently stored (expired ones included until cleanup)
+    pub fn session_count(&self) -> usize {
+        self.read_sessions().len()
+    }
+
     /// Get cookie name for session ID
     pub fn cookie_name(&self) -> &str {
         &self.cookie_name
@@ -266,6 +311,25 @@ impl SessionManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sessions_survive_a_panic_while_locked() {
+        let manager = SessionManager::new(3600);
+        let session_id = manager.create_session();
+
+        let poisoned = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _sessions = manager.write_sessions();
+            panic!("handler failed mid-update");
+        }));
+        assert!(poisoned.is_err());
+        assert!(manager.sessions.is_poisoned());
+
+        assert!(manager.get_session(&session_id).is_some());
+        assert!(manager
+            .update_session(&session_id, "k".to_string(), "v".to_string())
+            .is_ok());
+        assert_eq!(manager.session_count(), 1);
+    }
+
     #[test]
     fn test_session_creation() {
         let manager = SessionManager::new(3600);
@@ -321,4 +385,20 @@ mod tests {
         let session_id2 = manager.get_or_create_session(Some(&session_id1)).unwrap();
         assert_eq!(session_id1, session_id2);
     }
+
+    #[test]
+    fn test_session_ids_are_unique_128_bit_hex() {
+        let ids: std::collections::HashSet<String> = (0..10_000)
+            .map(|_| SessionManager::generate_session_id())
+            .collect();
+        assert_eq!(ids.len(), 10_000);
+        assert!(ids
+            .iter()
+            .all(|id| id.len() >= 32 && id.chars().all(|c| c.is_ascii_hexdigit())));
+
+        let fallback: std::collections::HashSet<_> = (0..1000)
+            .map(|_| SessionManager::fallback_id_bytes())
+            .collect();
+        assert_eq!(fallback.len(), 1000);
+    }
 }
@@ -1,21 +1,24 @@
+use crate::application::handler::request_context::RequestContext;
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
+use crate::common::constants::COMPRESSION_MIN_SIZE;
 use crate::common::error::{Result, ServerError};
+use crate::http::compression;
+use crate::http::headers::names as header_names;
+use crate::http::range;
 use crate::http::request::Request;
 use crate::http::response::Response;
-use crate::http::version::Version;
+use crate::http::status::StatusCode;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Handler for serving static files
-pub struct StaticFileHandler {
-    router: Router,
-}
+#[derive(Default)]
+pub struct StaticFileHandler;
 
 impl StaticFileHandler {
     /// Create a new static file handler
-    pub fn new(router: Router) -> Self {
-        Self { router }
+    pub fn new() -> Self {
+        Self
     }
 
     /// Determine MIME type from file extension
@@ -32,7 +35,7 @@ impl StaticFileHandler {
                 "svg" => "image/svg+xml",
                 "ico" => "image/x-icon",
                 "pdf" => "application/pdf",
-                "txt" => "text/plain",
+                "txt" | "py" | "sh" => "text/plain",
                 "xml" => "application/xml",
                 _ => "application/octet-stream",
             }
@@ -43,15 +46,28 @@ impl StaticFileHandler {
 }
 
 impl RequestHandler for StaticFileHandler {
-    fn handle(&self, request: &Request) -> Result<Response> {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
         // Validate route and method
-        let (route, error_response) = self.router.validate_request(request)?;
+        let (route, error_response) = ctx.router.validate_request(request)?;
         if let Some(response) = error_response {
             return Ok(response);
         }
 
-        // Resolve file path
-        let file_path = self.router.resolve_file_path(request, route)?;
+        // Resolve file path, preferring a language variant when the route negotiates language
+        let file_path = ctx.router.resolve_within_root(request, route)?;
+        let (file_path, language) = ctx
+            .router
+            .select_language_variant(&file_path, route, request);
+
+        // An extensionless path without a file of its own may name variants (`page.html`,
+        // `page.json`); pick the one the Accept header prefers
+        let negotiated = if !file_path.exists() && file_path.extension().is_none() {
+            self.select_media_variant(&file_path, request)
+        } else {
+            None
+        };
+        let media_negotiated = negotiated.is_some();
+        let file_path = negotiated.unwrap_or(file_path);
 
         // Check if file exists
         if !file_path.exists() {
@@ -59,46 +75,550 @@ impl RequestHandler for StaticFileHandler {
         }
 
         // Check if it's a directory
-        if file_path.is_dir() {
-            // If directory listing is enabled, it should be handled by DirectoryListingHandler
-            // Don't serve default_file when directory_listing is enabled
-            if self.router.is_directory_listing_enabled(route) {
-                return Ok(Response::forbidden_with_message(
-                    request.version,
-                    "Forbidden",
-                ));
-            }
-
-            // Directory listing disabled, check for default file
-            if let Some(default_file) = self.router.get_default_file(route) {
-                let default_path = file_path.join(default_file);
-                if crate::common::path_utils::is_valid_file(&default_path) {
-                    return self.serve_file(&default_path, request.version);
+        let is_index = file_path.is_dir();
+        let (file_path, language) = if is_index {
+            // The default file takes precedence over a directory listing
+            match ctx.router.directory_index(&file_path, route, request) {
+                Some(index) => index,
+                None => {
+                    // Listings are served by DirectoryListingHandler, never from here
+                    return Ok(Response::forbidden_with_message(
+                        request.version,
+                        "Forbidden",
+                    ));
                 }
             }
+        } else {
+            (file_path, language)
+        };
 
-            // No default file and directory listing disabled - return 403
-            return Ok(Response::forbidden_with_message(
-                request.version,
-                "Forbidden",
-            ));
+        // Serve the file; the route's cache policy wins over the server's
+        let cache_control = route
+            .cache_control
+            .as_deref()
+            .or(ctx.server_config.static_cache_control.as_deref());
+        let compress_min_size = ctx
+            .server_config
+            .enable_gzip
+            .then_some(COMPRESSION_MIN_SIZE);
+        let mut response = self.serve_file(
+            &file_path,
+            request,
+            language.as_deref(),
+            cache_control,
+            compress_min_size,
+        )?;
+        if media_negotiated {
+            response.add_vary("Accept");
         }
-
-        // Serve the file
-        self.serve_file(&file_path, request.version)
+        // Name the variant actually served, so caches key it apart from its siblings
+        if (media_negotiated || language.is_some()) && response.status.is_success() {
+            if let Some(location) = Self::variant_location(request.path(), &file_path, is_index) {
+                response
+                    .headers
+                    .set(header_names::CONTENT_LOCATION.to_string(), location);
+            }
+        }
+        if route.sidecar_headers {
+            Self::apply_sidecar_headers(&file_path, &mut response);
+        }
+        Ok(response)
     }
 }
 
 impl StaticFileHandler {
-    /// Serve a file
-    fn serve_file(&self, path: &Path, version: Version) -> Result<Response> {
+    /// Among the `<path>.<ext>` files next to `path`, the one whose media type the client
+    /// accepts with the highest q-value. Without an Accept header, or when nothing is
+    /// acceptable, the first variant by name is used.
+    fn select_media_variant(&self, path: &Path, request: &Request) -> Option<PathBuf> {
+        let stem = path.file_name()?.to_str()?;
+        let prefix = format!("{}.", stem);
+        let mut variants: Vec<PathBuf> = fs::read_dir(path.parent()?)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|candidate| {
+                candidate
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_prefix(&prefix))
+                    .map(|ext| !ext.is_empty() && !ext.contains('.'))
+                    .unwrap_or(false)
+                    && candidate.is_file()
+            })
+            .collect();
+        variants.sort();
+
+        let ranges = request.accept_media_ranges();
+        let best = variants
+            .iter()
+            .map(|variant| {
+                (
+                    variant,
+                    Self::media_quality(&ranges, self.get_mime_type(variant)),
+                )
+            })
+            .filter(|(_, q)| *q > 0.0)
+            // max_by keeps the last of equal maxima, so compare in reverse to favour the first
+            .rev()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(variant, _)| variant.clone());
+        best.or_else(|| variants.into_iter().next())
+    }
+
+    /// URL of a negotiated variant: the request path with its last segment replaced by
+    /// the served file's name, or with the name appended when a directory was requested
+    fn variant_location(request_path: &str, served: &Path, is_index: bool) -> Option<String> {
+        let name = served.file_name()?.to_str()?;
+        let dir = if is_index {
+            request_path.trim_end_matches('/')
+        } else {
+            request_path.rsplit_once('/').map_or("", |(dir, _)| dir)
+        };
+        Some(format!("{}/{}", dir, name))
+    }
+
+    /// q-value of the most specific Accept range matching `mime` (exact, `type/*`, `*/*`);
+    /// everything is acceptable when the header is absent
+    fn media_quality(ranges: &[(String, f32)], mime: &str) -> f32 {
+        if ranges.is_empty() {
+            return 1.0;
+        }
+        let top_level = mime.split('/').next().unwrap_or("");
+        let wildcard = format!("{}/*", top_level);
+        [mime, wildcard.as_str(), "*/*"]
+            .iter()
+            .find_map(|pattern| {
+                ranges
+                    .iter()
+                    .find(|(range, _)| range == pattern)
+                    .map(|(_, q)| *q)
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Headers a sidecar may not set, since they'd break message framing
+    const SIDECAR_RESERVED_HEADERS: &'static [&'static str] =
+        &["content-length", "transfer-encoding", "connection"];
+
+    /// Apply `Key: Value` lines from `<file>.headers`, if present, to the response.
+    /// Blank lines, `#` comments and malformed lines are skipped.
+    fn apply_sidecar_headers(path: &Path, response: &mut Response) {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".headers");
+        let content = match fs::read_to_string(&sidecar) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = match line.split_once(':') {
+                Some(parts) => parts,
+                None => continue,
+            };
+            let name = name.trim();
+            if name.is_empty()
+                || name.contains(char::is_whitespace)
+                || Self::SIDECAR_RESERVED_HEADERS.contains(&name.to_ascii_lowercase().as_str())
+            {
+                continue;
+            }
+            response
+                .headers
+                .set(name.to_string(), value.trim().to_string());
+        }
+    }
+
+    /// Serve a file, compressing textual content when the client accepts br or gzip.
+    /// `cache_control` is set on successful responses unless empty; bodies under
+    /// `compress_min_size` (or all, when None) are sent uncompressed.
+    fn serve_file(
+        &self,
+        path: &Path,
+        request: &Request,
+        language: Option<&str>,
+        cache_control: Option<&str>,
+        compress_min_size: Option<usize>,
+    ) -> Result<Response> {
         let content = fs::read(path)
             .map_err(|e| ServerError::HttpError(format!("Failed to read file: {}", e)))?;
+        let cache_control = cache_control.filter(|value| !value.is_empty());
 
-        let mut response = Response::ok(version);
-        response.set_content_type(self.get_mime_type(path));
+        let mime_type = self.get_mime_type(path);
+        let mut response = Response::ok(request.version);
+        response.set_content_type(mime_type);
         response.set_body(content);
+        // Ranges are taken over the unencoded file, so partial bodies are never compressed
+        if range::apply(request, &mut response, mime_type) {
+            if let Some(value) = cache_control.filter(|_| response.status.is_success()) {
+                response
+                    .headers
+                    .set("Cache-Control".to_string(), value.to_string());
+            }
+            Self::set_language(&mut response, language);
+            return Ok(response);
+        }
+
+        if let Some(value) = cache_control {
+            response
+                .headers
+                .set("Cache-Control".to_string(), value.to_string());
+        }
+        Self::set_language(&mut response, language);
+        if compression::compress_response(request, &mut response, compress_min_size)?.is_none() {
+            // Client refused every coding we could send (e.g. identity;q=0)
+            let mut not_acceptable = Response::new(request.version, StatusCode::NOT_ACCEPTABLE);
+            not_acceptable.set_body_str("Not Acceptable");
+            return Ok(not_acceptable);
+        }
+        response
+            .headers
+            .set(header_names::ACCEPT_RANGES.to_string(), "bytes".to_string());
 
         Ok(response)
     }
+
+    /// Mark a negotiated language variant; partial responses carry it like full ones
+    fn set_language(response: &mut Response, language: Option<&str>) {
+        if let Some(language) = language {
+            if response.status.is_success() {
+                response
+                    .headers
+                    .set("Content-Language".to_string(), language.to_string());
+            }
+            response.add_vary("Accept-Language");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::config::models::{RouteConfig, ServerConfig};
+    use crate::application::handler::router::Router;
+    use crate::http::method::Method;
+    use crate::http::version::Version;
+    use std::collections::HashMap;
+
+    fn server_config(root: &Path, sidecar_headers: bool) -> ServerConfig {
+        let mut routes = HashMap::new();
+        routes.insert(
+            "/".to_string(),
+            RouteConfig {
+                methods: vec!["GET".to_string()],
+                sidecar_headers,
+                ..Default::default()
+            },
+        );
+        ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: root.to_string_lossy().to_string(),
+            routes,
+            ..Default::default()
+        }
+    }
+
+    fn get(root: &Path, sidecar_headers: bool, target: &str) -> Response {
+        get_accepting(root, sidecar_headers, target, None)
+    }
+
+    fn get_accepting(
+        root: &Path,
+        sidecar_headers: bool,
+        target: &str,
+        accept: Option<&str>,
+    ) -> Response {
+        let config = server_config(root, sidecar_headers);
+        let router = Router::new(&config, root.to_path_buf());
+        let ctx = RequestContext::new(&router, &config, 8080);
+        let mut request = Request::new(Method::GET, target.to_string(), Version::Http11);
+        if let Some(accept) = accept {
+            request
+                .headers
+                .set("Accept".to_string(), accept.to_string());
+        }
+        StaticFileHandler::new().handle(&request, &ctx).unwrap()
+    }
+
+    #[test]
+    fn test_sidecar_headers_are_applied_when_enabled() {
+        let root = std::env::temp_dir().join(format!("localhost_sidecar_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        fs::write(root.join("page.html"), "<p>hi</p>").unwrap();
+        fs::write(
+            root.join("page.html.headers"),
+            "# deployment headers\nCache-Control: public, max-age=600\nX-Frame-Options:DENY\n\nnot a header\nContent-Length: 1\n",
+        )
+        .unwrap();
+
+        let response = get(&root, true, "/page.html");
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.headers.get("Cache-Control"),
+            Some(&"public, max-age=600".to_string())
+        );
+        assert_eq!(
+            response.headers.get("X-Frame-Options"),
+            Some(&"DENY".to_string())
+        );
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&"9".to_string())
+        );
+        assert_eq!(response.body, b"<p>hi</p>");
+
+        let response = get(&root, false, "/page.html");
+        assert_eq!(response.headers.get("Cache-Control"), None);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_extensionless_path_negotiates_on_accept() {
+        let root = std::env::temp_dir().join(format!("localhost_accept_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        fs::write(root.join("page.html"), "<p>page</p>").unwrap();
+        fs::write(root.join("page.json"), "{\"page\": true}").unwrap();
+
+        let json = get_accepting(&root, false, "/page", Some("application/json"));
+        assert_eq!(json.body, b"{\"page\": true}");
+        assert_eq!(
+            json.headers.get("Content-Type"),
+            Some(&"application/json".to_string())
+        );
+        assert!(json.headers.get("Vary").unwrap().contains("Accept"));
+        assert_eq!(
+            json.headers.get("Content-Location"),
+            Some(&"/page.json".to_string())
+        );
+
+        let html = get_accepting(&root, false, "/page", Some("text/html"));
+        assert_eq!(html.body, b"<p>page</p>");
+
+        let weighted = get_accepting(
+            &root,
+            false,
+            "/page",
+            Some("text/html;q=0.4, application/*;q=0.8"),
+        );
+        assert_eq!(weighted.body, b"{\"page\": true}");
+
+        // No Accept header, or nothing acceptable: the first variant
+        assert_eq!(get(&root, false, "/page").body, b"<p>page</p>");
+        assert_eq!(
+            get_accepting(&root, false, "/page", Some("image/png")).body,
+            b"<p>page</p>"
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_language_variant_sets_content_location() {
+        let root = std::env::temp_dir().join(format!("localhost_language_{}", std::process::id()));
+        fs::create_dir_all(root.join("docs")).unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        fs::write(root.join("page.html"), "<p>page</p>").unwrap();
+        fs::write(root.join("page.fr.html"), "<p>page fr</p>").unwrap();
+        fs::write(root.join("page.en.html"), "<p>page en</p>").unwrap();
+        fs::write(root.join("docs/index.fr.html"), "<p>docs fr</p>").unwrap();
+
+        let mut config = server_config(&root, false);
+        let route = config.routes.get_mut("/").unwrap();
+        route.default_language = Some("en".to_string());
+        route.default_file = Some("index.html".to_string());
+        let router = Router::new(&config, root.clone());
+        let ctx = RequestContext::new(&router, &config, 8080);
+        let get_in = |target: &str, language: &str| {
+            let mut request = Request::new(Method::GET, target.to_string(), Version::Http11);
+            request
+                .headers
+                .set("Accept-Language".to_string(), language.to_string());
+            StaticFileHandler::new().handle(&request, &ctx).unwrap()
+        };
+
+        let response = get_in("/page.html", "fr-CA, en;q=0.5");
+        assert_eq!(response.body, b"<p>page fr</p>");
+        assert_eq!(
+            response.headers.get("Content-Location"),
+            Some(&"/page.fr.html".to_string())
+        );
+
+        // A partial response is still marked with its language
+        let mut request = Request::new(Method::GET, "/page.html".to_string(), Version::Http11);
+        request
+            .headers
+            .set("Accept-Language".to_string(), "fr".to_string());
+        request
+            .headers
+            .set("Range".to_string(), "bytes=0-2".to_string());
+        let response = StaticFileHandler::new().handle(&request, &ctx).unwrap();
+        assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body, b"<p>");
+        assert_eq!(
+            response.headers.get("Content-Language"),
+            Some(&"fr".to_string())
+        );
+        assert_eq!(
+            response.headers.get("Vary"),
+            Some(&"Accept-Language".to_string())
+        );
+
+        let response = get_in("/docs/", "fr");
+        assert_eq!(response.body, b"<p>docs fr</p>");
+        assert_eq!(
+            response.headers.get("Content-Location"),
+            Some(&"/docs/index.fr.html".to_string())
+        );
+
+        // Nothing was negotiated for a plain file
+        let response = get(&root, false, "/page.html");
+        assert_eq!(response.headers.get("Content-Location"), None);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_cache_control_from_server_with_route_override() {
+        let root = std::env::temp_dir().join(format!("localhost_cache_{}", std::process::id()));
+        fs::create_dir_all(root.join("assets")).unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        fs::write(root.join("page.html"), "<p>hi</p>").unwrap();
+        fs::write(root.join("assets/app.js"), "let x;").unwrap();
+
+        let mut config = server_config(&root, false);
+        config.static_cache_control = Some("public, max-age=3600".to_string());
+        config.routes.insert(
+            "/assets".to_string(),
+            RouteConfig {
+                methods: vec!["GET".to_string()],
+                cache_control: Some("public, max-age=31536000, immutable".to_string()),
+                ..Default::default()
+            },
+        );
+        config.routes.insert(
+            "/private".to_string(),
+            RouteConfig {
+                methods: vec!["GET".to_string()],
+                directory: Some(".".to_string()),
+                cache_control: Some(String::new()),
+                ..Default::default()
+            },
+        );
+        let router = Router::new(&config, root.clone());
+        let ctx = RequestContext::new(&router, &config, 8080);
+        let cache_control = |target: &str| {
+            let request = Request::new(Method::GET, target.to_string(), Version::Http11);
+            let response = StaticFileHandler::new().handle(&request, &ctx).unwrap();
+            assert_eq!(response.status, StatusCode::OK, "{}", target);
+            response.headers.get("Cache-Control").cloned()
+        };
+
+        assert_eq!(
+            cache_control("/page.html").as_deref(),
+            Some("public, max-age=3600")
+        );
+        assert_eq!(
+            cache_control("/assets/app.js").as_deref(),
+            Some("public, max-age=31536000, immutable")
+        );
+        assert_eq!(cache_control("/private/page.html"), None);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_single_ranges_are_206_and_out_of_range_is_416() {
+        let root = std::env::temp_dir().join(format!("localhost_range_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        fs::write(root.join("digits.txt"), "0123456789").unwrap();
+
+        let config = server_config(&root, false);
+        let router = Router::new(&config, root.clone());
+        let ctx = RequestContext::new(&router, &config, 8080);
+        let get_range = |range: Option<&str>| {
+            let mut request = Request::new(Method::GET, "/digits.txt".to_string(), Version::Http11);
+            if let Some(range) = range {
+                request.headers.set("Range".to_string(), range.to_string());
+            }
+            StaticFileHandler::new().handle(&request, &ctx).unwrap()
+        };
+
+        let full = get_range(None);
+        assert_eq!(full.status, StatusCode::OK);
+        assert_eq!(full.body, b"0123456789");
+        assert_eq!(
+            full.headers.get("Accept-Ranges"),
+            Some(&"bytes".to_string())
+        );
+
+        for (range, body, content_range) in [
+            ("bytes=2-5", &b"2345"[..], "bytes 2-5/10"),
+            ("bytes=7-", &b"789"[..], "bytes 7-9/10"),
+            ("bytes=-3", &b"789"[..], "bytes 7-9/10"),
+            ("bytes=8-100", &b"89"[..], "bytes 8-9/10"),
+        ] {
+            let partial = get_range(Some(range));
+            assert_eq!(partial.status, StatusCode::PARTIAL_CONTENT, "{}", range);
+            assert_eq!(partial.body, body, "{}", range);
+            assert_eq!(
+                partial.headers.get("Content-Range"),
+                Some(&content_range.to_string())
+            );
+            assert_eq!(
+                partial.headers.get("Content-Length"),
+                Some(&body.len().to_string())
+            );
+            assert_eq!(
+                partial.headers.get("Content-Type"),
+                Some(&"text/plain".to_string())
+            );
+        }
+
+        let unsatisfiable = get_range(Some("bytes=10-20"));
+        assert_eq!(unsatisfiable.status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            unsatisfiable.headers.get("Content-Range"),
+            Some(&"bytes */10".to_string())
+        );
+
+        // Malformed or non-byte ranges fall back to the full file
+        assert_eq!(get_range(Some("items=0-1")).status, StatusCode::OK);
+
+        // So does a header repeating more ranges than anyone needs
+        let abusive = format!("bytes={}", vec!["0-"; 800].join(","));
+        let full = get_range(Some(&abusive));
+        assert_eq!(full.status, StatusCode::OK);
+        assert_eq!(full.body, b"0123456789");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_enable_gzip_gates_compression() {
+        let root = std::env::temp_dir().join(format!("localhost_gzip_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        fs::write(root.join("big.html"), "<p>compress me</p>".repeat(100)).unwrap();
+
+        let encoding = |enable_gzip: bool| {
+            let mut config = server_config(&root, false);
+            config.enable_gzip = enable_gzip;
+            let router = Router::new(&config, root.clone());
+            let ctx = RequestContext::new(&router, &config, 8080);
+            let mut request = Request::new(Method::GET, "/big.html".to_string(), Version::Http11);
+            request
+                .headers
+                .set("Accept-Encoding".to_string(), "gzip".to_string());
+            let response = StaticFileHandler::new().handle(&request, &ctx).unwrap();
+            response.headers.get("Content-Encoding").cloned()
+        };
+
+        assert_eq!(encoding(true).as_deref(), Some("gzip"));
+        assert_eq!(encoding(false), None);
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -1,25 +1,35 @@
+use crate::application::handler::request_context::RequestContext;
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
 use crate::common::error::{Result, ServerError};
+use crate::http::headers::names as header_names;
 use crate::http::method::Method;
+use crate::http::multipart::StreamedPart;
 use crate::http::request::Request;
 use crate::http::response::Response;
 use crate::http::status::StatusCode;
+use std::collections::HashMap;
 use std::fs;
 use std::hash::{Hash, Hasher};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Per-directory locks shared by all upload handlers in the process
+static UPLOAD_DIR_LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+
+/// Sequence number mixed into generated filenames
+static UPLOAD_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
 /// Handler for file uploads
-pub struct UploadHandler {
-    router: Router,
-    upload_dir: PathBuf,
-}
+#[derive(Default)]
+pub struct UploadHandler;
 
 impl UploadHandler {
     /// Create a new upload handler
-    pub fn new(router: Router, upload_dir: PathBuf) -> Self {
-        Self { router, upload_dir }
+    pub fn new() -> Self {
+        Self
     }
 
     /// Parse multipart/form-data body to extract file content, filename, and MIME type
@@ -261,32 +271,115 @@ impl UploadHandler {
         allowed_types.contains(&mime)
     }
 
+    /// Check the request Content-Type against a route's allowed list.
+    /// Entries match the media type exactly or by `type/*` wildcard; an empty list allows anything.
+    pub(crate) fn is_content_type_allowed(
+        allowed: &[String],
+        content_type: Option<&String>,
+    ) -> bool {
+        if allowed.is_empty() {
+            return true;
+        }
+        let mime = match content_type {
+            Some(ct) => ct
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase(),
+            None => return false,
+        };
+
+        allowed.iter().any(|pattern| {
+            let pattern = pattern.trim().to_ascii_lowercase();
+            match pattern.strip_suffix("/*") {
+                Some(top_level) => mime
+                    .split_once('/')
+                    .map(|(t, _)| t == top_level)
+                    .unwrap_or(false),
+                None => pattern == "*/*" || pattern == mime,
+            }
+        })
+    }
+
     /// Generate unique filename for uploaded file
     fn generate_filename(&self, content: &[u8]) -> String {
-        let timestamp = SystemTime::now()
+        let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+            .unwrap_or_default();
 
-        // Use timestamp + hash of content for uniqueness
+        // Use timestamp + full hash of content, time and a sequence number for uniqueness
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
         content.hash(&mut hasher);
+        now.subsec_nanos().hash(&mut hasher);
+        UPLOAD_SEQUENCE
+            .fetch_add(1, Ordering::Relaxed)
+            .hash(&mut hasher);
         let hash = hasher.finish();
 
-        format!("upload_{}_{:x}", timestamp, hash & 0xFFFF)
+        format!("upload_{}_{:016x}", now.as_secs(), hash)
+    }
+
+    /// Lock guarding name selection and writes in one upload directory, so
+    /// concurrent uploads can't pick the same free name and clobber each other
+    fn directory_lock(dir: &Path) -> Arc<Mutex<()>> {
+        let locks = UPLOAD_DIR_LOCKS.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut locks = locks.lock().unwrap_or_else(|e| e.into_inner());
+        locks.entry(dir.to_path_buf()).or_default().clone()
     }
 
     /// Save uploaded file and optionally store MIME type metadata
     /// Ensures the file extension matches the MIME type
     fn save_file(
         &self,
+        upload_dir: &Path,
         content: &[u8],
         original_filename: Option<&str>,
         mime_type: Option<&str>,
     ) -> Result<PathBuf> {
+        // Write to a temp file and rename into place so readers never see a partial upload
+        self.place_file(upload_dir, content, original_filename, mime_type, |path| {
+            crate::common::path_utils::write_atomic(path, content)
+        })
+    }
+
+    /// Move a part that was streamed to a temp file in `upload_dir` to its final name
+    fn save_streamed_part(
+        &self,
+        upload_dir: &Path,
+        part: &StreamedPart,
+        mime_type: Option<&str>,
+    ) -> Result<PathBuf> {
+        let seed = part.path.to_string_lossy();
+        self.place_file(
+            upload_dir,
+            seed.as_bytes(),
+            part.filename.as_deref(),
+            mime_type,
+            |path| fs::rename(&part.path, path),
+        )
+    }
+
+    /// Pick a free name in `upload_dir` and let `write` create the file there.
+    /// `seed` feeds the generated name when the client sent no filename.
+    fn place_file<F>(
+        &self,
+        upload_dir: &Path,
+        seed: &[u8],
+        original_filename: Option<&str>,
+        mime_type: Option<&str>,
+        write: F,
+    ) -> Result<PathBuf>
+    where
+        F: FnOnce(&Path) -> io::Result<()>,
+    {
+        // Serialize uploads into the same directory from name selection through the write
+        let lock = Self::directory_lock(upload_dir);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+
         // Ensure upload directory exists
-        if !self.upload_dir.exists() {
-            fs::create_dir_all(&self.upload_dir).map_err(|e| {
+        if !upload_dir.exists() {
+            fs::create_dir_all(upload_dir).map_err(|e| {
                 ServerError::HttpError(format!("Failed to create upload directory: {}", e))
             })?;
         }
@@ -322,7 +415,7 @@ impl UploadHandler {
 
             // Check if file already exists, append number if needed
             let mut counter = 1;
-            while self.upload_dir.join(&final_name).exists() {
+            while upload_dir.join(&final_name).exists() {
                 if let Some(ext) = final_ext {
                     final_name = format!("{}_{}.{}", base_name, counter, ext);
                 } else {
@@ -332,47 +425,142 @@ impl UploadHandler {
             }
             final_name
         } else {
-            // Generate unique filename with correct extension
-            let base_name = self.generate_filename(content);
-            if let Some(ext) = &correct_extension {
-                format!("{}.{}", base_name, ext)
-            } else {
-                base_name
+            // Generate unique filename with correct extension (regenerate on the unlikely collision)
+            loop {
+                let base_name = self.generate_filename(seed);
+                let name = if let Some(ext) = &correct_extension {
+                    format!("{}.{}", base_name, ext)
+                } else {
+                    base_name
+                };
+                if !upload_dir.join(&name).exists() {
+                    break name;
+                }
             }
         };
 
-        let file_path = self.upload_dir.join(&filename);
-
-        // Write file
-        fs::write(&file_path, content)
+        let file_path = upload_dir.join(&filename);
+        write(&file_path)
             .map_err(|e| ServerError::HttpError(format!("Failed to write uploaded file: {}", e)))?;
 
         Ok(file_path)
     }
+
+    /// Store the file part of a streamed multipart body. An upload carries one file,
+    /// so a body with several file parts is refused rather than partly stored.
+    fn handle_streamed(&self, request: &Request, upload_dir: &Path) -> Result<Response> {
+        let part = match request.streamed_parts.as_slice() {
+            [part] => part,
+            parts => {
+                return Ok(Response::bad_request_with_message(
+                    request.version,
+                    &format!("Expected one file per upload, got {}", parts.len()),
+                ));
+            }
+        };
+        let mime_type = part.content_type.clone().or_else(|| {
+            part.filename
+                .as_ref()
+                .and_then(|name| self.detect_mime_type_from_filename(name))
+        });
+
+        match mime_type {
+            Some(ref mime) if self.is_valid_mime_type(mime) => {}
+            Some(mime) => {
+                return Ok(Response::bad_request_with_message(
+                    request.version,
+                    &format!("Invalid or unsupported MIME type: {}", mime),
+                ));
+            }
+            None => {
+                return Ok(Response::bad_request_with_message(
+                    request.version,
+                    "Unable to determine file type. Please ensure Content-Type header is set or file has a recognized extension."
+                ));
+            }
+        }
+
+        let saved_path = self.save_streamed_part(upload_dir, part, mime_type.as_deref())?;
+        Ok(Self::created_response(request, &saved_path, mime_type))
+    }
+
+    /// 201 response describing a stored upload
+    fn created_response(
+        request: &Request,
+        saved_path: &Path,
+        final_mime_type: Option<String>,
+    ) -> Response {
+        // Return success response
+        let mut response = Response::new(request.version, StatusCode::CREATED);
+        response.set_content_type("application/json");
+
+        // Build JSON response with filename and MIME type
+        let filename_str = saved_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        let json_response = if let Some(mime) = final_mime_type {
+            format!(
+                r#"{{"status": "success", "message": "File uploaded successfully", "filename": "{}", "mime_type": "{}"}}"#,
+                filename_str, mime
+            )
+        } else {
+            format!(
+                r#"{{"status": "success", "message": "File uploaded successfully", "filename": "{}"}}"#,
+                filename_str
+            )
+        };
+        response.set_body_str(&json_response);
+
+        response
+    }
 }
 
 impl RequestHandler for UploadHandler {
-    fn handle(&self, request: &Request) -> Result<Response> {
+    fn handle(&self, request: &Request, ctx: &RequestContext) -> Result<Response> {
         // Only POST requests are allowed for uploads
         if request.method != Method::POST {
-            return Ok(Response::method_not_allowed_with_message(
+            let mut response = Response::method_not_allowed_with_message(
                 request.version,
                 "Only POST method is allowed for file uploads",
-            ));
+            );
+            response
+                .headers
+                .set(header_names::ALLOW.to_string(), "POST".to_string());
+            return Ok(response);
         }
 
         // Validate route and method
-        let (route, error_response) = self.router.validate_request(request)?;
+        let (route, error_response) = ctx.router.validate_request(request)?;
         if let Some(response) = error_response {
             return Ok(response);
         }
 
         // Check if upload directory is configured
-        if route.upload_dir.is_none() {
-            return Ok(Response::bad_request_with_message(
-                request.version,
-                "Upload directory not configured for this route",
+        let upload_dir = match &route.upload_dir {
+            Some(dir) => ctx.router.resolve_path(dir),
+            None => {
+                return Ok(Response::bad_request_with_message(
+                    request.version,
+                    "Upload directory not configured for this route",
+                ));
+            }
+        };
+
+        // Reject request Content-Types the route doesn't accept
+        if !Self::is_content_type_allowed(&route.allowed_content_types, request.content_type()) {
+            let mut response = Response::new(request.version, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            response.set_body_str(&format!(
+                "Unsupported Media Type: {}",
+                request.content_type().map(|s| s.as_str()).unwrap_or("none")
             ));
+            return Ok(response);
+        }
+
+        // Large multipart bodies arrive already split into files on disk
+        if !request.streamed_parts.is_empty() {
+            return self.handle_streamed(request, &upload_dir);
         }
 
         // Check if body is empty
@@ -445,34 +633,181 @@ impl RequestHandler for UploadHandler {
 
         // Save uploaded file
         let saved_path = self.save_file(
+            &upload_dir,
             &file_content,
             filename.as_deref(),
             final_mime_type.as_deref(),
         )?;
 
-        // Return success response
-        let mut response = Response::new(request.version, StatusCode::CREATED);
-        response.set_content_type("application/json");
+        Ok(Self::created_response(
+            request,
+            &saved_path,
+            final_mime_type,
+        ))
+    }
+}
 
-        // Build JSON response with filename and MIME type
-        let filename_str = saved_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::config::models::{RouteConfig, ServerConfig};
+    use crate::application::handler::router::Router;
+    use crate::http::version::Version;
+
+    fn upload_config(upload_dir: &Path) -> ServerConfig {
+        let mut routes = HashMap::new();
+        routes.insert(
+            "/upload".to_string(),
+            RouteConfig {
+                methods: vec!["POST".to_string()],
+                upload_dir: Some(upload_dir.to_string_lossy().to_string()),
+                allowed_content_types: vec![
+                    "image/*".to_string(),
+                    "multipart/form-data".to_string(),
+                ],
+                ..Default::default()
+            },
+        );
+        ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: ".".to_string(),
+            routes,
+            ..Default::default()
+        }
+    }
 
-        let json_response = if let Some(mime) = final_mime_type {
-            format!(
-                r#"{{"status": "success", "message": "File uploaded successfully", "filename": "{}", "mime_type": "{}"}}"#,
-                filename_str, mime
-            )
-        } else {
-            format!(
-                r#"{{"status": "success", "message": "File uploaded successfully", "filename": "{}"}}"#,
-                filename_str
-            )
-        };
-        response.set_body_str(&json_response);
+    /// Run a request through an upload handler configured to store into `upload_dir`
+    fn upload(upload_dir: &Path, request: &Request) -> Response {
+        let config = upload_config(upload_dir);
+        let router = Router::new(&config, PathBuf::from("."));
+        let ctx = RequestContext::new(&router, &config, 8080);
+        UploadHandler::new().handle(request, &ctx).unwrap()
+    }
+
+    fn upload_request(content_type: &str, body: &[u8]) -> Request {
+        let mut request = Request::new(Method::POST, "/upload".to_string(), Version::Http11);
+        request
+            .headers
+            .set("Content-Type".to_string(), content_type.to_string());
+        request.body = body.to_vec();
+        request
+    }
 
-        Ok(response)
+    #[test]
+    fn test_allowed_content_type_proceeds() {
+        let dir = std::env::temp_dir().join(format!("localhost_upload_ok_{}", std::process::id()));
+        let response = upload(&dir, &upload_request("image/png", b"\x89PNG data"));
+        assert_eq!(response.status, StatusCode::CREATED);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_streamed_upload_with_several_files_is_400() {
+        let dir =
+            std::env::temp_dir().join(format!("localhost_upload_many_{}", std::process::id()));
+        let spool = dir.with_extension("parts");
+        fs::create_dir_all(&spool).unwrap();
+        let mut request = upload_request("multipart/form-data; boundary=b", b"");
+        for name in ["a.png", "b.png"] {
+            let path = spool.join(name);
+            fs::write(&path, b"\x89PNG data").unwrap();
+            request.streamed_parts.push(StreamedPart {
+                filename: Some(name.to_string()),
+                content_type: Some("image/png".to_string()),
+                path,
+                size: 9,
+            });
+        }
+
+        let response = upload(&dir, &request);
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+        assert!(!dir.join("a.png").exists());
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&spool);
+    }
+
+    #[test]
+    fn test_non_post_is_405_with_allow() {
+        let dir = std::env::temp_dir().join(format!("localhost_upload_405_{}", std::process::id()));
+        let mut request = upload_request("image/png", b"data");
+        request.method = Method::PUT;
+        let response = upload(&dir, &request);
+        assert_eq!(response.status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers.get("Allow"), Some(&"POST".to_string()));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_disallowed_content_type_is_415() {
+        let dir = std::env::temp_dir().join(format!("localhost_upload_415_{}", std::process::id()));
+        let response = upload(&dir, &upload_request("text/plain", b"hello"));
+        assert_eq!(response.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(
+            !dir.exists(),
+            "nothing must be written for a rejected upload"
+        );
+    }
+
+    #[test]
+    fn test_content_type_matching() {
+        let allowed = vec!["image/*".to_string(), "application/json".to_string()];
+        let ct = |s: &str| Some(s.to_string());
+        assert!(UploadHandler::is_content_type_allowed(
+            &allowed,
+            ct("IMAGE/JPEG").as_ref()
+        ));
+        assert!(UploadHandler::is_content_type_allowed(
+            &allowed,
+            ct("application/json; charset=utf-8").as_ref()
+        ));
+        assert!(!UploadHandler::is_content_type_allowed(
+            &allowed,
+            ct("imagex/png").as_ref()
+        ));
+        assert!(!UploadHandler::is_content_type_allowed(&allowed, None));
+        assert!(UploadHandler::is_content_type_allowed(&[], None));
+    }
+
+    #[test]
+    fn test_concurrent_uploads_get_distinct_intact_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "localhost_upload_concurrent_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    // Half share an original filename, half rely on generated names
+                    let mut request = upload_request("image/png", &vec![i as u8; 64 * 1024]);
+                    if i % 2 == 0 {
+                        request.headers.set(
+                            "Content-Disposition".to_string(),
+                            "attachment; filename=\"same.png\"".to_string(),
+                        );
+                    }
+                    let response = upload(&dir, &request);
+                    assert_eq!(response.status, StatusCode::CREATED);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut contents: Vec<Vec<u8>> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(contents.len(), 8, "every upload must land in its own file");
+        contents.sort();
+        for (i, content) in contents.iter().enumerate() {
+            assert_eq!(content, &vec![i as u8; 64 * 1024]);
+        }
+        let _ = fs::remove_dir_all(&dir);
     }
 }
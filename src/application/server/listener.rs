@@ -1,6 +1,7 @@
-use crate::common::error::Result;
+use crate::common::error::{Result, ServerError};
 use crate::core::net::socket::{ClientSocket, ListeningSocket};
 use std::net::SocketAddr;
+use std::time::Duration;
 
 /// Listener manages a listening socket for accepting connections
 pub struct Listener {
@@ -15,6 +16,38 @@ impl Listener {
         Ok(Self { socket, addr })
     }
 
+    /// Create a listener, retrying up to `retries` times while the address is in use
+    /// (e.g. the previous process hasn't released the port yet). Any other failure,
+    /// such as a permission error, is returned at once. The delay starts at `backoff`
+    /// and doubles after each failed attempt.
+    pub fn bind_with_retry(addr: SocketAddr, retries: u32, backoff: Duration) -> Result<Self> {
+        let mut delay = backoff;
+        let mut attempt = 0;
+        loop {
+            match Self::new(addr) {
+                Ok(listener) => return Ok(listener),
+                Err(e) if attempt < retries && Self::is_addr_in_use(&e) => {
+                    attempt += 1;
+                    crate::common::logger::Logger::warn(&format!(
+                        "Bind to {} failed ({}), retry {}/{} in {}ms",
+                        addr,
+                        e,
+                        attempt,
+                        retries,
+                        delay.as_millis()
+                    ));
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn is_addr_in_use(error: &ServerError) -> bool {
+        matches!(error, ServerError::IoError(e) if e.kind() == std::io::ErrorKind::AddrInUse)
+    }
+
     /// Accept a new client connection (non-blocking)
     pub fn accept(&self) -> Result<Option<ClientSocket>> {
         self.socket.accept()
@@ -25,8 +58,61 @@ impl Listener {
         self.addr
     }
 
+    /// Address the socket is actually bound to; differs from `addr` when port 0 was
+    /// configured and the OS picked the port
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
     /// Get the file descriptor for event polling
     pub fn as_raw_fd(&self) -> i32 {
         self.socket.as_raw_fd()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_bind_with_retry_succeeds_once_port_is_released() {
+        let holder = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = holder.local_addr().unwrap();
+
+        let binder = std::thread::spawn(move || {
+            Listener::bind_with_retry(addr, 8, Duration::from_millis(20)).map(|l| l.addr())
+        });
+
+        std::thread::sleep(Duration::from_millis(50));
+        drop(holder);
+
+        assert_eq!(binder.join().unwrap().unwrap(), addr);
+    }
+
+    #[test]
+    fn test_local_addr_reports_ephemeral_port() {
+        let listener = Listener::new("127.0.0.1:0".parse().unwrap()).unwrap();
+        assert_eq!(listener.addr().port(), 0);
+        let bound = listener.local_addr().unwrap();
+        assert_ne!(bound.port(), 0);
+        assert_eq!(bound.ip(), listener.addr().ip());
+    }
+
+    #[test]
+    fn test_bind_without_retry_fails_while_port_is_held() {
+        let holder = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = holder.local_addr().unwrap();
+
+        let result = Listener::bind_with_retry(addr, 0, Duration::from_millis(1));
+        assert!(result.is_err_and(|e| Listener::is_addr_in_use(&e)));
+    }
+
+    #[test]
+    fn test_bind_with_retry_fails_fast_unless_address_in_use() {
+        // TEST-NET-1 is never a local address; retrying would sleep for minutes
+        let addr = "192.0.2.1:0".parse().unwrap();
+        let result = Listener::bind_with_retry(addr, 5, Duration::from_secs(60));
+        assert!(result.is_err_and(|e| !Listener::is_addr_in_use(&e)));
+    }
+}
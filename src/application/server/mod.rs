@@ -1,7 +1,13 @@
 pub mod listener;
+pub mod route_limiter;
+pub mod server_info;
 pub mod server_instance;
 pub mod server_manager;
+pub mod worker_pool;
 
 pub use listener::Listener;
+pub use route_limiter::{RouteLimiter, RoutePermit};
+pub use server_info::{RouteDetails, ServerDetails, ServerInfo};
 pub use server_instance::ServerInstance;
-pub use server_manager::ServerManager;
+pub use server_manager::{ServerManager, ServerSummary};
+pub use worker_pool::WorkerPool;
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Route identity: server index and matched route path
+type RouteKey = (usize, String);
+
+/// Counts in-flight requests per route, so routes with `max_concurrent` can refuse
+/// requests beyond their cap
+#[derive(Debug, Clone, Default)]
+pub struct RouteLimiter {
+    in_flight: Arc<Mutex<HashMap<RouteKey, usize>>>,
+}
+
+impl RouteLimiter {
+    /// Create a limiter with no requests in flight
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one of the route's `limit` slots; None when all are in use.
+    /// The slot is released when the permit is dropped.
+    pub fn try_acquire(
+        &self,
+        server_idx: usize,
+        route_path: &str,
+        limit: usize,
+    ) -> Option<RoutePermit> {
+        let key = (server_idx, route_path.to_string());
+        let mut in_flight = self.lock();
+        let count = in_flight.entry(key.clone()).or_insert(0);
+        if *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(RoutePermit {
+            limiter: self.clone(),
+            key,
+        })
+    }
+
+    /// Requests currently holding a slot on the route
+    pub fn in_flight(&self, server_idx: usize, route_path: &str) -> usize {
+        self.lock()
+            .get(&(server_idx, route_path.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<RouteKey, usize>> {
+        // Counts stay consistent even if a holder panicked, so poisoning is ignored
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A slot on a capped route, held while its handler runs
+#[derive(Debug)]
+pub struct RoutePermit {
+    limiter: RouteLimiter,
+    key: RouteKey,
+}
+
+impl Drop for RoutePermit {
+    fn drop(&mut self) {
+        let mut in_flight = self.limiter.lock();
+        if let Some(count) = in_flight.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_permits_are_bounded_per_route() {
+        let limiter = RouteLimiter::new();
+        let first = limiter.try_acquire(0, "/report", 2).unwrap();
+        let _second = limiter.try_acquire(0, "/report", 2).unwrap();
+        assert!(limiter.try_acquire(0, "/report", 2).is_none());
+
+        // Other routes, and the same path on another server, have their own slots
+        assert!(limiter.try_acquire(0, "/static", 1).is_some());
+        assert!(limiter.try_acquire(1, "/report", 1).is_some());
+
+        drop(first);
+        assert_eq!(limiter.in_flight(0, "/report"), 1);
+        assert!(limiter.try_acquire(0, "/report", 2).is_some());
+    }
+
+    #[test]
+    fn test_concurrency_stays_within_limit_under_load() {
+        let limiter = RouteLimiter::new();
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let rejected = Arc::new(AtomicUsize::new(0));
+
+        let workers: Vec<_> = (0..16)
+            .map(|_| {
+                let (limiter, running, peak, rejected) = (
+                    limiter.clone(),
+                    running.clone(),
+                    peak.clone(),
+                    rejected.clone(),
+                );
+                thread::spawn(move || {
+                    for _ in 0..20 {
+                        match limiter.try_acquire(0, "/report", 3) {
+                            Some(_permit) => {
+                                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                                peak.fetch_max(now, Ordering::SeqCst);
+                                thread::sleep(Duration::from_millis(1));
+                                running.fetch_sub(1, Ordering::SeqCst);
+                            }
+                            None => {
+                                rejected.fetch_add(1, Ordering::SeqCst);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert!(rejected.load(Ordering::SeqCst) > 0);
+        assert_eq!(limiter.in_flight(0, "/report"), 0);
+    }
+}
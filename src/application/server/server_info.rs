@@ -0,0 +1,184 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Startup description of all configured servers, for banners and service managers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// Server software version
+    pub version: String,
+    /// Servers in configuration order
+    pub servers: Vec<ServerDetails>,
+}
+
+/// One configured server as it was started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerDetails {
+    pub name: String,
+    /// Addresses actually listened on (the OS-assigned port for port 0)
+    pub listen_addrs: Vec<SocketAddr>,
+    /// Ports as configured
+    pub ports: Vec<u16>,
+    pub root: PathBuf,
+    /// Default server for its ports
+    pub is_default: bool,
+    pub admin_access: bool,
+    /// Routes sorted by path
+    pub routes: Vec<RouteDetails>,
+    /// CGI handlers as (extension, interpreter), sorted by extension
+    pub cgi_handlers: Vec<(String, String)>,
+}
+
+/// One route of a configured server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDetails {
+    pub path: String,
+    /// Allowed methods; empty allows all
+    pub methods: Vec<String>,
+    pub redirect: Option<String>,
+}
+
+impl ServerInfo {
+    /// Human-readable banner, one line per entry
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Localhost HTTP Server v{}", self.version);
+        let _ = writeln!(out, "================================");
+
+        for (idx, server) in self.servers.iter().enumerate() {
+            let _ = writeln!(out, "\nServer {}: {}", idx, server.name);
+            if let Some(addr) = server.listen_addrs.first() {
+                let _ = writeln!(out, "  Address: {}", addr.ip());
+            }
+            let ports: Vec<String> = server
+                .ports
+                .iter()
+                .zip(&server.listen_addrs)
+                .map(|(&port, bound)| match port {
+                    // Port 0 means the OS picked one; show where to connect
+                    0 => format!("{} (bound {})", port, bound.port()),
+                    _ => port.to_string(),
+                })
+                .collect();
+            let _ = writeln!(out, "  Ports: {}", ports.join(", "));
+            let _ = writeln!(out, "  Root: {}", server.root.display());
+
+            if server.is_default {
+                let _ = writeln!(out, "  Status: Default server for port(s)");
+            }
+            if server.admin_access {
+                let _ = writeln!(out, "  Admin access: Enabled");
+            }
+
+            if !server.routes.is_empty() {
+                let _ = writeln!(out, "  Routes:");
+                for route in &server.routes {
+                    let methods = if route.methods.is_empty() {
+                        "ALL".to_string()
+                    } else {
+                        route.methods.join(", ")
+                    };
+                    let redirect_info = route
+                        .redirect
+                        .as_ref()
+                        .map(|redirect| format!(" -> redirect: {}", redirect))
+                        .unwrap_or_default();
+                    let _ = writeln!(out, "    {} -> [{}]{}", route.path, methods, redirect_info);
+                }
+            }
+
+            if !server.cgi_handlers.is_empty() {
+                let _ = writeln!(out, "  CGI handlers:");
+                for (ext, interpreter) in &server.cgi_handlers {
+                    let _ = writeln!(out, "    {} -> {}", ext, interpreter);
+                }
+            }
+        }
+
+        let _ = writeln!(out, "\n================================");
+        out
+    }
+
+    /// Single-line JSON object, for service managers and log collectors
+    pub fn to_json(&self) -> String {
+        let servers: Vec<String> = self
+            .servers
+            .iter()
+            .map(|server| {
+                let routes: Vec<String> = server
+                    .routes
+                    .iter()
+                    .map(|route| {
+                        format!(
+                            r#"{{"path":{},"methods":{},"redirect":{}}}"#,
+                            json_string(&route.path),
+                            json_array(route.methods.iter().map(|m| json_string(m))),
+                            route
+                                .redirect
+                                .as_deref()
+                                .map_or_else(|| "null".to_string(), json_string)
+                        )
+                    })
+                    .collect();
+                let cgi_handlers: Vec<String> = server
+                    .cgi_handlers
+                    .iter()
+                    .map(|(ext, interpreter)| {
+                        format!("{}:{}", json_string(ext), json_string(interpreter))
+                    })
+                    .collect();
+                format!(
+                    r#"{{"name":{},"listen":{},"ports":{},"root":{},"default":{},"admin_access":{},"routes":{},"cgi_handlers":{{{}}}}}"#,
+                    json_string(&server.name),
+                    json_array(server.listen_addrs.iter().map(|a| json_string(&a.to_string()))),
+                    json_array(server.ports.iter().map(|p| p.to_string())),
+                    json_string(&server.root.to_string_lossy()),
+                    server.is_default,
+                    server.admin_access,
+                    json_array(routes.into_iter()),
+                    cgi_handlers.join(",")
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"version":{},"servers":{}}}"#,
+            json_string(&self.version),
+            json_array(servers.into_iter())
+        )
+    }
+}
+
+fn json_array(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(","))
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_escapes_strings() {
+        assert_eq!(json_string(r#"a "b" \ c"#), r#""a \"b\" \\ c""#);
+        assert_eq!(json_string("line\nbreak\u{1}"), r#""line\nbreak\u0001""#);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::application::config::models::ServerConfig;
+use crate::application::handler::router::Router;
 use crate::application::server::listener::Listener;
 use crate::common::error::{Result, ServerError};
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 /// Represents a single server instance (virtual host)
 pub struct ServerInstance {
@@ -13,6 +14,9 @@ pub struct ServerInstance {
     /// Root directory path (resolved absolute path)
     root_path: PathBuf,
 
+    /// Router with routes compiled once at startup and reused for every request
+    router: Router,
+
     /// Listeners for each port
     listeners: HashMap<u16, Listener>,
 
@@ -31,12 +35,7 @@ impl ServerInstance {
     /// Used when listeners are managed at ServerManager level for shared ports
     pub fn new_without_listeners(config: ServerConfig, is_default: bool) -> Result<Self> {
         // Resolve root path to absolute
-        let root_path = std::fs::canonicalize(&config.root).map_err(|e| {
-            ServerError::ConfigError(format!(
-                "Failed to resolve root path '{}': {}",
-                config.root, e
-            ))
-        })?;
+        let root_path = resolve_root(&config.root, |p| std::fs::canonicalize(p))?;
 
         // Verify root is a directory
         if !root_path.is_dir() {
@@ -46,9 +45,12 @@ impl ServerInstance {
             )));
         }
 
+        let router = Router::new(&config, root_path.clone());
+
         Ok(Self {
             config,
             root_path,
+            router,
             listeners: HashMap::new(),
             is_default,
         })
@@ -75,6 +77,11 @@ impl ServerInstance {
         &self.root_path
     }
 
+    /// Get the router built for this server
+    pub fn router(&self) -> &Router {
+        &self.router
+    }
+
     /// Get server configuration
     pub fn config(&self) -> &ServerConfig {
         &self.config
@@ -105,3 +112,101 @@ impl ServerInstance {
         self.config.admin_access
     }
 }
+
+/// Resolve the document root to an absolute path.
+///
+/// Prefers the canonical path; if canonicalization fails (untraversable parent,
+/// some network filesystems) but the directory exists and is readable, falls back
+/// to a lexically normalized absolute path and logs a warning instead of failing.
+fn resolve_root<F>(root: &str, canonicalize: F) -> Result<PathBuf>
+where
+    F: Fn(&Path) -> std::io::Result<PathBuf>,
+{
+    let path = Path::new(root);
+    let error = match canonicalize(path) {
+        Ok(canonical) => return Ok(canonical),
+        Err(e) => e,
+    };
+
+    if path.is_dir() && std::fs::read_dir(path).is_ok() {
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir()?.join(path)
+        };
+        let normalized = normalize_lexically(&absolute);
+        crate::common::logger::Logger::warn(&format!(
+            "Could not canonicalize root '{}' ({}), using '{}'",
+            root,
+            error,
+            normalized.display()
+        ));
+        return Ok(normalized);
+    }
+
+    Err(ServerError::ConfigError(format!(
+        "Failed to resolve root path '{}': {}",
+        root, error
+    )))
+}
+
+/// Normalize `.` and `..` components without touching the filesystem
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_router_is_built_once_per_instance() {
+        let config = ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            ports: vec![8080],
+            server_name: "test".to_string(),
+            root: ".".to_string(),
+            ..Default::default()
+        };
+        let instance = ServerInstance::new(config, true).unwrap();
+
+        // Each request clones the instance router; all clones share one route table
+        let first = instance.router().clone();
+        let second = instance.router().clone();
+        assert!(first.shares_routes_with(&second));
+        assert!(first.shares_routes_with(instance.router()));
+    }
+
+    #[test]
+    fn test_root_falls_back_when_canonicalize_fails() {
+        let failing = |_: &Path| -> std::io::Result<PathBuf> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "simulated canonicalize failure",
+            ))
+        };
+
+        // Relative root with redundant components still resolves to a clean absolute path
+        let resolved = resolve_root("./src/../src", failing).unwrap();
+        assert!(resolved.is_absolute());
+        assert_eq!(resolved, std::env::current_dir().unwrap().join("src"));
+    }
+
+    #[test]
+    fn test_root_fallback_still_rejects_missing_directory() {
+        let failing = |_: &Path| -> std::io::Result<PathBuf> {
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, "missing"))
+        };
+        assert!(resolve_root("./definitely-not-a-dir", failing).is_err());
+    }
+}
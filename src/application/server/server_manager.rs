@@ -1,24 +1,114 @@
-use crate::application::config::models::Config;
+use crate::application::config::models::{Config, RouteConfig};
+use crate::application::handler::acme_challenge_handler::AcmeChallengeHandler;
 use crate::application::handler::directory_listing_handler::DirectoryListingHandler;
+use crate::application::handler::options_handler::OptionsHandler;
+use crate::application::handler::request_context::{RequestContext, SessionHandle};
 use crate::application::handler::request_handler::RequestHandler;
-use crate::application::handler::router::Router;
 use crate::application::handler::session_manager::SessionManager;
 use crate::application::handler::static_file_handler::StaticFileHandler;
+use crate::application::handler::upload_handler::UploadHandler;
+use crate::application::server::route_limiter::RouteLimiter;
+use crate::application::server::server_info::{RouteDetails, ServerDetails, ServerInfo};
 use crate::application::server::server_instance::ServerInstance;
-use crate::common::constants::{DEFAULT_BUFFER_SIZE, DEFAULT_SESSION_TIMEOUT_SECS};
+use crate::application::server::worker_pool::WorkerPool;
+use crate::common::constants::{
+    CGI_BODY_SPOOL_THRESHOLD, DEFAULT_BUFFER_SIZE, DEFAULT_SESSION_TIMEOUT_SECS,
+    MULTIPART_STREAM_THRESHOLD,
+};
 use crate::common::error::{Result, ServerError};
 use crate::core::event::event_loop::EventLoop;
 use crate::core::event::event_manager::EventManager;
-use crate::core::event::poller::Kevent;
+use crate::core::event::poller::PollEvent;
 use crate::core::net::connection::{Connection, ConnectionState};
 use crate::core::net::io::{read_non_blocking, write_non_blocking};
+use crate::core::net::proxy_protocol::{self, ProxyHeader};
+use crate::http::body_spool::BodySpool;
 use crate::http::cookie::Cookie;
+use crate::http::multipart::{form_data_boundary, remove_part_files, MultipartStream};
 use crate::http::parser::RequestParser;
 use crate::http::request::Request;
 use crate::http::response::Response;
-use crate::http::serializer::ResponseSerializer;
-use std::collections::HashMap;
+use crate::http::serializer::{ResponseSerializer, SerializeOptions};
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// A handler call packaged to run on a worker thread
+type OffloadJob = Box<dyn FnOnce() -> Result<Response> + Send>;
+
+/// What routing a request produced. While dispatching, the offloaded side is the bare
+/// handler job; the finished dispatch pairs it with what completes its response.
+enum Dispatch<W = OffloadedWork> {
+    /// The response, ready to send
+    Ready(Response),
+    /// Handler work for the worker pool; the response is finished once it returns
+    Offloaded(W),
+}
+
+impl<W> From<Response> for Dispatch<W> {
+    fn from(response: Response) -> Self {
+        Dispatch::Ready(response)
+    }
+}
+
+/// Handler work for a worker, and what turns its result into the response
+struct OffloadedWork {
+    job: OffloadJob,
+    finish: ResponseFinish,
+}
+
+/// A request waiting on a worker, kept until its response can be sent
+struct OffloadedRequest {
+    fd: i32,
+    request: Request,
+    finish: ResponseFinish,
+}
+
+/// Everything applied to a handler's result after dispatch
+struct ResponseFinish {
+    server_idx: usize,
+    version: crate::http::version::Version,
+    /// Error page served when the handler fails; None passes the error on
+    fallback: Option<crate::http::status::StatusCode>,
+    session_id: Option<String>,
+    started: std::time::Instant,
+    routing_time: std::time::Duration,
+    dispatch_phase: &'static str,
+    dispatch_started: std::time::Instant,
+}
+
+/// Handler a matched route sends a request to. `for_request` is the one statement of
+/// the dispatch order, so decisions made before the body arrives agree with dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteHandler {
+    Redirect,
+    Proxy,
+    Options,
+    Delete,
+    Upload,
+    /// A path under the route: CGI script, directory or static file
+    File,
+}
+
+impl RouteHandler {
+    fn for_request(request: &Request, route: &RouteConfig) -> Self {
+        use crate::http::method::Method;
+
+        if route.redirect.is_some() {
+            Self::Redirect
+        } else if route.proxy_pass.is_some() {
+            Self::Proxy
+        } else if request.method == Method::OPTIONS {
+            Self::Options
+        } else if request.method == Method::DELETE {
+            Self::Delete
+        } else if route.upload_dir.is_some() && request.method == Method::POST {
+            Self::Upload
+        } else {
+            Self::File
+        }
+    }
+}
 
 /// Manages multiple server instances and coordinates the event loop
 pub struct ServerManager {
@@ -55,15 +145,150 @@ pub struct ServerManager {
 
     /// Maximum client body size from configuration
     max_body_size: usize,
+
+    /// Largest body any route accepts; parsers read up to it until a request's
+    /// route is known
+    largest_body_size: usize,
+
+    /// Requests between maintenance passes (0 = never)
+    maintenance_interval: u64,
+
+    /// Requests processed since the last maintenance pass
+    cleanup_counter: u64,
+
+    /// Cap on idle keep-alive connections (0 = unlimited)
+    max_idle_connections: usize,
+
+    /// How long the body may take to start after `100 Continue` (None = client timeout only)
+    expect_continue_timeout: Option<std::time::Duration>,
+
+    /// Most segments allowed in a request path (0 = unlimited)
+    max_path_depth: usize,
+
+    /// Answer 421 to Hosts no server is named for instead of using the default server
+    strict_host_matching: bool,
+
+    /// Chunk size and header casing used when writing responses
+    serialize_options: SerializeOptions,
+
+    /// Report per-phase durations in a `Server-Timing` response header
+    server_timing: bool,
+
+    /// Parse already-buffered pipelined bytes as soon as a keep-alive response is sent
+    keep_alive_read_ahead: bool,
+
+    /// Log the raw bytes of every read and write
+    dump_traffic: bool,
+
+    /// Most bytes shown per read or write when dumping traffic (0 = all)
+    dump_traffic_max_bytes: usize,
+
+    /// Ports whose connections start with a PROXY protocol header
+    proxy_protocol_ports: HashSet<u16>,
+
+    /// Reject bare LF line endings in request heads with 400
+    strict_line_endings: bool,
+
+    /// Drop GET/HEAD bodies instead of rejecting the request with 400
+    discard_get_body: bool,
+
+    /// Answer 400 to requests repeating a singleton header
+    reject_duplicate_headers: bool,
+
+    /// Collapse repeated slashes in request paths before routing
+    merge_slashes: bool,
+
+    /// Fold case and drop a trailing dot from Host before virtual host lookup
+    normalize_host: bool,
+
+    /// Most pipelined requests answered in a row before the connection is closed (0 = unlimited)
+    max_pipeline_depth: usize,
+
+    /// Most bytes allowed in a request head
+    max_header_size: usize,
+
+    /// Most header lines allowed in a request
+    max_header_count: usize,
+
+    /// Most cookies read from a request's Cookie header
+    max_cookies: usize,
+
+    /// Most bytes a request's Cookie headers may take together
+    max_cookie_header_size: usize,
+
+    /// Reject requests whose TE header names a coding other than trailers
+    reject_unsupported_te: bool,
+
+    /// How long a closing connection drains client input after its final response
+    lingering_close: std::time::Duration,
+
+    /// How long a kept-alive connection may wait idle for its next request (zero = no limit)
+    keep_alive_timeout: std::time::Duration,
+
+    /// Most requests answered per connection (0 = unlimited)
+    max_keep_alive_requests: usize,
+
+    /// Most connections accepted per event loop pass (0 = unlimited)
+    max_accepts_per_tick: usize,
+
+    /// Most bytes a connection may buffer across input and output (0 = unlimited)
+    max_connection_buffer_bytes: usize,
+
+    /// Runs CGI, proxy and static file handlers off the event loop when `worker_threads` is set
+    worker_pool: Option<WorkerPool<Result<Response>>>,
+
+    /// Requests whose handler is running on a worker, by job token
+    offloaded: HashMap<u64, OffloadedRequest>,
+
+    /// Job token each connection in the Processing state is waiting on
+    processing: HashMap<i32, u64>,
+
+    /// Kept-alive connections whose pipelined bytes are parsed on the next loop pass
+    /// instead of right away (read-ahead off), so they never wait on a read event
+    deferred_pipelined: Vec<i32>,
+
+    /// Token for the next offloaded job
+    next_job_token: u64,
+
+    /// Most open connections allowed per listener port (ports without an entry are unlimited)
+    port_connection_limits: HashMap<u16, usize>,
+
+    /// In-flight requests on routes with `max_concurrent`
+    route_limiter: RouteLimiter,
+
+    /// How `print_server_info` reports: "text", "log", "json" or "off"
+    banner: String,
+}
+
+/// Read-only description of a configured server, for management/introspection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSummary {
+    /// Configured server name
+    pub name: String,
+    /// Addresses the server listens on
+    pub listen_addrs: Vec<SocketAddr>,
+    /// Number of configured routes
+    pub route_count: usize,
 }
 
 impl ServerManager {
     /// Create a new server manager from configuration
     pub fn new(config: Config) -> Result<Self> {
+        crate::common::logger::Logger::set_verbose_errors(config.error_log_verbose);
+
         let event_loop = EventLoop::new()?;
         let poller = event_loop.poller();
         let event_manager = EventManager::new(poller);
 
+        // Finished offloaded work wakes the event loop through the pool's wake fd
+        let worker_pool = if config.worker_threads > 0 {
+            let pool = WorkerPool::new(config.worker_threads)?;
+            event_manager.register_read(pool.wake_fd(), pool.wake_fd() as usize)?;
+            Some(pool)
+        } else {
+            None
+        };
+
         let mut server_instances: Vec<ServerInstance> = Vec::new();
         let mut default_servers = HashMap::new();
         let mut server_lookup = HashMap::new();
@@ -105,7 +330,8 @@ impl ServerManager {
                                 ));
                             }
                         }
-                        let hostname = instance.server_name().to_lowercase();
+                        let hostname =
+                            Self::normalize_host(instance.server_name(), config.normalize_host);
                         server_lookup.insert((port, hostname), current_server_idx);
                     }
                     server_instances.push(instance);
@@ -163,7 +389,11 @@ impl ServerManager {
             let first_server = &server_instances[first_server_idx];
             let addr = SocketAddr::new(first_server.config().server_address, port);
 
-            match crate::application::server::listener::Listener::new(addr) {
+            match crate::application::server::listener::Listener::bind_with_retry(
+                addr,
+                config.bind_retries,
+                std::time::Duration::from_millis(config.bind_retry_backoff_ms),
+            ) {
                 Ok(listener) => {
                     let fd = listener.as_raw_fd();
                     match event_manager.register_read(fd, fd as usize) {
@@ -215,129 +445,278 @@ impl ServerManager {
             server_instances,
             session_manager: SessionManager::new(DEFAULT_SESSION_TIMEOUT_SECS),
             max_body_size: config.client_max_body_size,
+            largest_body_size: config
+                .servers
+                .iter()
+                .flat_map(|server| server.routes.values())
+                .filter_map(|route| route.max_body_size)
+                .fold(config.client_max_body_size, usize::max),
+            maintenance_interval: config.maintenance_interval_requests,
+            cleanup_counter: 0,
+            max_idle_connections: config.max_idle_connections,
+            expect_continue_timeout: (config.expect_continue_timeout_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.expect_continue_timeout_secs)),
+            max_path_depth: config.max_path_depth,
+            strict_host_matching: config.strict_host_matching,
+            serialize_options: SerializeOptions {
+                chunk_size: config.response_chunk_size,
+                canonical_header_names: config.canonical_header_names,
+            },
+            server_timing: config.server_timing,
+            keep_alive_read_ahead: config.keep_alive_read_ahead,
+            dump_traffic: config.dump_traffic,
+            dump_traffic_max_bytes: config.dump_traffic_max_bytes,
+            proxy_protocol_ports: config
+                .servers
+                .iter()
+                .filter(|server| server.proxy_protocol)
+                .flat_map(|server| server.ports.iter().copied())
+                .collect(),
+            strict_line_endings: config.strict_line_endings,
+            discard_get_body: config.discard_get_body,
+            reject_duplicate_headers: config.reject_duplicate_headers,
+            merge_slashes: config.merge_slashes,
+            normalize_host: config.normalize_host,
+            max_pipeline_depth: config.max_pipeline_depth,
+            max_header_size: config.max_header_size,
+            max_header_count: config.max_header_count,
+            max_cookies: config.max_cookies,
+            max_cookie_header_size: config.max_cookie_header_size,
+            reject_unsupported_te: config.reject_unsupported_te,
+            lingering_close: std::time::Duration::from_secs(config.lingering_close_secs),
+            keep_alive_timeout: std::time::Duration::from_secs(config.keep_alive_timeout_secs),
+            max_keep_alive_requests: config.max_keep_alive_requests,
+            max_accepts_per_tick: config.max_accepts_per_tick,
+            max_connection_buffer_bytes: config.max_connection_buffer_bytes,
+            worker_pool,
+            offloaded: HashMap::new(),
+            processing: HashMap::new(),
+            deferred_pipelined: Vec::new(),
+            next_job_token: 0,
+            port_connection_limits: Self::port_connection_limits(&config),
+            route_limiter: RouteLimiter::new(),
+            banner: config.banner.clone(),
         })
     }
 
-    /// Print information about all running servers
-    pub fn print_server_info(&self) {
-        println!("Localhost HTTP Server v0.1.0");
-        println!("================================");
-
-        for (idx, instance) in self.server_instances.iter().enumerate() {
-            let server_name = instance.server_name();
-            let address = instance.config().server_address;
-            let ports = instance.ports();
-            let root = instance.root_path().display();
-
-            println!("\nServer {}: {}", idx, server_name);
-            println!("  Address: {}", address);
-            println!(
-                "  Ports: {}",
-                ports
-                    .iter()
-                    .map(|p| p.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
-            println!("  Root: {}", root);
-
-            if instance.is_default() {
-                println!("  Status: Default server for port(s)");
+    /// Connection limit per port, taking the lowest limit of the servers sharing a port
+    fn port_connection_limits(config: &Config) -> HashMap<u16, usize> {
+        let mut limits: HashMap<u16, usize> = HashMap::new();
+        for server in config.servers.iter().filter(|s| s.max_connections > 0) {
+            for port in &server.ports {
+                let limit = limits.entry(*port).or_insert(server.max_connections);
+                *limit = (*limit).min(server.max_connections);
             }
+        }
+        limits
+    }
 
-            if instance.has_admin_access() {
-                println!("  Admin access: Enabled");
-            }
+    /// Get a server instance by its configured server name (case-insensitive)
+    pub fn server(&self, name: &str) -> Option<&ServerInstance> {
+        self.server_instances
+            .iter()
+            .find(|instance| instance.server_name().eq_ignore_ascii_case(name))
+    }
 
-            // Print routes
-            if !instance.config().routes.is_empty() {
-                println!("  Routes:");
-                for (path, route) in &instance.config().routes {
-                    let methods = if route.methods.is_empty() {
-                        "ALL".to_string()
-                    } else {
-                        route.methods.join(", ")
-                    };
-                    let redirect_info = if let Some(ref redirect) = route.redirect {
-                        format!(" -> redirect: {}", redirect)
-                    } else {
-                        String::new()
-                    };
-                    println!("    {} -> [{}]{}", path, methods, redirect_info);
+    /// Get a server instance by index (configuration order)
+    pub fn server_at(&self, idx: usize) -> Option<&ServerInstance> {
+        self.server_instances.get(idx)
+    }
+
+    /// Summaries of all configured servers, in configuration order
+    pub fn servers(&self) -> Vec<ServerSummary> {
+        self.server_instances
+            .iter()
+            .map(|instance| ServerSummary {
+                name: instance.server_name().to_string(),
+                listen_addrs: instance
+                    .ports()
+                    .into_iter()
+                    .map(|port| SocketAddr::new(instance.config().server_address, port))
+                    .collect(),
+                route_count: instance.config().routes.len(),
+            })
+            .collect()
+    }
+
+    /// Address the listener for configured `port` is actually bound to, so a server
+    /// configured with port 0 can be reached on the port the OS assigned
+    pub fn local_addr(&self, port: u16) -> Option<SocketAddr> {
+        self.port_to_listener
+            .get(&port)
+            .and_then(|listener| listener.local_addr().ok())
+    }
+
+    /// Select the virtual host for a TLS SNI server name on `port`, falling back to the
+    /// port's default server when the name is absent or unknown. This is the hook the
+    /// certificate resolver and vhost routing will share once TLS termination lands.
+    pub fn server_for_sni(&self, port: u16, sni: Option<&str>) -> Option<&ServerInstance> {
+        let by_name = sni.and_then(|name| {
+            // SNI carries a bare DNS name, keyed like a Host header
+            let name = Self::normalize_host(name, self.normalize_host);
+            self.server_lookup.get(&(port, name)).copied()
+        });
+        by_name
+            .or_else(|| self.default_servers.get(&port).copied())
+            .and_then(|idx| self.server_instances.get(idx))
+    }
+
+    /// Structured description of all configured servers, for callers formatting their own banner
+    pub fn server_info(&self) -> ServerInfo {
+        let servers = self
+            .server_instances
+            .iter()
+            .map(|instance| {
+                let config = instance.config();
+                let mut routes: Vec<RouteDetails> = config
+                    .routes
+                    .iter()
+                    .map(|(path, route)| RouteDetails {
+                        path: path.clone(),
+                        methods: route.methods.clone(),
+                        redirect: route.redirect.clone(),
+                    })
+                    .collect();
+                routes.sort_by(|a, b| a.path.cmp(&b.path));
+                let mut cgi_handlers: Vec<(String, String)> = config
+                    .cgi_handlers
+                    .iter()
+                    .map(|(ext, interpreter)| (ext.clone(), interpreter.clone()))
+                    .collect();
+                cgi_handlers.sort();
+
+                ServerDetails {
+                    name: instance.server_name().to_string(),
+                    listen_addrs: instance
+                        .ports()
+                        .into_iter()
+                        .map(|port| {
+                            self.local_addr(port)
+                                .unwrap_or_else(|| SocketAddr::new(config.server_address, port))
+                        })
+                        .collect(),
+                    ports: instance.ports(),
+                    root: instance.root_path().clone(),
+                    is_default: instance.is_default(),
+                    admin_access: instance.has_admin_access(),
+                    routes,
+                    cgi_handlers,
                 }
-            }
+            })
+            .collect();
+
+        ServerInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            servers,
+        }
+    }
 
-            // Print CGI handlers
-            if !instance.config().cgi_handlers.is_empty() {
-                println!("  CGI handlers:");
-                for (ext, interpreter) in &instance.config().cgi_handlers {
-                    println!("    {} -> {}", ext, interpreter);
+    /// Report information about all running servers the way `banner` is configured
+    pub fn print_server_info(&self) {
+        match self.banner.as_str() {
+            "off" => {}
+            "json" => println!("{}", self.server_info().to_json()),
+            "log" => {
+                for line in self.server_info().to_text().lines() {
+                    if !line.is_empty() && !line.starts_with("====") {
+                        crate::common::logger::Logger::info(line);
+                    }
                 }
             }
+            _ => {
+                print!("{}", self.server_info().to_text());
+                println!("Server is running. Press Ctrl+C to stop.\n");
+            }
         }
-
-        println!("\n================================");
-        println!("Server is running. Press Ctrl+C to stop.\n");
     }
 
     /// Run the main server loop
     pub fn run(&mut self) -> Result<()> {
         loop {
             // Wait for events (100ms timeout)
-            let events = self.event_loop.wait(100)?;
+            self.run_once(100)?;
+        }
+    }
 
-            // Collect events to process to avoid borrow checker issues
-            let mut listener_events = Vec::new();
-            let mut client_events = Vec::new();
+    /// One pass of the event loop: wait up to `timeout_ms` for events, serve them,
+    /// then collect finished worker results and time out stale connections
+    fn run_once(&mut self, timeout_ms: i32) -> Result<()> {
+        self.parse_deferred_pipelined();
 
-            // First pass: collect event data
-            for event in events {
-                let fd = event.ident as i32;
-                if let Some(&port) = self.listener_to_port.get(&fd) {
-                    listener_events.push((fd, port));
-                } else {
-                    // Copy event data (kevent is Copy)
-                    client_events.push((fd, *event));
-                }
-            }
+        let wake_fd = self.worker_pool.as_ref().map(|pool| pool.wake_fd());
+        let events = self.event_loop.wait(timeout_ms)?;
 
-            // Second pass: process listener events
-            for (fd, port) in listener_events {
-                if let Err(e) = self.handle_listener_event(fd, port) {
-                    // Log error but continue processing other events
-                    crate::common::logger::Logger::error(&format!(
-                        "Error handling listener event for fd {}: {}",
-                        fd, e
-                    ));
-                }
+        // Collect events to process to avoid borrow checker issues
+        let mut listener_events = Vec::new();
+        let mut client_events = Vec::new();
+
+        // First pass: collect event data
+        for event in events {
+            let fd = event.fd;
+            if Some(fd) == wake_fd {
+                // Collected below, with any other finished work
+                continue;
+            }
+            if let Some(&port) = self.listener_to_port.get(&fd) {
+                listener_events.push((fd, port));
+            } else {
+                client_events.push((fd, event));
             }
+        }
 
-            // Third pass: process client events
-            for (fd, event) in client_events {
-                if let Err(e) = self.handle_client_event(fd, &event) {
-                    // Log error but continue processing other events
-                    // Note: handle_client_event should not return errors for client events
-                    // as errors are handled internally, but we log just in case
-                    crate::common::logger::Logger::error(&format!(
-                        "Unexpected error handling client event for fd {}: {}",
-                        fd, e
-                    ));
-                }
+        // Second pass: process listener events, sharing one accept budget between them
+        let mut accept_budget = match self.max_accepts_per_tick {
+            0 => usize::MAX,
+            limit => limit,
+        };
+        for (fd, port) in listener_events {
+            if let Err(e) = self.handle_listener_event(fd, port, &mut accept_budget) {
+                // Log error but continue processing other events
+                crate::common::logger::Logger::error(&format!(
+                    "Error handling listener event for fd {}: {}",
+                    fd, e
+                ));
             }
+        }
 
-            // Clean up timed out connections
-            if let Err(e) = self.cleanup_connections() {
-                // Log cleanup errors but don't stop server
+        // Third pass: process client events
+        for (fd, event) in client_events {
+            if let Err(e) = self.handle_client_event(fd, event) {
+                // Log error but continue processing other events
+                // Note: handle_client_event should not return errors for client events
+                // as errors are handled internally, but we log just in case
                 crate::common::logger::Logger::error(&format!(
-                    "Error during connection cleanup: {}",
-                    e
+                    "Unexpected error handling client event for fd {}: {}",
+                    fd, e
                 ));
             }
         }
+
+        self.complete_offloaded_requests();
+
+        // Clean up timed out connections
+        if let Err(e) = self.cleanup_connections() {
+            // Log cleanup errors but don't stop server
+            crate::common::logger::Logger::error(&format!(
+                "Error during connection cleanup: {}",
+                e
+            ));
+        }
+        Ok(())
+    }
+
+    /// Accept pending connections on a listening socket, taking each from `budget` and
+    /// stopping when it runs out. Listeners stay readable while connections are
+    /// pending, so the rest are accepted on a later pass.
+    fn handle_listener_event(&mut self, fd: i32, port: u16, budget: &mut usize) -> Result<()> {
+        while *budget > 0 && self.accept_connection(fd, port)? {
+            *budget -= 1;
+        }
+        Ok(())
     }
 
-    /// Handle event on a listening socket
-    fn handle_listener_event(&mut self, fd: i32, port: u16) -> Result<()> {
+    /// Accept one connection on a listening socket; false when none is pending
+    fn accept_connection(&mut self, fd: i32, port: u16) -> Result<bool> {
         // Get the listener for this port
         let listener = self.port_to_listener.get_mut(&port).ok_or_else(|| {
             ServerError::NetworkError(format!("No listener found for port {}", port))
@@ -347,12 +726,16 @@ impl ServerManager {
             Ok(Some(client_socket)) => {
                 let client_fd = client_socket.as_raw_fd();
                 // Create connection with port tracking
-                let connection = Connection::with_port(
+                let mut connection = Connection::with_port(
                     client_socket,
                     crate::common::constants::DEFAULT_REQUEST_TIMEOUT_SECS,
                     port,
                 );
-                let parser = RequestParser::with_max_body_size(self.max_body_size);
+                if self.proxy_protocol_ports.contains(&port) {
+                    connection.expect_proxy_header();
+                }
+                let parser = self.new_parser();
+                let over_limit = self.port_at_connection_limit(port);
 
                 self.connections.insert(client_fd, connection);
                 self.parsers.insert(client_fd, parser);
@@ -371,10 +754,28 @@ impl ServerManager {
                     ));
                     return Err(e);
                 }
+
+                if over_limit {
+                    crate::common::logger::Logger::warn(&format!(
+                        "Connection limit reached on port {}, answering fd {} with 503",
+                        port, client_fd
+                    ));
+                    self.send_error_response(
+                        client_fd,
+                        crate::http::status::StatusCode::SERVICE_UNAVAILABLE,
+                        crate::http::version::Version::Http11,
+                    )?;
+                    return Ok(true);
+                }
+
+                // Make room for the new connection among idle keep-alive ones
+                self.enforce_idle_limit()?;
+                Ok(true)
             }
             Ok(None) => {
                 // No connection available (non-blocking accept)
                 // This is normal, just return
+                Ok(false)
             }
             Err(e) => {
                 // Error accepting connection - log but don't crash
@@ -382,10 +783,24 @@ impl ServerManager {
                     "Error accepting connection on listener fd {}: {}",
                     fd, e
                 ));
-                return Err(e);
+                Err(e)
             }
         }
-        Ok(())
+    }
+
+    /// Request parser configured with the server's body size and request head rules
+    fn new_parser(&self) -> RequestParser {
+        RequestParser::with_limits(
+            self.largest_body_size,
+            self.max_header_size,
+            self.max_header_count,
+        )
+        .with_strict_line_endings(self.strict_line_endings)
+        .with_discard_get_body(self.discard_get_body)
+        .with_reject_duplicate_headers(self.reject_duplicate_headers)
+        .with_merge_slashes(self.merge_slashes)
+        .with_reject_unsupported_te(self.reject_unsupported_te)
+        .with_cookie_limits(self.max_cookies, self.max_cookie_header_size)
     }
 
     /// Get connection or return error
@@ -444,7 +859,7 @@ impl ServerManager {
         keep_alive: bool,
     ) -> Result<()> {
         // Serialize response
-        let response_bytes = ResponseSerializer::serialize_auto(response)?;
+        let response_bytes = ResponseSerializer::serialize_with(response, &self.serialize_options)?;
 
         // Write response to connection buffer
         {
@@ -453,6 +868,9 @@ impl ServerManager {
             connection.write_buffer_mut().extend(&response_bytes);
             connection.set_state(ConnectionState::Writing);
         }
+        if self.close_if_over_buffer_cap(fd)? {
+            return Ok(());
+        }
 
         // Register for write events
         self.event_manager.register_write(fd, fd as usize)?;
@@ -461,7 +879,7 @@ impl ServerManager {
     }
 
     /// Handle event on a client connection
-    fn handle_client_event(&mut self, fd: i32, _event: &Kevent) -> Result<()> {
+    fn handle_client_event(&mut self, fd: i32, _event: PollEvent) -> Result<()> {
         // Get connection state first to avoid borrow issues
         let state = match self.get_connection(fd) {
             Ok(connection) => *connection.state(),
@@ -476,20 +894,31 @@ impl ServerManager {
                 if let Err(e) = self.handle_read(fd) {
                     // Error already handled in handle_read (connection closed)
                     // Log error but don't propagate to avoid breaking event loop
-                    crate::common::logger::Logger::error(&format!(
-                        "Error handling read event for fd {}: {}",
-                        fd, e
-                    ));
+                    crate::common::logger::Logger::error_with(
+                        &format!("Error handling read event for fd {}", fd),
+                        &e,
+                    );
                 }
             }
+            ConnectionState::Processing => {
+                // Nothing to do until the worker's response has been queued
+            }
             ConnectionState::Writing => {
                 if let Err(e) = self.handle_write(fd) {
                     // Error already handled in handle_write (connection closed)
                     // Log error but don't propagate to avoid breaking event loop
-                    crate::common::logger::Logger::error(&format!(
-                        "Error handling write event for fd {}: {}",
-                        fd, e
-                    ));
+                    crate::common::logger::Logger::error_with(
+                        &format!("Error handling write event for fd {}", fd),
+                        &e,
+                    );
+                }
+            }
+            ConnectionState::Lingering => {
+                if let Err(e) = self.drain_lingering(fd) {
+                    crate::common::logger::Logger::error_with(
+                        &format!("Error draining lingering fd {}", fd),
+                        &e,
+                    );
                 }
             }
             ConnectionState::Closed => {
@@ -524,8 +953,32 @@ impl ServerManager {
             return Ok(());
         }
 
+        {
+            let connection = self.get_connection_mut(fd)?;
+            connection.touch();
+            connection.set_idle(false);
+        }
+        if let Some(dump) = self.traffic_dump(fd, "received", &buf[..n]) {
+            crate::common::logger::Logger::info(&dump);
+        }
+
+        // On PROXY protocol ports, HTTP data starts after the proxy's header
+        let proxied_data;
+        let mut data = &buf[..n];
+        if self.get_connection(fd)?.awaiting_proxy_header() {
+            proxied_data = match self.read_proxy_header(fd, data) {
+                Ok(Some(rest)) => rest,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    self.close_connection_on_error(fd)?;
+                    return Err(e);
+                }
+            };
+            data = &proxied_data;
+        }
+
         // Add data to parser
-        if let Err(e) = self.get_parser_mut(fd)?.add_data(&buf[..n]) {
+        if let Err(e) = self.get_parser_mut(fd)?.add_data(data) {
             // Body size error - send 413 response
             if Self::is_body_size_error(&e) {
                 return self.send_error_response(
@@ -538,11 +991,38 @@ impl ServerManager {
             self.close_connection_on_error(fd)?;
             return Err(e);
         }
+        if self.close_if_over_buffer_cap(fd)? {
+            return Ok(());
+        }
+
+        self.parse_buffered_request(fd)
+    }
+
+    /// Close a connection holding more than `max_connection_buffer_bytes` across its
+    /// buffered input and queued output; true when it was closed
+    fn close_if_over_buffer_cap(&mut self, fd: i32) -> Result<bool> {
+        if self.max_connection_buffer_bytes == 0 {
+            return Ok(false);
+        }
+        let buffered = self.get_connection(fd)?.buffered_bytes()
+            + self.parsers.get(&fd).map_or(0, RequestParser::buffered);
+        if buffered <= self.max_connection_buffer_bytes {
+            return Ok(false);
+        }
+        crate::common::logger::Logger::warn(&format!(
+            "Closing fd {}: {} bytes buffered, over max_connection_buffer_bytes {}",
+            fd, buffered, self.max_connection_buffer_bytes
+        ));
+        self.close_connection_on_error(fd)?;
+        Ok(true)
+    }
 
-        // Try to parse request
+    /// Parse the bytes buffered for a connection and answer the request once it's complete
+    fn parse_buffered_request(&mut self, fd: i32) -> Result<()> {
         match self.get_parser_mut(fd)?.parse() {
             Ok(Some(request)) => {
                 // Request parsed successfully - process it
+                self.get_connection_mut(fd)?.clear_continue_wait();
                 if let Err(e) = self.process_request(fd, request) {
                     // Error processing request - close connection
                     self.close_connection_on_error(fd)?;
@@ -550,7 +1030,24 @@ impl ServerManager {
                 }
             }
             Ok(None) => {
-                // Need more data - continue reading
+                // Once the headers are in, the body is held to its route's limit and
+                // possibly streamed to disk
+                if let Err(e) = self.plan_pending_body(fd) {
+                    if Self::is_body_size_error(&e) {
+                        return self.send_error_response(
+                            fd,
+                            crate::http::status::StatusCode::PAYLOAD_TOO_LARGE,
+                            crate::http::version::Version::Http11,
+                        );
+                    }
+                    self.close_connection_on_error(fd)?;
+                    return Err(e);
+                }
+                // Need more data - invite the body if the client is waiting for 100 Continue
+                if let Err(e) = self.handle_expect_continue(fd) {
+                    self.close_connection_on_error(fd)?;
+                    return Err(e);
+                }
             }
             Err(e) => {
                 // Check if it's a body size error
@@ -562,6 +1059,15 @@ impl ServerManager {
                         crate::http::version::Version::Http11,
                     );
                 }
+                // Rejected request heads get a response instead of a dropped connection
+                if let Some(status) = Self::parse_error_status(&e) {
+                    crate::common::logger::Logger::warn(&format!("Rejecting request: {}", e));
+                    return self.send_error_response(
+                        fd,
+                        status,
+                        crate::http::version::Version::Http11,
+                    );
+                }
                 // Other parse error - close connection
                 self.close_connection_on_error(fd)?;
                 return Err(e);
@@ -571,67 +1077,709 @@ impl ServerManager {
         Ok(())
     }
 
-    /// Process a parsed HTTP request
-    fn process_request(&mut self, fd: i32, request: Request) -> Result<()> {
-        // Get connection to find the port it came in on
-        let port = self.get_connection_port(fd)?;
+    /// Buffer bytes until the connection's PROXY protocol header is complete, then
+    /// return whatever followed it for HTTP parsing
+    fn read_proxy_header(&mut self, fd: i32, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        let connection = self.get_connection_mut(fd)?;
+        connection.read_buffer_mut().extend(data);
+        let buffered = connection.read_buffer().as_slice();
 
-        // Log EVERY request at the very start
-        crate::common::logger::Logger::info(
-            "═══════════════════════════════════════════════════════════",
-        );
-        crate::common::logger::Logger::info(&format!(
-            "📥 NEW REQUEST: {} {} on port {}",
-            request.method,
-            request.path(),
-            port
-        ));
+        match proxy_protocol::parse_v1(&buffered)? {
+            ProxyHeader::Incomplete => Ok(None),
+            ProxyHeader::Complete { source, len } => {
+                connection.read_buffer_mut().clear();
+                connection.set_proxied_client(source);
+                if let Some(source) = source {
+                    crate::common::logger::Logger::debug(&format!(
+                        "PROXY protocol on fd {}: client {}",
+                        fd, source
+                    ));
+                }
+                Ok(Some(buffered[len..].to_vec()))
+            }
+        }
+    }
 
-        // Find server instance based on Host header and port
-        let server_idx = self.find_server_for_request(&request, port)?;
-        let server_instance = self.get_server_instance(server_idx)?;
+    /// Raw dump of bytes read from or written to `fd`, when traffic dumping is on
+    fn traffic_dump(&self, fd: i32, direction: &str, bytes: &[u8]) -> Option<String> {
+        self.dump_traffic.then(|| {
+            crate::common::logger::Logger::format_traffic(
+                &format!("Traffic fd {} {}", fd, direction),
+                bytes,
+                self.dump_traffic_max_bytes,
+            )
+        })
+    }
 
-        // Create router
-        let router = Router::new(
-            server_instance.config(),
-            server_instance.root_path().clone(),
+    /// Send `100 Continue` once a request that asked for it is waiting on its body,
+    /// and stop the continue timer as soon as the body starts arriving
+    fn handle_expect_continue(&mut self, fd: i32) -> Result<()> {
+        let awaiting = self.get_parser_mut(fd)?.awaiting_continue();
+        let timeout = self.expect_continue_timeout;
+        let connection = self.get_connection_mut(fd)?;
+        if !awaiting {
+            connection.clear_continue_wait();
+            return Ok(());
+        }
+        if connection.continue_sent() {
+            return Ok(());
+        }
+
+        let status = crate::http::status::StatusCode::CONTINUE;
+        let interim = format!(
+            "{} {} {}\r\n\r\n",
+            crate::http::version::Version::Http11,
+            status.as_u16(),
+            status.reason_phrase()
         );
+        let n = write_non_blocking(connection.socket_mut(), interim.as_bytes())?;
+        if n != interim.len() {
+            return Err(ServerError::HttpError(
+                "Failed to send 100 Continue".to_string(),
+            ));
+        }
+        connection.start_continue_wait(timeout);
+        Ok(())
+    }
 
-        // Log available routes for this server
-        let available_routes: Vec<String> = server_instance
-            .config()
-            .routes
-            .iter()
-            .map(|(path, route)| {
-                let redirect_info = route
-                    .redirect
-                    .as_ref()
-                    .map(|r| format!(" -> redirect: {}", r))
-                    .unwrap_or_default();
-                format!("{}[{}]{}", path, route.methods.join(","), redirect_info)
-            })
-            .collect();
-        crate::common::logger::Logger::info(&format!(
-            "Server '{}' (idx: {}) has {} routes: [{}]",
-            server_instance.server_name(),
-            server_idx,
-            available_routes.len(),
-            available_routes.join(", ")
-        ));
+    /// Once the headers of a request are in, route it once to settle how its body is read:
+    /// held to the route's size limit, and for large requests streamed to disk instead of
+    /// buffered in memory (multipart uploads into the upload directory, bodies for CGI
+    /// scripts into a spool file fed to the script's stdin). The parser keeps the result
+    /// until the next request, so later reads don't route again.
+    fn plan_pending_body(&mut self, fd: i32) -> Result<()> {
+        let port = self.get_connection_port(fd)?;
+        let (limit, target, spool) = {
+            let parser = self
+                .parsers
+                .get(&fd)
+                .ok_or_else(|| Self::not_found_error("Parser", fd))?;
+            let Some(request) = parser.request_awaiting_plan() else {
+                return Ok(());
+            };
+            let server_instance = self
+                .find_server_for_request(request, port)
+                .ok()
+                .and_then(|idx| self.get_server_instance(idx).ok());
+            let route = server_instance.and_then(|server| server.router().match_route(request));
+            match (server_instance, route) {
+                (Some(server_instance), Some(route)) => (
+                    route.max_body_size.unwrap_or(self.max_body_size),
+                    Self::multipart_upload_target(server_instance, request, route),
+                    Self::is_cgi_spool_target(server_instance, request, route),
+                ),
+                _ => (self.max_body_size, None, false),
+            }
+        };
 
-        // Log the request path and which server instance is handling it
-        crate::common::logger::Logger::info(&format!(
-            "Processing request: {} {} (Host: {}) -> Server: '{}' (idx: {})",
-            request.method,
-            request.path(),
+        let parser = self.get_parser_mut(fd)?;
+        parser.limit_body_size(limit)?;
+        if let Some((boundary, dir)) = target {
+            crate::common::logger::Logger::info(&format!(
+                "Streaming multipart upload to {}",
+                dir.display()
+            ));
+            parser.stream_multipart(MultipartStream::new(&boundary, dir));
+        } else if spool {
+            let spool = BodySpool::create(&std::env::temp_dir())?;
+            crate::common::logger::Logger::info("Spooling large CGI request body to disk");
+            parser.spool_body(spool);
+        }
+        Ok(())
+    }
+
+    /// Whether a request body large enough to spool is headed for a CGI script, which
+    /// then reads it from disk instead of memory
+    fn is_cgi_spool_target(
+        server_instance: &ServerInstance,
+        request: &Request,
+        route: &RouteConfig,
+    ) -> bool {
+        let router = server_instance.router();
+        if !request.method.allows_body()
+            || request.is_chunked()
+            || request.content_length().unwrap_or(0) < CGI_BODY_SPOOL_THRESHOLD
+            || (server_instance.config().read_only && request.method.is_mutating())
+            || RouteHandler::for_request(request, route) != RouteHandler::File
+            || !router.is_method_allowed(request, route)
+        {
+            return false;
+        }
+        router
+            .resolve_within_root(request, route)
+            .is_ok_and(|path| Self::is_cgi_script(server_instance, route, &path))
+    }
+
+    /// Whether `file_path` is a script to execute (routes may opt out to serve scripts as source)
+    fn is_cgi_script(
+        server_instance: &ServerInstance,
+        route: &RouteConfig,
+        file_path: &std::path::Path,
+    ) -> bool {
+        let is_cgi = server_instance.router().is_cgi_enabled(route)
+            && (route.cgi_extension.is_some()
+                || file_path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|ext| {
+                        let ext_with_dot = format!(".{}", ext);
+                        server_instance
+                            .config()
+                            .cgi_handlers
+                            .contains_key(&ext_with_dot)
+                    })
+                    .unwrap_or(false));
+        is_cgi && crate::common::path_utils::is_valid_file(file_path)
+    }
+
+    /// Boundary and upload directory when `request` is a multipart POST large enough to
+    /// stream, headed for an upload route that will accept it
+    fn multipart_upload_target(
+        server_instance: &ServerInstance,
+        request: &Request,
+        route: &RouteConfig,
+    ) -> Option<(String, PathBuf)> {
+        if request.method != crate::http::method::Method::POST
+            || request.is_chunked()
+            || request.content_length().unwrap_or(0) < MULTIPART_STREAM_THRESHOLD
+        {
+            return None;
+        }
+        let boundary = form_data_boundary(request.content_type()?)?;
+
+        let router = server_instance.router();
+        if server_instance.config().read_only
+            || RouteHandler::for_request(request, route) != RouteHandler::Upload
+            || !router.is_method_allowed(request, route)
+            || !UploadHandler::is_content_type_allowed(
+                &route.allowed_content_types,
+                request.content_type(),
+            )
+        {
+            return None;
+        }
+        let dir = router.resolve_path(route.upload_dir.as_ref()?);
+        Some((boundary, dir))
+    }
+
+    /// Count a processed request and run maintenance once the configured interval is reached.
+    /// Returns whether maintenance ran.
+    fn record_request_for_maintenance(&mut self) -> bool {
+        if self.maintenance_interval == 0 {
+            return false;
+        }
+        self.cleanup_counter += 1;
+        if self.cleanup_counter < self.maintenance_interval {
+            return false;
+        }
+        self.cleanup_counter = 0;
+        self.run_maintenance();
+        true
+    }
+
+    /// Drop expired sessions and give back memory held by shrunken maps
+    fn run_maintenance(&mut self) {
+        let removed = self.session_manager.cleanup_expired();
+        self.session_manager.compact();
+        self.connections.shrink_to_fit();
+        self.parsers.shrink_to_fit();
+        if removed > 0 {
+            crate::common::logger::Logger::info(&format!(
+                "Maintenance: removed {} expired sessions",
+                removed
+            ));
+        }
+    }
+
+    /// Process a parsed HTTP request
+    fn process_request(&mut self, fd: i32, mut request: Request) -> Result<()> {
+        // Get connection to find the port it came in on
+        let port = self.get_connection_port(fd)?;
+        let client_addr = self.get_connection(fd)?.client_addr();
+        if client_addr.is_none() {
+            // Serve the request anyway; address-based features just see no client address
+            crate::common::logger::Logger::warn(&format!(
+                "Peer address unavailable for fd {}, serving without it",
+                fd
+            ));
+        }
+
+        let request_line = format!("{} {}", request.method, request.path());
+        let dispatch = self.isolate_panics(&request_line, port, |manager| {
+            manager.dispatch_request(&mut request, port, client_addr)
+        });
+        match dispatch {
+            Ok(Dispatch::Offloaded(work)) => self.offload_request(fd, request, work),
+            Ok(Dispatch::Ready(response)) => self.respond(fd, request, Ok(response)),
+            Err(e) => self.respond(fd, request, Err(e)),
+        }
+    }
+
+    /// Send the response to a request on its connection, deciding whether the
+    /// connection stays open
+    fn respond(&mut self, fd: i32, request: Request, response: Result<Response>) -> Result<()> {
+        // Streamed parts the handler didn't move into place are no longer needed
+        Self::discard_request_files(&request);
+        let mut response = response?;
+        let mut keep_alive = Self::keep_alive_after(&request, &mut response);
+        if request.method == crate::http::method::Method::HEAD {
+            response.omit_body();
+        }
+
+        // Bytes still buffered belong to requests the client pipelined behind this one;
+        // past the depth limit they are dropped rather than answered one after another
+        let more_queued = self.parsers.get(&fd).is_some_and(|p| p.buffered() > 0);
+        let depth = self.get_connection_mut(fd)?.record_pipelined(more_queued);
+        if keep_alive && self.max_pipeline_depth > 0 && depth >= self.max_pipeline_depth {
+            crate::common::logger::Logger::warn(&format!(
+                "Closing fd {} after {} pipelined requests",
+                fd, depth
+            ));
+            response.set_connection("close");
+            keep_alive = false;
+        }
+        let served = self.get_connection_mut(fd)?.record_request();
+        if keep_alive && self.max_keep_alive_requests > 0 && served >= self.max_keep_alive_requests
+        {
+            crate::common::logger::Logger::info(&format!(
+                "Closing fd {} after {} requests",
+                fd, served
+            ));
+            response.set_connection("close");
+            keep_alive = false;
+        }
+
+        // Write response to connection
+        self.write_response_to_connection(fd, &response, keep_alive)?;
+
+        Ok(())
+    }
+
+    /// Remove the spooled body and streamed upload parts a request left on disk
+    fn discard_request_files(request: &Request) {
+        remove_part_files(&request.streamed_parts);
+        if let Some(ref body_file) = request.body_file {
+            let _ = std::fs::remove_file(body_file);
+        }
+    }
+
+    /// Hand a request's handler work to the worker pool; the connection reads nothing
+    /// more until the response is queued, so pipelined requests are answered in order
+    fn offload_request(&mut self, fd: i32, request: Request, work: OffloadedWork) -> Result<()> {
+        let token = self.next_job_token;
+        self.next_job_token += 1;
+        let pool = self
+            .worker_pool
+            .as_ref()
+            .ok_or_else(|| ServerError::HttpError("No worker pool configured".to_string()))?;
+        if let Err(e) = pool.submit(token, work.job) {
+            Self::discard_request_files(&request);
+            return Err(e);
+        }
+        self.offloaded.insert(
+            token,
+            OffloadedRequest {
+                fd,
+                request,
+                finish: work.finish,
+            },
+        );
+        self.processing.insert(fd, token);
+        let _ = self.event_manager.unregister_read(fd);
+        self.get_connection_mut(fd)?
+            .set_state(ConnectionState::Processing);
+        Ok(())
+    }
+
+    /// Send the responses of offloaded requests whose workers have finished
+    fn complete_offloaded_requests(&mut self) {
+        let finished = match &self.worker_pool {
+            Some(pool) => pool.completed(),
+            None => return,
+        };
+        for (token, result) in finished {
+            let Some(offloaded) = self.offloaded.remove(&token) else {
+                continue;
+            };
+            let fd = offloaded.fd;
+            if self.processing.get(&fd) != Some(&token) {
+                // The connection closed while the worker ran (its fd may be reused since)
+                Self::discard_request_files(&offloaded.request);
+                continue;
+            }
+            self.processing.remove(&fd);
+
+            let response = match result {
+                Ok(result) => self.finish_response(result, &offloaded.finish),
+                Err(payload) => {
+                    // Like a panic on the event loop, only this request is answered with 500
+                    crate::common::logger::Logger::panic(
+                        &format!(
+                            "Request {} {}",
+                            offloaded.request.method,
+                            offloaded.request.path()
+                        ),
+                        payload.as_ref(),
+                    );
+                    self.get_server_instance(offloaded.finish.server_idx)
+                        .and_then(|server_instance| {
+                            self.generate_error_response(
+                                server_instance,
+                                crate::http::status::StatusCode::INTERNAL_SERVER_ERROR,
+                                offloaded.finish.version,
+                            )
+                        })
+                }
+            };
+            let resumed = self
+                .event_manager
+                .register_read(fd, fd as usize)
+                .and_then(|_| self.respond(fd, offloaded.request, response));
+            if let Err(e) = resumed {
+                crate::common::logger::Logger::error_with(
+                    &format!("Error completing offloaded request on fd {}", fd),
+                    &e,
+                );
+                let _ = self.close_connection_on_error(fd);
+            }
+        }
+    }
+
+    /// Run `build` so that a panic answers just this request with 500 instead of taking
+    /// down the event loop. The session store recovers a lock the panic poisoned, so later
+    /// requests keep their sessions.
+    fn isolate_panics<T, F>(&mut self, request_line: &str, port: u16, build: F) -> Result<T>
+    where
+        T: From<Response>,
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| build(self))) {
+            Ok(result) => result,
+            Err(payload) => {
+                crate::common::logger::Logger::panic(
+                    &format!("Request {}", request_line),
+                    payload.as_ref(),
+                );
+                self.error_response_for_port(
+                    port,
+                    crate::http::status::StatusCode::INTERNAL_SERVER_ERROR,
+                )
+                .map(T::from)
+            }
+        }
+    }
+
+    /// Decide whether the connection outlives this response. A body without a definite
+    /// length can only be delimited by closing, and a client that won't keep the
+    /// connection is told it closes, so both get `Connection: close`.
+    fn keep_alive_after(request: &Request, response: &mut Response) -> bool {
+        if !response.has_definite_length() || !request.should_keep_alive() {
+            response.set_connection("close");
+            return false;
+        }
+        !response.closes_connection()
+    }
+
+    /// Process one request given as raw bytes, bypassing sockets and the event loop,
+    /// and return the serialized response. The request is handled as if it arrived
+    /// on the first configured port; parse failures are answered as on a connection.
+    pub fn handle_request_bytes(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let port = self
+            .server_instances
+            .first()
+            .and_then(|server| server.config().ports.first().copied())
+            .unwrap_or(0);
+
+        let mut parser = self.new_parser();
+        let parsed = parser.add_data(bytes).and_then(|_| parser.parse());
+        let response = match parsed {
+            Ok(Some(mut request)) => {
+                let request_line = format!("{} {}", request.method, request.path());
+                let response = self.isolate_panics(&request_line, port, |manager| {
+                    manager.build_response(&mut request, port, None)
+                });
+                response.map(|mut response| {
+                    if request.method == crate::http::method::Method::HEAD {
+                        response.omit_body();
+                    }
+                    response
+                })
+            }
+            Ok(None) => {
+                self.error_response_for_port(port, crate::http::status::StatusCode::BAD_REQUEST)
+            }
+            Err(e) => {
+                let status = if Self::is_body_size_error(&e) {
+                    crate::http::status::StatusCode::PAYLOAD_TOO_LARGE
+                } else {
+                    Self::parse_error_status(&e)
+                        .unwrap_or(crate::http::status::StatusCode::BAD_REQUEST)
+                };
+                self.error_response_for_port(port, status)
+            }
+        };
+
+        let response = response.unwrap_or_else(|e| {
+            Response::internal_error_with_message(
+                crate::http::version::Version::Http11,
+                &e.to_string(),
+            )
+        });
+        ResponseSerializer::serialize_with(&response, &self.serialize_options).unwrap_or_default()
+    }
+
+    /// Error response from the default server for a port
+    fn error_response_for_port(
+        &self,
+        port: u16,
+        status_code: crate::http::status::StatusCode,
+    ) -> Result<Response> {
+        let server_idx = self.get_default_server_for_port(port)?;
+        let server_instance = self.get_server_instance(server_idx)?;
+        self.generate_error_response(
+            server_instance,
+            status_code,
+            crate::http::version::Version::Http11,
+        )
+    }
+
+    /// Route a request to its handler and build the response (session cookie included),
+    /// running offloaded handler work in place
+    fn build_response(
+        &mut self,
+        request: &mut Request,
+        port: u16,
+        client_addr: Option<SocketAddr>,
+    ) -> Result<Response> {
+        match self.dispatch_request(request, port, client_addr)? {
+            Dispatch::Ready(response) => Ok(response),
+            Dispatch::Offloaded(work) => {
+                let result = (work.job)();
+                self.finish_response(result, &work.finish)
+            }
+        }
+    }
+
+    /// Route a request to its handler. CPU-bound or blocking handlers are returned as
+    /// work for the worker pool when there is one; everything else answers right away.
+    fn dispatch_request(
+        &mut self,
+        request: &mut Request,
+        port: u16,
+        client_addr: Option<SocketAddr>,
+    ) -> Result<Dispatch> {
+        let started = std::time::Instant::now();
+
+        // Log EVERY request at the very start
+        crate::common::logger::Logger::info(
+            "═══════════════════════════════════════════════════════════",
+        );
+        crate::common::logger::Logger::info(&format!(
+            "📥 NEW REQUEST: {} {} on port {}",
+            request.method,
+            request.path(),
+            port
+        ));
+
+        // HTTP/2 is not supported: ignore an h2c upgrade offer and answer as HTTP/1.1
+        if request.ignore_h2c_upgrade() {
+            crate::common::logger::Logger::info(
+                "Ignoring 'Upgrade: h2c' offer, serving request as HTTP/1.1",
+            );
+        }
+
+        // Under strict host matching, a Host no server is named for is not ours to answer
+        if self.strict_host_matching {
+            if let Some(host) = request.host() {
+                if self.server_for_host(host, port).is_none() {
+                    crate::common::logger::Logger::warn(&format!(
+                        "No server named '{}' on port {}, answering 421",
+                        host, port
+                    ));
+                    return self
+                        .error_response_for_port(
+                            port,
+                            crate::http::status::StatusCode::MISDIRECTED_REQUEST,
+                        )
+                        .map(Dispatch::Ready);
+                }
+            }
+        }
+
+        // Find server instance based on Host header and port
+        let server_idx = self.find_server_for_request(request, port)?;
+        let server_instance = self.get_server_instance(server_idx)?;
+
+        // Reuse the router compiled for this server (cloning only shares the route table)
+        let router = server_instance.router().clone();
+
+        // Log available routes for this server
+        let available_routes: Vec<String> = server_instance
+            .config()
+            .routes
+            .iter()
+            .map(|(path, route)| {
+                let redirect_info = route
+                    .redirect
+                    .as_ref()
+                    .map(|r| format!(" -> redirect: {}", r))
+                    .unwrap_or_default();
+                format!("{}[{}]{}", path, route.methods.join(","), redirect_info)
+            })
+            .collect();
+        crate::common::logger::Logger::info(&format!(
+            "Server '{}' (idx: {}) has {} routes: [{}]",
+            server_instance.server_name(),
+            server_idx,
+            available_routes.len(),
+            available_routes.join(", ")
+        ));
+
+        // Log the request path and which server instance is handling it
+        crate::common::logger::Logger::info(&format!(
+            "Processing request: {} {} (Host: {}) -> Server: '{}' (idx: {})",
+            request.method,
+            request.path(),
             request.host().map(|h| h.as_str()).unwrap_or("none"),
             server_instance.server_name(),
             server_idx
         ));
 
+        // Get or create the client's session before dispatch so handlers can use it
+        let session_id = request.cookie(self.session_manager.cookie_name());
+        let session_id = self
+            .session_manager
+            .get_or_create_session(session_id.as_deref());
+
+        let mut ctx = RequestContext::new(&router, server_instance.config(), port);
+        if let Some(client_addr) = client_addr {
+            ctx = ctx.with_client_addr(client_addr);
+        }
+        if let Some(sid) = &session_id {
+            ctx = ctx.with_session(SessionHandle::new(&self.session_manager, sid.clone()));
+        }
+
         // Determine which handler to use based on route
-        let route_match = router.match_route_with_path(&request);
-        let response = if let Some((matched_path, route)) = route_match {
+        let route_match = router.match_route_with_path(request);
+
+        // A capped route keeps its slot until the handler below has returned
+        let mut route_busy = false;
+        let route_permit = route_match.and_then(|(matched_path, route)| {
+            let permit =
+                self.route_limiter
+                    .try_acquire(server_idx, matched_path, route.max_concurrent?);
+            route_busy = permit.is_none();
+            permit
+        });
+        let routing_time = started.elapsed();
+        let dispatch_started = std::time::Instant::now();
+        // Which Server-Timing metric the dispatch below counts towards
+        let mut dispatch_phase = "handler";
+        // The error page served if handler work on the worker pool fails
+        let mut fallback = None;
+        let handled: Dispatch<OffloadJob> = if request.has_unsupported_expectation() {
+            // Only 100-continue is understood - anything else must be refused (RFC 7231 §5.1.1)
+            crate::common::logger::Logger::warn(&format!(
+                "Unsupported Expect value '{}' for {} {}",
+                request.expect().map(|v| v.as_str()).unwrap_or(""),
+                request.method,
+                request.path()
+            ));
+            self.generate_error_response(
+                server_instance,
+                crate::http::status::StatusCode::EXPECTATION_FAILED,
+                request.version,
+            )?
+            .into()
+        } else if self.max_path_depth > 0
+            && crate::common::path_utils::path_depth(request.path()) > self.max_path_depth
+        {
+            // Refuse absurdly nested paths before any resolution or filesystem work
+            crate::common::logger::Logger::warn(&format!(
+                "Rejecting {} request: path deeper than {} segments",
+                request.method, self.max_path_depth
+            ));
+            self.generate_error_response(
+                server_instance,
+                crate::http::status::StatusCode::BAD_REQUEST,
+                request.version,
+            )?
+            .into()
+        } else if request.has_oversized_cookie_header() {
+            crate::common::logger::Logger::warn(&format!(
+                "Rejecting {} {}: Cookie header exceeds the size limit",
+                request.method,
+                request.path()
+            ));
+            self.generate_error_response(
+                server_instance,
+                crate::http::status::StatusCode::BAD_REQUEST,
+                request.version,
+            )?
+            .into()
+        } else if request
+            .body
+            .len()
+            .max(request.content_length().unwrap_or(0))
+            > route_match
+                .and_then(|(_, route)| route.max_body_size)
+                .unwrap_or(self.max_body_size)
+        {
+            // A body that arrived whole before its route was known is checked here
+            crate::common::logger::Logger::warn(&format!(
+                "Rejecting {} {}: body over the route's size limit",
+                request.method,
+                request.path()
+            ));
+            self.generate_error_response(
+                server_instance,
+                crate::http::status::StatusCode::PAYLOAD_TOO_LARGE,
+                request.version,
+            )?
+            .into()
+        } else if server_instance.config().read_only && request.method.is_mutating() {
+            // Locked-down servers refuse mutation before any route, upload or delete handler
+            crate::common::logger::Logger::warn(&format!(
+                "Rejecting {} {}: server '{}' is read-only",
+                request.method,
+                request.path(),
+                server_instance.server_name()
+            ));
+            let mut response = self.generate_error_response(
+                server_instance,
+                crate::http::status::StatusCode::METHOD_NOT_ALLOWED,
+                request.version,
+            )?;
+            // The router leaves mutating methods out on a read-only server
+            let allowed = match route_match {
+                Some((_, route)) => router.allowed_methods(route),
+                None => router.all_allowed_methods(),
+            };
+            response.headers.set(
+                crate::http::headers::names::ALLOW.to_string(),
+                allowed.join(", "),
+            );
+            response.into()
+        } else if AcmeChallengeHandler::is_challenge_request(request, server_instance.config()) {
+            // ACME HTTP-01 challenges bypass routing so certificates can be issued for any vhost
+            AcmeChallengeHandler::new().handle(request, &ctx)?.into()
+        } else if route_busy {
+            crate::common::logger::Logger::warn(&format!(
+                "Rejecting {} {}: route at its max_concurrent limit",
+                request.method,
+                request.path()
+            ));
+            self.generate_error_response(
+                server_instance,
+                crate::http::status::StatusCode::SERVICE_UNAVAILABLE,
+                request.version,
+            )?
+            .into()
+        } else if request.method == crate::http::method::Method::OPTIONS
+            && OptionsHandler::is_server_wide(request)
+        {
+            OptionsHandler::new().handle(request, &ctx)?.into()
+        } else if let Some((matched_path, route)) = route_match {
             // Log matched route with more details including which route path was matched
             crate::common::logger::Logger::info(&format!(
                 "✓ Matched route '{}' for request '{}' on server '{}': redirect={:?}, directory={:?}, filename={:?}, methods={:?}",
@@ -653,106 +1801,97 @@ impl ServerManager {
                 ));
             }
 
-            // Check for redirect first (highest priority)
-            if let Some(redirect_value) = &route.redirect {
-                crate::common::logger::Logger::info(&format!(
-                    "→ Redirect detected! Route '{}' has redirect='{}', creating RedirectionHandler",
-                    matched_path,
-                    redirect_value
-                ));
-                use crate::application::handler::redirection_handler::RedirectionHandler;
-                let handler = RedirectionHandler::new(router);
-                handler.handle(&request)?
-            } else if request.method == crate::http::method::Method::DELETE {
-                // DELETE request - check if route allows DELETE method
-                if router.is_method_allowed(&request, route) {
-                    // DELETE request - handle file deletion
-                    use crate::application::handler::delete_handler::DeleteHandler;
-                    let handler = DeleteHandler::new(router);
-                    handler.handle(&request)?
-                } else {
-                    // Route doesn't allow DELETE method
-                    Response::method_not_allowed_with_message(request.version, "Method Not Allowed")
-                }
-            } else if route.upload_dir.is_some()
-                && request.method == crate::http::method::Method::POST
-            {
-                // File upload - check upload_dir before other handlers
-                use crate::application::handler::upload_handler::UploadHandler;
-                let upload_dir = if let Some(ref dir) = route.upload_dir {
-                    router.resolve_path(dir)
-                } else {
-                    return Err(ServerError::HttpError(
-                        "Upload directory not configured".to_string(),
+            match RouteHandler::for_request(request, route) {
+                RouteHandler::Redirect => {
+                    crate::common::logger::Logger::info(&format!(
+                        "→ Redirect detected! Route '{}' has redirect='{}', creating RedirectionHandler",
+                        matched_path,
+                        route.redirect.as_deref().unwrap_or_default()
                     ));
-                };
-                let handler = UploadHandler::new(router, upload_dir);
-                handler.handle(&request)?
-            } else {
-                let file_path = router.resolve_file_path(&request, route)?;
-
-                // Check if this is a CGI script
-                let is_cgi = route.cgi_extension.is_some()
-                    || (file_path
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .map(|ext| {
-                            let ext_with_dot = format!(".{}", ext);
-                            server_instance
-                                .config()
-                                .cgi_handlers
-                                .contains_key(&ext_with_dot)
-                        })
-                        .unwrap_or(false));
-
-                if is_cgi && crate::common::path_utils::is_valid_file(&file_path) {
-                    // Execute CGI script
-                    use crate::application::handler::cgi_handler::CgiHandler;
-                    let cgi_handler = CgiHandler::new(
-                        router,
-                        server_instance.config().clone(),
-                        port, // Use the port from the connection
-                    );
-                    cgi_handler.handle(&request)?
-                } else if file_path.is_dir() {
-                    // If directory_listing is enabled, show directory listing instead of default_file
-                    if router.is_directory_listing_enabled(route) {
-                        let handler = DirectoryListingHandler::new(router);
-                        self.handle_with_error_fallback(
-                            handler,
-                            &request,
-                            server_instance,
-                            crate::http::status::StatusCode::NOT_FOUND,
-                        )?
-                    } else if let Some(default_file) = router.get_default_file(route) {
-                        // Directory listing disabled, check for default_file
-                        let default_path = file_path.join(default_file);
-                        if crate::common::path_utils::is_valid_file(&default_path) {
+                    use crate::application::handler::redirection_handler::RedirectionHandler;
+                    RedirectionHandler::new().handle(request, &ctx)?.into()
+                }
+                RouteHandler::Proxy => {
+                    // Reverse proxy: the upstream exchange blocks, so it always runs on a
+                    // worker (the validator requires `worker_threads` for proxy routes)
+                    use crate::application::handler::proxy_handler::ProxyHandler;
+                    Dispatch::Offloaded(Self::offload_job(ProxyHandler::new(), request, &ctx))
+                }
+                RouteHandler::Options => OptionsHandler::new().handle(request, &ctx)?.into(),
+                RouteHandler::Delete => {
+                    if router.is_method_allowed(request, route) {
+                        use crate::application::handler::delete_handler::DeleteHandler;
+                        dispatch_phase = "fs";
+                        DeleteHandler::new().handle(request, &ctx)?.into()
+                    } else {
+                        // Route doesn't allow DELETE method
+                        router.method_not_allowed(request, route).into()
+                    }
+                }
+                RouteHandler::Upload => {
+                    dispatch_phase = "fs";
+                    UploadHandler::new().handle(request, &ctx)?.into()
+                }
+                RouteHandler::File => {
+                    let file_path = router.resolve_within_root(request, route)?;
+
+                    if Self::is_cgi_script(server_instance, route, &file_path) {
+                        // Execute CGI script
+                        use crate::application::handler::cgi_handler::CgiHandler;
+                        dispatch_phase = "cgi";
+                        if self.worker_pool.is_some() {
+                            // The script blocks a worker instead
+                            Dispatch::Offloaded(Self::offload_job(CgiHandler::new(), request, &ctx))
+                        } else {
+                            CgiHandler::new().handle(request, &ctx)?.into()
+                        }
+                    } else if file_path.is_dir() {
+                        dispatch_phase = "fs";
+                        // Precedence: default file, then directory listing, then 403
+                        if router.directory_index(&file_path, route, request).is_some() {
                             // Serve default file via StaticFileHandler
-                            let handler = StaticFileHandler::new(router);
                             self.handle_with_error_fallback(
-                                handler,
-                                &request,
+                                StaticFileHandler::new(),
+                                request,
+                                &ctx,
+                                server_instance,
+                                crate::http::status::StatusCode::NOT_FOUND,
+                            )?
+                            .into()
+                        } else if router.is_directory_listing_enabled(route) {
+                            self.handle_with_error_fallback(
+                                DirectoryListingHandler::new(),
+                                request,
+                                &ctx,
                                 server_instance,
                                 crate::http::status::StatusCode::NOT_FOUND,
                             )?
+                            .into()
                         } else {
-                            // Default file doesn't exist and directory listing disabled - return 403
-                            Response::forbidden_with_message(request.version, "Forbidden")
+                            // No default file and directory listing disabled - return 403
+                            Response::forbidden_with_message(request.version, "Forbidden").into()
                         }
+                    } else if self.worker_pool.is_some() {
+                        // Static file, read and compressed on a worker
+                        dispatch_phase = "fs";
+                        fallback = Some(crate::http::status::StatusCode::NOT_FOUND);
+                        Dispatch::Offloaded(Self::offload_job(
+                            StaticFileHandler::new(),
+                            request,
+                            &ctx,
+                        ))
                     } else {
-                        // No default_file and directory listing disabled - return 403
-                        Response::forbidden_with_message(request.version, "Forbidden")
-                    }
-                } else {
-                    // Static file
-                    let handler = StaticFileHandler::new(router);
-                    self.handle_with_error_fallback(
-                        handler,
-                        &request,
-                        server_instance,
-                        crate::http::status::StatusCode::NOT_FOUND,
-                    )?
+                        // Static file
+                        dispatch_phase = "fs";
+                        self.handle_with_error_fallback(
+                            StaticFileHandler::new(),
+                            request,
+                            &ctx,
+                            server_instance,
+                            crate::http::status::StatusCode::NOT_FOUND,
+                        )?
+                        .into()
+                    }
                 }
             }
         } else {
@@ -767,16 +1906,77 @@ impl ServerManager {
                 crate::http::status::StatusCode::NOT_FOUND,
                 request.version,
             )?
+            .into()
         };
 
-        // Handle session management - get or create session
-        let mut response = response;
-        let session_id = request.cookie(self.session_manager.cookie_name());
-        let session_id = self
-            .session_manager
-            .get_or_create_session(session_id.as_deref());
+        let finish = ResponseFinish {
+            server_idx,
+            version: request.version,
+            fallback,
+            session_id,
+            started,
+            routing_time,
+            dispatch_phase,
+            dispatch_started,
+        };
+
+        // Periodic session cleanup and compaction
+        self.record_request_for_maintenance();
+
+        match handled {
+            Dispatch::Offloaded(job) => Ok(Dispatch::Offloaded(OffloadedWork {
+                // The route's slot stays taken until the worker is done
+                job: Box::new(move || {
+                    let _route_permit = route_permit;
+                    job()
+                }),
+                finish,
+            })),
+            Dispatch::Ready(response) => Ok(Dispatch::Ready(
+                self.finish_response(Ok(response), &finish)?,
+            )),
+        }
+    }
 
-        if let Some(sid) = session_id {
+    /// Package a handler call for a worker thread with owned copies of what its context
+    /// borrows. Offloaded handlers don't use the session, so it is left out.
+    fn offload_job<H: RequestHandler + Send + 'static>(
+        handler: H,
+        request: &Request,
+        ctx: &RequestContext,
+    ) -> OffloadJob {
+        let request = request.clone();
+        let router = ctx.router.clone();
+        let config = ctx.server_config.clone();
+        let (port, client_addr) = (ctx.server_port, ctx.client_addr);
+        Box::new(move || {
+            let mut ctx = RequestContext::new(&router, &config, port);
+            if let Some(client_addr) = client_addr {
+                ctx = ctx.with_client_addr(client_addr);
+            }
+            handler.handle(&request, &ctx)
+        })
+    }
+
+    /// Turn a handler's result into the response: a failed handler gets the fallback
+    /// error page, then the session cookie and Server-Timing are added
+    fn finish_response(
+        &self,
+        result: Result<Response>,
+        finish: &ResponseFinish,
+    ) -> Result<Response> {
+        let mut response = match (result, finish.fallback) {
+            (Ok(response), _) => response,
+            (Err(_), Some(status)) => self.generate_error_response(
+                self.get_server_instance(finish.server_idx)?,
+                status,
+                finish.version,
+            )?,
+            (Err(e), None) => return Err(e),
+        };
+
+        // Set the session cookie resolved before dispatch
+        if let Some(sid) = &finish.session_id {
             // Set session cookie in response
             let cookie = Cookie::new(self.session_manager.cookie_name().to_string(), sid.clone())
                 .set_path("/".to_string())
@@ -786,20 +1986,27 @@ impl ServerManager {
             response.add_cookie(cookie);
         }
 
-        // Cleanup expired sessions periodically (every 100 requests - simplified)
-        // In production, use a background task or timer
-        static mut CLEANUP_COUNTER: u64 = 0;
-        unsafe {
-            CLEANUP_COUNTER += 1;
-            if CLEANUP_COUNTER.is_multiple_of(100) {
-                self.session_manager.cleanup_expired();
-            }
+        if self.server_timing {
+            response.headers.set(
+                "Server-Timing".to_string(),
+                Self::server_timing_value(&[
+                    ("route", finish.routing_time),
+                    (finish.dispatch_phase, finish.dispatch_started.elapsed()),
+                    ("total", finish.started.elapsed()),
+                ]),
+            );
         }
 
-        // Write response to connection
-        self.write_response_to_connection(fd, &response, request.should_keep_alive())?;
+        Ok(response)
+    }
 
-        Ok(())
+    /// `Server-Timing` value for the measured phases, durations in milliseconds
+    fn server_timing_value(phases: &[(&str, std::time::Duration)]) -> String {
+        phases
+            .iter()
+            .map(|(name, duration)| format!("{};dur={:.3}", name, duration.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
     /// Send error response to client
@@ -840,272 +2047,2504 @@ impl ServerManager {
         error_handler.generate_error_response(status_code, version)
     }
 
-    /// Handle a request handler and return response, falling back to error page on failure
-    /// Helper function to reduce redundancy in handler error handling
-    fn handle_with_error_fallback<H: RequestHandler>(
-        &self,
-        handler: H,
-        request: &Request,
-        server_instance: &ServerInstance,
-        error_status: crate::http::status::StatusCode,
-    ) -> Result<Response> {
-        match handler.handle(request) {
-            Ok(response) => Ok(response),
-            Err(_) => {
-                // Handler failed - use custom error page
-                self.generate_error_response(server_instance, error_status, request.version)
+    /// Handle a request handler and return response, falling back to error page on failure
+    /// Helper function to reduce redundancy in handler error handling
+    fn handle_with_error_fallback<H: RequestHandler>(
+        &self,
+        handler: H,
+        request: &Request,
+        ctx: &RequestContext,
+        server_instance: &ServerInstance,
+        error_status: crate::http::status::StatusCode,
+    ) -> Result<Response> {
+        match handler.handle(request, ctx) {
+            Ok(response) => Ok(response),
+            Err(_) => {
+                // Handler failed - use custom error page
+                self.generate_error_response(server_instance, error_status, request.version)
+            }
+        }
+    }
+
+    /// Reduce a Host header value to the name compared against `server_name`:
+    /// the port is removed and, when `fold` is set, case is folded and a trailing
+    /// root dot is dropped, so `Example.COM.:8080` matches `example.com`.
+    fn normalize_host(host: &str, fold: bool) -> String {
+        let host = host.trim();
+        let name = if host.starts_with('[') {
+            // IPv6 literal: keep the brackets, drop any port after them
+            host.find(']').map_or(host, |end| &host[..=end])
+        } else {
+            host.split(':').next().unwrap_or(host)
+        };
+        if fold {
+            name.trim_end_matches('.').to_ascii_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Server whose name matches `host` on `port`, without falling back to the default
+    fn server_for_host(&self, host: &str, port: u16) -> Option<usize> {
+        let hostname = Self::normalize_host(host, self.normalize_host);
+        self.server_lookup
+            .get(&(port, Self::loopback_as_localhost(&hostname)))
+            .or_else(|| self.server_lookup.get(&(port, hostname)))
+            .copied()
+    }
+
+    /// Handle common localhost variations: 127.0.0.1 and ::1 should match "localhost"
+    fn loopback_as_localhost(hostname: &str) -> String {
+        if hostname == "127.0.0.1" || hostname == "::1" || hostname == "[::1]" {
+            "localhost".to_string()
+        } else {
+            hostname.to_string()
+        }
+    }
+
+    /// Find server instance for a request based on Host header and port
+    fn find_server_for_request(&self, request: &Request, port: u16) -> Result<usize> {
+        // Log the raw Host header for debugging
+        let raw_host = request.host().map(|h| h.as_str()).unwrap_or("(missing)");
+        crate::common::logger::Logger::info(&format!(
+            "Server selection for {} {}: port={}, Host header='{}'",
+            request.method,
+            request.path(),
+            port,
+            raw_host
+        ));
+
+        // Try to match by Host header and port
+        if let Some(host) = request.host() {
+            // Extract hostname (port removed; lowercased and trailing dot stripped if enabled)
+            let hostname = Self::normalize_host(host, self.normalize_host);
+            let normalized_hostname = Self::loopback_as_localhost(&hostname);
+
+            // Log available servers for this port for debugging
+            let available_servers: Vec<String> = self
+                .server_lookup
+                .iter()
+                .filter(|((p, _), _)| *p == port)
+                .map(|((_, h), idx)| format!("'{}' (idx: {})", h, idx))
+                .collect();
+
+            // Log default server for this port
+            let default_idx = self.default_servers.get(&port).copied();
+            let default_info = if let Some(idx) = default_idx {
+                if let Ok(server) = self.get_server_instance(idx) {
+                    format!("'{}' (idx: {})", server.server_name(), idx)
+                } else {
+                    format!("(idx: {})", idx)
+                }
+            } else {
+                "none".to_string()
+            };
+
+            crate::common::logger::Logger::info(&format!(
+                "Looking for server match: Host header='{}' (normalized from '{}' -> '{}'), port={}, available servers: [{}], default: {}",
+                normalized_hostname,
+                hostname,
+                host,
+                port,
+                available_servers.join(", "),
+                default_info
+            ));
+
+            // Try the configured server names for this port
+            if let Some(server_idx) = self.server_for_host(host, port) {
+                let server_instance = self.get_server_instance(server_idx)?;
+                crate::common::logger::Logger::info(&format!(
+                    "Request {} {} -> Resolved server_name: '{}' (matched by Host header: '{}') on port {}",
+                    request.method,
+                    request.path(),
+                    server_instance.config().server_name,
+                    normalized_hostname,
+                    port
+                ));
+                return Ok(server_idx);
+            }
+
+            crate::common::logger::Logger::warn(&format!(
+                "No server match found for Host header '{}' (normalized: '{}', original: '{}') on port {}, falling back to default server",
+                normalized_hostname,
+                hostname,
+                host,
+                port
+            ));
+        } else {
+            crate::common::logger::Logger::warn(&format!(
+                "No Host header present for request {} {} on port {}, falling back to default server",
+                request.method,
+                request.path(),
+                port
+            ));
+        }
+
+        // Fall back to default server for this port
+        let server_idx = self.get_default_server_for_port(port)?;
+        let server_instance = self.get_server_instance(server_idx)?;
+        let host_header = request.host().map(|h| h.as_str()).unwrap_or("none");
+        crate::common::logger::Logger::info(&format!(
+            "Request {} {} -> Resolved server_name: '{}' (default server, Host header: '{}') on port {}",
+            request.method,
+            request.path(),
+            server_instance.config().server_name,
+            host_header,
+            port
+        ));
+        Ok(server_idx)
+    }
+
+    /// Handle write event - send response to client
+    fn handle_write(&mut self, fd: i32) -> Result<()> {
+        // Get data to write first
+        let data: Vec<u8> = {
+            let connection = self.get_connection_mut(fd)?;
+            let write_buffer = connection.write_buffer_mut();
+            if write_buffer.is_empty() {
+                // Nothing to write
+                connection.set_state(ConnectionState::Reading);
+                // Connection reference dropped at end of this block
+                Vec::new()
+            } else {
+                // Copy the data so we can drop the connection reference
+                write_buffer.as_slice().to_vec()
+            }
+        };
+
+        // Handle empty write buffer case after dropping connection reference
+        if data.is_empty() {
+            if let Err(e) = self.event_manager.unregister_write(fd) {
+                // Error unregistering - close connection
+                self.set_connection_state_and_close(fd, ConnectionState::Closed)?;
+                return Err(e);
+            }
+            return Ok(());
+        }
+
+        // Write data
+        let write_result = {
+            let connection = self.get_connection_mut(fd)?;
+            let socket = connection.socket_mut();
+            write_non_blocking(socket, &data)
+        };
+        let n = match write_result {
+            Ok(n) => n,
+            Err(e) => {
+                // I/O error occurred - close connection
+                self.close_connection_on_error(fd)?;
+                return Err(e);
+            }
+        };
+
+        if n > 0 {
+            if let Some(dump) = self.traffic_dump(fd, "sent", &data[..n]) {
+                crate::common::logger::Logger::info(&dump);
+            }
+            // Remove written data from buffer
+            let connection = self.get_connection_mut(fd)?;
+            connection.write_buffer_mut().drain(n);
+            connection.record_written(n);
+            connection.touch();
+        }
+
+        // Check if all data sent
+        let is_empty = self.get_connection(fd)?.write_buffer().is_empty();
+        if is_empty {
+            // All data sent
+            let connection = self.get_connection(fd)?;
+            crate::common::logger::Logger::debug(&format!(
+                "Response complete: {} bytes written to {}",
+                connection.bytes_written(),
+                connection
+                    .client_addr()
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_else(|| "unknown peer".to_string())
+            ));
+            let should_keep_alive = connection.should_keep_alive();
+            if should_keep_alive {
+                self.reset_for_next_request(fd)?;
+            } else {
+                self.close_gracefully(fd)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prepare a kept-alive connection for its next request once a response is fully sent
+    fn reset_for_next_request(&mut self, fd: i32) -> Result<()> {
+        // Reset parser for the next request; bytes already pipelined after the
+        // answered request are kept
+        let pipelined = self
+            .parsers
+            .get_mut(&fd)
+            .map(|parser| {
+                parser.reset_keeping_leftover();
+                parser.buffered()
+            })
+            .unwrap_or(0);
+        // Reset connection for next request
+        {
+            let connection = self.get_connection_mut(fd)?;
+            connection.set_state(ConnectionState::Reading);
+            connection.read_buffer_mut().clear();
+            connection.reset_bytes_written();
+            connection.set_idle(pipelined == 0);
+        }
+        // Unregister write after dropping connection reference
+        if let Err(e) = self.event_manager.unregister_write(fd) {
+            // Error unregistering - close connection
+            self.set_connection_state_and_close(fd, ConnectionState::Closed)?;
+            return Err(e);
+        }
+        // A pipelined request may already be complete, and no read event may ever come
+        // for it: answer it in this iteration, or the next one without read-ahead
+        if pipelined > 0 {
+            if self.keep_alive_read_ahead {
+                self.parse_buffered_request(fd)?;
+            } else {
+                self.deferred_pipelined.push(fd);
+            }
+        }
+        self.enforce_idle_limit()
+    }
+
+    /// Parse the pipelined bytes connections were left holding on the previous pass
+    fn parse_deferred_pipelined(&mut self) {
+        for fd in std::mem::take(&mut self.deferred_pipelined) {
+            let waiting = self
+                .connections
+                .get(&fd)
+                .is_some_and(|c| *c.state() == ConnectionState::Reading);
+            if !waiting {
+                continue;
+            }
+            if let Err(e) = self.parse_buffered_request(fd) {
+                crate::common::logger::Logger::error(&format!(
+                    "Error handling pipelined request on fd {}: {}",
+                    fd, e
+                ));
+            }
+        }
+    }
+
+    /// Close a connection whose final response is fully written: shut down the write
+    /// side so the client sees the end of the response, then linger until it closes
+    fn close_gracefully(&mut self, fd: i32) -> Result<()> {
+        let linger = self.lingering_close;
+        if linger.is_zero() {
+            return self.close_connection(fd);
+        }
+        let connection = self.get_connection_mut(fd)?;
+        if connection.socket().shutdown_write().is_err() {
+            // Already disconnected: nothing left to deliver
+            return self.close_connection(fd);
+        }
+        connection.start_lingering(linger);
+        let _ = self.event_manager.unregister_write(fd);
+        Ok(())
+    }
+
+    /// Discard whatever a lingering connection's client still sends, closing once it
+    /// hangs up
+    fn drain_lingering(&mut self, fd: i32) -> Result<()> {
+        let mut buf = [0u8; DEFAULT_BUFFER_SIZE];
+        let connection = self.get_connection_mut(fd)?;
+        // Bounded, so a client that keeps sending can't hold the loop; the linger
+        // timeout ends it
+        for _ in 0..16 {
+            match std::io::Read::read(connection.socket_mut().as_stream_mut(), &mut buf) {
+                Ok(0) => break,
+                Ok(_) => continue,
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(()),
+                Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+        self.close_connection(fd)
+    }
+
+    /// Clean up timed out or closed connections
+    fn cleanup_connections(&mut self) -> Result<()> {
+        let mut to_remove = Vec::new();
+        let mut continue_expired = Vec::new();
+
+        for (fd, connection) in &self.connections {
+            if connection.is_timeout()
+                || (*connection.state() == ConnectionState::Lingering
+                    && connection.is_linger_expired())
+                || (!self.keep_alive_timeout.is_zero()
+                    && connection.is_idle_expired(self.keep_alive_timeout))
+            {
+                to_remove.push(*fd);
+            } else if connection.is_continue_expired()
+                && *connection.state() == ConnectionState::Reading
+            {
+                continue_expired.push(*fd);
+            }
+        }
+
+        for fd in to_remove {
+            self.close_connection(fd)?;
+        }
+
+        // Clients that were sent 100 Continue but never started their body
+        for fd in continue_expired {
+            crate::common::logger::Logger::warn(&format!(
+                "No request body after 100 Continue on fd {}, sending 408",
+                fd
+            ));
+            self.get_connection_mut(fd)?.clear_continue_wait();
+            self.send_error_response(
+                fd,
+                crate::http::status::StatusCode::REQUEST_TIMEOUT,
+                crate::http::version::Version::Http11,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `port` already holds as many connections as its configured limit allows
+    fn port_at_connection_limit(&self, port: u16) -> bool {
+        self.port_connection_limits.get(&port).is_some_and(|limit| {
+            let open = self
+                .connections
+                .values()
+                .filter(|c| c.server_port() == Some(port))
+                .count();
+            open >= *limit
+        })
+    }
+
+    /// Close the least recently active idle keep-alive connections until at most
+    /// `max_idle_connections` remain idle
+    fn enforce_idle_limit(&mut self) -> Result<()> {
+        if self.max_idle_connections == 0 {
+            return Ok(());
+        }
+        let mut idle: Vec<(std::time::Instant, i32)> = self
+            .connections
+            .iter()
+            .filter(|(_, c)| c.is_idle() && *c.state() == ConnectionState::Reading)
+            .map(|(fd, c)| (c.last_activity(), *fd))
+            .collect();
+        if idle.len() <= self.max_idle_connections {
+            return Ok(());
+        }
+
+        idle.sort();
+        let excess = idle.len() - self.max_idle_connections;
+        for (_, fd) in idle.into_iter().take(excess) {
+            crate::common::logger::Logger::info(&format!(
+                "Closing idle keep-alive connection fd {} (idle limit {} reached)",
+                fd, self.max_idle_connections
+            ));
+            self.close_connection(fd)?;
+        }
+        Ok(())
+    }
+
+    /// Check if error is a body size violation
+    /// Checks for all possible body size error message patterns from the parser
+    fn is_body_size_error(error: &ServerError) -> bool {
+        if let ServerError::HttpError(ref msg) = error {
+            // Match all body size error patterns used in RequestParser:
+            // - "exceeds maximum allowed size" (used in multiple places)
+            // - "would exceed maximum allowed size" (used in add_data and chunked parsing)
+            msg.contains("exceeds maximum allowed size")
+                || msg.contains("would exceed maximum allowed size")
+        } else {
+            false
+        }
+    }
+
+    /// Status for a request head the parser rejected: 501 for a well-formed method we
+    /// don't implement, 431 for an oversized head, 400 for anything else it refused
+    /// (a malformed method, bare LF line endings, a body on GET/HEAD, a repeated
+    /// singleton header, an unsupported TE coding)
+    fn parse_error_status(error: &ServerError) -> Option<crate::http::status::StatusCode> {
+        match error {
+            ServerError::BadRequest(_) => Some(crate::http::status::StatusCode::BAD_REQUEST),
+            ServerError::MethodNotImplemented(_) => {
+                Some(crate::http::status::StatusCode::NOT_IMPLEMENTED)
+            }
+            ServerError::HeaderFieldsTooLarge(_) => {
+                Some(crate::http::status::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+            }
+            _ => None,
+        }
+    }
+
+    /// Close connection on error - helper to reduce code duplication
+    fn close_connection_on_error(&mut self, fd: i32) -> Result<()> {
+        self.set_connection_state_and_close(fd, ConnectionState::Closed)
+    }
+
+    /// Set connection state and close connection (helper to reduce redundancy)
+    fn set_connection_state_and_close(&mut self, fd: i32, state: ConnectionState) -> Result<()> {
+        if let Ok(connection) = self.get_connection_mut(fd) {
+            connection.set_state(state);
+        }
+        self.close_connection(fd)
+    }
+
+    /// Close a connection and clean up resources
+    fn close_connection(&mut self, fd: i32) -> Result<()> {
+        // Try to unregister events, but ignore errors if they're already unregistered
+        // This can happen during cleanup or when connection is already closed
+        let _ = self.event_manager.unregister_read(fd);
+        let _ = self.event_manager.unregister_write(fd);
+
+        // FIN goes out behind any data still queued in the kernel before the socket is dropped
+        if let Some(connection) = self.connections.remove(&fd) {
+            let _ = connection.socket().shutdown_write();
+        }
+        self.parsers.remove(&fd);
+        // A worker still producing this connection's response has its result dropped
+        self.processing.remove(&fd);
+        self.deferred_pipelined.retain(|&deferred| deferred != fd);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::config::models::{RouteConfig, ServerConfig};
+    use crate::common::constants::DEFAULT_MAX_PATH_DEPTH;
+    use crate::http::method::Method;
+    use crate::http::version::Version;
+
+    fn server_config(name: &str, routes: &[&str]) -> ServerConfig {
+        ServerConfig {
+            server_address: "127.0.0.1".parse().unwrap(),
+            // Port 0 lets the OS pick a free port so tests never collide
+            ports: vec![0],
+            server_name: name.to_string(),
+            root: ".".to_string(),
+            routes: routes
+                .iter()
+                .map(|r| (r.to_string(), RouteConfig::default()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Fresh temp directory for a test's document root
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("localhost_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// Connect a client socket and register the server end with `manager` as a
+    /// connection on `port`, the way `accept_connection` would. Returns its fd and
+    /// the client end, which gives up reading after 5 seconds.
+    fn connect_client(manager: &mut ServerManager, port: u16) -> (i32, std::net::TcpStream) {
+        use crate::core::net::socket::ClientSocket;
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let (stream, addr) = listener.accept().unwrap();
+        let connection =
+            Connection::with_port(ClientSocket::from_stream(stream, addr).unwrap(), 30, port);
+        let fd = connection.as_raw_fd();
+        manager.connections.insert(fd, connection);
+        manager.parsers.insert(fd, manager.new_parser());
+        (fd, client)
+    }
+
+    fn manager() -> ServerManager {
+        ServerManager::new(Config {
+            servers: vec![
+                server_config("alpha", &["/"]),
+                server_config("beta", &["/", "/api", "/static"]),
+            ],
+            ..Config::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_server_lookup_by_name() {
+        let manager = manager();
+
+        assert_eq!(manager.server("beta").unwrap().server_name(), "beta");
+        assert_eq!(manager.server("ALPHA").unwrap().server_name(), "alpha");
+        assert!(manager.server("gamma").is_none());
+        assert_eq!(manager.server_at(1).unwrap().server_name(), "beta");
+        assert!(manager.server_at(2).is_none());
+    }
+
+    #[test]
+    fn test_server_summaries() {
+        let manager = manager();
+        let summaries = manager.servers();
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].name, "alpha");
+        assert_eq!(summaries[0].route_count, 1);
+        assert_eq!(summaries[1].route_count, 3);
+        assert_eq!(
+            summaries[1].listen_addrs,
+            vec!["127.0.0.1:0".parse::<SocketAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_server_for_sni_selects_vhost() {
+        let manager = manager();
+
+        assert_eq!(
+            manager
+                .server_for_sni(0, Some("beta"))
+                .unwrap()
+                .server_name(),
+            "beta"
+        );
+        assert_eq!(
+            manager
+                .server_for_sni(0, Some("Beta."))
+                .unwrap()
+                .server_name(),
+            "beta"
+        );
+        // Unknown or missing SNI falls back to the port's default server
+        assert_eq!(
+            manager
+                .server_for_sni(0, Some("gamma"))
+                .unwrap()
+                .server_name(),
+            "alpha"
+        );
+        assert_eq!(
+            manager.server_for_sni(0, None).unwrap().server_name(),
+            "alpha"
+        );
+        assert!(manager.server_for_sni(1, Some("beta")).is_none());
+    }
+
+    #[test]
+    fn test_maintenance_runs_at_configured_interval() {
+        let mut manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            maintenance_interval_requests: 3,
+            ..Config::default()
+        })
+        .unwrap();
+
+        let runs: Vec<bool> = (0..7)
+            .map(|_| manager.record_request_for_maintenance())
+            .collect();
+        assert_eq!(runs, [false, false, true, false, false, true, false]);
+    }
+
+    #[test]
+    fn test_maintenance_disabled_with_zero_interval() {
+        let mut manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            maintenance_interval_requests: 0,
+            ..Config::default()
+        })
+        .unwrap();
+
+        assert!((0..500).all(|_| !manager.record_request_for_maintenance()));
+    }
+
+    #[test]
+    fn test_cleanup_counter_triggers_session_cleanup() {
+        let mut manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            maintenance_interval_requests: 3,
+            ..Config::default()
+        })
+        .unwrap();
+        // Sessions expire immediately so the cleanup pass is observable
+        manager.session_manager = SessionManager::new(0);
+        manager.session_manager.create_session();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        manager.record_request_for_maintenance();
+        manager.record_request_for_maintenance();
+        assert_eq!(manager.cleanup_counter, 2);
+        assert_eq!(manager.session_manager.session_count(), 1);
+
+        manager.record_request_for_maintenance();
+        assert_eq!(manager.cleanup_counter, 0);
+        assert_eq!(manager.session_manager.session_count(), 0);
+    }
+
+    #[test]
+    fn test_normalize_host() {
+        assert_eq!(
+            ServerManager::normalize_host("Example.COM.", true),
+            "example.com"
+        );
+        assert_eq!(
+            ServerManager::normalize_host("example.com:8080", true),
+            "example.com"
+        );
+        assert_eq!(
+            ServerManager::normalize_host("EXAMPLE.com.:80", true),
+            "example.com"
+        );
+        assert_eq!(ServerManager::normalize_host("[::1]:8080", true), "[::1]");
+        assert_eq!(
+            ServerManager::normalize_host("Example.COM.:80", false),
+            "Example.COM."
+        );
+    }
+
+    #[test]
+    fn test_host_matching_ignores_case_trailing_dot_and_port() {
+        let manager = ServerManager::new(Config {
+            servers: vec![
+                server_config("default", &["/"]),
+                server_config("example.com", &["/"]),
+            ],
+            ..Config::default()
+        })
+        .unwrap();
+
+        for host in ["example.com", "Example.COM.", "example.com:8080"] {
+            let mut request = Request::new(Method::GET, "/".to_string(), Version::Http11);
+            request.headers.set("Host".to_string(), host.to_string());
+            let idx = manager.find_server_for_request(&request, 0).unwrap();
+            assert_eq!(
+                manager.server_at(idx).unwrap().server_name(),
+                "example.com",
+                "Host {:?}",
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_normalization_can_be_disabled() {
+        let manager = ServerManager::new(Config {
+            servers: vec![
+                server_config("default", &["/"]),
+                server_config("example.com", &["/"]),
+            ],
+            normalize_host: false,
+            ..Config::default()
+        })
+        .unwrap();
+
+        for (host, expected) in [
+            ("example.com:8080", "example.com"),
+            ("Example.COM.", "default"),
+            ("example.com.", "default"),
+        ] {
+            let mut request = Request::new(Method::GET, "/".to_string(), Version::Http11);
+            request.headers.set("Host".to_string(), host.to_string());
+            let idx = manager.find_server_for_request(&request, 0).unwrap();
+            assert_eq!(
+                manager.server_at(idx).unwrap().server_name(),
+                expected,
+                "Host {:?}",
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn test_idle_limit_closes_least_recently_active() {
+        use crate::core::net::socket::ClientSocket;
+        use std::net::{TcpListener, TcpStream};
+
+        let mut manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            max_idle_connections: 2,
+            ..Config::default()
+        })
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut clients = Vec::new();
+        let mut fds = Vec::new();
+        for _ in 0..3 {
+            clients.push(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+            let (stream, addr) = listener.accept().unwrap();
+            let mut connection =
+                Connection::new(ClientSocket::from_stream(stream, addr).unwrap(), 30);
+            connection.set_idle(true);
+            connection.touch();
+            let fd = connection.as_raw_fd();
+            manager.connections.insert(fd, connection);
+            fds.push(fd);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        // The oldest idle connection shows activity again, so the second one is now oldest
+        manager.connections.get_mut(&fds[0]).unwrap().touch();
+        manager.enforce_idle_limit().unwrap();
+
+        assert!(manager.connections.contains_key(&fds[0]));
+        assert!(!manager.connections.contains_key(&fds[1]));
+        assert!(manager.connections.contains_key(&fds[2]));
+
+        // Active (non-idle) connections are never counted or closed
+        manager
+            .connections
+            .get_mut(&fds[0])
+            .unwrap()
+            .set_idle(false);
+        manager.enforce_idle_limit().unwrap();
+        assert_eq!(manager.connections.len(), 2);
+    }
+
+    #[test]
+    fn test_handle_request_bytes_answers_without_sockets() {
+        let mut manager = manager();
+
+        let response = manager.handle_request_bytes(b"get / HTTP/1.1\r\nHost: alpha\r\n\r\n");
+        assert!(response.starts_with(b"HTTP/1.1 501"));
+
+        let response = manager.handle_request_bytes(b"GET / HTTP/1.1\r\nHost: al");
+        assert!(response.starts_with(b"HTTP/1.1 400"));
+
+        let response =
+            manager.handle_request_bytes(b"GET /nowhere HTTP/1.1\r\nHost: alpha\r\n\r\n");
+        assert!(response.starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_percent_encoded_route_prefix_gets_that_routes_rules() {
+        let root = temp_root("encoded_prefix");
+        std::fs::create_dir_all(root.join("admin")).unwrap();
+        std::fs::write(root.join("admin/x.txt"), "secret").unwrap();
+        let mut config = server_config("alpha", &["/"]);
+        config.root = root.to_string_lossy().into_owned();
+        config.routes.insert(
+            "/admin".to_string(),
+            RouteConfig {
+                methods: vec!["POST".to_string()],
+                ..Default::default()
+            },
+        );
+        let mut manager = ServerManager::new(Config {
+            servers: vec![config],
+            ..Config::default()
+        })
+        .unwrap();
+
+        for target in ["/admin/x.txt", "/%61dmin/x.txt", "/%61%64min/x.txt"] {
+            let request = format!("GET {} HTTP/1.1\r\nHost: alpha\r\n\r\n", target);
+            let response = manager.handle_request_bytes(request.as_bytes());
+            assert!(response.starts_with(b"HTTP/1.1 405"), "target {}", target);
+        }
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_keep_alive_requires_definite_response_length() {
+        let request = Request::new(Method::GET, "/cgi/run.py".to_string(), Version::Http11);
+
+        // Buffered CGI output is framed with its real length, so the connection may stay open
+        let mut cgi = crate::application::cgi::cgi_io::CgiIo::parse_cgi_output(
+            b"Content-Type: text/plain\r\n\r\nno length given",
+        )
+        .unwrap();
+        assert!(ServerManager::keep_alive_after(&request, &mut cgi));
+        assert!(!cgi.closes_connection());
+
+        // Without a length or chunking the body can only end with the connection
+        let mut unframed = Response::ok(Version::Http11);
+        unframed.body = b"streamed".to_vec();
+        assert!(!ServerManager::keep_alive_after(&request, &mut unframed));
+        assert!(unframed.closes_connection());
+
+        let mut closing = Response::ok(Version::Http11);
+        closing.set_connection("close");
+        assert!(!ServerManager::keep_alive_after(&request, &mut closing));
+    }
+
+    #[test]
+    fn test_over_depth_path_is_400() {
+        let mut manager = manager();
+        let deep = format!(
+            "GET {}/ HTTP/1.1\r\nHost: alpha\r\n\r\n",
+            "/d".repeat(DEFAULT_MAX_PATH_DEPTH + 1)
+        );
+        assert!(manager
+            .handle_request_bytes(deep.as_bytes())
+            .starts_with(b"HTTP/1.1 400"));
+
+        let at_limit = format!(
+            "GET {}/ HTTP/1.1\r\nHost: alpha\r\n\r\n",
+            "/d".repeat(DEFAULT_MAX_PATH_DEPTH)
+        );
+        assert!(!manager
+            .handle_request_bytes(at_limit.as_bytes())
+            .starts_with(b"HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_unknown_host_is_421_only_under_strict_matching() {
+        let unknown = b"GET / HTTP/1.1\r\nHost: unknown.example\r\n\r\n";
+        let known = b"GET / HTTP/1.1\r\nHost: alpha\r\n\r\n";
+
+        let mut lenient = manager();
+        let default_response = lenient.handle_request_bytes(known);
+        let fallback = lenient.handle_request_bytes(unknown);
+        assert!(!fallback.starts_with(b"HTTP/1.1 421"));
+        assert_eq!(fallback[..12], default_response[..12]);
+
+        let mut strict = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            strict_host_matching: true,
+            ..Config::default()
+        })
+        .unwrap();
+        assert!(strict
+            .handle_request_bytes(unknown)
+            .starts_with(b"HTTP/1.1 421 Misdirected Request"));
+        assert!(!strict
+            .handle_request_bytes(known)
+            .starts_with(b"HTTP/1.1 421"));
+        // Matching still ignores case and the port
+        assert!(!strict
+            .handle_request_bytes(b"GET / HTTP/1.1\r\nHost: ALPHA:8080\r\n\r\n")
+            .starts_with(b"HTTP/1.1 421"));
+    }
+
+    #[test]
+    fn test_canonical_header_names_for_cgi_output() {
+        let response =
+            crate::application::cgi::CgiIo::parse_cgi_output(b"content-type: text/plain\r\n\r\nhi")
+                .unwrap();
+
+        let manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            canonical_header_names: true,
+            ..Config::default()
+        })
+        .unwrap();
+        let bytes =
+            ResponseSerializer::serialize_with(&response, &manager.serialize_options).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.contains("\r\nContent-Type: text/plain\r\n"));
+        assert!(!text.contains("content-type"));
+    }
+
+    #[test]
+    fn test_server_timing_header_reports_phases_when_enabled() {
+        let root = temp_root("timing");
+        std::fs::write(root.join("page.txt"), "hello").unwrap();
+        std::fs::write(
+            root.join("script.sh"),
+            "printf 'Content-Type: text/plain\\r\\n\\r\\nhi'\n",
+        )
+        .unwrap();
+
+        let mut config = server_config("alpha", &["/"]);
+        config.root = root.to_string_lossy().to_string();
+        config.routes.insert(
+            "/cgi".to_string(),
+            RouteConfig {
+                directory: Some(".".to_string()),
+                cgi_extension: Some("sh".to_string()),
+                ..RouteConfig::default()
+            },
+        );
+        config
+            .cgi_handlers
+            .insert("sh".to_string(), "/bin/sh".to_string());
+        let timed = |enabled: bool| {
+            ServerManager::new(Config {
+                servers: vec![config.clone()],
+                server_timing: enabled,
+                ..Config::default()
+            })
+            .unwrap()
+        };
+        let timing_header = |bytes: Vec<u8>| {
+            String::from_utf8_lossy(&bytes)
+                .lines()
+                .find_map(|line| line.strip_prefix("Server-Timing: ").map(str::to_string))
+        };
+
+        let mut manager = timed(true);
+        let static_timing = timing_header(
+            manager.handle_request_bytes(b"GET /page.txt HTTP/1.1\r\nHost: alpha\r\n\r\n"),
+        )
+        .expect("Server-Timing header present");
+        let metrics: Vec<(&str, f64)> = static_timing
+            .split(", ")
+            .map(|metric| {
+                let (name, dur) = metric.split_once(";dur=").unwrap();
+                (name, dur.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            metrics.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            ["route", "fs", "total"]
+        );
+        assert!(metrics
+            .iter()
+            .all(|(_, dur)| *dur >= 0.0 && *dur < 10_000.0));
+        assert!(metrics[2].1 >= metrics[0].1);
+
+        let cgi_timing = timing_header(
+            manager.handle_request_bytes(b"GET /cgi/script.sh HTTP/1.1\r\nHost: alpha\r\n\r\n"),
+        )
+        .unwrap();
+        assert!(cgi_timing.contains(", cgi;dur="), "{}", cgi_timing);
+
+        let mut untimed = timed(false);
+        assert_eq!(
+            timing_header(
+                untimed.handle_request_bytes(b"GET /page.txt HTTP/1.1\r\nHost: alpha\r\n\r\n")
+            ),
+            None
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_pipelined_request_answered_without_another_read_event() {
+        use std::io::{Read, Write};
+
+        let root = temp_root("read_ahead");
+        std::fs::write(root.join("a.txt"), "first").unwrap();
+        std::fs::write(root.join("b.txt"), "second").unwrap();
+        let mut config = server_config("alpha", &["/"]);
+        config.root = root.to_string_lossy().to_string();
+
+        // Returns the first response, then what is queued once it has been sent, then
+        // what the client receives over the next loop passes
+        let run = |read_ahead: bool| {
+            let mut manager = ServerManager::new(Config {
+                servers: vec![config.clone()],
+                keep_alive_read_ahead: read_ahead,
+                ..Config::default()
+            })
+            .unwrap();
+            let port = manager.server_instances[0].config().ports[0];
+
+            let (fd, mut client) = connect_client(&mut manager, port);
+
+            client
+                .write_all(
+                    b"GET /a.txt HTTP/1.1\r\nHost: alpha\r\n\r\nGET /b.txt HTTP/1.1\r\nHost: alpha\r\n\r\n",
+                )
+                .unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+
+            // A single read event carries both requests; only the first is answered
+            manager.handle_read(fd).unwrap();
+            let connection = manager.connections.get_mut(&fd).unwrap();
+            let first = String::from_utf8(connection.write_buffer().as_slice()).unwrap();
+            // The first response has been sent
+            connection.write_buffer_mut().clear();
+
+            manager.reset_for_next_request(fd).unwrap();
+            let connection = &manager.connections[&fd];
+            let writing = *connection.state() == ConnectionState::Writing;
+            let queued = String::from_utf8(connection.write_buffer().as_slice()).unwrap();
+
+            let mut later = Vec::new();
+            let mut chunk = [0u8; 1024];
+            for _ in 0..20 {
+                manager.run_once(10).unwrap();
+                if manager.connections[&fd].write_buffer().is_empty()
+                    && *manager.connections[&fd].state() == ConnectionState::Reading
+                {
+                    break;
+                }
+            }
+            if !read_ahead {
+                while !later.ends_with(b"second") {
+                    let n = client.read(&mut chunk).unwrap();
+                    assert!(n > 0);
+                    later.extend_from_slice(&chunk[..n]);
+                }
+            }
+            (first, writing, queued, String::from_utf8(later).unwrap())
+        };
+
+        let (first, writing, queued, _) = run(true);
+        assert!(first.starts_with("HTTP/1.1 200 OK") && first.ends_with("first"));
+        assert!(writing);
+        assert!(queued.starts_with("HTTP/1.1 200 OK") && queued.ends_with("second"));
+
+        // Without read-ahead the pipelined request waits for the next loop pass, but
+        // still needs no read event
+        let (first, writing, queued, later) = run(false);
+        assert!(first.ends_with("first"));
+        assert!(!writing);
+        assert!(queued.is_empty());
+        assert!(later.starts_with("HTTP/1.1 200 OK"), "{}", later);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_panicking_handler_is_500_and_server_keeps_serving() {
+        let mut manager = manager();
+        let port = manager.server_instances[0].config().ports[0];
+
+        let response: Response = manager
+            .isolate_panics("GET /boom", port, |_| panic!("index out of bounds"))
+            .unwrap();
+        assert_eq!(
+            response.status,
+            crate::http::status::StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        // Later requests on the same manager are answered normally
+        assert!(manager
+            .handle_request_bytes(b"GET /nowhere HTTP/1.1\r\nHost: alpha\r\n\r\n")
+            .starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_port_zero_reports_connectable_bound_port() {
+        let manager = manager();
+
+        let bound = manager.local_addr(0).unwrap();
+        assert_ne!(bound.port(), 0);
+        assert!(std::net::TcpStream::connect(bound).is_ok());
+        assert_eq!(manager.local_addr(1), None);
+    }
+
+    #[test]
+    fn test_connection_without_peer_addr_is_still_served() {
+        use crate::core::net::socket::ClientSocket;
+        use std::io::Write;
+        use std::net::{TcpListener, TcpStream};
+
+        let mut manager = manager();
+        let port = manager.server_instances[0].config().ports[0];
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        // As if the peer reset before its address could be read
+        let connection =
+            Connection::with_port(ClientSocket::with_peer(stream, None).unwrap(), 30, port);
+        assert_eq!(connection.peer_addr(), None);
+        let fd = connection.as_raw_fd();
+        manager.connections.insert(fd, connection);
+        manager.parsers.insert(fd, RequestParser::new());
+
+        client
+            .write_all(b"GET /nowhere HTTP/1.1\r\nHost: alpha\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+
+        let connection = &manager.connections[&fd];
+        assert!(*connection.state() == ConnectionState::Writing);
+        assert!(connection
+            .write_buffer()
+            .as_slice()
+            .starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_proxy_protocol_header_supplies_client_address() {
+        use std::io::Write;
+        use std::net::TcpStream;
+
+        let root = temp_root("proxy");
+        std::fs::write(
+            root.join("script.sh"),
+            "printf 'Content-Type: text/plain\\r\\n\\r\\n%s' \"$REMOTE_ADDR\"\n",
+        )
+        .unwrap();
+        let mut config = server_config("alpha", &["/"]);
+        config.root = root.to_string_lossy().to_string();
+        config.proxy_protocol = true;
+        config.routes.insert(
+            "/cgi".to_string(),
+            RouteConfig {
+                directory: Some(".".to_string()),
+                cgi_extension: Some("sh".to_string()),
+                ..RouteConfig::default()
+            },
+        );
+        config
+            .cgi_handlers
+            .insert("sh".to_string(), "/bin/sh".to_string());
+        let mut manager = ServerManager::new(Config {
+            servers: vec![config],
+            ..Config::default()
+        })
+        .unwrap();
+
+        // Accept through the listener so the connection is flagged like a real one
+        let mut client = TcpStream::connect(manager.local_addr(0).unwrap()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(manager.accept_connection(-1, 0).unwrap());
+        let fd = *manager.connections.keys().next().unwrap();
+        assert!(manager.connections[&fd].awaiting_proxy_header());
+
+        // The header may arrive split across reads
+        client
+            .write_all(b"PROXY TCP4 203.0.113.7 10.0.0.1 ")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        assert!(manager.connections[&fd].awaiting_proxy_header());
+
+        client
+            .write_all(b"56324 80\r\nGET /cgi/script.sh HTTP/1.1\r\nHost: alpha\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        let connection = &manager.connections[&fd];
+        assert_eq!(
+            connection.client_addr(),
+            Some("203.0.113.7:56324".parse().unwrap())
+        );
+        let response = String::from_utf8(connection.write_buffer().as_slice()).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("\r\n\r\n203.0.113.7"), "{}", response);
+    }
+
+    #[test]
+    fn test_bare_lf_request_answered_in_both_line_ending_modes() {
+        use std::io::Write;
+
+        let request = b"GET /nowhere HTTP/1.1\nHost: alpha\n\n";
+        let configured = |strict: bool| {
+            ServerManager::new(Config {
+                servers: vec![server_config("alpha", &["/"])],
+                strict_line_endings: strict,
+                ..Config::default()
+            })
+            .unwrap()
+        };
+
+        let response = configured(false).handle_request_bytes(request);
+        assert!(response.starts_with(b"HTTP/1.1 404"));
+
+        // Strict mode answers at once instead of waiting for a CRLF until timeout
+        let mut manager = configured(true);
+        let port = manager.server_instances[0].config().ports[0];
+        let (fd, mut client) = connect_client(&mut manager, port);
+
+        client.write_all(request).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        assert!(manager.connections[&fd]
+            .write_buffer()
+            .as_slice()
+            .starts_with(b"HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_connection_limit_applies_only_to_its_port() {
+        use std::net::{TcpListener, TcpStream};
+
+        // Two distinct ports are needed, so borrow free ones from the OS
+        let free_port = || {
+            TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port()
+        };
+        let (limited_port, open_port) = (free_port(), free_port());
+        let mut limited = server_config("limited", &["/"]);
+        limited.ports = vec![limited_port];
+        limited.max_connections = 1;
+        let mut open = server_config("open", &["/"]);
+        open.ports = vec![open_port];
+        let mut manager = ServerManager::new(Config {
+            servers: vec![limited, open],
+            ..Config::default()
+        })
+        .unwrap();
+
+        let mut clients = Vec::new();
+        let mut accept = |manager: &mut ServerManager, port: u16| {
+            let before: HashSet<i32> = manager.connections.keys().copied().collect();
+            clients.push(TcpStream::connect(("127.0.0.1", port)).unwrap());
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(manager.accept_connection(-1, port).unwrap());
+            *manager
+                .connections
+                .keys()
+                .find(|fd| !before.contains(fd))
+                .unwrap()
+        };
+        let rejected = |manager: &ServerManager, fd: i32| {
+            manager.connections[&fd]
+                .write_buffer()
+                .as_slice()
+                .starts_with(b"HTTP/1.1 503")
+        };
+
+        let first = accept(&mut manager, limited_port);
+        let second = accept(&mut manager, limited_port);
+        assert!(!rejected(&manager, first));
+        assert!(rejected(&manager, second));
+
+        for _ in 0..3 {
+            let fd = accept(&mut manager, open_port);
+            assert!(!rejected(&manager, fd));
+            assert!(*manager.connections[&fd].state() == ConnectionState::Reading);
+        }
+    }
+
+    #[test]
+    fn test_get_with_body_rejected_or_discarded() {
+        let configured = |discard: bool| {
+            ServerManager::new(Config {
+                servers: vec![server_config("alpha", &["/"])],
+                discard_get_body: discard,
+                ..Config::default()
+            })
+            .unwrap()
+        };
+        let request = b"GET /nowhere HTTP/1.1\r\nHost: alpha\r\nContent-Length: 5\r\n\r\nhello";
+
+        let response = configured(false).handle_request_bytes(request);
+        assert!(response.starts_with(b"HTTP/1.1 400"));
+
+        // The body is read and dropped, and the request is served normally
+        let response = configured(true).handle_request_bytes(request);
+        assert!(response.starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_duplicate_singleton_header_answered_with_400() {
+        let mut manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            ..Config::default()
+        })
+        .unwrap();
+
+        let response = manager.handle_request_bytes(
+            b"POST /nowhere HTTP/1.1\r\nHost: alpha\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\nhello!",
+        );
+        assert!(response.starts_with(b"HTTP/1.1 400"));
+
+        let response = manager.handle_request_bytes(
+            b"GET /nowhere HTTP/1.1\r\nHost: alpha\r\nAccept: text/html\r\nAccept: */*\r\n\r\n",
+        );
+        assert!(response.starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_connection_close_response_reaches_slow_reader_in_full() {
+        use std::io::{Read, Write};
+
+        let root = temp_root("linger");
+        let content: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root.join("big.bin"), &content).unwrap();
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.root = root.to_string_lossy().to_string();
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            ..Config::default()
+        })
+        .unwrap();
+
+        let port = manager.server_instances[0].config().ports[0];
+        let (fd, mut client) = connect_client(&mut manager, port);
+
+        client
+            .write_all(b"GET /big.bin HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        // Input the server never reads: closing on top of it would reset the connection
+        client.write_all(b"more bytes the server ignores").unwrap();
+
+        let reader = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut buf = [0u8; 16 * 1024];
+            loop {
+                match client.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => received.extend_from_slice(&buf[..n]),
+                    Err(e) => panic!("read failed after {} bytes: {}", received.len(), e),
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            received
+        });
+
+        let event = PollEvent {
+            fd,
+            readable: true,
+            writable: true,
+        };
+        let started = std::time::Instant::now();
+        while manager.connections.contains_key(&fd)
+            && started.elapsed() < std::time::Duration::from_secs(20)
+        {
+            manager.handle_client_event(fd, event).unwrap();
+            std::thread::sleep(std::time::Duration::from_micros(200));
+        }
+        let received = reader.join().unwrap();
+
+        // Closed once the client hung up, not left lingering
+        assert!(!manager.connections.contains_key(&fd));
+        let head_end = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&received[..head_end]);
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(received[head_end + 4..] == content[..]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    /// A manager serving `test.txt` from a temp root, plus a socket pair connected to it
+    fn keep_alive_fixture(
+        name: &str,
+        configure: impl FnOnce(&mut Config),
+    ) -> (ServerManager, i32, std::net::TcpStream, std::path::PathBuf) {
+        let root = temp_root(name);
+        std::fs::write(root.join("test.txt"), "keep me alive").unwrap();
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.root = root.to_string_lossy().to_string();
+        let mut config = Config {
+            servers: vec![alpha],
+            ..Config::default()
+        };
+        configure(&mut config);
+        let mut manager = ServerManager::new(config).unwrap();
+
+        let port = manager.server_instances[0].config().ports[0];
+        let (fd, client) = connect_client(&mut manager, port);
+        (manager, fd, client, root)
+    }
+
+    /// Send one request and drive the connection until its response is written,
+    /// returning the response head as the client read it
+    fn exchange(manager: &mut ServerManager, fd: i32, client: &mut std::net::TcpStream) -> String {
+        use std::io::{Read, Write};
+
+        client
+            .write_all(b"GET /test.txt HTTP/1.1\r\nHost: alpha\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        let event = PollEvent {
+            fd,
+            readable: false,
+            writable: true,
+        };
+        while manager
+            .connections
+            .get(&fd)
+            .is_some_and(|c| *c.state() == ConnectionState::Writing)
+        {
+            manager.handle_client_event(fd, event).unwrap();
+        }
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !received.ends_with(b"keep me alive") {
+            let n = client.read(&mut buf).unwrap();
+            assert!(n > 0, "connection closed mid-response");
+            received.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(received).unwrap()
+    }
+
+    #[test]
+    fn test_keep_alive_reuses_connection_until_request_cap() {
+        use std::io::Read;
+
+        let (mut manager, fd, mut client, root) = keep_alive_fixture("keepalive_cap", |config| {
+            config.max_keep_alive_requests = 3;
+        });
+
+        for _ in 0..2 {
+            let response = exchange(&mut manager, fd, &mut client);
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+            assert!(!response.contains("Connection: close"), "{}", response);
+            // Still open and waiting for the next request on the same stream
+            let connection = &manager.connections[&fd];
+            assert!(*connection.state() == ConnectionState::Reading && connection.is_idle());
+        }
+
+        let response = exchange(&mut manager, fd, &mut client);
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("Connection: close\r\n"), "{}", response);
+        assert_eq!(manager.connections[&fd].requests_served(), 3);
+        let mut rest = Vec::new();
+        assert_eq!(client.read_to_end(&mut rest).unwrap(), 0);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_idle_keep_alive_connection_times_out() {
+        let (mut manager, fd, mut client, root) = keep_alive_fixture("keepalive_idle", |config| {
+            config.keep_alive_timeout_secs = 1;
+        });
+
+        exchange(&mut manager, fd, &mut client);
+        manager.cleanup_connections().unwrap();
+        assert!(manager.connections.contains_key(&fd));
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        manager.cleanup_connections().unwrap();
+        assert!(!manager.connections.contains_key(&fd));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_connection_over_buffer_cap_is_closed() {
+        use std::io::{Read, Write};
+
+        let (mut manager, fd, mut client, root) = keep_alive_fixture("buffer_cap", |config| {
+            config.max_connection_buffer_bytes = 3000;
+        });
+        std::fs::write(root.join("big.txt"), "x".repeat(2000)).unwrap();
+
+        // Small exchanges stay well under the cap
+        assert!(exchange(&mut manager, fd, &mut client).starts_with("HTTP/1.1 200"));
+
+        // A 2000-byte response queued behind 1500 bytes of a pipelined request is over it,
+        // though each alone is not
+        let mut requests = b"GET /big.txt HTTP/1.1\r\nHost: alpha\r\n\r\n".to_vec();
+        requests.extend_from_slice(b"GET /test.txt HTTP/1.1\r\nX-Fill: ");
+        requests.extend_from_slice(&[b'a'; 1500]);
+        client.write_all(&requests).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        assert!(!manager.connections.contains_key(&fd));
+        assert!(!manager.parsers.contains_key(&fd));
+
+        // The client sees the connection end without the big response
+        let mut rest = Vec::new();
+        let _ = client.read_to_end(&mut rest);
+        assert!(!String::from_utf8_lossy(&rest).contains("xxxx"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_connection_burst_is_accepted_a_few_per_tick() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let root = temp_root("accepts");
+        std::fs::write(root.join("test.txt"), "still served").unwrap();
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.root = root.to_string_lossy().to_string();
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            max_accepts_per_tick: 4,
+            ..Config::default()
+        })
+        .unwrap();
+        let addr = manager.local_addr(0).unwrap();
+
+        let mut busy = TcpStream::connect(addr).unwrap();
+        busy.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        manager.run_once(1000).unwrap();
+        assert_eq!(manager.connections.len(), 1);
+
+        let burst: Vec<TcpStream> = (0..40).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        for _ in 0..3 {
+            let accepted = manager.connections.len();
+            busy.write_all(b"GET /test.txt HTTP/1.1\r\nHost: alpha\r\n\r\n")
+                .unwrap();
+            // One pass reads and answers the request, the next flushes the response
+            manager.run_once(1000).unwrap();
+            manager.run_once(1000).unwrap();
+            assert!(manager.connections.len() <= accepted + 8);
+
+            let mut response = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !response.ends_with(b"still served") {
+                let n = busy.read(&mut buf).unwrap();
+                assert!(n > 0, "connection closed before the response arrived");
+                response.extend_from_slice(&buf[..n]);
+            }
+            assert!(response.starts_with(b"HTTP/1.1 200"));
+        }
+        assert!(manager.connections.len() < 1 + burst.len());
+
+        // The rest of the burst still gets in over later passes
+        for _ in 0..20 {
+            manager.run_once(10).unwrap();
+        }
+        assert_eq!(manager.connections.len(), 1 + burst.len());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_slow_cgi_on_a_worker_does_not_block_a_fast_request() {
+        use std::io::{Read, Write};
+        use std::time::{Duration, Instant};
+
+        let root = temp_root("workers");
+        std::fs::create_dir_all(root.join("cgi")).unwrap();
+        std::fs::write(root.join("fast.txt"), "fast").unwrap();
+        // The script holds its worker until the test creates the release file
+        let release = root.join("release");
+        std::fs::write(
+            root.join("cgi/slow.sh"),
+            format!(
+                "while [ ! -f '{}' ]; do sleep 0.01; done\nprintf 'Content-Type: text/plain\\r\\n\\r\\nslow'\n",
+                release.display()
+            ),
+        )
+        .unwrap();
+        let mut alpha = server_config("alpha", &["/", "/cgi"]);
+        alpha.root = root.to_string_lossy().to_string();
+        alpha.routes.get_mut("/cgi").unwrap().cgi_extension = Some("sh".to_string());
+        alpha
+            .cgi_handlers
+            .insert("sh".to_string(), "/bin/sh".to_string());
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            worker_threads: 2,
+            ..Config::default()
+        })
+        .unwrap();
+
+        let port = manager.server_instances[0].config().ports[0];
+        let mut connect = |request: &str| {
+            let (fd, mut client) = connect_client(&mut manager, port);
+            client.write_all(request.as_bytes()).unwrap();
+            (fd, client)
+        };
+        let (slow_fd, mut slow) =
+            connect("GET /cgi/slow.sh HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n");
+        let (fast_fd, mut fast) =
+            connect("GET /fast.txt HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n");
+        std::thread::sleep(Duration::from_millis(50));
+
+        let started = Instant::now();
+        manager.handle_read(slow_fd).unwrap();
+        manager.handle_read(fast_fd).unwrap();
+        let busy = |manager: &ServerManager, fd: i32| {
+            manager.connections.get(&fd).is_some_and(|c| {
+                matches!(
+                    c.state(),
+                    ConnectionState::Processing | ConnectionState::Writing
+                )
+            })
+        };
+        // Drive the connections the way the event loop does
+        let drive_until_done = |manager: &mut ServerManager, fd: i32| {
+            while busy(manager, fd) && started.elapsed() < Duration::from_secs(10) {
+                manager.complete_offloaded_requests();
+                for fd in [slow_fd, fast_fd] {
+                    let event = PollEvent {
+                        fd,
+                        readable: false,
+                        writable: true,
+                    };
+                    manager.handle_client_event(fd, event).unwrap();
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+
+        drive_until_done(&mut manager, fast_fd);
+        let mut response = String::new();
+        fast.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200") && response.ends_with("fast"));
+        // The fast response went out while the script was still running
+        assert!(*manager.connections[&slow_fd].state() == ConnectionState::Processing);
+
+        std::fs::write(&release, "").unwrap();
+        drive_until_done(&mut manager, slow_fd);
+        let mut response = String::new();
+        slow.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200") && response.ends_with("slow"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_proxied_head_runs_on_a_worker_with_the_upstream_length() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::time::{Duration, Instant};
+
+        // Upstream that answers HEAD with a length and no body once released, then keeps
+        // the connection open until the test is done with the response
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (close_tx, close_rx) = std::sync::mpsc::channel::<()>();
+        let upstream_thread = std::thread::spawn(move || {
+            let (mut stream, _) = upstream.accept().unwrap();
+            let mut received = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut chunk).unwrap();
+                received.extend_from_slice(&chunk[..n]);
+            }
+            let _ = release_rx.recv();
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 1234\r\n\r\n",
+                )
+                .unwrap();
+            // Kept open: the response must be recognised as complete without EOF
+            let _ = close_rx.recv();
+            String::from_utf8_lossy(&received).to_string()
+        });
+
+        let root = temp_root("proxy_worker");
+        std::fs::write(root.join("fast.txt"), "fast").unwrap();
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.root = root.to_string_lossy().to_string();
+        alpha.routes.insert(
+            "/api".to_string(),
+            RouteConfig {
+                proxy_pass: Some(format!("http://127.0.0.1:{}", upstream_port)),
+                ..RouteConfig::default()
+            },
+        );
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            worker_threads: 2,
+            ..Config::default()
+        })
+        .unwrap();
+        let port = manager.server_instances[0].config().ports[0];
+
+        let (proxied_fd, mut proxied) = connect_client(&mut manager, port);
+        proxied
+            .write_all(b"HEAD /api/thing HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let (fast_fd, mut fast) = connect_client(&mut manager, port);
+        fast.write_all(b"GET /fast.txt HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let started = Instant::now();
+        manager.handle_read(proxied_fd).unwrap();
+        manager.handle_read(fast_fd).unwrap();
+        let drive_until_done = |manager: &mut ServerManager, fd: i32| {
+            while manager.connections.get(&fd).is_some_and(|c| {
+                matches!(
+                    c.state(),
+                    ConnectionState::Processing | ConnectionState::Writing
+                )
+            }) && started.elapsed() < Duration::from_secs(5)
+            {
+                manager.complete_offloaded_requests();
+                let event = PollEvent {
+                    fd,
+                    readable: false,
+                    writable: true,
+                };
+                manager.handle_client_event(fd, event).unwrap();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+        drive_until_done(&mut manager, fast_fd);
+        let mut response = String::new();
+        fast.read_to_string(&mut response).unwrap();
+        assert!(response.ends_with("fast"));
+        // The static file was answered while the upstream was still thinking
+        assert!(*manager.connections[&proxied_fd].state() == ConnectionState::Processing);
+
+        release_tx.send(()).unwrap();
+        drive_until_done(&mut manager, proxied_fd);
+        let mut response = String::new();
+        proxied.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("Content-Length: 1234\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+
+        close_tx.send(()).unwrap();
+
+        assert!(upstream_thread
+            .join()
+            .unwrap()
+            .starts_with("HEAD /api/thing HTTP/1.1"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_panic_on_a_worker_answers_500_and_keeps_the_pool() {
+        use std::io::{Read, Write};
+        use std::time::{Duration, Instant};
+
+        let root = temp_root("worker_panic");
+        std::fs::write(root.join("fast.txt"), "fast").unwrap();
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.root = root.to_string_lossy().to_string();
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            worker_threads: 1,
+            ..Config::default()
+        })
+        .unwrap();
+
+        let port = manager.server_instances[0].config().ports[0];
+        let drive_until_done = |manager: &mut ServerManager, fd: i32| {
+            let started = Instant::now();
+            while manager.connections.get(&fd).is_some_and(|c| {
+                matches!(
+                    c.state(),
+                    ConnectionState::Processing | ConnectionState::Writing
+                )
+            }) && started.elapsed() < Duration::from_secs(5)
+            {
+                manager.complete_offloaded_requests();
+                let event = PollEvent {
+                    fd,
+                    readable: false,
+                    writable: true,
+                };
+                manager.handle_client_event(fd, event).unwrap();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+
+        let (panic_fd, mut panicked) = connect_client(&mut manager, port);
+        let mut request = Request::new(
+            crate::http::method::Method::GET,
+            "/fast.txt".to_string(),
+            crate::http::version::Version::Http11,
+        );
+        request
+            .headers
+            .set("Connection".to_string(), "close".to_string());
+        let work = OffloadedWork {
+            job: Box::new(|| panic!("handler bug")),
+            finish: ResponseFinish {
+                server_idx: 0,
+                version: request.version,
+                fallback: None,
+                session_id: None,
+                started: Instant::now(),
+                routing_time: Duration::ZERO,
+                dispatch_phase: "static",
+                dispatch_started: Instant::now(),
+            },
+        };
+        manager.offload_request(panic_fd, request, work).unwrap();
+        drive_until_done(&mut manager, panic_fd);
+        let mut response = String::new();
+        panicked.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 500"), "{}", response);
+
+        // The pool's only worker survived to serve the next offloaded request
+        let (fast_fd, mut fast) = connect_client(&mut manager, port);
+        fast.write_all(b"GET /fast.txt HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        manager.handle_read(fast_fd).unwrap();
+        drive_until_done(&mut manager, fast_fd);
+        let mut response = String::new();
+        fast.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200") && response.ends_with("fast"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_options_reports_allowed_methods() {
+        let mut alpha = server_config("alpha", &["/", "/upload", "/private"]);
+        let methods = |list: &[&str]| list.iter().map(|m| m.to_string()).collect();
+        alpha.routes.get_mut("/").unwrap().methods = methods(&["GET", "HEAD", "OPTIONS"]);
+        alpha.routes.get_mut("/upload").unwrap().methods = methods(&["POST", "OPTIONS"]);
+        alpha.routes.get_mut("/private").unwrap().methods = methods(&["GET"]);
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            ..Config::default()
+        })
+        .unwrap();
+        let mut options = |target: &str| {
+            String::from_utf8(manager.handle_request_bytes(
+                format!("OPTIONS {} HTTP/1.1\r\nHost: alpha\r\n\r\n", target).as_bytes(),
+            ))
+            .unwrap()
+        };
+
+        let response = options("/");
+        assert!(response.starts_with("HTTP/1.1 204"), "{}", response);
+        assert!(
+            response.contains("Allow: GET, HEAD, OPTIONS\r\n"),
+            "{}",
+            response
+        );
+
+        let response = options("*");
+        assert!(response.starts_with("HTTP/1.1 204"), "{}", response);
+        assert!(
+            response.contains("Allow: GET, HEAD, POST, OPTIONS\r\n"),
+            "{}",
+            response
+        );
+
+        // A route that leaves OPTIONS out refuses it
+        assert!(options("/private").starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn test_auto_head_and_auto_options_on_get_only_route() {
+        let root = temp_root("auto");
+        std::fs::write(root.join("test.txt"), "0123456789").unwrap();
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.root = root.to_string_lossy().to_string();
+        alpha.routes.get_mut("/").unwrap().methods = vec!["GET".to_string()];
+        let send = |alpha: &ServerConfig, request: &str| {
+            let mut manager = ServerManager::new(Config {
+                servers: vec![alpha.clone()],
+                ..Config::default()
+            })
+            .unwrap();
+            String::from_utf8(manager.handle_request_bytes(request.as_bytes())).unwrap()
+        };
+        let head = "HEAD /test.txt HTTP/1.1\r\nHost: alpha\r\n\r\n";
+        let options = "OPTIONS /test.txt HTTP/1.1\r\nHost: alpha\r\n\r\n";
+        assert!(send(&alpha, head).starts_with("HTTP/1.1 405"));
+        assert!(send(&alpha, options).starts_with("HTTP/1.1 405"));
+
+        alpha.auto_head = true;
+        alpha.auto_options = true;
+        let response = send(&alpha, head);
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("Content-Length: 10\r\n"), "{}", response);
+        assert!(response.ends_with("\r\n\r\n"), "{}", response);
+        let response = send(&alpha, options);
+        assert!(response.starts_with("HTTP/1.1 204"), "{}", response);
+        assert!(
+            response.contains("Allow: GET, HEAD, OPTIONS\r\n"),
+            "{}",
+            response
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_head_sends_get_headers_without_body() {
+        let root = temp_root("head");
+        std::fs::create_dir_all(root.join("listing")).unwrap();
+        std::fs::write(root.join("test.txt"), "0123456789abcdef").unwrap();
+        std::fs::write(root.join("listing/entry.txt"), "x").unwrap();
+        std::fs::create_dir_all(root.join("cgi")).unwrap();
+        std::fs::write(
+            root.join("cgi/run.sh"),
+            "printf 'Content-Type: text/plain\\r\\n\\r\\n'\necho \"hello from cgi\"\n",
+        )
+        .unwrap();
+        let mut alpha = server_config("alpha", &["/", "/listing", "/cgi"]);
+        alpha.root = root.to_string_lossy().to_string();
+        for route in alpha.routes.values_mut() {
+            route.methods = vec!["GET".to_string(), "HEAD".to_string()];
+        }
+        alpha.routes.get_mut("/listing").unwrap().directory_listing = true;
+        alpha.routes.get_mut("/cgi").unwrap().cgi_extension = Some("sh".to_string());
+        alpha
+            .cgi_handlers
+            .insert("sh".to_string(), "/bin/sh".to_string());
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            ..Config::default()
+        })
+        .unwrap();
+        let split = |response: Vec<u8>| {
+            let head_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+            (
+                String::from_utf8_lossy(&response[..head_end]).to_string(),
+                response[head_end + 4..].to_vec(),
+            )
+        };
+
+        // The length is taken from the full body each handler produced, before it is dropped
+        for path in ["/test.txt", "/listing/", "/cgi/run.sh"] {
+            let (get_head, get_body) = split(manager.handle_request_bytes(
+                format!("GET {} HTTP/1.1\r\nHost: alpha\r\n\r\n", path).as_bytes(),
+            ));
+            let (head, body) = split(manager.handle_request_bytes(
+                format!("HEAD {} HTTP/1.1\r\nHost: alpha\r\n\r\n", path).as_bytes(),
+            ));
+            assert!(head.starts_with("HTTP/1.1 200"), "{}", path);
+            assert!(body.is_empty(), "{}", path);
+            let length = format!("Content-Length: {}", get_body.len());
+            assert!(
+                get_head.contains(&length) && head.contains(&length),
+                "{}",
+                path
+            );
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_te_trailers_accepted_and_unsupported_codings_configurable() {
+        let run = |reject_unsupported_te: bool, te: &str| {
+            let mut manager = ServerManager::new(Config {
+                servers: vec![server_config("alpha", &["/"])],
+                reject_unsupported_te,
+                ..Config::default()
+            })
+            .unwrap();
+            manager.handle_request_bytes(
+                format!("GET /nowhere HTTP/1.1\r\nHost: alpha\r\nTE: {}\r\n\r\n", te).as_bytes(),
+            )
+        };
+
+        assert!(run(true, "trailers").starts_with(b"HTTP/1.1 404"));
+        assert!(run(true, "gzip").starts_with(b"HTTP/1.1 400"));
+        // By default an unsupported coding is ignored like any other preference
+        assert!(run(false, "gzip").starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_oversized_request_head_answered_with_431() {
+        use std::io::Write;
+
+        let mut manager = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            max_header_count: 5,
+            ..Config::default()
+        })
+        .unwrap();
+        let port = manager.server_instances[0].config().ports[0];
+        let (fd, mut client) = connect_client(&mut manager, port);
+
+        // An 80KB header line that never ends is cut off, not buffered
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: alpha\r\nX-Big: ")
+            .unwrap();
+        client.write_all(&[b'a'; 80 * 1024]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        for _ in 0..20 {
+            if !manager.connections[&fd].write_buffer().is_empty() {
+                break;
             }
+            manager.handle_read(fd).unwrap();
+        }
+        let response = manager.connections[&fd].write_buffer().as_slice();
+        assert!(response.starts_with(b"HTTP/1.1 431 Request Header Fields Too Large"));
+
+        let mut headers = "GET / HTTP/1.1\r\nHost: alpha\r\n".to_string();
+        for i in 0..5 {
+            headers.push_str(&format!("X-H{}: v\r\n", i));
         }
+        let response = manager.handle_request_bytes(format!("{}\r\n", headers).as_bytes());
+        assert!(response.starts_with(b"HTTP/1.1 431"));
     }
 
-    /// Find server instance for a request based on Host header and port
-    fn find_server_for_request(&self, request: &Request, port: u16) -> Result<usize> {
-        // Log the raw Host header for debugging
-        let raw_host = request.host().map(|h| h.as_str()).unwrap_or("(missing)");
-        crate::common::logger::Logger::info(&format!(
-            "Server selection for {} {}: port={}, Host header='{}'",
-            request.method,
-            request.path(),
-            port,
-            raw_host
-        ));
+    #[test]
+    fn test_conflicting_content_lengths_answered_with_400() {
+        use std::io::Write;
 
-        // Try to match by Host header and port
-        if let Some(host) = request.host() {
-            // Extract hostname (remove port if present)
-            let hostname = host.split(':').next().unwrap_or(host).to_lowercase();
+        let mut manager = manager();
+        let port = manager.server_instances[0].config().ports[0];
+        let (fd, mut client) = connect_client(&mut manager, port);
 
-            // Handle common localhost variations: 127.0.0.1 and ::1 should match "localhost"
-            let normalized_hostname =
-                if hostname == "127.0.0.1" || hostname == "::1" || hostname == "[::1]" {
-                    "localhost".to_string()
-                } else {
-                    hostname.clone()
-                };
+        client
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: alpha\r\nContent-Length: 3\r\nContent-Length: 4\r\n\r\nabcd",
+            )
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        let response = manager.connections[&fd].write_buffer().as_slice();
+        assert!(response.starts_with(b"HTTP/1.1 400"));
+    }
 
-            // Log available servers for this port for debugging
-            let available_servers: Vec<String> = self
-                .server_lookup
-                .iter()
-                .filter(|((p, _), _)| *p == port)
-                .map(|((_, h), idx)| format!("'{}' (idx: {})", h, idx))
-                .collect();
+    #[test]
+    fn test_pipelined_requests_beyond_depth_close_the_connection() {
+        use std::io::Write;
 
-            // Log default server for this port
-            let default_idx = self.default_servers.get(&port).copied();
-            let default_info = if let Some(idx) = default_idx {
-                if let Ok(server) = self.get_server_instance(idx) {
-                    format!("'{}' (idx: {})", server.server_name(), idx)
-                } else {
-                    format!("(idx: {})", idx)
-                }
-            } else {
-                "none".to_string()
-            };
+        // Sends `count` pipelined requests at once and returns the second response and
+        // whether the connection stays open after it
+        let run = |max_pipeline_depth: usize, count: usize| {
+            let mut manager = ServerManager::new(Config {
+                servers: vec![server_config("alpha", &["/"])],
+                max_pipeline_depth,
+                ..Config::default()
+            })
+            .unwrap();
+            let port = manager.server_instances[0].config().ports[0];
+            let (fd, mut client) = connect_client(&mut manager, port);
 
-            crate::common::logger::Logger::info(&format!(
-                "Looking for server match: Host header='{}' (normalized from '{}' -> '{}'), port={}, available servers: [{}], default: {}",
-                normalized_hostname,
-                hostname,
-                host,
-                port,
-                available_servers.join(", "),
-                default_info
-            ));
+            client
+                .write_all(&b"GET /nowhere HTTP/1.1\r\nHost: alpha\r\n\r\n".repeat(count))
+                .unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            manager.handle_read(fd).unwrap();
+            assert!(manager.connections[&fd].should_keep_alive());
 
-            // Try exact match first
-            if let Some(&server_idx) = self.server_lookup.get(&(port, normalized_hostname.clone()))
-            {
-                let server_instance = self.get_server_instance(server_idx)?;
-                crate::common::logger::Logger::info(&format!(
-                    "Request {} {} -> Resolved server_name: '{}' (matched by Host header: '{}') on port {}",
-                    request.method,
-                    request.path(),
-                    server_instance.config().server_name,
-                    normalized_hostname,
-                    port
-                ));
-                return Ok(server_idx);
-            }
+            // The first response has been sent; the next queued request is answered
+            manager
+                .connections
+                .get_mut(&fd)
+                .unwrap()
+                .write_buffer_mut()
+                .clear();
+            manager.reset_for_next_request(fd).unwrap();
+            let connection = &manager.connections[&fd];
+            let second = String::from_utf8(connection.write_buffer().as_slice()).unwrap();
+            (second, connection.should_keep_alive())
+        };
 
-            // Try original hostname if normalization changed it
-            if normalized_hostname != hostname {
-                if let Some(&server_idx) = self.server_lookup.get(&(port, hostname.clone())) {
-                    let server_instance = self.get_server_instance(server_idx)?;
-                    crate::common::logger::Logger::info(&format!(
-                        "Request {} {} -> Resolved server_name: '{}' (matched by Host header: '{}') on port {}",
-                        request.method,
-                        request.path(),
-                        server_instance.config().server_name,
-                        hostname,
-                        port
-                    ));
-                    return Ok(server_idx);
-                }
-            }
+        let (second, keep_alive) = run(2, 5);
+        assert!(second.starts_with("HTTP/1.1 404"));
+        assert!(second.contains("Connection: close\r\n"));
+        assert!(!keep_alive);
 
-            crate::common::logger::Logger::warn(&format!(
-                "No server match found for Host header '{}' (normalized: '{}', original: '{}') on port {}, falling back to default server",
-                normalized_hostname,
-                hostname,
-                host,
-                port
-            ));
-        } else {
-            crate::common::logger::Logger::warn(&format!(
-                "No Host header present for request {} {} on port {}, falling back to default server",
-                request.method,
-                request.path(),
-                port
-            ));
-        }
+        // Within the depth, or without a limit, the connection carries on
+        let (_, keep_alive) = run(2, 2);
+        assert!(keep_alive);
+        let (_, keep_alive) = run(0, 5);
+        assert!(keep_alive);
+    }
 
-        // Fall back to default server for this port
-        let server_idx = self.get_default_server_for_port(port)?;
-        let server_instance = self.get_server_instance(server_idx)?;
-        let host_header = request.host().map(|h| h.as_str()).unwrap_or("none");
-        crate::common::logger::Logger::info(&format!(
-            "Request {} {} -> Resolved server_name: '{}' (default server, Host header: '{}') on port {}",
-            request.method,
-            request.path(),
-            server_instance.config().server_name,
-            host_header,
-            port
-        ));
-        Ok(server_idx)
+    #[test]
+    fn test_repeated_slashes_route_and_resolve_like_single_ones() {
+        let root = temp_root("slashes");
+        std::fs::create_dir_all(root.join("files/b")).unwrap();
+        std::fs::write(root.join("files/b/c.txt"), "collapsed").unwrap();
+        let configured = |merge: bool| {
+            let mut alpha = server_config("alpha", &["/", "/api"]);
+            alpha.root = root.to_string_lossy().to_string();
+            alpha.routes.get_mut("/api").unwrap().directory = Some("files".to_string());
+            ServerManager::new(Config {
+                servers: vec![alpha],
+                merge_slashes: merge,
+                ..Config::default()
+            })
+            .unwrap()
+        };
+        let request = b"GET //api//b///c.txt?x=1 HTTP/1.1\r\nHost: alpha\r\n\r\n";
+
+        let response = configured(true).handle_request_bytes(request);
+        assert!(response.starts_with(b"HTTP/1.1 200"));
+        assert!(response.ends_with(b"collapsed"));
+
+        // Unmerged, "//api" is no "/api" and the root route finds nothing
+        let response = configured(false).handle_request_bytes(request);
+        assert!(response.starts_with(b"HTTP/1.1 404"));
+        let _ = std::fs::remove_dir_all(&root);
     }
 
-    /// Handle write event - send response to client
-    fn handle_write(&mut self, fd: i32) -> Result<()> {
-        // Get data to write first
-        let data: Vec<u8> = {
-            let connection = self.get_connection_mut(fd)?;
-            let write_buffer = connection.write_buffer_mut();
-            if write_buffer.is_empty() {
-                // Nothing to write
-                connection.set_state(ConnectionState::Reading);
-                // Connection reference dropped at end of this block
-                Vec::new()
-            } else {
-                // Copy the data so we can drop the connection reference
-                write_buffer.as_slice().to_vec()
-            }
+    #[test]
+    fn test_read_only_server_refuses_mutating_methods() {
+        let root = temp_root("read_only");
+        std::fs::create_dir_all(root.join("uploads")).unwrap();
+        let configured = |read_only: bool| {
+            let mut alpha = server_config("alpha", &["/"]);
+            alpha.root = root.to_string_lossy().to_string();
+            alpha.read_only = read_only;
+            let route = alpha.routes.get_mut("/").unwrap();
+            route.methods = ["GET", "POST", "PUT", "DELETE", "PATCH"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect();
+            route.upload_dir = Some("uploads".to_string());
+            ServerManager::new(Config {
+                servers: vec![alpha],
+                ..Config::default()
+            })
+            .unwrap()
+        };
+        let request = |method: &str| {
+            format!(
+                "{} /page.txt HTTP/1.1\r\nHost: alpha\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nhi",
+                method
+            )
         };
 
-        // Handle empty write buffer case after dropping connection reference
-        if data.is_empty() {
-            if let Err(e) = self.event_manager.unregister_write(fd) {
-                // Error unregistering - close connection
-                self.set_connection_state_and_close(fd, ConnectionState::Closed)?;
-                return Err(e);
-            }
-            return Ok(());
+        std::fs::write(root.join("page.txt"), "page").unwrap();
+        let mut locked = configured(true);
+        for method in ["POST", "PUT", "DELETE", "PATCH"] {
+            let response = locked.handle_request_bytes(request(method).as_bytes());
+            assert!(response.starts_with(b"HTTP/1.1 405"), "{}", method);
+            // Allow names only the methods the read-only server still takes
+            let response = String::from_utf8_lossy(&response);
+            assert!(response.contains("\r\nAllow: GET\r\n"), "{}", response);
         }
+        let response =
+            locked.handle_request_bytes(b"GET /page.txt HTTP/1.1\r\nHost: alpha\r\n\r\n");
+        assert!(response.starts_with(b"HTTP/1.1 200"));
+        assert!(root.join("page.txt").exists());
+        assert_eq!(std::fs::read_dir(root.join("uploads")).unwrap().count(), 0);
 
-        // Write data
-        let write_result = {
-            let connection = self.get_connection_mut(fd)?;
-            let socket = connection.socket_mut();
-            write_non_blocking(socket, &data)
+        // Without the override the route's upload and delete handlers run as configured
+        let mut open = configured(false);
+        let response = open.handle_request_bytes(request("POST").as_bytes());
+        assert!(response.starts_with(b"HTTP/1.1 201"));
+        assert_eq!(std::fs::read_dir(root.join("uploads")).unwrap().count(), 1);
+        let response = open.handle_request_bytes(request("DELETE").as_bytes());
+        assert!(response.starts_with(b"HTTP/1.1 2"));
+        assert!(!root.join("page.txt").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    /// Config with a 100-byte global body limit, raised on /uploads and lowered on /tiny
+    fn route_body_limits(config: &mut Config, root: &std::path::Path) {
+        std::fs::create_dir_all(root.join("uploads")).unwrap();
+        config.client_max_body_size = 100;
+        let alpha = &mut config.servers[0];
+        for (path, limit) in [("/", None), ("/uploads", Some(1000)), ("/tiny", Some(10))] {
+            alpha.routes.insert(
+                path.to_string(),
+                RouteConfig {
+                    methods: vec!["GET".to_string(), "POST".to_string()],
+                    upload_dir: Some("uploads".to_string()),
+                    max_body_size: limit,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn post(path: &str, body_len: usize) -> Vec<u8> {
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: alpha\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n",
+            path, body_len
+        )
+        .into_bytes();
+        request.resize(request.len() + body_len, b'x');
+        request
+    }
+
+    #[test]
+    fn test_route_body_limit_overrides_the_global_one() {
+        let root = temp_root("body_limit");
+        let mut alpha = server_config("alpha", &[]);
+        alpha.root = root.to_string_lossy().to_string();
+        let mut config = Config {
+            servers: vec![alpha],
+            ..Config::default()
         };
-        let n = match write_result {
-            Ok(n) => n,
-            Err(e) => {
-                // I/O error occurred - close connection
-                self.close_connection_on_error(fd)?;
-                return Err(e);
-            }
+        route_body_limits(&mut config, &root);
+        let mut manager = ServerManager::new(config).unwrap();
+
+        // Raised: a body over the global limit is accepted on /uploads only
+        assert!(manager
+            .handle_request_bytes(&post("/uploads/big.txt", 500))
+            .starts_with(b"HTTP/1.1 201"));
+        assert!(manager
+            .handle_request_bytes(&post("/big.txt", 500))
+            .starts_with(b"HTTP/1.1 413"));
+        assert!(manager
+            .handle_request_bytes(&post("/uploads/huge.txt", 1001))
+            .starts_with(b"HTTP/1.1 413"));
+
+        // Lowered: a body under the global limit is still too large for /tiny
+        assert!(manager
+            .handle_request_bytes(&post("/tiny/small.txt", 50))
+            .starts_with(b"HTTP/1.1 413"));
+        assert!(manager
+            .handle_request_bytes(&post("/small.txt", 50))
+            .starts_with(b"HTTP/1.1 201"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_route_body_limit_applies_before_the_body_is_read() {
+        use std::io::Write;
+
+        let mut root = std::path::PathBuf::new();
+        let (mut manager, fd, mut client, fixture_root) =
+            keep_alive_fixture("body_limit_early", |config| {
+                root = std::path::PathBuf::from(&config.servers[0].root);
+                route_body_limits(config, &root);
+            });
+        let queued = |manager: &ServerManager, fd: i32| {
+            String::from_utf8_lossy(&manager.connections[&fd].write_buffer().as_slice())
+                .into_owned()
         };
 
-        if n > 0 {
-            // Remove written data from buffer
-            self.get_connection_mut(fd)?.write_buffer_mut().drain(n);
-        }
+        // Only the head of a request to / is sent: its declared body is refused at once
+        let head = post("/big.txt", 500);
+        client.write_all(&head[..head.len() - 500]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        assert!(queued(&manager, fd).starts_with("HTTP/1.1 413"));
 
-        // Check if all data sent
-        let is_empty = self.get_connection(fd)?.write_buffer().is_empty();
-        if is_empty {
-            // All data sent
-            let should_keep_alive = self.get_connection(fd)?.should_keep_alive();
-            if should_keep_alive {
-                // Reset for next request
-                {
-                    let connection = self.get_connection_mut(fd)?;
-                    connection.set_state(ConnectionState::Reading);
-                    connection.read_buffer_mut().clear();
-                }
-                // Reset parser after dropping connection reference
-                if let Some(parser) = self.parsers.get_mut(&fd) {
-                    parser.reset();
+        // The same body is read and stored on /uploads
+        let (mut manager, fd, mut client, _) = keep_alive_fixture("body_limit_early", |config| {
+            route_body_limits(config, &root);
+        });
+        let request = post("/uploads/big.txt", 500);
+        let (head, body) = request.split_at(request.len() - 500);
+        client.write_all(head).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        assert!(*manager.connections[&fd].state() == ConnectionState::Reading);
+        // Routed once with the head; the body's reads don't route it again
+        assert!(manager.parsers[&fd].request_awaiting_plan().is_none());
+        client.write_all(body).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        manager.handle_read(fd).unwrap();
+        assert!(queued(&manager, fd).starts_with("HTTP/1.1 201"));
+        let _ = std::fs::remove_dir_all(&fixture_root);
+    }
+
+    #[test]
+    fn test_route_max_concurrent_rejects_only_that_route() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::time::{Duration, Instant};
+
+        // Upstream that holds its first exchange open until told to answer
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let upstream_thread = std::thread::spawn(move || {
+            for exchange in 0..2 {
+                let (mut stream, _) = upstream.accept().unwrap();
+                let mut received = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut chunk).unwrap();
+                    received.extend_from_slice(&chunk[..n]);
                 }
-                // Unregister write after dropping connection reference
-                if let Err(e) = self.event_manager.unregister_write(fd) {
-                    // Error unregistering - close connection
-                    self.set_connection_state_and_close(fd, ConnectionState::Closed)?;
-                    return Err(e);
+                if exchange == 0 {
+                    released.recv().unwrap();
                 }
-            } else {
-                // Close connection
-                self.close_connection_on_error(fd)?;
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nup")
+                    .unwrap();
             }
-        }
+        });
 
-        Ok(())
+        let mut alpha = server_config("alpha", &["/"]);
+        alpha.routes.insert(
+            "/report".to_string(),
+            RouteConfig {
+                proxy_pass: Some(format!("http://127.0.0.1:{}", upstream_port)),
+                max_concurrent: Some(1),
+                ..RouteConfig::default()
+            },
+        );
+        let mut manager = ServerManager::new(Config {
+            servers: vec![alpha],
+            worker_threads: 2,
+            ..Config::default()
+        })
+        .unwrap();
+        let port = manager.server_instances[0].config().ports[0];
+        let send = |manager: &mut ServerManager, path: &str| {
+            let (fd, mut client) = connect_client(manager, port);
+            client
+                .write_all(
+                    format!(
+                        "GET {} HTTP/1.1\r\nHost: alpha\r\nConnection: close\r\n\r\n",
+                        path
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            manager.handle_read(fd).unwrap();
+            (fd, client)
+        };
+        let queued = |manager: &ServerManager, fd: i32| {
+            String::from_utf8(manager.connections[&fd].write_buffer().as_slice()).unwrap()
+        };
+        let started = Instant::now();
+        let finish = |manager: &mut ServerManager, fd: i32, client: &mut std::net::TcpStream| {
+            while manager.connections.get(&fd).is_some_and(|c| {
+                matches!(
+                    c.state(),
+                    ConnectionState::Processing | ConnectionState::Writing
+                )
+            }) && started.elapsed() < Duration::from_secs(5)
+            {
+                manager.complete_offloaded_requests();
+                let event = PollEvent {
+                    fd,
+                    readable: false,
+                    writable: true,
+                };
+                manager.handle_client_event(fd, event).unwrap();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        // The first request holds the only slot while its upstream exchange is in flight
+        let (first_fd, mut first) = send(&mut manager, "/report/one");
+        assert_eq!(manager.route_limiter.in_flight(0, "/report"), 1);
+        let (busy_fd, _busy) = send(&mut manager, "/report/two");
+        assert!(queued(&manager, busy_fd).starts_with("HTTP/1.1 503"));
+        let (other_fd, mut other) = send(&mut manager, "/missing");
+        assert!(finish(&mut manager, other_fd, &mut other).starts_with("HTTP/1.1 404"));
+
+        release.send(()).unwrap();
+        assert!(finish(&mut manager, first_fd, &mut first).ends_with("up"));
+        assert_eq!(manager.route_limiter.in_flight(0, "/report"), 0);
+
+        // The slot is free again once the first request is answered
+        let (again_fd, mut again) = send(&mut manager, "/report/three");
+        assert!(finish(&mut manager, again_fd, &mut again).ends_with("up"));
+        upstream_thread.join().unwrap();
     }
 
-    /// Clean up timed out or closed connections
-    fn cleanup_connections(&mut self) -> Result<()> {
-        let mut to_remove = Vec::new();
+    #[test]
+    fn test_server_info_matches_config() {
+        let mut alpha = server_config("alpha", &["/", "/static"]);
+        alpha.routes.get_mut("/static").unwrap().methods = vec!["GET".to_string()];
+        alpha.routes.get_mut("/").unwrap().redirect = Some("/static".to_string());
+        alpha
+            .cgi_handlers
+            .insert(".sh".to_string(), "/bin/sh".to_string());
+        alpha.admin_access = true;
+        let manager = ServerManager::new(Config {
+            servers: vec![alpha, server_config("beta", &[])],
+            ..Config::default()
+        })
+        .unwrap();
 
-        for (fd, connection) in &self.connections {
-            if connection.is_timeout() {
-                to_remove.push(*fd);
-            }
-        }
+        let info = manager.server_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.servers.len(), 2);
 
-        for fd in to_remove {
-            self.close_connection(fd)?;
-        }
+        let alpha = &info.servers[0];
+        assert_eq!(alpha.name, "alpha");
+        assert_eq!(alpha.ports, vec![0]);
+        // Port 0 reports the port the OS actually bound
+        assert_eq!(alpha.listen_addrs, vec![manager.local_addr(0).unwrap()]);
+        assert_ne!(alpha.listen_addrs[0].port(), 0);
+        assert!(alpha.is_default && alpha.admin_access);
+        assert_eq!(alpha.root, *manager.server_instances[0].root_path());
+        assert_eq!(
+            alpha.routes,
+            vec![
+                RouteDetails {
+                    path: "/".to_string(),
+                    methods: vec![],
+                    redirect: Some("/static".to_string()),
+                },
+                RouteDetails {
+                    path: "/static".to_string(),
+                    methods: vec!["GET".to_string()],
+                    redirect: None,
+                },
+            ]
+        );
+        assert_eq!(
+            alpha.cgi_handlers,
+            vec![(".sh".to_string(), "/bin/sh".to_string())]
+        );
 
-        Ok(())
-    }
+        let beta = &info.servers[1];
+        assert_eq!(beta.name, "beta");
+        assert!(!beta.is_default && !beta.admin_access);
+        assert!(beta.routes.is_empty());
 
-    /// Check if error is a body size violation
-    /// Checks for all possible body size error message patterns from the parser
-    fn is_body_size_error(error: &ServerError) -> bool {
-        if let ServerError::HttpError(ref msg) = error {
-            // Match all body size error patterns used in RequestParser:
-            // - "exceeds maximum allowed size" (used in multiple places)
-            // - "would exceed maximum allowed size" (used in add_data and chunked parsing)
-            msg.contains("exceeds maximum allowed size")
-                || msg.contains("would exceed maximum allowed size")
-        } else {
-            false
-        }
+        let json = info.to_json();
+        assert!(json.starts_with(r#"{"version":""#));
+        assert!(json.contains(r#""name":"alpha""#));
+        assert!(json.contains(r#""methods":["GET"]"#));
+        assert!(json.contains(r#""cgi_handlers":{".sh":"/bin/sh"}"#));
+        assert!(!json.contains('\n'));
+        assert!(info.to_text().contains("Server 1: beta"));
     }
 
-    /// Close connection on error - helper to reduce code duplication
-    fn close_connection_on_error(&mut self, fd: i32) -> Result<()> {
-        self.set_connection_state_and_close(fd, ConnectionState::Closed)
-    }
+    #[test]
+    fn test_large_cgi_body_streams_through_disk_to_stdin() {
+        use crate::core::net::socket::ClientSocket;
+        use std::io::Write;
+        use std::net::{TcpListener, TcpStream};
 
-    /// Set connection state and close connection (helper to reduce redundancy)
-    fn set_connection_state_and_close(&mut self, fd: i32, state: ConnectionState) -> Result<()> {
-        if let Ok(connection) = self.get_connection_mut(fd) {
-            connection.set_state(state);
+        let root = temp_root("cgi_spool");
+        std::fs::write(
+            root.join("echo.sh"),
+            "printf 'Content-Type: application/octet-stream\\r\\n\\r\\n'\ncat\n",
+        )
+        .unwrap();
+        let mut config = server_config("alpha", &["/"]);
+        config.root = root.to_string_lossy().to_string();
+        config.routes.insert(
+            "/".to_string(),
+            RouteConfig {
+                directory: Some(".".to_string()),
+                cgi_extension: Some("sh".to_string()),
+                ..RouteConfig::default()
+            },
+        );
+        config
+            .cgi_handlers
+            .insert("sh".to_string(), "/bin/sh".to_string());
+        let mut manager = ServerManager::new(Config {
+            servers: vec![config],
+            ..Config::default()
+        })
+        .unwrap();
+        let port = manager.server_instances[0].config().ports[0];
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, addr) = listener.accept().unwrap();
+        let peek = stream.try_clone().unwrap();
+        let connection =
+            Connection::with_port(ClientSocket::from_stream(stream, addr).unwrap(), 30, port);
+        let fd = connection.as_raw_fd();
+        manager.connections.insert(fd, connection);
+        manager.parsers.insert(fd, manager.new_parser());
+
+        let body: Vec<u8> = (0..3 * CGI_BODY_SPOOL_THRESHOLD as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let upload = body.clone();
+        let writer = std::thread::spawn(move || {
+            client
+                .write_all(
+                    format!(
+                        "POST /echo.sh HTTP/1.1\r\nHost: alpha\r\nContent-Length: {}\r\n\r\n",
+                        upload.len()
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+            client.write_all(&upload).unwrap();
+            client
+        });
+
+        while *manager.connections[&fd].state() == ConnectionState::Reading {
+            // Only read once data is there; an empty non-blocking read counts as EOF
+            while peek.peek(&mut [0u8; 1]).is_err() {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            manager.handle_read(fd).unwrap();
+
+            // Memory stays bounded by a single read while the body arrives
+            if let Some(parser) = manager.parsers.get(&fd) {
+                assert!(parser.buffered() <= DEFAULT_BUFFER_SIZE);
+                if let Some(request) = parser.pending_request() {
+                    assert!(request.body.is_empty());
+                    assert!(parser.is_streaming());
+                }
+            }
         }
-        self.close_connection(fd)
+        let _client = writer.join().unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        let response = manager.connections[&fd].write_buffer().as_slice();
+        let head_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 200"));
+        assert!(response[head_end + 4..] == body[..]);
     }
 
-    /// Close a connection and clean up resources
-    fn close_connection(&mut self, fd: i32) -> Result<()> {
-        // Try to unregister events, but ignore errors if they're already unregistered
-        // This can happen during cleanup or when connection is already closed
-        let _ = self.event_manager.unregister_read(fd);
-        let _ = self.event_manager.unregister_write(fd);
+    #[test]
+    fn test_dump_traffic_logs_raw_request_line() {
+        let request = b"GET /index.html HTTP/1.1\r\nHost: alpha\r\n\r\n";
 
-        self.connections.remove(&fd);
-        self.parsers.remove(&fd);
-        Ok(())
+        let dumping = ServerManager::new(Config {
+            servers: vec![server_config("alpha", &["/"])],
+            dump_traffic: true,
+            ..Config::default()
+        })
+        .unwrap();
+        let dump = dumping.traffic_dump(9, "received", request).unwrap();
+        assert!(dump.starts_with("Traffic fd 9 received"));
+        assert!(dump.contains("GET /index.html HTTP/1.1\\r\\n"));
+
+        assert_eq!(manager().traffic_dump(9, "received", request), None);
     }
 }
@@ -0,0 +1,191 @@
+use crate::common::error::{Result, ServerError};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Threads that run CPU-bound or blocking handler work off the event loop.
+///
+/// Each job is tagged with a token; finished results are queued for the event loop,
+/// which is woken through `wake_fd` so it can collect them with `completed`.
+/// A job that panics comes back as `Err` with the panic payload, and its worker lives on.
+pub struct WorkerPool<T> {
+    tasks: Option<Sender<Task>>,
+    results: Sender<(u64, thread::Result<T>)>,
+    completed: Receiver<(u64, thread::Result<T>)>,
+    /// Read end polled by the event loop; workers write a byte per finished job
+    wake_reader: UnixStream,
+    wake_writer: UnixStream,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> WorkerPool<T> {
+    /// Start `threads` workers (at least one)
+    pub fn new(threads: usize) -> Result<Self> {
+        let (wake_reader, wake_writer) = UnixStream::pair()?;
+        wake_reader.set_nonblocking(true)?;
+        // A full wake pipe already guarantees a wakeup, so writers never wait on it
+        wake_writer.set_nonblocking(true)?;
+
+        let (tasks, queue) = mpsc::channel::<Task>();
+        let queue = Arc::new(Mutex::new(queue));
+        let workers = (0..threads.max(1))
+            .map(|i| {
+                let queue = Arc::clone(&queue);
+                thread::Builder::new()
+                    .name(format!("worker-{}", i))
+                    .spawn(move || loop {
+                        // The lock is held only while waiting, never while a task runs
+                        let task = match queue.lock() {
+                            Ok(queue) => queue.recv(),
+                            Err(_) => break,
+                        };
+                        match task {
+                            Ok(task) => task(),
+                            Err(_) => break, // Pool dropped
+                        }
+                    })
+                    .map_err(|e| {
+                        ServerError::ConfigError(format!("Failed to start worker thread: {}", e))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (results, completed) = mpsc::channel();
+        Ok(Self {
+            tasks: Some(tasks),
+            results,
+            completed,
+            wake_reader,
+            wake_writer,
+            workers,
+        })
+    }
+
+    /// Run `job` on a worker; its result comes back from `completed` with `token`
+    pub fn submit<F>(&self, token: u64, job: F) -> Result<()>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let results = self.results.clone();
+        let mut wake = self.wake_writer.try_clone()?;
+        let task: Task = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(job));
+            if results.send((token, result)).is_ok() {
+                let _ = wake.write(&[1]);
+            }
+        });
+        self.tasks
+            .as_ref()
+            .and_then(|tasks| tasks.send(task).ok())
+            .ok_or_else(|| ServerError::HttpError("Worker pool has shut down".to_string()))
+    }
+
+    /// Fd that becomes readable when a job has finished
+    pub fn wake_fd(&self) -> RawFd {
+        self.wake_reader.as_raw_fd()
+    }
+
+    /// Results of every job finished since the last call, clearing the wakeup
+    pub fn completed(&self) -> Vec<(u64, thread::Result<T>)> {
+        let mut buf = [0u8; 64];
+        loop {
+            match (&self.wake_reader).read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => continue,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+        self.completed.try_iter().collect()
+    }
+}
+
+impl<T> Drop for WorkerPool<T> {
+    fn drop(&mut self) {
+        // Closing the queue lets each worker finish its current task and exit
+        self.tasks = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Finished jobs, with `None` for those that panicked
+    fn wait_for<T: Send + 'static>(pool: &WorkerPool<T>, count: usize) -> Vec<(u64, Option<T>)> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut done = Vec::new();
+        while done.len() < count && Instant::now() < deadline {
+            done.extend(
+                pool.completed()
+                    .into_iter()
+                    .map(|(token, result)| (token, result.ok())),
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+        done
+    }
+
+    #[test]
+    fn test_jobs_run_concurrently_and_return_their_tokens() {
+        let pool = WorkerPool::new(2).unwrap();
+        let started = Instant::now();
+        pool.submit(1, || {
+            thread::sleep(Duration::from_millis(300));
+            "slow"
+        })
+        .unwrap();
+        pool.submit(2, || {
+            thread::sleep(Duration::from_millis(300));
+            "also slow"
+        })
+        .unwrap();
+
+        let mut done = wait_for(&pool, 2);
+        done.sort();
+        assert_eq!(done, vec![(1, Some("slow")), (2, Some("also slow"))]);
+        assert!(started.elapsed() < Duration::from_millis(550));
+    }
+
+    #[test]
+    fn test_wake_fd_is_readable_once_a_job_finishes() {
+        use crate::core::event::poller::{event_buffer, Poller};
+
+        let pool = WorkerPool::new(1).unwrap();
+        let poller = Poller::new().unwrap();
+        poller.register_read(pool.wake_fd(), 0).unwrap();
+        let mut events = event_buffer(4);
+        assert_eq!(poller.wait(&mut events, 0).unwrap().count(), 0);
+
+        pool.submit(7, || 42).unwrap();
+        let woken = poller.wait(&mut events, 2000).unwrap();
+        assert!(woken
+            .into_iter()
+            .any(|e| e.fd == pool.wake_fd() && e.readable));
+        assert_eq!(wait_for(&pool, 1), vec![(7, Some(42))]);
+
+        // Collecting clears the wakeup
+        assert_eq!(poller.wait(&mut events, 0).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_panicking_job_reports_an_error_and_keeps_its_worker() {
+        let pool = WorkerPool::new(1).unwrap();
+        pool.submit(1, || -> u32 { panic!("handler bug") }).unwrap();
+        assert_eq!(wait_for(&pool, 1), vec![(1, None)]);
+
+        // The only worker is still there for the next job
+        pool.submit(2, || 2).unwrap();
+        assert_eq!(wait_for(&pool, 1), vec![(2, Some(2))]);
+    }
+}
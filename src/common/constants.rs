@@ -1,12 +1,47 @@
 pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
 pub const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024; // 10MB
 pub const DEFAULT_BUFFER_SIZE: usize = 8192; // 8KB
-pub const DEFAULT_MAX_HEADER_SIZE: usize = 8192; // 8KB
+pub const DEFAULT_RESPONSE_CHUNK_SIZE: usize = 16 * 1024; // 16KB
+pub const DEFAULT_MAX_GENERATED_BODY_SIZE: usize = 1024 * 1024; // 1MB cap for listings/error pages
+pub const DEFAULT_MAX_PROXY_RESPONSE_SIZE: usize = 10 * 1024 * 1024; // 10MB; larger upstream responses get 502
+pub const MULTIPART_STREAM_THRESHOLD: usize = 1024 * 1024; // 1MB; larger multipart uploads stream to disk
+pub const CGI_BODY_SPOOL_THRESHOLD: usize = 1024 * 1024; // 1MB; larger CGI request bodies spool to disk
+pub const COMPRESSION_MIN_SIZE: usize = 1024; // 1KB; smaller bodies are sent uncompressed
+pub const MAX_BYTE_RANGES: usize = 16; // Range headers asking for more are answered with the full body
+pub const DEFAULT_MAX_HEADER_SIZE: usize = 8192; // 8KB for the request line and headers together
+pub const DEFAULT_MAX_HEADER_COUNT: usize = 100;
+pub const DEFAULT_LINGERING_CLOSE_SECS: u64 = 2; // Drain input this long after a final response
 pub const DEFAULT_KEEP_ALIVE_TIMEOUT_SECS: u64 = 5;
+pub const DEFAULT_MAX_KEEP_ALIVE_REQUESTS: usize = 100;
+pub const DEFAULT_MAX_ACCEPTS_PER_TICK: usize = 64; // New connections accepted per event loop pass
+pub const DEFAULT_BIND_RETRY_BACKOFF_MS: u64 = 100;
+pub const DEFAULT_MAINTENANCE_INTERVAL_REQUESTS: u64 = 100;
+pub const DEFAULT_EXPECT_CONTINUE_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_MAX_PATH_DEPTH: usize = 64;
+pub const DEFAULT_MAX_ROUTES: usize = 1000; // Per server; routes are compiled at startup
+pub const DEFAULT_DUMP_TRAFFIC_MAX_BYTES: usize = 4096; // Per read/write in dump mode
+pub const DEFAULT_MAX_PIPELINE_DEPTH: usize = 100;
 
 pub const CRLF: &str = "\r\n";
 pub const CRLF_BYTES: &[u8] = b"\r\n";
 
 pub const DEFAULT_ERROR_PAGES: &[u16] = &[400, 403, 404, 405, 413, 500];
 
+pub const DEFAULT_CGI_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+pub const DEFAULT_CGI_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_CGI_MAX_HEADER_LINE: usize = 8192; // 8KB per CGI response header line
+
+pub const DEFAULT_BANNER: &str = "text"; // Startup server info on stdout
+pub const BANNER_MODES: &[&str] = &["text", "log", "json", "off"];
+
+pub const SUPPORTED_METHODS: &[&str] =
+    &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+pub const REDIRECT_TYPES: &[&str] = &["permanent", "temporary", "301", "302"];
+
+pub const ACME_CHALLENGE_PREFIX: &str = "/.well-known/acme-challenge/";
+
 pub const DEFAULT_SESSION_TIMEOUT_SECS: u64 = 3600; // 1 hour
+pub const SESSION_ID_BYTES: usize = 16; // 128-bit session IDs, 32 hex digits
+pub const DEFAULT_MAX_COOKIES: usize = 50;
+pub const DEFAULT_MAX_COOKIE_HEADER_SIZE: usize = 4096; // 4KB
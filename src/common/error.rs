@@ -8,7 +8,17 @@ pub enum ServerError {
     NetworkError(String),
     HttpError(String),
     CgiError(String),
+    /// The CGI interpreter or script could not be started at all
+    CgiSpawnError(String),
+    /// A CGI response header line was longer than the configured limit
+    CgiHeaderTooLong(String),
     TimeoutError(String),
+    /// A request the parser rejected as malformed; answered with 400
+    BadRequest(String),
+    /// A well-formed request method the server doesn't implement; answered with 501
+    MethodNotImplemented(String),
+    /// A request head over the size or header count limit; answered with 431
+    HeaderFieldsTooLarge(String),
 }
 
 impl fmt::Display for ServerError {
@@ -20,12 +30,28 @@ impl fmt::Display for ServerError {
             ServerError::NetworkError(msg) => write!(f, "Network error: {}", msg),
             ServerError::HttpError(msg) => write!(f, "HTTP error: {}", msg),
             ServerError::CgiError(msg) => write!(f, "CGI error: {}", msg),
+            ServerError::CgiSpawnError(msg) => write!(f, "CGI spawn error: {}", msg),
+            ServerError::CgiHeaderTooLong(msg) => write!(f, "CGI header too long: {}", msg),
             ServerError::TimeoutError(msg) => write!(f, "Timeout error: {}", msg),
+            ServerError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
+            ServerError::MethodNotImplemented(method) => {
+                write!(f, "Method not implemented: {}", method)
+            }
+            ServerError::HeaderFieldsTooLarge(msg) => {
+                write!(f, "Request header fields too large: {}", msg)
+            }
         }
     }
 }
 
-impl std::error::Error for ServerError {}
+impl std::error::Error for ServerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServerError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for ServerError {
     fn from(err: std::io::Error) -> Self {
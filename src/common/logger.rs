@@ -1,3 +1,24 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+/// Whether errors are logged with their full source chain (and panics with a backtrace)
+static VERBOSE_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Bytes per line in a hex dump
+const HEX_DUMP_WIDTH: usize = 16;
+
+/// Guards installing the backtrace-capturing panic hook
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Backtrace of the last panic on this thread, captured where it happened
+    static PANIC_BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
 pub struct Logger;
 
 impl Logger {
@@ -20,4 +41,205 @@ impl Logger {
 
     #[cfg(not(debug_assertions))]
     pub fn debug(_msg: &str) {}
+
+    /// Switch verbose error logging on or off. Turning it on also installs a panic hook
+    /// (chained to the existing one) that records where a panic happened.
+    pub fn set_verbose_errors(enabled: bool) {
+        VERBOSE_ERRORS.store(enabled, Ordering::Relaxed);
+        if enabled {
+            PANIC_HOOK.call_once(|| {
+                let previous = std::panic::take_hook();
+                std::panic::set_hook(Box::new(move |info| {
+                    if Self::verbose_errors() {
+                        let backtrace = Backtrace::force_capture().to_string();
+                        PANIC_BACKTRACE.with(|slot| *slot.borrow_mut() = Some(backtrace));
+                    }
+                    previous(info);
+                }));
+            });
+        }
+    }
+
+    /// Whether verbose error logging is on
+    pub fn verbose_errors() -> bool {
+        VERBOSE_ERRORS.load(Ordering::Relaxed)
+    }
+
+    /// Log an error at the current detail level
+    pub fn error_with(context: &str, error: &dyn Error) {
+        Self::error(&Self::format_error(context, error, Self::verbose_errors()));
+    }
+
+    /// One line with the error's message, or (verbose) the whole message, every
+    /// underlying cause and the error's debug form
+    pub fn format_error(context: &str, error: &dyn Error, verbose: bool) -> String {
+        let message = error.to_string();
+        if !verbose {
+            let first_line = message.lines().next().unwrap_or_default();
+            return format!("{}: {}", context, first_line);
+        }
+
+        let mut out = format!("{}: {}", context, message);
+        let mut source = error.source();
+        while let Some(cause) = source {
+            out.push_str(&format!("\n  caused by: {}", cause));
+            source = cause.source();
+        }
+        out.push_str(&format!("\n  detail: {:?}", error));
+        out
+    }
+
+    /// Log a panic caught at the request boundary; verbose logging adds the backtrace
+    /// of the panic site
+    pub fn panic(context: &str, payload: &(dyn Any + Send)) {
+        let backtrace = PANIC_BACKTRACE.with(|slot| slot.borrow_mut().take());
+        let backtrace = backtrace.filter(|_| Self::verbose_errors());
+        Self::error(&Self::format_panic(context, payload, backtrace.as_deref()));
+    }
+
+    /// Message for a caught panic, with its backtrace when one is given
+    pub fn format_panic(
+        context: &str,
+        payload: &(dyn Any + Send),
+        backtrace: Option<&str>,
+    ) -> String {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        match backtrace {
+            Some(backtrace) => format!("{}: panicked: {}\n{}", context, message, backtrace),
+            None => format!("{}: panicked: {}", context, message),
+        }
+    }
+
+    /// Render raw connection traffic: text as-is with CR/LF made visible, and anything
+    /// after the message head that isn't text as a hex dump. At most `max_bytes` are
+    /// shown (0 = all).
+    pub fn format_traffic(label: &str, bytes: &[u8], max_bytes: usize) -> String {
+        let shown = if max_bytes > 0 && bytes.len() > max_bytes {
+            &bytes[..max_bytes]
+        } else {
+            bytes
+        };
+        let mut out = format!("{} ({} bytes)", label, bytes.len());
+
+        // A message head ends at the blank line; what follows may be binary
+        let head_len = shown
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .map(|pos| pos + 4)
+            .unwrap_or(0);
+        let (head, body) = shown.split_at(head_len);
+        for part in [head, body] {
+            if part.is_empty() {
+                continue;
+            }
+            if Self::is_text(part) {
+                for line in String::from_utf8_lossy(part).split_inclusive('\n') {
+                    out.push_str("\n  ");
+                    out.push_str(&line.replace('\r', "\\r").replace('\n', "\\n"));
+                }
+            } else {
+                for (i, row) in part.chunks(HEX_DUMP_WIDTH).enumerate() {
+                    let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+                    let ascii: String = row
+                        .iter()
+                        .map(|&b| {
+                            if b.is_ascii_graphic() || b == b' ' {
+                                b as char
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect();
+                    out.push_str(&format!(
+                        "\n  {:04x}  {:<width$}  |{}|",
+                        i * HEX_DUMP_WIDTH,
+                        hex.join(" "),
+                        ascii,
+                        width = HEX_DUMP_WIDTH * 3 - 1
+                    ));
+                }
+            }
+        }
+        if shown.len() < bytes.len() {
+            out.push_str(&format!("\n  ... {} more bytes", bytes.len() - shown.len()));
+        }
+        out
+    }
+
+    /// UTF-8 without control characters other than CR, LF and tab
+    fn is_text(bytes: &[u8]) -> bool {
+        std::str::from_utf8(bytes)
+            .map(|s| {
+                s.chars()
+                    .all(|c| !c.is_control() || matches!(c, '\r' | '\n' | '\t'))
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::error::ServerError;
+
+    #[test]
+    fn test_verbose_error_includes_cause_and_detail() {
+        let error = ServerError::IoError(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "script.py is not readable",
+        ));
+
+        let terse = Logger::format_error("CGI /script.py failed", &error, false);
+        assert_eq!(
+            terse,
+            "CGI /script.py failed: IO error: script.py is not readable"
+        );
+
+        let verbose = Logger::format_error("CGI /script.py failed", &error, true);
+        assert!(verbose.starts_with(&terse));
+        assert!(verbose.contains("\n  caused by: script.py is not readable"));
+        assert!(verbose.contains("\n  detail: IoError(Custom { kind: PermissionDenied"));
+    }
+
+    #[test]
+    fn test_terse_error_keeps_first_line_only() {
+        let error = ServerError::CgiError(
+            "script exited with code 1: Traceback (most recent call last):\n  File \"x.py\""
+                .to_string(),
+        );
+        let terse = Logger::format_error("CGI failed", &error, false);
+        assert!(!terse.contains('\n'));
+        assert!(Logger::format_error("CGI failed", &error, true).contains("File \"x.py\""));
+    }
+
+    #[test]
+    fn test_format_panic() {
+        let payload: Box<dyn Any + Send> = Box::new(String::from("index out of bounds"));
+        assert_eq!(
+            Logger::format_panic("GET /x", payload.as_ref(), None),
+            "GET /x: panicked: index out of bounds"
+        );
+        let with_trace = Logger::format_panic("GET /x", payload.as_ref(), Some("0: frame"));
+        assert!(with_trace.ends_with("\n0: frame"));
+    }
+
+    #[test]
+    fn test_format_traffic_shows_text_and_hex_dumps_binary() {
+        let mut bytes = b"POST /up HTTP/1.1\r\nHost: x\r\n\r\n".to_vec();
+        bytes.extend_from_slice(&[0x89, b'P', b'N', b'G', 0x00, 0x01]);
+
+        let dump = Logger::format_traffic("fd 7 received", &bytes, 0);
+        assert!(dump.starts_with("fd 7 received (36 bytes)"));
+        assert!(dump.contains("\n  POST /up HTTP/1.1\\r\\n\n  Host: x\\r\\n\n  \\r\\n"));
+        assert!(dump.contains("\n  0000  89 50 4e 47 00 01"));
+        assert!(dump.ends_with("|.PNG..|"));
+
+        let truncated = Logger::format_traffic("fd 7 received", &bytes, 8);
+        assert!(truncated.contains("\n  POST /up"));
+        assert!(truncated.ends_with("\n  ... 28 more bytes"));
+    }
 }
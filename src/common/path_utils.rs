@@ -1,4 +1,11 @@
-use std::path::Path;
+use crate::common::error::{Result, ServerError};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Sequence number keeping temp file names unique within the process
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 /// Check if a path exists and is a file (helper to reduce redundancy)
 pub fn is_valid_file(path: &Path) -> bool {
@@ -9,3 +16,324 @@ pub fn is_valid_file(path: &Path) -> bool {
 pub fn is_valid_directory(path: &Path) -> bool {
     path.exists() && path.is_dir()
 }
+
+/// Number of segments in a request path, ignoring empty and `.` segments
+/// (so `/a//b/./c/` has depth 3)
+pub fn path_depth(path: &str) -> usize {
+    path.split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .count()
+}
+
+/// Collapse runs of `/` into one (`/a//b///c.txt` -> `/a/b/c.txt`)
+pub fn collapse_slashes(path: &str) -> String {
+    let mut collapsed = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c != '/' || !collapsed.ends_with('/') {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// Decode `%XX` escapes in a URL path (`/my%20file.txt` -> `/my file.txt`). Unlike
+/// query strings, `+` is kept as-is; malformed escapes are left undecoded.
+pub fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Escape the bytes of a decoded path that may not appear raw in a request target
+/// (`/my file.txt` -> `/my%20file.txt`). `%` is kept, so escapes the decoder left
+/// in place pass through unchanged.
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for &byte in path.as_bytes() {
+        if byte.is_ascii_graphic() && !b"\"#<>\\^`{|}".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Join a request-derived path onto `base`, treating every segment as relative.
+/// Leading or repeated `/` never make the result absolute, `.` and empty segments
+/// are skipped, and `..` or NUL bytes are rejected, so the result always stays under `base`.
+pub fn safe_join(base: &Path, relative: &str) -> Result<PathBuf> {
+    let mut joined = base.to_path_buf();
+
+    for segment in relative.split(['/', '\\']) {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                return Err(ServerError::HttpError(
+                    "Path contains '..' - directory traversal attempt".to_string(),
+                ))
+            }
+            s if s.contains('\0') => {
+                return Err(ServerError::HttpError(
+                    "Path contains a NUL byte".to_string(),
+                ))
+            }
+            s => joined.push(s),
+        }
+    }
+
+    // Defensive: a single pushed segment can never be absolute after splitting, but
+    // keep the invariant explicit in case the splitting rules ever change
+    if !joined.starts_with(base) {
+        return Err(ServerError::HttpError(
+            "Path escapes the document root".to_string(),
+        ));
+    }
+
+    Ok(joined)
+}
+
+/// Canonicalize the directories leading to `path` (following symlinks) while keeping
+/// the final component unresolved, so a symlinked file is still addressed by its own name.
+/// Components that don't exist yet are appended unchanged.
+pub fn canonicalize_parent(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut current = path;
+    // The final component is never resolved, even when it exists
+    if let (Some(parent), Some(name)) = (current.parent(), current.file_name()) {
+        missing.push(name.to_os_string());
+        current = parent;
+    }
+
+    loop {
+        if let Ok(canonical) = std::fs::canonicalize(current) {
+            let mut resolved = canonical;
+            for name in missing.iter().rev() {
+                resolved.push(name);
+            }
+            return resolved;
+        }
+        match (current.parent(), current.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                current = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Write `content` to `path` atomically: readers see either no file / the old file,
+/// or the complete new content, never a partial write.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    write_atomic_with(path, |file| file.write_all(content))
+}
+
+/// Like `write_atomic`, but lets the caller stream the content into the temp file.
+/// The temp file lives in the target directory so the final `rename` stays on one filesystem.
+pub fn write_atomic_with<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("upload");
+    let temp_path = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|_| persist(&temp_path, path, |from, to| fs::rename(from, to)));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Move the finished temp file into place, copying when rename crosses filesystems
+fn persist<R>(temp_path: &Path, path: &Path, rename: R) -> io::Result<()>
+where
+    R: Fn(&Path, &Path) -> io::Result<()>,
+{
+    match rename(temp_path, path) {
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            fs::copy(temp_path, path)?;
+            fs::remove_file(temp_path)
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_depth() {
+        assert_eq!(path_depth("/"), 0);
+        assert_eq!(path_depth("/index.html"), 1);
+        assert_eq!(path_depth("/a//b/./c/"), 3);
+        assert_eq!(path_depth(&"/x".repeat(65)), 65);
+    }
+
+    #[test]
+    fn test_collapse_slashes() {
+        assert_eq!(collapse_slashes("/a//b///c.txt"), "/a/b/c.txt");
+        assert_eq!(collapse_slashes("//api"), "/api");
+        assert_eq!(collapse_slashes("/dir//"), "/dir/");
+        assert_eq!(collapse_slashes("/plain/path"), "/plain/path");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/my%20file.txt"), "/my file.txt");
+        assert_eq!(percent_decode("/a%2Fb%2fc"), "/a/b/c");
+        assert_eq!(percent_decode("/caf%C3%A9"), "/café");
+        // Only query strings treat + as a space
+        assert_eq!(percent_decode("/a+b"), "/a+b");
+        assert_eq!(percent_decode("/100%/%zz/%4"), "/100%/%zz/%4");
+    }
+
+    #[test]
+    fn test_percent_encode_path() {
+        assert_eq!(percent_encode_path("/my file.txt"), "/my%20file.txt");
+        assert_eq!(percent_encode_path("/café"), "/caf%C3%A9");
+        assert_eq!(percent_encode_path("/a%3Fb#c"), "/a%3Fb%23c");
+        assert_eq!(percent_encode_path("/plain/path.txt"), "/plain/path.txt");
+    }
+
+    #[test]
+    fn test_safe_join_keeps_normal_paths() {
+        let base = Path::new("/srv/www");
+        assert_eq!(
+            safe_join(base, "css/site.css").unwrap(),
+            PathBuf::from("/srv/www/css/site.css")
+        );
+        assert_eq!(safe_join(base, "").unwrap(), PathBuf::from("/srv/www"));
+        assert_eq!(
+            safe_join(base, "./a/./b").unwrap(),
+            PathBuf::from("/srv/www/a/b")
+        );
+    }
+
+    #[test]
+    fn test_safe_join_treats_absolute_segments_as_relative() {
+        let base = Path::new("/srv/www");
+        for attempt in [
+            "/etc/passwd",
+            "//etc/passwd",
+            "\\etc\\passwd",
+            "/./etc/passwd",
+        ] {
+            assert_eq!(
+                safe_join(base, attempt).unwrap(),
+                PathBuf::from("/srv/www/etc/passwd"),
+                "attempt {:?}",
+                attempt
+            );
+        }
+    }
+
+    #[test]
+    fn test_safe_join_rejects_traversal_and_nul() {
+        let base = Path::new("/srv/www");
+        assert!(safe_join(base, "../etc/passwd").is_err());
+        assert!(safe_join(base, "a/../../etc").is_err());
+        assert!(safe_join(base, "a\\..\\..\\etc").is_err());
+        assert!(safe_join(base, "file\0.txt").is_err());
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "localhost_path_utils_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write_atomic_never_exposes_partial_file() {
+        let dir = temp_dir("atomic");
+        let target = dir.join("big.bin");
+        let chunk = vec![7u8; 256 * 1024];
+
+        write_atomic_with(&target, |file| {
+            for _ in 0..8 {
+                file.write_all(&chunk)?;
+                // Mid-write, the final path must not exist yet
+                assert!(!target.exists());
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(fs::metadata(&target).unwrap().len(), 8 * 256 * 1024);
+        // Only the final file remains, no temp leftovers
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_existing_file_completely() {
+        let dir = temp_dir("replace");
+        let target = dir.join("file.txt");
+        fs::write(&target, "old content").unwrap();
+
+        write_atomic_with(&target, |file| {
+            file.write_all(b"new")?;
+            // Readers still see the old, complete file
+            assert_eq!(fs::read(&target).unwrap(), b"old content");
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_persist_falls_back_to_copy_across_filesystems() {
+        let dir = temp_dir("exdev");
+        let temp = dir.join(".tmp");
+        let target = dir.join("final.txt");
+        fs::write(&temp, "content").unwrap();
+
+        let cross_device = |_: &Path, _: &Path| Err(io::Error::from_raw_os_error(libc::EXDEV));
+        persist(&temp, &target, cross_device).unwrap();
+
+        assert_eq!(fs::read(&target).unwrap(), b"content");
+        assert!(!temp.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
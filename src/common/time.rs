@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub struct Timeout {
     deadline: Instant,
@@ -19,3 +19,171 @@ impl Timeout {
         self.deadline.saturating_duration_since(Instant::now())
     }
 }
+
+/// Last second an IMF-fixdate's four-digit year can express (9999-12-31 23:59:59 GMT)
+const MAX_HTTP_DATE_SECS: u64 = 253_402_300_799;
+
+/// Format a time as an RFC 7231 IMF-fixdate, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`.
+/// Times before the Unix epoch or after year 9999 are clamped to those bounds.
+pub fn format_http_date(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .min(MAX_HTTP_DATE_SECS);
+    let days = secs / 86400;
+    let secs_of_day = secs % 86400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Parse an RFC 7231 IMF-fixdate (`Wed, 21 Oct 2015 07:28:00 GMT`); the dashed cookie
+/// form (`21-Oct-2015`) is accepted too. None for other formats, impossible dates, or
+/// years outside 1970..=9999.
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    // The weekday is redundant and ignored
+    let date = value.split_once(',').map_or(value, |(_, date)| date);
+    let date = date.replace('-', " ");
+    let fields: Vec<&str> = date.split_whitespace().collect();
+    let [day, month, year, time, "GMT"] = fields.as_slice() else {
+        return None;
+    };
+    let day: u64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? as u64 + 1;
+    let year: u64 = year.parse().ok()?;
+    let mut clock = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+    if clock.next().is_some()
+        || !(1970..=9999).contains(&year)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Number of days in `month` (1-12) of `year` in the proleptic Gregorian calendar
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Convert a civil date from 1970 on to days since 1970-01-01 (inverse of `civil_from_days`)
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date
+/// (Howard Hinnant's `civil_from_days`, restricted to non-negative days)
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_format_http_date_known_values() {
+        assert_eq!(format_http_date(at(0)), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            format_http_date(at(1_445_412_480)),
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+        // Leap day and end of a leap year
+        assert_eq!(
+            format_http_date(at(951_782_400)),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+        assert_eq!(
+            format_http_date(at(1_735_689_599)),
+            "Tue, 31 Dec 2024 23:59:59 GMT"
+        );
+    }
+
+    #[test]
+    fn test_format_http_date_clamps_past_year_9999() {
+        let last = "Fri, 31 Dec 9999 23:59:59 GMT";
+        assert_eq!(format_http_date(at(MAX_HTTP_DATE_SECS)), last);
+        assert_eq!(format_http_date(at(u32::MAX as u64 * 1000)), last);
+        assert_eq!(parse_http_date(last), Some(at(MAX_HTTP_DATE_SECS)));
+    }
+
+    #[test]
+    fn test_parse_http_date_round_trips() {
+        for secs in [0, 951_782_400, 1_445_412_480, 1_735_689_599] {
+            assert_eq!(parse_http_date(&format_http_date(at(secs))), Some(at(secs)));
+        }
+        assert_eq!(
+            parse_http_date("Wed, 21-Oct-2015 07:28:00 GMT"),
+            Some(at(1_445_412_480))
+        );
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"),
+            Some(at(951_782_400))
+        );
+        for bad in [
+            "",
+            "Wed, 21 Oct 2015 07:28:00",
+            "Wed, 21 Foo 2015 07:28:00 GMT",
+            "Wed, 21 Oct 2015 25:28:00 GMT",
+            "Wed, 21 Oct 1969 07:28:00 GMT",
+            "Sat, 31 Feb 2015 07:28:00 GMT",
+            "Fri, 29 Feb 2019 07:28:00 GMT",
+            "Mon, 31 Jun 2015 07:28:00 GMT",
+            "Sat, 01 Jan 10000 00:00:00 GMT",
+            "Thu, 01 Jan 18446744073709551615 00:00:00 GMT",
+        ] {
+            assert_eq!(parse_http_date(bad), None, "{}", bad);
+        }
+    }
+}
@@ -1,12 +1,11 @@
 // Main event loop orchestrator
 use crate::common::error::Result;
-use crate::core::event::poller::Poller;
-use libc::kevent;
+use crate::core::event::poller::{self, PollEvent, Poller, RawEvent};
 use std::rc::Rc;
 
 pub struct EventLoop {
     poller: Rc<Poller>,
-    events: Vec<kevent>,
+    events: Vec<RawEvent>,
 }
 
 impl EventLoop {
@@ -14,7 +13,7 @@ impl EventLoop {
         let poller = Rc::new(Poller::new()?);
         Ok(Self {
             poller,
-            events: vec![unsafe { std::mem::zeroed() }; 1024],
+            events: poller::event_buffer(1024),
         })
     }
 
@@ -22,8 +21,7 @@ impl EventLoop {
         &self.poller
     }
 
-    pub fn wait(&mut self, timeout_ms: i32) -> Result<&[kevent]> {
-        let n = self.poller.wait(&mut self.events, timeout_ms)?;
-        Ok(&self.events[..n])
+    pub fn wait(&mut self, timeout_ms: i32) -> Result<impl Iterator<Item = PollEvent> + '_> {
+        self.poller.wait(&mut self.events, timeout_ms)
     }
 }
@@ -1,18 +1,50 @@
 use crate::common::error::{Result, ServerError};
-use libc::{c_int, c_void};
+use libc::c_int;
 use std::os::unix::io::RawFd;
 
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+compile_error!("localhost needs kqueue (macOS) or epoll (Linux); this target has neither");
+
+#[cfg(target_os = "macos")]
+use libc::c_void;
 #[cfg(target_os = "macos")]
 use libc::{
     kevent, kevent as KeventStruct, kqueue, EVFILT_READ, EVFILT_WRITE, EV_ADD, EV_DELETE, EV_ENABLE,
 };
 
-pub use libc::kevent as Kevent;
+#[cfg(target_os = "linux")]
+use std::cell::RefCell;
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+
+/// Readiness of one file descriptor, whichever backend reported it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollEvent {
+    pub fd: RawFd,
+    pub readable: bool,
+    pub writable: bool,
+}
+
+/// Event record filled in by the backend's wait call
+#[cfg(target_os = "macos")]
+pub type RawEvent = libc::kevent;
+
+/// Event record filled in by the backend's wait call
+#[cfg(target_os = "linux")]
+pub type RawEvent = libc::epoll_event;
 
+/// Zeroed storage for `wait` to fill
+pub fn event_buffer(capacity: usize) -> Vec<RawEvent> {
+    // Both kevent and epoll_event are plain C structs, valid when zeroed
+    vec![unsafe { std::mem::zeroed::<RawEvent>() }; capacity]
+}
+
+#[cfg(target_os = "macos")]
 pub struct Poller {
     kq: RawFd,
 }
 
+#[cfg(target_os = "macos")]
 impl Poller {
     pub fn new() -> Result<Self> {
         unsafe {
@@ -99,8 +131,13 @@ impl Poller {
         Ok(())
     }
 
-    pub fn wait(&self, events: &mut [KeventStruct], timeout_ms: i32) -> Result<usize> {
-        unsafe {
+    /// Wait for readiness and report it as (fd, readable, writable)
+    pub fn wait<'a>(
+        &self,
+        events: &'a mut [RawEvent],
+        timeout_ms: i32,
+    ) -> Result<impl Iterator<Item = PollEvent> + 'a> {
+        let n = unsafe {
             let timeout = if timeout_ms >= 0 {
                 libc::timespec {
                     tv_sec: (timeout_ms / 1000) as i64,
@@ -133,9 +170,14 @@ impl Poller {
                     "Failed to wait for events".to_string(),
                 ));
             }
+            n as usize
+        };
 
-            Ok(n as usize)
-        }
+        Ok(events[..n].iter().map(|event| PollEvent {
+            fd: event.ident as RawFd,
+            readable: event.filter == EVFILT_READ,
+            writable: event.filter == EVFILT_WRITE,
+        }))
     }
 
     pub fn as_raw_fd(&self) -> RawFd {
@@ -143,6 +185,7 @@ impl Poller {
     }
 }
 
+#[cfg(target_os = "macos")]
 impl Drop for Poller {
     fn drop(&mut self) {
         unsafe {
@@ -150,3 +193,182 @@ impl Drop for Poller {
         }
     }
 }
+
+/// epoll keeps one interest set per fd, so read and write interest are tracked here and
+/// combined into a single registration
+#[cfg(target_os = "linux")]
+pub struct Poller {
+    epfd: RawFd,
+    interest: RefCell<HashMap<RawFd, u32>>,
+}
+
+#[cfg(target_os = "linux")]
+impl Poller {
+    pub fn new() -> Result<Self> {
+        let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if epfd < 0 {
+            return Err(ServerError::NetworkError(
+                "Failed to create epoll instance".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            epfd,
+            interest: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Interest is keyed by fd, which epoll hands back in its events; `user_data` is
+    /// accepted for parity with kqueue
+    pub fn register_read(&self, fd: RawFd, _user_data: usize) -> Result<()> {
+        self.update_interest(fd, libc::EPOLLIN as u32, 0)
+    }
+
+    pub fn register_write(&self, fd: RawFd, _user_data: usize) -> Result<()> {
+        self.update_interest(fd, libc::EPOLLOUT as u32, 0)
+    }
+
+    pub fn unregister_read(&self, fd: RawFd) -> Result<()> {
+        self.update_interest(fd, 0, libc::EPOLLIN as u32)
+    }
+
+    pub fn unregister_write(&self, fd: RawFd) -> Result<()> {
+        self.update_interest(fd, 0, libc::EPOLLOUT as u32)
+    }
+
+    fn update_interest(&self, fd: RawFd, add: u32, remove: u32) -> Result<()> {
+        let mut interest = self.interest.borrow_mut();
+        let old = interest.get(&fd).copied();
+        let new = (old.unwrap_or(0) | add) & !remove;
+        let op = match (old, new) {
+            (None, 0) => return Ok(()),
+            (None, _) => libc::EPOLL_CTL_ADD,
+            (Some(_), 0) => libc::EPOLL_CTL_DEL,
+            (Some(_), _) => libc::EPOLL_CTL_MOD,
+        };
+        if new == 0 {
+            interest.remove(&fd);
+        } else {
+            interest.insert(fd, new);
+        }
+
+        let mut event = libc::epoll_event {
+            events: new,
+            u64: fd as u64,
+        };
+        let mut rc = unsafe { libc::epoll_ctl(self.epfd, op, fd, &mut event) };
+        if rc < 0 && op == libc::EPOLL_CTL_MOD {
+            // A closed fd leaves the epoll set on its own; its number may since have been
+            // reused by a new socket that was never added
+            rc = unsafe { libc::epoll_ctl(self.epfd, libc::EPOLL_CTL_ADD, fd, &mut event) };
+        }
+
+        // Like kqueue, removing interest in an fd that is already gone is harmless
+        if rc < 0 && op != libc::EPOLL_CTL_DEL {
+            interest.remove(&fd);
+            return Err(ServerError::NetworkError(format!(
+                "Failed to register event for fd {}",
+                fd
+            )));
+        }
+        Ok(())
+    }
+
+    /// Wait for readiness and report it as (fd, readable, writable)
+    pub fn wait<'a>(
+        &self,
+        events: &'a mut [RawEvent],
+        timeout_ms: i32,
+    ) -> Result<impl Iterator<Item = PollEvent> + 'a> {
+        let n = unsafe {
+            libc::epoll_wait(
+                self.epfd,
+                events.as_mut_ptr(),
+                events.len() as c_int,
+                timeout_ms,
+            )
+        };
+        let n = if n < 0 {
+            // A signal ending the wait early is just an empty round
+            if std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+                return Err(ServerError::NetworkError(
+                    "Failed to wait for events".to_string(),
+                ));
+            }
+            0
+        } else {
+            n as usize
+        };
+
+        // Hangups and errors count as readable so the next read sees EOF or the error
+        let closed = (libc::EPOLLHUP | libc::EPOLLERR | libc::EPOLLRDHUP) as u32;
+        Ok(events[..n].iter().map(move |event| {
+            let flags = event.events;
+            PollEvent {
+                fd: event.u64 as RawFd,
+                readable: flags & (libc::EPOLLIN as u32 | closed) != 0,
+                writable: flags & libc::EPOLLOUT as u32 != 0,
+            }
+        }))
+    }
+
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.epfd
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Poller {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.epfd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    fn ready(poller: &Poller, timeout_ms: i32) -> Vec<PollEvent> {
+        let mut events = event_buffer(16);
+        poller.wait(&mut events, timeout_ms).unwrap().collect()
+    }
+
+    #[test]
+    fn test_reports_read_and_write_readiness() {
+        let poller = Poller::new().unwrap();
+        let (mut a, b) = UnixStream::pair().unwrap();
+        let fd = b.as_raw_fd();
+
+        poller.register_read(fd, fd as usize).unwrap();
+        assert!(ready(&poller, 0).is_empty());
+
+        a.write_all(b"ping").unwrap();
+        let events = ready(&poller, 1000);
+        assert!(events.iter().any(|e| e.fd == fd && e.readable));
+
+        // A socket with buffer space is writable as soon as write interest is added
+        poller.register_write(fd, fd as usize).unwrap();
+        let events = ready(&poller, 1000);
+        assert!(events.iter().any(|e| e.fd == fd && e.writable));
+
+        poller.unregister_write(fd).unwrap();
+        poller.unregister_read(fd).unwrap();
+        assert!(ready(&poller, 0).is_empty());
+    }
+
+    #[test]
+    fn test_hangup_is_readable() {
+        let poller = Poller::new().unwrap();
+        let (a, b) = UnixStream::pair().unwrap();
+        poller.register_read(b.as_raw_fd(), 0).unwrap();
+        drop(a);
+
+        let events = ready(&poller, 1000);
+        assert!(events.iter().any(|e| e.fd == b.as_raw_fd() && e.readable));
+    }
+}
@@ -1,11 +1,18 @@
 use crate::common::buffer::Buffer;
 use crate::common::time::Timeout;
 use crate::core::net::socket::ClientSocket;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionState {
     Reading,
+    /// A worker is producing the response; reads wait until it has been queued
+    Processing,
     Writing,
+    /// Response sent and write side shut down; reading and discarding until the peer
+    /// closes, so unread input doesn't turn the close into a reset
+    Lingering,
     Closed,
 }
 
@@ -18,6 +25,26 @@ pub struct Connection {
     keep_alive: bool,
     /// Server port this connection came in on (for virtual host routing)
     server_port: Option<u16>,
+    /// Bytes successfully written for the current response (headers included)
+    bytes_written: usize,
+    /// Last time data was read from or written to the peer
+    last_activity: Instant,
+    /// Kept alive and waiting for the next request
+    idle: bool,
+    /// `100 Continue` was sent and the body hasn't started arriving yet
+    continue_sent: bool,
+    /// When a client that was sent `100 Continue` must have started its body
+    continue_deadline: Option<Instant>,
+    /// A PROXY protocol header must be read before any HTTP data
+    awaiting_proxy_header: bool,
+    /// Client address announced by a PROXY protocol header
+    proxied_client: Option<SocketAddr>,
+    /// Requests answered in a row while more were already queued behind them
+    pipeline_depth: usize,
+    /// When a lingering connection is closed even if the peer hasn't
+    linger_deadline: Option<Instant>,
+    /// Requests answered on this connection so far
+    requests_served: usize,
 }
 
 impl Connection {
@@ -30,6 +57,16 @@ impl Connection {
             timeout: Timeout::new(timeout_secs),
             keep_alive: false,
             server_port: None,
+            bytes_written: 0,
+            last_activity: Instant::now(),
+            idle: false,
+            continue_sent: false,
+            continue_deadline: None,
+            awaiting_proxy_header: false,
+            proxied_client: None,
+            pipeline_depth: 0,
+            linger_deadline: None,
+            requests_served: 0,
         }
     }
 
@@ -42,6 +79,16 @@ impl Connection {
             timeout: Timeout::new(timeout_secs),
             keep_alive: false,
             server_port: Some(server_port),
+            bytes_written: 0,
+            last_activity: Instant::now(),
+            idle: false,
+            continue_sent: false,
+            continue_deadline: None,
+            awaiting_proxy_header: false,
+            proxied_client: None,
+            pipeline_depth: 0,
+            linger_deadline: None,
+            requests_served: 0,
         }
     }
 
@@ -53,6 +100,32 @@ impl Connection {
         self.server_port = Some(port);
     }
 
+    /// Client address, if it could be determined
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// Address of the client the request comes from: the one a PROXY protocol
+    /// header announced, otherwise the socket peer
+    pub fn client_addr(&self) -> Option<SocketAddr> {
+        self.proxied_client.or_else(|| self.peer_addr())
+    }
+
+    /// Require a PROXY protocol header before the first request
+    pub fn expect_proxy_header(&mut self) {
+        self.awaiting_proxy_header = true;
+    }
+
+    pub fn awaiting_proxy_header(&self) -> bool {
+        self.awaiting_proxy_header
+    }
+
+    /// Record the PROXY protocol header as read, with the client address it carried
+    pub fn set_proxied_client(&mut self, client: Option<SocketAddr>) {
+        self.awaiting_proxy_header = false;
+        self.proxied_client = client;
+    }
+
     pub fn socket(&self) -> &ClientSocket {
         &self.socket
     }
@@ -77,6 +150,11 @@ impl Connection {
         &mut self.write_buffer
     }
 
+    /// Bytes held in the read and write buffers together
+    pub fn buffered_bytes(&self) -> usize {
+        self.read_buffer.len() + self.write_buffer.len()
+    }
+
     pub fn state(&self) -> &ConnectionState {
         &self.state
     }
@@ -94,10 +172,151 @@ impl Connection {
     }
 
     pub fn should_keep_alive(&self) -> bool {
-        self.keep_alive && !self.write_buffer.is_empty()
+        self.keep_alive
+    }
+
+    /// Count a request being answered and return how many this connection has answered
+    pub fn record_request(&mut self) -> usize {
+        self.requests_served += 1;
+        self.requests_served
+    }
+
+    pub fn requests_served(&self) -> usize {
+        self.requests_served
+    }
+
+    /// Record bytes successfully written to the socket
+    pub fn record_written(&mut self, n: usize) {
+        self.bytes_written += n;
+    }
+
+    /// Total bytes written for the current response
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    /// Reset the written-bytes counter before the next request on a kept-alive connection
+    pub fn reset_bytes_written(&mut self) {
+        self.bytes_written = 0;
+    }
+
+    /// Record I/O on the connection
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn last_activity(&self) -> Instant {
+        self.last_activity
+    }
+
+    /// Record that `100 Continue` was sent; the body must start within `timeout`
+    /// (`None` leaves only the client timeout in force)
+    pub fn start_continue_wait(&mut self, timeout: Option<Duration>) {
+        self.continue_sent = true;
+        self.continue_deadline = timeout.map(|t| Instant::now() + t);
+    }
+
+    /// Stop waiting for a body after `100 Continue` (it arrived or the request ended)
+    pub fn clear_continue_wait(&mut self) {
+        self.continue_sent = false;
+        self.continue_deadline = None;
+    }
+
+    pub fn continue_sent(&self) -> bool {
+        self.continue_sent
+    }
+
+    /// The body didn't start in time after `100 Continue`
+    pub fn is_continue_expired(&self) -> bool {
+        self.continue_deadline
+            .map(|deadline| Instant::now() >= deadline)
+            .unwrap_or(false)
+    }
+
+    /// Count a request being answered and return how many in a row had more requests
+    /// queued behind them; a request with nothing queued ends the run
+    pub fn record_pipelined(&mut self, more_queued: bool) -> usize {
+        self.pipeline_depth = if more_queued {
+            self.pipeline_depth + 1
+        } else {
+            0
+        };
+        self.pipeline_depth
+    }
+
+    /// Mark whether the connection sits idle between keep-alive requests
+    pub fn set_idle(&mut self, idle: bool) {
+        self.idle = idle;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Kept alive with no new request for at least `timeout`
+    pub fn is_idle_expired(&self, timeout: Duration) -> bool {
+        self.idle
+            && self.state == ConnectionState::Reading
+            && self.last_activity.elapsed() >= timeout
+    }
+
+    /// Stop sending and drain the peer's input for at most `timeout` before closing
+    pub fn start_lingering(&mut self, timeout: Duration) {
+        self.state = ConnectionState::Lingering;
+        self.linger_deadline = Some(Instant::now() + timeout);
+    }
+
+    /// The peer didn't close within the linger timeout
+    pub fn is_linger_expired(&self) -> bool {
+        self.linger_deadline
+            .map(|deadline| Instant::now() >= deadline)
+            .unwrap_or(false)
     }
 
     pub fn as_raw_fd(&self) -> i32 {
         self.socket.as_raw_fd()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::net::io::write_non_blocking;
+    use crate::http::response::Response;
+    use crate::http::serializer::ResponseSerializer;
+    use crate::http::version::Version;
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_bytes_written_matches_serialized_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, addr) = listener.accept().unwrap();
+        let mut connection = Connection::new(ClientSocket::from_stream(stream, addr).unwrap(), 30);
+
+        let mut response = Response::ok(Version::Http11);
+        response.set_body_str(&"x".repeat(64 * 1024));
+        let bytes = ResponseSerializer::serialize_auto(&response).unwrap();
+        connection.write_buffer_mut().extend(&bytes);
+
+        // Drive writes the way the event loop does, draining the peer in between
+        let mut received = Vec::new();
+        let mut chunk = [0u8; 16 * 1024];
+        while !connection.write_buffer().is_empty() {
+            let data = connection.write_buffer().as_slice();
+            let n = write_non_blocking(connection.socket_mut(), &data).unwrap();
+            connection.write_buffer_mut().drain(n);
+            connection.record_written(n);
+            if n == 0 {
+                let read = client.read(&mut chunk).unwrap();
+                received.extend_from_slice(&chunk[..read]);
+            }
+        }
+
+        assert_eq!(connection.bytes_written(), bytes.len());
+
+        connection.reset_bytes_written();
+        assert_eq!(connection.bytes_written(), 0);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod connection;
 pub mod fd;
 pub mod io;
+pub mod proxy_protocol;
 pub mod socket;
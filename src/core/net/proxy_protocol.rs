@@ -0,0 +1,144 @@
+use crate::common::error::{Result, ServerError};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Longest PROXY protocol v1 header, CRLF included
+const MAX_V1_HEADER_LEN: usize = 107;
+
+const V1_SIGNATURE: &[u8] = b"PROXY ";
+
+/// Result of reading a PROXY protocol v1 header from the start of a connection
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProxyHeader {
+    /// The header line isn't complete yet
+    Incomplete,
+    /// The header was read
+    Complete {
+        /// Original client address; None for `PROXY UNKNOWN`, where the socket peer applies
+        source: Option<SocketAddr>,
+        /// Bytes taken by the header, CRLF included; HTTP data starts after them
+        len: usize,
+    },
+}
+
+/// Parse a PROXY protocol v1 header (`PROXY TCP4 <src> <dst> <sport> <dport>\r\n`)
+pub fn parse_v1(data: &[u8]) -> Result<ProxyHeader> {
+    let prefix = data.len().min(V1_SIGNATURE.len());
+    if data[..prefix] != V1_SIGNATURE[..prefix] {
+        return Err(invalid("missing PROXY signature"));
+    }
+
+    let end = match data.windows(2).position(|w| w == b"\r\n") {
+        Some(end) if end + 2 <= MAX_V1_HEADER_LEN => end,
+        Some(_) => return Err(invalid("header too long")),
+        None if data.len() >= MAX_V1_HEADER_LEN => return Err(invalid("header too long")),
+        None => return Ok(ProxyHeader::Incomplete),
+    };
+    let line = std::str::from_utf8(&data[..end]).map_err(|_| invalid("header is not ASCII"))?;
+    let fields: Vec<&str> = line.split(' ').collect();
+
+    let source = match fields.get(1).copied() {
+        // The proxy couldn't tell; the rest of the line is ignored
+        Some("UNKNOWN") => None,
+        Some(family @ ("TCP4" | "TCP6")) => {
+            let [_, _, src, dst, sport, dport] = fields[..] else {
+                return Err(invalid("expected source, destination and ports"));
+            };
+            let ip = parse_ip(family, src)?;
+            parse_ip(family, dst)?;
+            let port = parse_port(sport)?;
+            parse_port(dport)?;
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(invalid("unknown protocol family")),
+    };
+
+    Ok(ProxyHeader::Complete {
+        source,
+        len: end + 2,
+    })
+}
+
+fn parse_ip(family: &str, value: &str) -> Result<IpAddr> {
+    let ip = if family == "TCP4" {
+        value.parse::<Ipv4Addr>().map(IpAddr::V4)
+    } else {
+        value.parse::<Ipv6Addr>().map(IpAddr::V6)
+    };
+    ip.map_err(|_| invalid(&format!("bad {} address '{}'", family, value)))
+}
+
+fn parse_port(value: &str) -> Result<u16> {
+    // Ports are plain decimal without leading zeros
+    if value.len() > 1 && value.starts_with('0') {
+        return Err(invalid(&format!("bad port '{}'", value)));
+    }
+    value
+        .parse()
+        .map_err(|_| invalid(&format!("bad port '{}'", value)))
+}
+
+fn invalid(reason: &str) -> ServerError {
+    ServerError::ParseError(format!("Invalid PROXY protocol header: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tcp4_and_tcp6() {
+        let data = b"PROXY TCP4 203.0.113.9 10.0.0.1 51234 80\r\nGET / HTTP/1.1\r\n";
+        assert_eq!(
+            parse_v1(data).unwrap(),
+            ProxyHeader::Complete {
+                source: Some("203.0.113.9:51234".parse().unwrap()),
+                len: 42,
+            }
+        );
+
+        let data = b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 443\r\n";
+        assert_eq!(
+            parse_v1(data).unwrap(),
+            ProxyHeader::Complete {
+                source: Some("[2001:db8::1]:4000".parse().unwrap()),
+                len: data.len(),
+            }
+        );
+
+        assert_eq!(
+            parse_v1(b"PROXY UNKNOWN whatever\r\n").unwrap(),
+            ProxyHeader::Complete {
+                source: None,
+                len: 24
+            }
+        );
+    }
+
+    #[test]
+    fn test_incomplete_until_crlf() {
+        assert_eq!(parse_v1(b"PRO").unwrap(), ProxyHeader::Incomplete);
+        assert_eq!(
+            parse_v1(b"PROXY TCP4 203.0.113.9 10.0.0.1 51234").unwrap(),
+            ProxyHeader::Incomplete
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_headers() {
+        for data in [
+            &b"GET / HTTP/1.1\r\n"[..],
+            b"PROXY TCP5 1.2.3.4 5.6.7.8 1 2\r\n",
+            b"PROXY TCP4 1.2.3.4 5.6.7.8 1\r\n",
+            b"PROXY TCP4 ::1 ::1 1 2\r\n",
+            b"PROXY TCP4 1.2.3.4 5.6.7.8 70000 2\r\n",
+            b"PROXY TCP4 1.2.3.4 5.6.7.8 01 2\r\n",
+        ] {
+            assert!(
+                parse_v1(data).is_err(),
+                "{:?}",
+                String::from_utf8_lossy(data)
+            );
+        }
+        assert!(parse_v1(&[b'P', b'R', b'O', b'X', b'Y', b' '].repeat(20)).is_err());
+    }
+}
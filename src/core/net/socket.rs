@@ -9,9 +9,12 @@ pub struct ListeningSocket {
 }
 
 impl ListeningSocket {
+    /// Bind a non-blocking listener. Failures stay I/O errors of the original kind, so
+    /// callers can tell a busy port (`AddrInUse`) from other bind failures.
     pub fn bind(addr: SocketAddr) -> Result<Self> {
-        let listener = TcpListener::bind(addr)
-            .map_err(|e| ServerError::NetworkError(format!("Failed to bind to {}: {}", addr, e)))?;
+        let listener = TcpListener::bind(addr).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("Failed to bind to {}: {}", addr, e))
+        })?;
 
         let fd = FileDescriptor::new(listener.as_raw_fd());
         fd.set_non_blocking()?;
@@ -33,6 +36,13 @@ impl ListeningSocket {
         }
     }
 
+    /// Address actually bound, with the OS-assigned port when port 0 was requested
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .map_err(|e| ServerError::NetworkError(format!("Failed to get local address: {}", e)))
+    }
+
     pub fn as_raw_fd(&self) -> i32 {
         self.fd.as_raw_fd()
     }
@@ -40,19 +50,25 @@ impl ListeningSocket {
 
 pub struct ClientSocket {
     stream: TcpStream,
-    addr: SocketAddr,
+    /// None when the peer address couldn't be determined (e.g. reset right after accept)
+    addr: Option<SocketAddr>,
     fd: FileDescriptor,
 }
 
 impl ClientSocket {
     pub fn from_stream(stream: TcpStream, addr: SocketAddr) -> Result<Self> {
+        Self::with_peer(stream, Some(addr))
+    }
+
+    /// Wrap an accepted stream whose peer address may be unknown
+    pub fn with_peer(stream: TcpStream, addr: Option<SocketAddr>) -> Result<Self> {
         let fd = FileDescriptor::new(stream.as_raw_fd());
         fd.set_non_blocking()?;
 
         Ok(Self { stream, addr, fd })
     }
 
-    pub fn peer_addr(&self) -> SocketAddr {
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
         self.addr
     }
 
@@ -67,4 +83,10 @@ impl ClientSocket {
     pub fn as_stream_mut(&mut self) -> &mut TcpStream {
         &mut self.stream
     }
+
+    /// Send FIN after whatever is queued, leaving the read side open
+    pub fn shutdown_write(&self) -> Result<()> {
+        self.stream.shutdown(std::net::Shutdown::Write)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,165 @@
+use crate::common::error::{Result, ServerError};
+use std::collections::hash_map::RandomState;
+use std::fs::{self, File, OpenOptions};
+use std::hash::BuildHasher;
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sequence number keeping spool file names unique within the process
+static SPOOL_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Names tried before giving up when a spool file name is already taken
+const SPOOL_CREATE_ATTEMPTS: usize = 8;
+
+/// Writes a request body to a temporary file as it arrives, so a large body never has
+/// to fit in memory. The file is removed if the spool is dropped before `finish`.
+pub struct BodySpool {
+    /// Open until the body is complete
+    file: Option<File>,
+    path: PathBuf,
+    size: u64,
+}
+
+impl BodySpool {
+    /// Start spooling into a new temporary file in `dir`, readable only by the server.
+    /// `dir` may be shared (like /tmp): the file is created under an unpredictable name
+    /// and never opened if something, such as a planted symlink, is already there.
+    pub fn create(dir: &Path) -> Result<Self> {
+        let mut attempts = 0;
+        loop {
+            let path = dir.join(format!(
+                ".body_{}_{:016x}.spool",
+                std::process::id(),
+                Self::random_suffix()
+            ));
+            match Self::open_private(&path) {
+                Ok(file) => {
+                    return Ok(Self {
+                        file: Some(file),
+                        path,
+                        size: 0,
+                    })
+                }
+                Err(e)
+                    if e.kind() == ErrorKind::AlreadyExists && attempts < SPOOL_CREATE_ATTEMPTS =>
+                {
+                    attempts += 1;
+                }
+                Err(e) => {
+                    return Err(ServerError::HttpError(format!(
+                        "Failed to create body spool: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Create `path` for the server alone, failing if anything already exists there
+    fn open_private(path: &Path) -> std::io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+    }
+
+    /// Hard-to-guess part of a spool file name
+    fn random_suffix() -> u64 {
+        let count = SPOOL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        // RandomState is seeded from the OS, so the hash can't be predicted from its inputs
+        RandomState::new().hash_one((count, nanos))
+    }
+
+    /// Append the next slice of the body
+    pub fn feed(&mut self, data: &[u8]) -> Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(data).map_err(|e| {
+                ServerError::HttpError(format!("Failed to write body spool: {}", e))
+            })?;
+            self.size += data.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Bytes spooled so far
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Complete the spool and hand over its file; the caller removes it when done
+    pub fn finish(mut self) -> Result<PathBuf> {
+        // On a failed flush the file stays owned by the spool, so Drop removes it
+        if let Some(file) = self.file.as_mut() {
+            file.flush().map_err(|e| {
+                ServerError::HttpError(format!("Failed to write body spool: {}", e))
+            })?;
+        }
+        self.file = None;
+        Ok(std::mem::take(&mut self.path))
+    }
+}
+
+impl Drop for BodySpool {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spool_keeps_file_only_when_finished() {
+        let dir = std::env::temp_dir();
+
+        let mut spool = BodySpool::create(&dir).unwrap();
+        spool.feed(b"hello ").unwrap();
+        spool.feed(b"world").unwrap();
+        assert_eq!(spool.size(), 11);
+        let path = spool.finish().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+        fs::remove_file(&path).unwrap();
+
+        // An abandoned upload leaves nothing behind
+        let mut spool = BodySpool::create(&dir).unwrap();
+        spool.feed(b"partial").unwrap();
+        let path = spool.path.clone();
+        drop(spool);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_spool_file_is_private_and_never_follows_a_planted_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("localhost_spool_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let spool = BodySpool::create(&dir).unwrap();
+        let mode = fs::metadata(&spool.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let taken = spool.path.clone();
+        drop(spool);
+
+        // A symlink waiting at a spool name is refused rather than followed and truncated
+        let target = dir.join("victim");
+        fs::write(&target, "keep").unwrap();
+        std::os::unix::fs::symlink(&target, &taken).unwrap();
+        let refused = BodySpool::open_private(&taken);
+        assert_eq!(refused.unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&target).unwrap(), b"keep");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,415 @@
+use crate::common::error::Result;
+use crate::http::headers::names as header_names;
+use crate::http::request::Request;
+use crate::http::response::Response;
+use std::io::Write;
+
+/// Content codings the server can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionKind {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl CompressionKind {
+    /// Token used in `Accept-Encoding` / `Content-Encoding`
+    pub fn token(&self) -> &'static str {
+        match self {
+            CompressionKind::Identity => "identity",
+            CompressionKind::Gzip => "gzip",
+            CompressionKind::Brotli => "br",
+        }
+    }
+}
+
+/// Parse an `Accept-Encoding` value into `(coding, q)` pairs.
+/// Codings are lowercased; a missing or malformed q-value counts as 1.0.
+pub fn parse_accept_encoding(accept_encoding: &str) -> Vec<(String, f32)> {
+    accept_encoding
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let coding = parts.next()?.trim().to_ascii_lowercase();
+            if coding.is_empty() {
+                return None;
+            }
+            let q = parts
+                .filter_map(|p| {
+                    let p = p.trim();
+                    p.strip_prefix("q=").or_else(|| p.strip_prefix("Q="))
+                })
+                .find_map(|v| v.trim().parse::<f32>().ok())
+                .map(|q| q.clamp(0.0, 1.0))
+                .unwrap_or(1.0);
+            Some((coding, q))
+        })
+        .collect()
+}
+
+/// Quality the client assigns to a coding: an explicit entry wins, then `*`.
+/// Identity stays acceptable unless excluded explicitly or via `*;q=0`.
+fn quality(entries: &[(String, f32)], kind: CompressionKind) -> f32 {
+    let lookup = |coding: &str| entries.iter().find(|(c, _)| c == coding).map(|(_, q)| *q);
+    lookup(kind.token())
+        .or_else(|| lookup("*"))
+        .unwrap_or(if kind == CompressionKind::Identity {
+            1.0
+        } else {
+            0.0
+        })
+}
+
+/// Pick the best acceptable encoding from an `Accept-Encoding` value.
+/// The highest q-value wins; ties prefer Brotli, then gzip, then identity.
+/// Returns `None` when nothing is acceptable (e.g. `identity;q=0` alone), which maps to 406.
+pub fn negotiate(accept_encoding: &str) -> Option<CompressionKind> {
+    let entries = parse_accept_encoding(accept_encoding);
+    best_of(
+        &entries,
+        &[
+            CompressionKind::Brotli,
+            CompressionKind::Gzip,
+            CompressionKind::Identity,
+        ],
+    )
+}
+
+/// Highest-quality coding among `candidates` (in preference order), if any is acceptable
+fn best_of(entries: &[(String, f32)], candidates: &[CompressionKind]) -> Option<CompressionKind> {
+    let mut best: Option<(CompressionKind, f32)> = None;
+    for kind in candidates {
+        let q = quality(entries, *kind);
+        if q > 0.0 && best.map(|(_, best_q)| q > best_q).unwrap_or(true) {
+            best = Some((*kind, q));
+        }
+    }
+    best.map(|(kind, _)| kind)
+}
+
+/// Compress a body with the given coding
+pub fn compress(kind: CompressionKind, body: &[u8]) -> Result<Vec<u8>> {
+    match kind {
+        CompressionKind::Identity => Ok(body.to_vec()),
+        CompressionKind::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body)?;
+            Ok(encoder.finish()?)
+        }
+        CompressionKind::Brotli => {
+            let mut output = Vec::new();
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+                encoder.write_all(body)?;
+                encoder.flush()?;
+            }
+            Ok(output)
+        }
+    }
+}
+
+/// Check whether a content type is worth compressing (text-like formats only)
+pub fn is_compressible(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/javascript" | "application/json" | "application/xml" | "image/svg+xml"
+        )
+}
+
+/// Compress the response body in place if the client accepts br or gzip
+/// and the body is textual and at least `min_size` bytes (`None` sends it as is).
+/// Returns the coding that was applied, or `None` when the client refuses every
+/// coding the response could be sent with.
+pub fn compress_response(
+    request: &Request,
+    response: &mut Response,
+    min_size: Option<usize>,
+) -> Result<Option<CompressionKind>> {
+    let entries = request
+        .headers
+        .get(header_names::ACCEPT_ENCODING)
+        .map(|v| parse_accept_encoding(v))
+        .unwrap_or_default();
+
+    let compressible = min_size.is_some()
+        && response
+            .headers
+            .get(header_names::CONTENT_TYPE)
+            .map(|ct| is_compressible(ct))
+            .unwrap_or(false)
+        && !response.chunked
+        && !response.body.is_empty()
+        && !response.headers.contains(header_names::CONTENT_ENCODING);
+    let below_min_size = min_size.is_some_and(|min| response.body.len() < min);
+    // Which coding goes out depends on Accept-Encoding even when identity wins,
+    // so caches must key every eligible response on it
+    if compressible {
+        response.add_vary(header_names::ACCEPT_ENCODING);
+    }
+
+    let candidates: &[CompressionKind] = if compressible {
+        &[
+            CompressionKind::Brotli,
+            CompressionKind::Gzip,
+            CompressionKind::Identity,
+        ]
+    } else {
+        &[CompressionKind::Identity]
+    };
+
+    // Small bodies go out as they are, unless the client refuses identity
+    let kind = if compressible && below_min_size {
+        best_of(&entries, &[CompressionKind::Identity]).or_else(|| best_of(&entries, candidates))
+    } else {
+        best_of(&entries, candidates)
+    };
+    let kind = match kind {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+    if kind == CompressionKind::Identity {
+        return Ok(Some(kind));
+    }
+
+    let compressed = compress(kind, &response.body)?;
+    response.headers.set(
+        header_names::CONTENT_ENCODING.to_string(),
+        kind.token().to_string(),
+    );
+    response.set_body(compressed);
+
+    Ok(Some(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::method::Method;
+    use crate::http::version::Version;
+    use std::io::Read;
+
+    fn request_accepting(accept_encoding: Option<&str>) -> Request {
+        let mut request = Request::new(Method::GET, "/".to_string(), Version::Http11);
+        if let Some(value) = accept_encoding {
+            request
+                .headers
+                .set("Accept-Encoding".to_string(), value.to_string());
+        }
+        request
+    }
+
+    fn text_response(body: &str) -> Response {
+        let mut response = Response::ok(Version::Http11);
+        response.set_content_type("text/html");
+        response.set_body_str(body);
+        response
+    }
+
+    #[test]
+    fn test_negotiate_prefers_highest_q_value() {
+        let matrix = [
+            ("gzip;q=0.5, br;q=1.0", Some(CompressionKind::Brotli)),
+            ("gzip;q=1.0, br;q=0.4", Some(CompressionKind::Gzip)),
+            ("gzip, br", Some(CompressionKind::Brotli)),
+            (
+                "GZIP;Q=0.4, identity;q=0.5",
+                Some(CompressionKind::Identity),
+            ),
+            ("br;q=0, gzip;q=0", Some(CompressionKind::Identity)),
+            ("deflate", Some(CompressionKind::Identity)),
+            ("", Some(CompressionKind::Identity)),
+            (
+                "identity;q=0.9, gzip;q=0.1",
+                Some(CompressionKind::Identity),
+            ),
+        ];
+        for (header, expected) in matrix {
+            assert_eq!(negotiate(header), expected, "Accept-Encoding: {}", header);
+        }
+    }
+
+    #[test]
+    fn test_negotiate_identity_q0_forbids_uncompressed() {
+        assert_eq!(negotiate("identity;q=0"), None);
+        assert_eq!(negotiate("identity;q=0, deflate"), None);
+        assert_eq!(
+            negotiate("identity;q=0, gzip;q=0.3"),
+            Some(CompressionKind::Gzip)
+        );
+    }
+
+    #[test]
+    fn test_negotiate_wildcard() {
+        assert_eq!(negotiate("*"), Some(CompressionKind::Brotli));
+        assert_eq!(negotiate("br;q=0, *"), Some(CompressionKind::Gzip));
+        assert_eq!(negotiate("*;q=0"), None);
+        assert_eq!(
+            negotiate("*;q=0, identity"),
+            Some(CompressionKind::Identity)
+        );
+        assert_eq!(
+            negotiate("gzip;q=0.2, *;q=0.5"),
+            Some(CompressionKind::Brotli)
+        );
+    }
+
+    #[test]
+    fn test_parse_accept_encoding_q_values() {
+        let entries = parse_accept_encoding("gzip;q=0.5, br , identity;q=bogus, ,x;q=7");
+        assert_eq!(
+            entries,
+            vec![
+                ("gzip".to_string(), 0.5),
+                ("br".to_string(), 1.0),
+                ("identity".to_string(), 1.0),
+                ("x".to_string(), 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_acceptable_when_only_identity_is_possible() {
+        // Binary content can only go out as identity, which the client forbids
+        let request = request_accepting(Some("br, identity;q=0"));
+        let mut response = Response::ok(Version::Http11);
+        response.set_content_type("image/png");
+        response.set_body(vec![0x89, b'P', b'N', b'G']);
+
+        assert_eq!(
+            compress_response(&request, &mut response, Some(0)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_br_preferred_client_gets_brotli() {
+        let body = "<p>hello brotli</p>".repeat(50);
+        let request = request_accepting(Some("gzip;q=0.8, br"));
+        let mut response = text_response(&body);
+
+        let kind = compress_response(&request, &mut response, Some(0)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Brotli));
+        assert_eq!(
+            response.headers.get("Content-Encoding"),
+            Some(&"br".to_string())
+        );
+        assert_eq!(response.content_length(), Some(response.body.len()));
+
+        let mut decoded = String::new();
+        brotli::Decompressor::new(&response.body[..], 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn test_gzip_preferred_client_gets_gzip() {
+        let body = "<p>hello gzip</p>".repeat(50);
+        let request = request_accepting(Some("br;q=0.2, gzip"));
+        let mut response = text_response(&body);
+
+        let kind = compress_response(&request, &mut response, Some(0)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Gzip));
+        assert_eq!(
+            response.headers.get("Content-Encoding"),
+            Some(&"gzip".to_string())
+        );
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&response.body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn test_no_compression_without_accept_encoding() {
+        let request = request_accepting(None);
+        let mut response = text_response("plain body");
+
+        let kind = compress_response(&request, &mut response, Some(0)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Identity));
+        assert!(response.headers.get("Content-Encoding").is_none());
+        assert_eq!(response.body, b"plain body");
+        assert_eq!(
+            response.headers.get("Vary"),
+            Some(&"Accept-Encoding".to_string())
+        );
+    }
+
+    #[test]
+    fn test_small_bodies_and_disabled_compression_stay_identity() {
+        let body = "<p>hello gzip</p>".repeat(100);
+        let request = request_accepting(Some("gzip"));
+
+        let mut response = text_response(&body);
+        let kind = compress_response(&request, &mut response, Some(1024)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Gzip));
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&response.body.len().to_string())
+        );
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&response.body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+
+        let mut small = text_response("<p>small</p>");
+        let kind = compress_response(&request, &mut small, Some(1024)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Identity));
+        assert_eq!(small.body, b"<p>small</p>");
+        assert_eq!(
+            small.headers.get("Vary"),
+            Some(&"Accept-Encoding".to_string())
+        );
+
+        // A client refusing identity still gets a small body compressed
+        let strict = request_accepting(Some("gzip, identity;q=0"));
+        let mut small = text_response("<p>small</p>");
+        let kind = compress_response(&strict, &mut small, Some(1024)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Gzip));
+
+        let mut disabled = text_response(&body);
+        let kind = compress_response(&request, &mut disabled, None).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Identity));
+        assert!(disabled.headers.get("Content-Encoding").is_none());
+        assert_eq!(disabled.body, body.as_bytes());
+        assert!(disabled.headers.get("Vary").is_none());
+    }
+
+    #[test]
+    fn test_already_encoded_or_chunked_bodies_are_left_alone() {
+        let body = "<p>hello gzip</p>".repeat(100);
+        let request = request_accepting(Some("gzip"));
+
+        let mut encoded = text_response(&body);
+        encoded
+            .headers
+            .set("Content-Encoding".to_string(), "deflate".to_string());
+        let kind = compress_response(&request, &mut encoded, Some(0)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Identity));
+        assert_eq!(encoded.body, body.as_bytes());
+
+        let mut chunked = text_response(&body);
+        chunked.chunked = true;
+        let kind = compress_response(&request, &mut chunked, Some(0)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Identity));
+    }
+
+    #[test]
+    fn test_binary_content_is_not_compressed() {
+        let request = request_accepting(Some("br, gzip"));
+        let mut response = Response::ok(Version::Http11);
+        response.set_content_type("image/png");
+        response.set_body(vec![0x89, b'P', b'N', b'G']);
+
+        let kind = compress_response(&request, &mut response, Some(0)).unwrap();
+        assert_eq!(kind, Some(CompressionKind::Identity));
+        assert!(response.headers.get("Content-Encoding").is_none());
+        assert!(response.headers.get("Vary").is_none());
+    }
+}
@@ -1,5 +1,7 @@
+use crate::common::constants::DEFAULT_MAX_COOKIES;
+use crate::common::time::{format_http_date, parse_http_date};
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime};
 
 /// HTTP Cookie structure
 ///
@@ -32,6 +34,12 @@ pub struct Cookie {
 
     /// SameSite attribute (optional)
     pub same_site: Option<SameSite>,
+
+    /// When the cookie was created; Max-Age counts from here
+    pub created_at: SystemTime,
+
+    /// Also emit an Expires derived from Max-Age, for clients that ignore Max-Age
+    pub expires_from_max_age: bool,
 }
 
 /// SameSite attribute values
@@ -58,6 +66,8 @@ impl Cookie {
             secure: false,
             http_only: false,
             same_site: None,
+            created_at: SystemTime::now(),
+            expires_from_max_age: false,
         }
     }
 
@@ -103,17 +113,71 @@ impl Cookie {
         self
     }
 
+    /// Emit an Expires attribute computed from creation time + Max-Age
+    pub fn set_expires_from_max_age(mut self, enabled: bool) -> Self {
+        self.expires_from_max_age = enabled;
+        self
+    }
+
+    /// Parse a `Set-Cookie` header value back into a cookie. Attributes may come in any
+    /// order and are matched case-insensitively; unknown ones and unparsable values are
+    /// skipped. None when the leading `name=value` pair is missing or has no name.
+    pub fn parse_set_cookie(header: &str) -> Option<Cookie> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie::new(name.to_string(), value.trim().to_string());
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "path" => cookie.path = Some(value.to_string()),
+                "domain" => cookie.domain = Some(value.to_string()),
+                // A negative Max-Age expires the cookie at once (RFC 6265 §5.2.2)
+                "max-age" => {
+                    if let Ok(secs) = value.parse::<i64>() {
+                        cookie.max_age = Some(secs.max(0) as u64);
+                    }
+                }
+                "expires" => {
+                    if let Some(expires) = parse_http_date(value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => Some(SameSite::Strict),
+                        "lax" => Some(SameSite::Lax),
+                        "none" => Some(SameSite::None),
+                        _ => cookie.same_site,
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+
+    /// Point in time the cookie expires. Max-Age takes precedence over Expires (RFC 6265 §5.3);
+    /// one too large to represent means the cookie never expires.
+    pub fn expiry_time(&self) -> Option<SystemTime> {
+        match self.max_age {
+            Some(max_age) => self.created_at.checked_add(Duration::from_secs(max_age)),
+            None => self.expires,
+        }
+    }
+
     /// Check if cookie is expired
     pub fn is_expired(&self) -> bool {
-        if let Some(expires) = self.expires {
-            SystemTime::now() > expires
-        } else if self.max_age.is_some() {
-            // For Max-Age, we'd need creation time, which we don't store
-            // This is a simplified check - in production, store creation time
-            false
-        } else {
-            false
-        }
+        self.expiry_time()
+            .map(|expiry| SystemTime::now() >= expiry)
+            .unwrap_or(false)
     }
 
     /// Serialize cookie to Set-Cookie header value format
@@ -129,23 +193,14 @@ impl Cookie {
             parts.push(format!("Domain={}", domain));
         }
 
-        if let Some(expires) = self.expires {
-            // Format: Wed, 21 Oct 2015 07:28:00 GMT
-            // Simplified - in production use proper date formatting
-            if let Ok(duration) = expires.duration_since(UNIX_EPOCH) {
-                let secs = duration.as_secs();
-                // Simple date calculation (not accurate, but functional)
-                let days_since_epoch = secs / 86400;
-                let day_of_week = (days_since_epoch + 4) % 7;
-                let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
-                let year = 1970 + (days_since_epoch / 365);
-                let day = (days_since_epoch % 365) + 1;
-                let month = "Jan"; // Simplified
-                parts.push(format!(
-                    "Expires={}, {:02} {} {} 12:00:00 GMT",
-                    days[day_of_week as usize], day, month, year
-                ));
-            }
+        // Format: Wed, 21 Oct 2015 07:28:00 GMT
+        let expires = match (self.expires, self.max_age) {
+            (Some(expires), _) => Some(expires),
+            (None, Some(_)) if self.expires_from_max_age => self.expiry_time(),
+            _ => None,
+        };
+        if let Some(expires) = expires {
+            parts.push(format!("Expires={}", format_http_date(expires)));
         }
 
         if let Some(max_age) = self.max_age {
@@ -176,10 +231,23 @@ impl Cookie {
 /// Parse Cookie header value into a HashMap of name-value pairs
 ///
 /// Cookie header format: name1=value1; name2=value2; name3=value3
+/// At most `DEFAULT_MAX_COOKIES` pairs are parsed; the rest are ignored.
 pub fn parse_cookie_header(cookie_header: &str) -> HashMap<String, String> {
+    parse_cookie_header_limited(cookie_header, DEFAULT_MAX_COOKIES)
+}
+
+/// Parse a Cookie header value, stopping after `max_cookies` name-value pairs
+pub fn parse_cookie_header_limited(
+    cookie_header: &str,
+    max_cookies: usize,
+) -> HashMap<String, String> {
     let mut cookies = HashMap::new();
 
+    // The cap counts stored cookies, so empty or `=`-less segments can't use it up
     for part in cookie_header.split(';') {
+        if cookies.len() >= max_cookies {
+            break;
+        }
         let part = part.trim();
         if let Some(equal_pos) = part.find('=') {
             let name = part[..equal_pos].trim().to_string();
@@ -250,4 +318,145 @@ mod tests {
         assert_eq!(cookies.get("session_id"), Some(&"abc123".to_string()));
         assert_eq!(cookies.get("user"), Some(&"john".to_string()));
     }
+
+    #[test]
+    fn test_parse_cookie_header_caps_cookie_count() {
+        let header: Vec<String> = (0..80).map(|i| format!("c{}=v{}", i, i)).collect();
+        let cookies = parse_cookie_header(&header.join("; "));
+
+        assert_eq!(cookies.len(), DEFAULT_MAX_COOKIES);
+        assert_eq!(cookies.get("c0").unwrap(), "v0");
+        assert_eq!(cookies.get("c49").unwrap(), "v49");
+        assert!(!cookies.contains_key("c50"));
+
+        assert_eq!(parse_cookie_header_limited("a=1; b=2; c=3", 2).len(), 2);
+        let padded = format!("{}session=x", ";".repeat(50));
+        let cookies = parse_cookie_header_limited(&padded, 2);
+        assert_eq!(cookies.get("session"), Some(&"x".to_string()));
+        let cookies = parse_cookie_header_limited("junk; ; other; session=x", 1);
+        assert_eq!(cookies.get("session"), Some(&"x".to_string()));
+    }
+
+    #[test]
+    fn test_max_age_expiry_uses_creation_time() {
+        let fresh = Cookie::new("a".to_string(), "1".to_string()).set_max_age(3600);
+        assert!(!fresh.is_expired());
+
+        let mut old = Cookie::new("a".to_string(), "1".to_string()).set_max_age(60);
+        old.created_at = SystemTime::now() - Duration::from_secs(120);
+        assert!(old.is_expired());
+
+        // Max-Age=0 expires immediately
+        assert!(Cookie::new("a".to_string(), "1".to_string())
+            .set_max_age(0)
+            .is_expired());
+
+        // Max-Age wins over a future Expires
+        let mut both = Cookie::new("a".to_string(), "1".to_string())
+            .set_expires(SystemTime::now() + Duration::from_secs(3600))
+            .set_max_age(60);
+        both.created_at = SystemTime::now() - Duration::from_secs(120);
+        assert!(both.is_expired());
+    }
+
+    #[test]
+    fn test_huge_max_age_never_expires() {
+        let cookie = Cookie::parse_set_cookie("a=1; Max-Age=9223372036854775807").unwrap();
+        assert!(!cookie.is_expired());
+        assert_eq!(
+            cookie.set_expires_from_max_age(true).to_set_cookie_string(),
+            "a=1; Max-Age=9223372036854775807"
+        );
+    }
+
+    #[test]
+    fn test_max_age_cookie_expires_once_its_time_passes() {
+        let cookie = Cookie::new("a".to_string(), "1".to_string()).set_max_age(1);
+        assert!(!cookie.is_expired());
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(cookie.is_expired());
+    }
+
+    #[test]
+    fn test_expires_alone_still_decides_expiry() {
+        let past = Cookie::new("a".to_string(), "1".to_string())
+            .set_expires(SystemTime::now() - Duration::from_secs(1));
+        assert!(past.is_expired());
+
+        let future = Cookie::new("a".to_string(), "1".to_string())
+            .set_expires(SystemTime::now() + Duration::from_secs(3600));
+        assert!(!future.is_expired());
+
+        // Neither attribute: a session cookie never expires on its own
+        assert!(!Cookie::new("a".to_string(), "1".to_string()).is_expired());
+    }
+
+    #[test]
+    fn test_set_cookie_round_trip() {
+        let cookie = Cookie::new("session".to_string(), "abc123".to_string())
+            .set_path("/app".to_string())
+            .set_domain("example.com".to_string())
+            .set_expires(std::time::UNIX_EPOCH + Duration::from_secs(1_445_412_480))
+            .set_max_age(3600)
+            .set_secure(true)
+            .set_http_only(true)
+            .set_same_site(SameSite::Lax);
+        let header = cookie.to_set_cookie_string();
+
+        let parsed = Cookie::parse_set_cookie(&header).unwrap();
+        assert_eq!(parsed.name, "session");
+        assert_eq!(parsed.value, "abc123");
+        assert_eq!(parsed.path.as_deref(), Some("/app"));
+        assert_eq!(parsed.domain.as_deref(), Some("example.com"));
+        assert_eq!(parsed.expires, cookie.expires);
+        assert_eq!(parsed.max_age, Some(3600));
+        assert!(parsed.secure && parsed.http_only);
+        assert_eq!(parsed.same_site, Some(SameSite::Lax));
+        assert_eq!(parsed.to_set_cookie_string(), header);
+
+        // A bare cookie stays bare
+        let plain = Cookie::parse_set_cookie("a=1").unwrap();
+        assert_eq!(plain.to_set_cookie_string(), "a=1");
+    }
+
+    #[test]
+    fn test_parse_set_cookie_attributes_in_any_order_and_case() {
+        let cookie = Cookie::parse_set_cookie(
+            "id = 42 ; SAMESITE=strict; httponly; max-age=-5; PATH=/; Unknown=x; secure",
+        )
+        .unwrap();
+        assert_eq!((cookie.name.as_str(), cookie.value.as_str()), ("id", "42"));
+        assert_eq!(cookie.same_site, Some(SameSite::Strict));
+        assert!(cookie.http_only && cookie.secure);
+        assert_eq!(cookie.path.as_deref(), Some("/"));
+        assert_eq!(cookie.max_age, Some(0));
+        assert!(cookie.is_expired());
+
+        // Values may contain '='; a bad Expires is skipped
+        let cookie = Cookie::parse_set_cookie("t=a=b; Expires=soon").unwrap();
+        assert_eq!(cookie.value, "a=b");
+        assert_eq!(cookie.expires, None);
+
+        assert!(Cookie::parse_set_cookie("").is_none());
+        assert!(Cookie::parse_set_cookie("novalue; Path=/").is_none());
+        assert!(Cookie::parse_set_cookie("=1; Path=/").is_none());
+    }
+
+    #[test]
+    fn test_expires_derived_from_max_age() {
+        let mut cookie = Cookie::new("sid".to_string(), "abc".to_string())
+            .set_max_age(3600)
+            .set_expires_from_max_age(true);
+        // 1 hour before Wed, 21 Oct 2015 07:28:00 GMT
+        cookie.created_at = std::time::UNIX_EPOCH + Duration::from_secs(1_445_412_480 - 3600);
+
+        assert_eq!(
+            cookie.to_set_cookie_string(),
+            "sid=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=3600"
+        );
+
+        // Off by default: only Max-Age is sent
+        let plain = Cookie::new("sid".to_string(), "abc".to_string()).set_max_age(3600);
+        assert_eq!(plain.to_set_cookie_string(), "sid=abc; Max-Age=3600");
+    }
 }
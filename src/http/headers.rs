@@ -77,6 +77,64 @@ impl Headers {
         self.headers.len()
     }
 
+    /// Whether the copies of a header, or the comma-separated items of one copy, carry
+    /// more than one distinct value, counting names case-insensitively
+    pub fn has_conflicting_values(&self, name: &str) -> bool {
+        let mut values = self
+            .headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .flat_map(|(_, values)| values)
+            .flat_map(|value| value.split(','))
+            .map(str::trim);
+        values
+            .next()
+            .is_some_and(|first| values.any(|value| value != first))
+    }
+
+    /// First header that may appear at most once in a request but was sent more than
+    /// once, counting names case-insensitively
+    pub fn duplicate_singleton(&self) -> Option<&'static str> {
+        SINGLETON_HEADERS.iter().copied().find(|singleton| {
+            let count: usize = self
+                .headers
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(singleton))
+                .map(|(_, values)| values.len())
+                .sum();
+            count > 1
+        })
+    }
+
+    /// Title-case each `-`-separated word of a header name (`content-type` -> `Content-Type`)
+    pub fn canonical_name(name: &str) -> String {
+        name.split('-')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => {
+                        first.to_ascii_uppercase().to_string()
+                            + &chars.as_str().to_ascii_lowercase()
+                    }
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Serialize like `to_string`, but with every name in canonical casing
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        for (name, values) in &self.headers {
+            let name = Self::canonical_name(name);
+            for value in values {
+                out.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        out
+    }
+
     /// Parse headers from raw header lines
     pub fn from_lines(lines: &[String]) -> Result<Self, HeaderParseError> {
         let mut headers = Headers::new();
@@ -136,6 +194,17 @@ impl fmt::Display for HeaderParseError {
 
 impl std::error::Error for HeaderParseError {}
 
+/// Request headers that must not be repeated; a second copy makes the request ambiguous
+pub const SINGLETON_HEADERS: &[&str] = &[
+    names::CONTENT_LENGTH,
+    names::CONTENT_TYPE,
+    names::HOST,
+    names::AUTHORIZATION,
+    names::RANGE,
+    names::IF_MODIFIED_SINCE,
+    names::IF_UNMODIFIED_SINCE,
+];
+
 // Common header names as constants
 pub mod names {
     pub const CONTENT_TYPE: &str = "Content-Type";
@@ -146,11 +215,22 @@ pub mod names {
     pub const USER_AGENT: &str = "User-Agent";
     pub const ACCEPT: &str = "Accept";
     pub const ACCEPT_ENCODING: &str = "Accept-Encoding";
+    pub const CONTENT_ENCODING: &str = "Content-Encoding";
+    pub const VARY: &str = "Vary";
+    pub const RANGE: &str = "Range";
+    pub const CONTENT_RANGE: &str = "Content-Range";
+    pub const ACCEPT_RANGES: &str = "Accept-Ranges";
+    pub const ALLOW: &str = "Allow";
     pub const COOKIE: &str = "Cookie";
     pub const SET_COOKIE: &str = "Set-Cookie";
     pub const LOCATION: &str = "Location";
     pub const SERVER: &str = "Server";
     pub const DATE: &str = "Date";
+    pub const AUTHORIZATION: &str = "Authorization";
+    pub const IF_MODIFIED_SINCE: &str = "If-Modified-Since";
+    pub const IF_UNMODIFIED_SINCE: &str = "If-Unmodified-Since";
+    pub const RETRY_AFTER: &str = "Retry-After";
+    pub const CONTENT_LOCATION: &str = "Content-Location";
 }
 
 #[cfg(test)]
@@ -174,6 +254,14 @@ mod tests {
         assert!(headers.contains("Content-Type"));
     }
 
+    #[test]
+    fn test_canonical_name() {
+        assert_eq!(Headers::canonical_name("content-type"), "Content-Type");
+        assert_eq!(Headers::canonical_name("X-POWERED-BY"), "X-Powered-By");
+        assert_eq!(Headers::canonical_name("etag"), "Etag");
+        assert_eq!(Headers::canonical_name("a--b-"), "A--B-");
+    }
+
     #[test]
     fn test_headers_multiple_values() {
         let mut headers = Headers::new();
@@ -193,4 +281,16 @@ mod tests {
         assert_eq!(headers.get("Content-Type"), Some(&"text/html".to_string()));
         assert_eq!(headers.get("Content-Length"), Some(&"123".to_string()));
     }
+
+    #[test]
+    fn test_duplicate_singleton() {
+        let mut headers = Headers::new();
+        headers.add("Accept".to_string(), "text/html".to_string());
+        headers.add("accept".to_string(), "application/json".to_string());
+        headers.add("Host".to_string(), "example.com".to_string());
+        assert_eq!(headers.duplicate_singleton(), None);
+
+        headers.add("HOST".to_string(), "other.com".to_string());
+        assert_eq!(headers.duplicate_singleton(), Some(names::HOST));
+    }
 }
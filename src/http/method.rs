@@ -24,6 +24,14 @@ impl Method {
         )
     }
 
+    /// Check if method is one a read-only server refuses (POST, PUT, DELETE, PATCH)
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Method::POST | Method::PUT | Method::DELETE | Method::PATCH
+        )
+    }
+
     /// Check if method is idempotent (can be safely repeated)
     pub fn is_idempotent(&self) -> bool {
         matches!(
@@ -41,6 +49,14 @@ impl Method {
     pub fn allows_body(&self) -> bool {
         matches!(self, Method::POST | Method::PUT | Method::PATCH)
     }
+
+    /// Check if a string is a syntactically valid method token (RFC 9110 `tchar`s),
+    /// whether or not the server implements it
+    pub fn is_token(s: &str) -> bool {
+        !s.is_empty()
+            && s.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+    }
 }
 
 impl fmt::Display for Method {
@@ -101,6 +117,16 @@ impl std::error::Error for MethodParseError {}
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_method_token_syntax() {
+        assert!(Method::is_token("GET"));
+        assert!(Method::is_token("get"));
+        assert!(Method::is_token("M-SEARCH"));
+        assert!(!Method::is_token(""));
+        assert!(!Method::is_token("GE(T"));
+        assert!(!Method::is_token("G\u{e9}T"));
+    }
+
     #[test]
     fn test_method_parsing() {
         assert_eq!(Method::from_str("GET").unwrap(), Method::GET);
@@ -119,6 +145,8 @@ mod tests {
     fn test_method_properties() {
         assert!(Method::GET.is_safe());
         assert!(!Method::POST.is_safe());
+        assert!(Method::PATCH.is_mutating());
+        assert!(!Method::HEAD.is_mutating());
         assert!(Method::GET.is_idempotent());
         assert!(!Method::POST.is_idempotent());
         assert!(Method::POST.allows_body());
@@ -1,14 +1,18 @@
+pub mod body_spool;
+pub mod compression;
 pub mod cookie;
 pub mod headers;
 pub mod method;
+pub mod multipart;
 pub mod parser;
+pub mod range;
 pub mod request;
 pub mod response;
 pub mod serializer;
 pub mod status;
 pub mod version;
 
-pub use cookie::{parse_cookie_header, Cookie, SameSite};
+pub use cookie::{parse_cookie_header, parse_cookie_header_limited, Cookie, SameSite};
 pub use headers::{names as header_names, Headers};
 pub use method::Method;
 pub use request::Request;
@@ -0,0 +1,348 @@
+use crate::common::error::{Result, ServerError};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Sequence number keeping temporary part files unique within the process
+static PART_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Largest header block accepted for a single part
+const MAX_PART_HEADER_SIZE: usize = 8192;
+
+/// A file part of a multipart body, already written to a temporary file
+#[derive(Debug, Clone)]
+pub struct StreamedPart {
+    /// `filename` from the part's Content-Disposition
+    pub filename: Option<String>,
+    /// The part's own Content-Type
+    pub content_type: Option<String>,
+    /// Temporary file holding the part's content
+    pub path: PathBuf,
+    /// Content size in bytes
+    pub size: u64,
+}
+
+/// Remove the temporary files of parts nobody claimed (missing files are ignored)
+pub fn remove_part_files(parts: &[StreamedPart]) {
+    for part in parts {
+        let _ = fs::remove_file(&part.path);
+    }
+}
+
+/// Boundary parameter of a `multipart/form-data` Content-Type, if it is one
+pub fn form_data_boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    let media_type = params.next()?.trim();
+    if !media_type.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        .filter(|boundary| !boundary.is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the first boundary
+    Preamble,
+    /// Just after a boundary: either CRLF (another part) or `--` (the end)
+    AfterBoundary,
+    Headers,
+    Body,
+    /// Closing boundary seen; anything after it is ignored
+    Done,
+}
+
+/// Incremental `multipart/form-data` parser that writes file parts straight to disk.
+///
+/// Only a boundary-sized tail of the input is held in memory, so the body size is bounded
+/// by disk space rather than RAM. Parts without a filename (plain form fields) are skipped.
+/// Temporary files are removed if the stream is dropped before `finish`.
+pub struct MultipartStream {
+    /// `\r\n--boundary`: the delimiter between parts
+    delimiter: Vec<u8>,
+    dir: PathBuf,
+    state: State,
+    /// Input not yet processed
+    pending: Vec<u8>,
+    /// Part being written; None while skipping a non-file part
+    current: Option<(File, StreamedPart)>,
+    parts: Vec<StreamedPart>,
+}
+
+impl MultipartStream {
+    /// Stream parts delimited by `boundary` into temporary files in `dir`
+    pub fn new(boundary: &str, dir: PathBuf) -> Self {
+        Self {
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            dir,
+            state: State::Preamble,
+            // The first boundary may open the body without a preceding CRLF
+            pending: b"\r\n".to_vec(),
+            current: None,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Process the next slice of the body
+    pub fn feed(&mut self, data: &[u8]) -> Result<()> {
+        self.pending.extend_from_slice(data);
+        loop {
+            match self.state {
+                State::Preamble => match find(&self.pending, &self.delimiter) {
+                    Some(pos) => {
+                        self.pending.drain(..pos + self.delimiter.len());
+                        self.state = State::AfterBoundary;
+                    }
+                    None => {
+                        let keep = self.delimiter.len() - 1;
+                        let discard = self.pending.len().saturating_sub(keep);
+                        self.pending.drain(..discard);
+                        return Ok(());
+                    }
+                },
+                State::AfterBoundary => {
+                    if self.pending.len() < 2 {
+                        return Ok(());
+                    }
+                    if self.pending.starts_with(b"--") {
+                        self.pending.clear();
+                        self.state = State::Done;
+                    } else if self.pending.starts_with(b"\r\n") {
+                        self.pending.drain(..2);
+                        self.state = State::Headers;
+                    } else {
+                        return Err(ServerError::BadRequest(
+                            "Malformed multipart boundary".to_string(),
+                        ));
+                    }
+                }
+                State::Headers => match find(&self.pending, b"\r\n\r\n") {
+                    Some(pos) => {
+                        let headers = String::from_utf8_lossy(&self.pending[..pos]).to_string();
+                        self.pending.drain(..pos + 4);
+                        self.start_part(&headers)?;
+                        self.state = State::Body;
+                    }
+                    None if self.pending.len() > MAX_PART_HEADER_SIZE => {
+                        return Err(ServerError::BadRequest(
+                            "Multipart part headers too large".to_string(),
+                        ));
+                    }
+                    None => return Ok(()),
+                },
+                State::Body => match find(&self.pending, &self.delimiter) {
+                    Some(pos) => {
+                        let content: Vec<u8> = self.pending.drain(..pos).collect();
+                        self.write_current(&content)?;
+                        self.pending.drain(..self.delimiter.len());
+                        self.finish_part()?;
+                        self.state = State::AfterBoundary;
+                    }
+                    None => {
+                        // Keep a possible partial delimiter at the end for the next call
+                        let keep = self.delimiter.len() - 1;
+                        let ready = self.pending.len().saturating_sub(keep);
+                        let content: Vec<u8> = self.pending.drain(..ready).collect();
+                        self.write_current(&content)?;
+                        return Ok(());
+                    }
+                },
+                State::Done => {
+                    self.pending.clear();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Complete the stream and hand over the written parts
+    pub fn finish(mut self) -> Result<Vec<StreamedPart>> {
+        if self.state != State::Done {
+            return Err(ServerError::BadRequest(
+                "Multipart body ended before the closing boundary".to_string(),
+            ));
+        }
+        Ok(std::mem::take(&mut self.parts))
+    }
+
+    /// Open a temporary file for a part with a filename; other parts are skipped
+    fn start_part(&mut self, headers: &str) -> Result<()> {
+        let mut filename = None;
+        let mut content_type = None;
+        for line in headers.split("\r\n") {
+            let (name, value) = match line.split_once(':') {
+                Some(parts) => parts,
+                None => continue,
+            };
+            if name.trim().eq_ignore_ascii_case("Content-Disposition") {
+                filename = value
+                    .split(';')
+                    .filter_map(|param| param.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("filename"))
+                    .map(|(_, v)| v.trim().trim_matches('"').to_string())
+                    .filter(|v| !v.is_empty());
+            } else if name.trim().eq_ignore_ascii_case("Content-Type") {
+                content_type = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            }
+        }
+
+        if filename.is_none() {
+            self.current = None;
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|e| {
+            ServerError::HttpError(format!("Failed to create upload directory: {}", e))
+        })?;
+        let path = self.dir.join(format!(
+            ".upload_{}_{}.part",
+            std::process::id(),
+            PART_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::create(&path)
+            .map_err(|e| ServerError::HttpError(format!("Failed to create part file: {}", e)))?;
+        self.current = Some((
+            file,
+            StreamedPart {
+                filename,
+                content_type,
+                path,
+                size: 0,
+            },
+        ));
+        Ok(())
+    }
+
+    fn write_current(&mut self, content: &[u8]) -> Result<()> {
+        if let Some((file, part)) = self.current.as_mut() {
+            file.write_all(content)
+                .map_err(|e| ServerError::HttpError(format!("Failed to write part file: {}", e)))?;
+            part.size += content.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn finish_part(&mut self) -> Result<()> {
+        if let Some((mut file, part)) = self.current.take() {
+            // Keep the part even if flushing fails so Drop still removes its file
+            let flushed = file.flush();
+            self.parts.push(part);
+            flushed
+                .map_err(|e| ServerError::HttpError(format!("Failed to write part file: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MultipartStream {
+    fn drop(&mut self) {
+        if let Some((_, part)) = self.current.take() {
+            let _ = fs::remove_file(&part.path);
+        }
+        remove_part_files(&self.parts);
+    }
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "localhost_multipart_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn body(file: &[u8]) -> Vec<u8> {
+        let mut body = b"--XyZ\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhello\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"pic.png\"\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        body.extend_from_slice(file);
+        body.extend_from_slice(b"\r\n--XyZ--\r\n");
+        body
+    }
+
+    #[test]
+    fn test_boundary_from_content_type() {
+        assert_eq!(
+            form_data_boundary("multipart/form-data; boundary=\"abc\"").as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            form_data_boundary("Multipart/Form-Data;charset=utf-8; Boundary=q1").as_deref(),
+            Some("q1")
+        );
+        assert_eq!(form_data_boundary("text/plain; boundary=x"), None);
+        assert_eq!(form_data_boundary("multipart/form-data"), None);
+    }
+
+    #[test]
+    fn test_streams_file_part_across_any_split() {
+        // Content that contains near-delimiters must survive intact
+        let file: Vec<u8> = b"\r\n--XyY\r\n--Xy\r\n-"
+            .iter()
+            .chain((0u8..=255).cycle().take(5000).collect::<Vec<_>>().iter())
+            .copied()
+            .collect();
+        let body = body(&file);
+
+        for chunk_size in [1, 3, 7, 64, body.len()] {
+            let dir = temp_dir(&format!("split_{}", chunk_size));
+            let mut stream = MultipartStream::new("XyZ", dir.clone());
+            for chunk in body.chunks(chunk_size) {
+                stream.feed(chunk).unwrap();
+            }
+            let parts = stream.finish().unwrap();
+
+            assert_eq!(parts.len(), 1, "form fields are skipped");
+            assert_eq!(parts[0].filename.as_deref(), Some("pic.png"));
+            assert_eq!(parts[0].content_type.as_deref(), Some("image/png"));
+            assert_eq!(parts[0].size, file.len() as u64);
+            assert_eq!(fs::read(&parts[0].path).unwrap(), file);
+            remove_part_files(&parts);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn test_malformed_stream_is_a_bad_request() {
+        let dir = temp_dir("malformed");
+        let mut stream = MultipartStream::new("XyZ", dir.clone());
+        assert!(matches!(
+            stream.feed(b"--XyZ!!"),
+            Err(ServerError::BadRequest(_))
+        ));
+
+        let mut stream = MultipartStream::new("XyZ", dir.clone());
+        stream.feed(b"--XyZ\r\n").unwrap();
+        assert!(matches!(
+            stream.feed(&vec![b'a'; MAX_PART_HEADER_SIZE + 1]),
+            Err(ServerError::BadRequest(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_incomplete_stream_fails_and_cleans_up() {
+        let dir = temp_dir("incomplete");
+        let full = body(b"partial data");
+        let mut stream = MultipartStream::new("XyZ", dir.clone());
+        stream.feed(&full[..full.len() - 12]).unwrap();
+        assert!(matches!(stream.finish(), Err(ServerError::BadRequest(_))));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
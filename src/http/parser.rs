@@ -1,8 +1,10 @@
 use crate::common::buffer::Buffer;
 use crate::common::constants::CRLF_BYTES;
 use crate::common::error::{Result, ServerError};
+use crate::http::body_spool::BodySpool;
 use crate::http::headers::Headers;
 use crate::http::method::Method;
+use crate::http::multipart::MultipartStream;
 use crate::http::request::Request;
 use crate::http::version::Version;
 use std::str;
@@ -27,11 +29,44 @@ pub struct RequestParser {
     expected_body_size: Option<usize>,
     header_lines: Vec<String>,
     max_body_size: usize,
+    /// Body limit for the current request once its route is known; replaces
+    /// `max_body_size` until the next reset
+    request_body_limit: Option<usize>,
+    /// Most bytes the request line and header lines may take together
+    max_header_size: usize,
+    /// Most header lines a request may carry
+    max_header_count: usize,
+    /// Most cookies read from a request's Cookie header
+    max_cookies: usize,
+    /// Most bytes a request's Cookie headers may take together
+    max_cookie_header_size: usize,
+    /// Bytes of the request head consumed so far
+    head_size: usize,
     current_body_size: usize,
     /// Accumulator for chunked body data; persists across parse() calls so that
     /// chunks already drained from `buffer` are not lost when we return
     /// `Ok(false)` waiting for the next CRLF/chunk to arrive.
     chunked_body: Vec<u8>,
+    /// Total bytes handed to `add_data` since the last reset
+    total_received: usize,
+    /// When set, a Content-Length body is fed here as it arrives instead of being buffered
+    multipart: Option<MultipartStream>,
+    /// When set, a Content-Length body is written here as it arrives instead of being buffered
+    spool: Option<BodySpool>,
+    /// Reject request and header lines ending in a bare LF instead of accepting them
+    strict_line_endings: bool,
+    /// Read and drop a body sent with GET/HEAD instead of rejecting the request
+    discard_get_body: bool,
+    /// Reject requests repeating a header that must appear at most once
+    reject_duplicate_headers: bool,
+    /// Collapse repeated slashes in the request path before it is routed
+    merge_slashes: bool,
+    /// Reject a `TE` header naming a transfer coding other than `trailers`/`chunked`
+    reject_unsupported_te: bool,
+    /// The last chunk has arrived and the trailer fields after it are being skipped
+    in_trailer_section: bool,
+    /// The current request's body is read only to be dropped
+    discarding_body: bool,
 }
 
 impl RequestParser {
@@ -42,6 +77,20 @@ impl RequestParser {
 
     /// Create a new parser with specified max body size
     pub fn with_max_body_size(max_body_size: usize) -> Self {
+        Self::with_limits(
+            max_body_size,
+            crate::common::constants::DEFAULT_MAX_HEADER_SIZE,
+            crate::common::constants::DEFAULT_MAX_HEADER_COUNT,
+        )
+    }
+
+    /// Create a new parser bounding the body and the request head; a head over
+    /// `max_header_size` bytes or `max_header_count` lines is rejected (431)
+    pub fn with_limits(
+        max_body_size: usize,
+        max_header_size: usize,
+        max_header_count: usize,
+    ) -> Self {
         Self {
             state: ParseState::RequestLine,
             buffer: Buffer::new(),
@@ -49,17 +98,76 @@ impl RequestParser {
             expected_body_size: None,
             header_lines: Vec::new(),
             max_body_size,
+            request_body_limit: None,
+            max_header_size,
+            max_header_count,
+            max_cookies: crate::common::constants::DEFAULT_MAX_COOKIES,
+            max_cookie_header_size: crate::common::constants::DEFAULT_MAX_COOKIE_HEADER_SIZE,
+            head_size: 0,
             current_body_size: 0,
             chunked_body: Vec::new(),
+            total_received: 0,
+            multipart: None,
+            spool: None,
+            strict_line_endings: false,
+            discard_get_body: false,
+            reject_duplicate_headers: true,
+            merge_slashes: true,
+            reject_unsupported_te: false,
+            in_trailer_section: false,
+            discarding_body: false,
         }
     }
 
+    /// Reject bare LF line endings in the request head (default: accept them like CRLF)
+    pub fn with_strict_line_endings(mut self, strict: bool) -> Self {
+        self.strict_line_endings = strict;
+        self
+    }
+
+    /// Read and drop bodies sent with GET/HEAD (default: reject such requests)
+    pub fn with_discard_get_body(mut self, discard: bool) -> Self {
+        self.discard_get_body = discard;
+        self
+    }
+
+    /// Reject a repeated `Content-Length`, `Host` or other singleton header (default: on)
+    pub fn with_reject_duplicate_headers(mut self, reject: bool) -> Self {
+        self.reject_duplicate_headers = reject;
+        self
+    }
+
+    /// Rewrite `/a//b` to `/a/b` so routing and file resolution see one path (default: on)
+    pub fn with_merge_slashes(mut self, merge: bool) -> Self {
+        self.merge_slashes = merge;
+        self
+    }
+
+    /// Reject `TE` values naming codings other than `trailers` (default: ignore them)
+    pub fn with_reject_unsupported_te(mut self, reject: bool) -> Self {
+        self.reject_unsupported_te = reject;
+        self
+    }
+
+    /// Bound the cookies each request reads and the size its Cookie headers may reach
+    pub fn with_cookie_limits(mut self, max_cookies: usize, max_cookie_header_size: usize) -> Self {
+        self.max_cookies = max_cookies;
+        self.max_cookie_header_size = max_cookie_header_size;
+        self
+    }
+
+    /// Body limit in force for the current request
+    fn body_limit(&self) -> usize {
+        self.request_body_limit.unwrap_or(self.max_body_size)
+    }
+
     /// Check if body size exceeds limit and return error if so (helper to reduce redundancy)
     fn check_body_size_limit(&self, size: usize) -> Result<()> {
-        if size > self.max_body_size {
+        if size > self.body_limit() {
             return Err(ServerError::HttpError(format!(
                 "Request body size {} exceeds maximum allowed size {}",
-                size, self.max_body_size
+                size,
+                self.body_limit()
             )));
         }
         Ok(())
@@ -68,10 +176,10 @@ impl RequestParser {
     /// Check if adding additional data would exceed body size limit (helper to reduce redundancy)
     fn check_would_exceed_limit(&self, current_size: usize, additional_size: usize) -> Result<()> {
         let total_size = current_size + additional_size;
-        if total_size > self.max_body_size {
+        if total_size > self.body_limit() {
             return Err(ServerError::HttpError(format!(
                 "Request body size would exceed maximum allowed size {}",
-                self.max_body_size
+                self.body_limit()
             )));
         }
         Ok(())
@@ -79,10 +187,11 @@ impl RequestParser {
 
     /// Check current body size against limit (helper to reduce redundancy)
     fn check_current_body_size(&self, current_size: usize) -> Result<()> {
-        if current_size > self.max_body_size {
+        if current_size > self.body_limit() {
             return Err(ServerError::HttpError(format!(
                 "Request body size {} exceeds maximum allowed size {}",
-                current_size, self.max_body_size
+                current_size,
+                self.body_limit()
             )));
         }
         Ok(())
@@ -101,9 +210,8 @@ impl RequestParser {
             // Before body parsing, we need to be more careful
             // Headers are still in buffer, so we can't accurately measure body size yet
             // But if total buffer is way too large, it's likely a problem
-            // Use a more lenient check: allow buffer up to max_body_size + reasonable header size (8KB)
-            let max_header_size = 8192;
-            if self.buffer.len() + data.len() > self.max_body_size + max_header_size {
+            // Use a more lenient check: allow buffer up to max_body_size + the header size limit
+            if self.buffer.len() + data.len() > self.max_body_size + self.max_header_size {
                 return Err(ServerError::HttpError(format!(
                     "Request body size would exceed maximum allowed size {}",
                     self.max_body_size
@@ -112,6 +220,7 @@ impl RequestParser {
             // Not in body state yet, so we can't determine if it's a body size error
             // Let it through and check later when we parse body
             self.buffer.extend(data);
+            self.total_received += data.len();
             return Ok(());
         };
 
@@ -120,9 +229,115 @@ impl RequestParser {
             self.check_would_exceed_limit(self.current_body_size, self.buffer.len() + data.len())?;
         }
         self.buffer.extend(data);
+        self.total_received += data.len();
         Ok(())
     }
 
+    /// Whether the headers are in, the client asked for `100 Continue` and no body
+    /// bytes have arrived yet
+    pub fn awaiting_continue(&self) -> bool {
+        matches!(self.state, ParseState::Body | ParseState::ChunkedBody)
+            && self.buffer.is_empty()
+            && self.current_body_size == 0
+            && self.chunked_body.is_empty()
+            && self
+                .request
+                .as_ref()
+                .map(|r| r.expects_continue())
+                .unwrap_or(false)
+    }
+
+    /// The request whose headers are in and whose body is still arriving
+    pub fn pending_request(&self) -> Option<&Request> {
+        match self.state {
+            ParseState::Body => self.request.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The request whose headers are in and whose body, of either framing, is still
+    /// arriving, until `limit_body_size` records how that body is to be read
+    pub fn request_awaiting_plan(&self) -> Option<&Request> {
+        match self.state {
+            ParseState::Body | ParseState::ChunkedBody if self.request_body_limit.is_none() => {
+                self.request.as_ref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Hold the body of the request being parsed to `limit` instead of the parser's own
+    /// limit, until the next reset (e.g. once its route is known). A declared
+    /// Content-Length already over it is rejected at once.
+    pub fn limit_body_size(&mut self, limit: usize) -> Result<()> {
+        self.request_body_limit = Some(limit);
+        match self.expected_body_size {
+            Some(length) => self.check_body_size_limit(length),
+            None => self.check_current_body_size(self.current_body_size),
+        }
+    }
+
+    /// Stream the rest of the pending request's body through `stream`, so the parts end up
+    /// in `Request::streamed_parts` and `Request::body` stays empty
+    pub fn stream_multipart(&mut self, stream: MultipartStream) {
+        self.multipart = Some(stream);
+    }
+
+    /// Write the rest of the pending request's body to `spool`, so it ends up in
+    /// `Request::body_file` and `Request::body` stays empty
+    pub fn spool_body(&mut self, spool: BodySpool) {
+        self.spool = Some(spool);
+    }
+
+    /// Whether the current body is being streamed to disk
+    pub fn is_streaming(&self) -> bool {
+        self.multipart.is_some() || self.spool.is_some()
+    }
+
+    /// Number of input bytes consumed by parsing so far
+    pub fn consumed(&self) -> usize {
+        self.total_received - self.buffer.len()
+    }
+
+    /// Number of input bytes buffered but not yet consumed
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Parse one request from a borrowed slice without touching this parser's state.
+    ///
+    /// The slice is parsed by a scratch parser with this parser's limits and options, so
+    /// the same bytes parse the same way here as through `add_data` and `parse`.
+    /// Returns the request and the number of bytes it occupied, or `(None, 0)` when the
+    /// slice doesn't hold a complete request yet (retry with more data). Bytes after the
+    /// consumed prefix belong to the next request.
+    pub fn parse_slice(&self, data: &[u8]) -> Result<(Option<Request>, usize)> {
+        let mut parser = self.configured_copy();
+        parser.add_data(data)?;
+        match parser.parse()? {
+            Some(request) => Ok((Some(request), parser.consumed())),
+            None => Ok((None, 0)),
+        }
+    }
+
+    /// A fresh parser with the same limits and options as this one
+    fn configured_copy(&self) -> Self {
+        Self {
+            max_cookies: self.max_cookies,
+            max_cookie_header_size: self.max_cookie_header_size,
+            strict_line_endings: self.strict_line_endings,
+            discard_get_body: self.discard_get_body,
+            reject_duplicate_headers: self.reject_duplicate_headers,
+            merge_slashes: self.merge_slashes,
+            reject_unsupported_te: self.reject_unsupported_te,
+            ..Self::with_limits(
+                self.max_body_size,
+                self.max_header_size,
+                self.max_header_count,
+            )
+        }
+    }
+
     /// Parse available data
     pub fn parse(&mut self) -> Result<Option<Request>> {
         loop {
@@ -146,9 +361,8 @@ impl RequestParser {
                 ParseState::Body => {
                     if self.parse_body()? {
                         self.state = ParseState::Complete;
-                        if let Some(mut request) = self.request.take() {
-                            request.parse_query_params();
-                            return Ok(Some(request));
+                        if let Some(request) = self.request.take() {
+                            return Ok(Some(self.finish_request(request)));
                         }
                     } else {
                         return Ok(None); // Need more data
@@ -157,9 +371,8 @@ impl RequestParser {
                 ParseState::ChunkedBody => {
                     if self.parse_chunked_body()? {
                         self.state = ParseState::Complete;
-                        if let Some(mut request) = self.request.take() {
-                            request.parse_query_params();
-                            return Ok(Some(request));
+                        if let Some(request) = self.request.take() {
+                            return Ok(Some(self.finish_request(request)));
                         }
                     } else {
                         return Ok(None); // Need more data
@@ -175,11 +388,58 @@ impl RequestParser {
         }
     }
 
+    /// Final touches on a fully read request
+    fn finish_request(&self, mut request: Request) -> Request {
+        if self.discarding_body {
+            request.body.clear();
+        }
+        request.parse_query_params();
+        request
+    }
+
+    /// Take the next line of the request head, without its terminator. CRLF ends a line;
+    /// a bare LF does too unless strict line endings are on, where it's an error.
+    fn take_line(&mut self) -> Result<Option<Vec<u8>>> {
+        let Some(lf_pos) = self.buffer.find(b"\n") else {
+            return Ok(None); // Need more data
+        };
+        let mut line = self.buffer.drain(lf_pos + 1);
+        line.pop();
+        if line.last() == Some(&CRLF_BYTES[0]) {
+            line.pop();
+        } else if self.strict_line_endings {
+            return Err(ServerError::BadRequest(
+                "Bare LF line ending in request head".to_string(),
+            ));
+        }
+        Ok(Some(line))
+    }
+
+    /// Take the next line of the request head, counting it against `max_header_size`.
+    /// An unterminated line counts too, so a head that never ends can't grow unbounded.
+    fn take_head_line(&mut self) -> Result<Option<Vec<u8>>> {
+        let buffered = self.buffer.len();
+        let line = self.take_line()?;
+        let pending = match line {
+            Some(_) => {
+                self.head_size += buffered - self.buffer.len();
+                0
+            }
+            None => buffered,
+        };
+        if self.head_size + pending > self.max_header_size {
+            return Err(ServerError::HeaderFieldsTooLarge(format!(
+                "head exceeds {} bytes",
+                self.max_header_size
+            )));
+        }
+        Ok(line)
+    }
+
     /// Parse request line: "METHOD /path HTTP/1.1\r\n"
     fn parse_request_line(&mut self) -> Result<Option<Request>> {
-        if let Some(crlf_pos) = self.buffer.find(CRLF_BYTES) {
-            let line_bytes = self.buffer.drain(crlf_pos + CRLF_BYTES.len());
-            let line = str::from_utf8(&line_bytes[..crlf_pos]).map_err(|e| {
+        if let Some(line_bytes) = self.take_head_line()? {
+            let line = str::from_utf8(&line_bytes).map_err(|e| {
                 ServerError::ParseError(format!("Invalid UTF-8 in request line: {}", e))
             })?;
 
@@ -190,11 +450,7 @@ impl RequestParser {
                 ));
             }
 
-            let method = Method::from_str(parts[0])
-                .map_err(|e| ServerError::ParseError(format!("Invalid method: {}", e)))?;
-
-            let target = parts[1].to_string();
-
+            // The version is checked first so a garbled line isn't mistaken for an unknown method
             let version = if parts.len() >= 3 {
                 Version::from_str(parts[2])
                     .map_err(|e| ServerError::ParseError(format!("Invalid version: {}", e)))?
@@ -202,7 +458,27 @@ impl RequestParser {
                 Version::Http11 // Default to HTTP/1.1
             };
 
-            Ok(Some(Request::new(method, target, version)))
+            // Methods are case-sensitive: a well-formed but unknown token (including a
+            // mis-cased one like `get`) is unsupported, anything else is malformed
+            let method = Method::from_str(parts[0]).map_err(|e| {
+                if Method::is_token(parts[0]) {
+                    ServerError::MethodNotImplemented(parts[0].to_string())
+                } else {
+                    ServerError::BadRequest(format!("Invalid method: {}", e))
+                }
+            })?;
+
+            let target = parts[1].to_string();
+
+            let mut request = Request::new(method, target, version);
+            request.max_cookies = self.max_cookies;
+            request.max_cookie_header_size = self.max_cookie_header_size;
+            // Decoded before routing, so route matching and file resolution see the same path
+            request.decode_path();
+            if self.merge_slashes {
+                request.collapse_slashes();
+            }
+            Ok(Some(request))
         } else {
             Ok(None) // Need more data
         }
@@ -211,9 +487,8 @@ impl RequestParser {
     /// Parse headers until empty line
     fn parse_headers(&mut self) -> Result<bool> {
         loop {
-            if let Some(crlf_pos) = self.buffer.find(CRLF_BYTES) {
-                let line_bytes = self.buffer.drain(crlf_pos + CRLF_BYTES.len());
-                let line = str::from_utf8(&line_bytes[..crlf_pos]).map_err(|e| {
+            if let Some(line_bytes) = self.take_head_line()? {
+                let line = str::from_utf8(&line_bytes).map_err(|e| {
                     ServerError::ParseError(format!("Invalid UTF-8 in header: {}", e))
                 })?;
 
@@ -222,6 +497,12 @@ impl RequestParser {
                     return Ok(true);
                 }
 
+                if self.header_lines.len() >= self.max_header_count {
+                    return Err(ServerError::HeaderFieldsTooLarge(format!(
+                        "more than {} headers",
+                        self.max_header_count
+                    )));
+                }
                 self.header_lines.push(line.to_string());
             } else {
                 return Ok(false); // Need more data
@@ -237,13 +518,69 @@ impl RequestParser {
                 request.headers = headers;
             }
 
+            // Differing lengths let a proxy and us disagree on where the request ends,
+            // so they are refused even when repeated headers are allowed (RFC 9112 §6.3)
+            if request.headers.has_conflicting_values("Content-Length") {
+                return Err(ServerError::BadRequest(
+                    "Conflicting Content-Length headers".to_string(),
+                ));
+            }
+            // Identical copies of a singleton header are refused only on request
+            if self.reject_duplicate_headers {
+                if let Some(name) = request.headers.duplicate_singleton() {
+                    return Err(ServerError::BadRequest(format!(
+                        "Duplicate {} header",
+                        name
+                    )));
+                }
+            }
+
+            // TE lists the codings the client takes in the response; we only ever send
+            // chunked, and trailers are fine to announce
+            if self.reject_unsupported_te {
+                if let Some(coding) = request.unsupported_te_coding() {
+                    return Err(ServerError::BadRequest(format!(
+                        "Unsupported TE coding: {}",
+                        coding
+                    )));
+                }
+            }
+
+            // A body on GET/HEAD has no meaning and is a request smuggling vector
+            let has_body = request.is_chunked() || request.content_length().unwrap_or(0) > 0;
+            if has_body && matches!(request.method, Method::GET | Method::HEAD) {
+                if !self.discard_get_body {
+                    return Err(ServerError::BadRequest(format!(
+                        "Unexpected body on {} request",
+                        request.method
+                    )));
+                }
+                self.discarding_body = true;
+            }
+
             // Check for chunked encoding
             if request.is_chunked() {
                 self.state = ParseState::ChunkedBody;
                 return Ok(());
             }
 
-            // Get Content-Length
+            // A declared length too large to represent exceeds any limit - reject it
+            // now rather than reading the body as if no length had been sent
+            if let Some(declared) = request.headers.get("Content-Length") {
+                let declared = declared.trim();
+                if !declared.is_empty()
+                    && declared.bytes().all(|b| b.is_ascii_digit())
+                    && declared.parse::<usize>().is_err()
+                {
+                    return Err(ServerError::HttpError(format!(
+                        "Request body size {} exceeds maximum allowed size {}",
+                        declared, self.max_body_size
+                    )));
+                }
+            }
+
+            // Get Content-Length - a declared size over the limit is rejected before any
+            // body bytes are read
             if let Some(length) = request.content_length() {
                 // Check if body size exceeds limit
                 self.check_body_size_limit(length)?;
@@ -275,6 +612,10 @@ impl RequestParser {
                 // Check if we're exceeding max body size
                 self.check_would_exceed_limit(current_size, available)?;
 
+                if self.is_streaming() {
+                    return self.stream_body(expected_size);
+                }
+
                 if available >= expected_size {
                     let body = self.buffer.drain(expected_size);
                     self.current_body_size += body.len();
@@ -318,6 +659,38 @@ impl RequestParser {
         Ok(false) // Need more data
     }
 
+    /// Feed the body bytes received so far to the multipart stream or spool, finishing it once
+    /// `expected_size` bytes have gone through
+    fn stream_body(&mut self, expected_size: usize) -> Result<bool> {
+        let take = self
+            .buffer
+            .len()
+            .min(expected_size.saturating_sub(self.current_body_size));
+        if take > 0 {
+            let chunk = self.buffer.drain(take);
+            self.current_body_size += chunk.len();
+            if let Some(stream) = self.multipart.as_mut() {
+                stream.feed(&chunk)?;
+            } else if let Some(spool) = self.spool.as_mut() {
+                spool.feed(&chunk)?;
+            }
+        }
+        if self.current_body_size < expected_size {
+            return Ok(false);
+        }
+
+        let parts = match self.multipart.take() {
+            Some(stream) => stream.finish()?,
+            None => Vec::new(),
+        };
+        let body_file = self.spool.take().map(BodySpool::finish).transpose()?;
+        if let Some(ref mut request) = self.request {
+            request.streamed_parts = parts;
+            request.body_file = body_file;
+        }
+        Ok(true)
+    }
+
     /// Parse chunked body.
     ///
     /// Chunks already extracted from `self.buffer` are appended to
@@ -326,6 +699,10 @@ impl RequestParser {
     /// chunk we have already consumed from the network buffer.
     fn parse_chunked_body(&mut self) -> Result<bool> {
         loop {
+            if self.in_trailer_section {
+                return self.skip_trailer_section();
+            }
+
             // Parse chunk size line
             if let Some(crlf_pos) = self.buffer.find(CRLF_BYTES) {
                 let line_bytes = self.buffer.drain(crlf_pos + CRLF_BYTES.len());
@@ -342,20 +719,10 @@ impl RequestParser {
                 let current_size = self.current_body_size;
 
                 if chunk_size == 0 {
-                    // Last chunk - read trailing CRLF
-                    if self.buffer.len() >= CRLF_BYTES.len() {
-                        self.buffer.drain(CRLF_BYTES.len());
-                    }
-
-                    // Final check for max body size
+                    // Last chunk - trailer fields (if any) and the final CRLF follow
                     self.check_current_body_size(current_size)?;
-
-                    // Move accumulated body into the request
-                    let body = std::mem::take(&mut self.chunked_body);
-                    if let Some(ref mut request) = self.request {
-                        request.body = body;
-                    }
-                    return Ok(true);
+                    self.in_trailer_section = true;
+                    continue;
                 }
 
                 // Check if adding this chunk would exceed max body size
@@ -383,6 +750,33 @@ impl RequestParser {
         }
     }
 
+    /// Skip the trailer fields after the last chunk up to the empty line ending the
+    /// request, then hand over the body. Trailers are not merged into the headers.
+    fn skip_trailer_section(&mut self) -> Result<bool> {
+        loop {
+            let Some(crlf_pos) = self.buffer.find(CRLF_BYTES) else {
+                if self.buffer.len() > self.max_header_size {
+                    return Err(ServerError::HeaderFieldsTooLarge(format!(
+                        "trailer exceeds {} bytes",
+                        self.max_header_size
+                    )));
+                }
+                return Ok(false); // Need more data
+            };
+            self.buffer.drain(crlf_pos + CRLF_BYTES.len());
+            if crlf_pos == 0 {
+                break;
+            }
+        }
+
+        // Move accumulated body into the request
+        let body = std::mem::take(&mut self.chunked_body);
+        if let Some(ref mut request) = self.request {
+            request.body = body;
+        }
+        Ok(true)
+    }
+
     /// Reset parser for new request
     pub fn reset(&mut self) {
         self.state = ParseState::RequestLine;
@@ -390,8 +784,24 @@ impl RequestParser {
         self.request = None;
         self.expected_body_size = None;
         self.header_lines.clear();
+        self.head_size = 0;
         self.current_body_size = 0;
         self.chunked_body.clear();
+        self.total_received = 0;
+        self.multipart = None;
+        self.spool = None;
+        self.in_trailer_section = false;
+        self.discarding_body = false;
+        self.request_body_limit = None;
+    }
+
+    /// Reset for the next request on the connection, keeping bytes received after the
+    /// last complete request (a pipelined request) as the start of the next one
+    pub fn reset_keeping_leftover(&mut self) {
+        let leftover = self.buffer.as_slice();
+        self.reset();
+        self.buffer.extend(&leftover);
+        self.total_received = leftover.len();
     }
 
     /// Check if parser is in error state
@@ -462,6 +872,23 @@ mod tests {
         assert!(result.is_err(), "invalid method must produce ParseError");
     }
 
+    #[test]
+    fn test_method_errors_distinguish_unknown_from_malformed() {
+        let parse_err = |line: &str| {
+            let mut parser = RequestParser::new();
+            parser.add_data(line.as_bytes()).unwrap();
+            parser.parse().map(|_| ()).unwrap_err()
+        };
+        assert!(matches!(
+            parse_err("get / HTTP/1.1\r\n\r\n"),
+            ServerError::MethodNotImplemented(method) if method == "get"
+        ));
+        assert!(matches!(
+            parse_err("G(T / HTTP/1.1\r\n\r\n"),
+            ServerError::BadRequest(msg) if msg.starts_with("Invalid method")
+        ));
+    }
+
     #[test]
     fn test_parse_invalid_version_returns_error() {
         let request_str = "GET / HTTP/2.0\r\nHost: localhost\r\n\r\n";
@@ -645,4 +1072,446 @@ mod tests {
         let request = parser.parse().unwrap().unwrap();
         assert_eq!(request.body, body);
     }
+
+    #[test]
+    fn test_body_limit_for_one_request() {
+        // A raised limit admits a body the parser's own limit would refuse
+        let mut parser = RequestParser::with_max_body_size(4);
+        parser
+            .add_data(b"POST /up HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n")
+            .unwrap();
+        assert!(parser.parse().unwrap().is_none());
+        assert!(parser.request_awaiting_plan().is_some());
+        parser.limit_body_size(64).unwrap();
+        // Planned once per request
+        assert!(parser.request_awaiting_plan().is_none());
+        parser.add_data(b"6\r\nabcdef\r\n0\r\n\r\n").unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().body, b"abcdef");
+
+        // ...until the next request, which is back to the parser's own
+        parser.reset();
+        parser
+            .add_data(b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 6\r\n\r\nabcdef")
+            .unwrap();
+        assert!(parser.parse().is_err());
+
+        // A lowered limit refuses a declared length at once
+        let mut parser = RequestParser::with_max_body_size(100);
+        parser
+            .add_data(b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 50\r\n\r\n")
+            .unwrap();
+        assert!(parser.parse().unwrap().is_none());
+        assert!(parser.limit_body_size(10).is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // Slice-based API and consumption accounting
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn test_parse_slice_partial_input_consumes_nothing() {
+        let parser = RequestParser::new();
+        let full = b"POST /form HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhello";
+
+        for end in [0, 10, 30, full.len() - 1] {
+            let (request, consumed) = parser.parse_slice(&full[..end]).unwrap();
+            assert!(request.is_none(), "prefix of {} bytes is incomplete", end);
+            assert_eq!(consumed, 0);
+        }
+    }
+
+    #[test]
+    fn test_parse_slice_complete_input_reports_consumed() {
+        let parser = RequestParser::new();
+        let first = b"POST /form HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhello";
+        let mut pipelined = first.to_vec();
+        pipelined.extend_from_slice(b"GET /next HTTP/1.1\r\nHost: x\r\n\r\n");
+
+        let (request, consumed) = parser.parse_slice(&pipelined).unwrap();
+        let request = request.unwrap();
+        assert_eq!(request.path(), "/form");
+        assert_eq!(request.body, b"hello");
+        assert_eq!(consumed, first.len());
+
+        let (next, rest) = parser.parse_slice(&pipelined[consumed..]).unwrap();
+        assert_eq!(next.unwrap().path(), "/next");
+        assert_eq!(consumed + rest, pipelined.len());
+        // The parser's own buffer was never used
+        assert_eq!(parser.buffered(), 0);
+    }
+
+    #[test]
+    fn test_parse_slice_uses_the_parser_options() {
+        let bare_lf = b"GET /a//b HTTP/1.1\nHost: x\n\n";
+
+        let lenient = RequestParser::new().with_merge_slashes(false);
+        let (request, _) = lenient.parse_slice(bare_lf).unwrap();
+        assert_eq!(request.unwrap().path(), "/a//b");
+
+        let strict = RequestParser::new().with_strict_line_endings(true);
+        assert!(strict.parse_slice(bare_lf).is_err());
+        let mut buffered = RequestParser::new().with_strict_line_endings(true);
+        buffered.add_data(bare_lf).unwrap();
+        assert!(buffered.parse().is_err());
+
+        let get_with_body = b"GET / HTTP/1.1\r\nHost: x\r\nContent-Length: 3\r\n\r\nabc";
+        let discarding = RequestParser::new().with_discard_get_body(true);
+        let (request, consumed) = discarding.parse_slice(get_with_body).unwrap();
+        assert!(request.unwrap().body.is_empty());
+        assert_eq!(consumed, get_with_body.len());
+        assert!(RequestParser::new().parse_slice(get_with_body).is_err());
+    }
+
+    #[test]
+    fn test_consumed_and_buffered_with_add_data() {
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"GET /a HTTP/1.1\r\nHost: x\r\n\r\nGET /b")
+            .unwrap();
+        assert_eq!(parser.consumed(), 0);
+
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.path(), "/a");
+        assert_eq!(parser.consumed(), 28);
+        assert_eq!(parser.buffered(), 6);
+
+        parser.reset();
+        assert_eq!(parser.consumed(), 0);
+        assert_eq!(parser.buffered(), 0);
+    }
+
+    #[test]
+    fn test_reset_keeping_leftover_parses_pipelined_request() {
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"GET /a HTTP/1.1\r\nHost: x\r\n\r\nGET /b HTTP/1.1\r\n")
+            .unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().path(), "/a");
+
+        parser.reset_keeping_leftover();
+        assert_eq!(parser.consumed(), 0);
+        assert_eq!(parser.buffered(), 17);
+        assert!(parser.parse().unwrap().is_none());
+        parser.add_data(b"Host: x\r\n\r\n").unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().path(), "/b");
+    }
+
+    #[test]
+    fn test_bare_lf_line_endings_lenient_and_strict() {
+        let data = b"POST /a HTTP/1.1\nHost: x\r\nContent-Length: 2\n\nhi";
+
+        let mut parser = RequestParser::new();
+        parser.add_data(data).unwrap();
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.path(), "/a");
+        assert_eq!(request.headers.get("host"), Some(&"x".to_string()));
+        assert_eq!(request.body, b"hi");
+
+        let mut parser = RequestParser::new().with_strict_line_endings(true);
+        parser.add_data(data).unwrap();
+        assert!(matches!(
+            parser.parse(),
+            Err(ServerError::BadRequest(msg)) if msg.starts_with("Bare LF")
+        ));
+    }
+
+    #[test]
+    fn test_get_body_rejected_by_default() {
+        for data in [
+            &b"GET / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello"[..],
+            b"HEAD / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+        ] {
+            let mut parser = RequestParser::new();
+            parser.add_data(data).unwrap();
+            assert!(matches!(
+                parser.parse(),
+                Err(ServerError::BadRequest(msg)) if msg.starts_with("Unexpected body")
+            ));
+        }
+
+        // An explicit empty body is no body
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        assert!(parser.parse().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_repeated_slashes_collapsed_in_path_only() {
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"GET /a//b///c.txt?next=//x HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.path(), "/a/b/c.txt");
+        assert_eq!(request.query_string(), Some("next=//x"));
+
+        let mut parser = RequestParser::new().with_merge_slashes(false);
+        parser.add_data(b"GET /a//b HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().path(), "/a//b");
+    }
+
+    #[test]
+    fn test_get_body_discarded_when_configured() {
+        let mut parser = RequestParser::new().with_discard_get_body(true);
+        parser
+            .add_data(b"GET /a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloGET /b HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.path(), "/a");
+        assert!(request.body.is_empty());
+
+        // The dropped body doesn't bleed into the next request
+        parser.reset_keeping_leftover();
+        assert_eq!(parser.parse().unwrap().unwrap().path(), "/b");
+
+        // POST bodies are untouched
+        let mut parser = RequestParser::new().with_discard_get_body(true);
+        parser
+            .add_data(b"POST /a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello")
+            .unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().body, b"hello");
+    }
+
+    #[test]
+    fn test_duplicate_singleton_header_rejected() {
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"POST / HTTP/1.1\r\nContent-Length: 5\r\ncontent-length: 5\r\n\r\nhello")
+            .unwrap();
+        assert!(matches!(
+            parser.parse(),
+            Err(ServerError::BadRequest(msg)) if msg == "Duplicate Content-Length header"
+        ));
+
+        // Repeatable headers are kept, every value
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"GET / HTTP/1.1\r\nAccept: text/html\r\nAccept: application/json\r\n\r\n")
+            .unwrap();
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.headers.get_all("Accept").unwrap().len(), 2);
+
+        let mut parser = RequestParser::new().with_reject_duplicate_headers(false);
+        parser
+            .add_data(b"POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 5\r\n\r\nhello")
+            .unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().body, b"hello");
+    }
+
+    #[test]
+    fn test_differing_content_lengths_always_rejected() {
+        for head in [
+            &b"POST / HTTP/1.1\r\nContent-Length: 5\r\ncontent-length: 50\r\n\r\nhello"[..],
+            &b"POST / HTTP/1.1\r\nContent-Length: 5, 50\r\n\r\nhello"[..],
+        ] {
+            for reject_duplicates in [true, false] {
+                let mut parser =
+                    RequestParser::new().with_reject_duplicate_headers(reject_duplicates);
+                parser.add_data(head).unwrap();
+                assert!(matches!(
+                    parser.parse(),
+                    Err(ServerError::BadRequest(msg)) if msg == "Conflicting Content-Length headers"
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_awaiting_continue_until_body_arrives() {
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"POST /up HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nContent-Length: 4\r\n\r\n")
+            .unwrap();
+        assert!(!parser.awaiting_continue());
+        assert!(parser.parse().unwrap().is_none());
+        assert!(parser.awaiting_continue());
+
+        parser.add_data(b"da").unwrap();
+        assert!(parser.parse().unwrap().is_none());
+        assert!(!parser.awaiting_continue());
+        parser.add_data(b"ta").unwrap();
+        assert_eq!(parser.parse().unwrap().unwrap().body, b"data");
+
+        let mut plain = RequestParser::new();
+        plain
+            .add_data(b"POST /up HTTP/1.1\r\nHost: x\r\nContent-Length: 4\r\n\r\n")
+            .unwrap();
+        assert!(plain.parse().unwrap().is_none());
+        assert!(!plain.awaiting_continue());
+    }
+
+    #[test]
+    fn test_oversized_content_length_rejected_after_headers() {
+        for declared in ["1000000", "99999999999999999999999999"] {
+            let mut parser = RequestParser::with_max_body_size(1024);
+            parser
+                .add_data(
+                    format!(
+                        "POST /up HTTP/1.1\r\nHost: x\r\nContent-Length: {}\r\n\r\n",
+                        declared
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+            match parser.parse() {
+                Err(ServerError::HttpError(msg)) => {
+                    assert!(msg.contains("exceeds maximum allowed size"), "{}", msg)
+                }
+                other => panic!("expected a body size error, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_streamed_multipart_body_is_not_buffered() {
+        let dir =
+            std::env::temp_dir().join(format!("localhost_parser_stream_{}", std::process::id()));
+        let content = vec![b'z'; 64 * 1024];
+        let mut body =
+            b"--b\r\nContent-Disposition: form-data; name=\"f\"; filename=\"z.txt\"\r\n\r\n"
+                .to_vec();
+        body.extend_from_slice(&content);
+        body.extend_from_slice(b"\r\n--b--\r\n");
+
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(
+                format!(
+                    "POST /up HTTP/1.1\r\nHost: x\r\nContent-Type: multipart/form-data; boundary=b\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        assert!(parser.parse().unwrap().is_none());
+        assert!(parser.pending_request().is_some());
+        parser.stream_multipart(MultipartStream::new("b", dir.clone()));
+
+        let mut request = None;
+        for chunk in body.chunks(4096) {
+            parser.add_data(chunk).unwrap();
+            request = parser.parse().unwrap();
+            // Body bytes go straight to the part file instead of piling up in the buffer
+            assert_eq!(parser.buffered(), 0);
+        }
+
+        let request = request.unwrap();
+        assert!(request.body.is_empty());
+        assert_eq!(request.streamed_parts.len(), 1);
+        assert_eq!(
+            std::fs::read(&request.streamed_parts[0].path).unwrap(),
+            content
+        );
+        crate::http::multipart::remove_part_files(&request.streamed_parts);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_oversized_header_is_rejected_while_streaming_in() {
+        let mut parser = RequestParser::new();
+        parser
+            .add_data(b"GET / HTTP/1.1\r\nHost: x\r\nX-Big: ")
+            .unwrap();
+        assert!(parser.parse().unwrap().is_none());
+
+        // 80KB of a header that never ends: the parser gives up once past the limit
+        let chunk = [b'a'; 1024];
+        let mut fed = 0;
+        let error = loop {
+            assert!(fed < 80, "parser buffered an 80KB header");
+            parser.add_data(&chunk).unwrap();
+            fed += 1;
+            if let Err(e) = parser.parse() {
+                break e;
+            }
+        };
+        assert!(fed <= 9);
+        assert!(error
+            .to_string()
+            .contains("Request header fields too large"));
+    }
+
+    #[test]
+    fn test_header_limits_are_configurable() {
+        let head = |headers: usize| {
+            let mut head = "GET / HTTP/1.1\r\n".to_string();
+            for i in 0..headers {
+                head.push_str(&format!("X-H{}: v\r\n", i));
+            }
+            head + "\r\n"
+        };
+        let parse = |parser: &mut RequestParser, data: &str| {
+            parser.add_data(data.as_bytes()).unwrap();
+            parser.parse()
+        };
+
+        let mut parser = RequestParser::with_limits(1024, 4096, 10);
+        assert!(parse(&mut parser, &head(10)).unwrap().is_some());
+        let mut parser = RequestParser::with_limits(1024, 4096, 10);
+        let error = parse(&mut parser, &head(11)).unwrap_err();
+        assert!(error.to_string().contains("more than 10 headers"));
+
+        // The request line counts towards the size limit too
+        let mut parser = RequestParser::with_limits(1024, 32, 10);
+        let error = parse(
+            &mut parser,
+            "GET /a-rather-long-path-to-something HTTP/1.1\r\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("exceeds 32 bytes"));
+    }
+
+    #[test]
+    fn test_chunked_trailer_fields_are_skipped() {
+        let parts: &[&[u8]] = &[
+            b"POST / HTTP/1.1\r\nHost: x\r\nTE: trailers\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\n",
+            b"X-Checksum: abc\r\n",
+            b"X-Other: 1\r\n\r\nGET /next HTTP/1.1\r\n",
+        ];
+        let mut parser = RequestParser::new().with_reject_unsupported_te(true);
+        parser.add_data(parts[0]).unwrap();
+        assert!(parser.parse().unwrap().is_none());
+        parser.add_data(parts[1]).unwrap();
+        assert!(parser.parse().unwrap().is_none());
+        parser.add_data(parts[2]).unwrap();
+
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.body, b"Hello");
+        assert!(request.headers.get("X-Checksum").is_none());
+        // Only the pipelined request after the trailer section is left over
+        assert_eq!(parser.buffered(), b"GET /next HTTP/1.1\r\n".len());
+    }
+
+    #[test]
+    fn test_unsupported_te_rejected_only_when_configured() {
+        let head = b"GET / HTTP/1.1\r\nHost: x\r\nTE: gzip\r\n\r\n";
+
+        let mut parser = RequestParser::new().with_reject_unsupported_te(true);
+        parser.add_data(head).unwrap();
+        let error = parser.parse().unwrap_err();
+        assert!(error.to_string().contains("Unsupported TE coding: gzip"));
+
+        let mut parser = RequestParser::new();
+        parser.add_data(head).unwrap();
+        assert!(parser.parse().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_cookie_limits_reach_the_request() {
+        let head = b"GET / HTTP/1.1\r\nHost: x\r\nCookie: a=1; b=2; c=3\r\n\r\n";
+
+        let mut parser = RequestParser::new().with_cookie_limits(2, 12);
+        parser.add_data(head).unwrap();
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.cookies().len(), 2);
+        assert!(request.has_oversized_cookie_header());
+
+        let mut parser = RequestParser::new();
+        parser.add_data(head).unwrap();
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.cookies().len(), 3);
+        assert!(!request.has_oversized_cookie_header());
+    }
 }
@@ -0,0 +1,363 @@
+use crate::common::constants::MAX_BYTE_RANGES;
+use crate::http::headers::names as header_names;
+use crate::http::method::Method;
+use crate::http::request::Request;
+use crate::http::response::Response;
+use crate::http::status::StatusCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sequence number keeping multipart boundaries unique within the process
+static BOUNDARY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Inclusive byte range within a representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// `Content-Range` value for this range of a representation of `total` bytes
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+/// Outcome of evaluating a `Range` header against a representation length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// Header absent, malformed or not in bytes: serve the full representation
+    Ignore,
+    /// At least one range overlaps the representation
+    Satisfiable(Vec<ByteRange>),
+    /// Well-formed, but no range overlaps the representation
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header value (`bytes=0-99,200-`, `bytes=-500`) for a representation of `len` bytes.
+/// Ranges starting past the end are dropped, ends are clamped to the last byte, and
+/// overlapping or adjacent ranges are merged in ascending order. Headers listing more than
+/// `MAX_BYTE_RANGES` ranges are ignored, so a short request can't demand a huge response.
+pub fn parse_range(value: &str, len: u64) -> RangeRequest {
+    let specs = match value.trim().split_once('=') {
+        Some((unit, specs)) if unit.trim().eq_ignore_ascii_case("bytes") => specs,
+        _ => return RangeRequest::Ignore,
+    };
+    if specs
+        .split(',')
+        .filter(|spec| !spec.trim().is_empty())
+        .count()
+        > MAX_BYTE_RANGES
+    {
+        return RangeRequest::Ignore;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',') {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            continue;
+        }
+        let (first, last) = match spec.split_once('-') {
+            Some(parts) => parts,
+            None => return RangeRequest::Ignore,
+        };
+        let (first, last) = (first.trim(), last.trim());
+
+        let range = if first.is_empty() {
+            // Suffix range: the last N bytes
+            let suffix = match last.parse::<u64>() {
+                Ok(n) => n,
+                Err(_) => return RangeRequest::Ignore,
+            };
+            if suffix == 0 || len == 0 {
+                None
+            } else {
+                Some(ByteRange {
+                    start: len.saturating_sub(suffix),
+                    end: len - 1,
+                })
+            }
+        } else {
+            let start = match first.parse::<u64>() {
+                Ok(n) => n,
+                Err(_) => return RangeRequest::Ignore,
+            };
+            let end = if last.is_empty() {
+                None
+            } else {
+                match last.parse::<u64>() {
+                    Ok(n) if n >= start => Some(n),
+                    _ => return RangeRequest::Ignore,
+                }
+            };
+            if start >= len {
+                None
+            } else {
+                Some(ByteRange {
+                    start,
+                    end: end.map_or(len - 1, |e| e.min(len - 1)),
+                })
+            }
+        };
+        ranges.extend(range);
+    }
+
+    if ranges.is_empty() {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Satisfiable(merge_ranges(ranges))
+    }
+}
+
+/// Sort ranges and coalesce any that overlap or touch
+fn merge_ranges(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Answer a GET or HEAD `Range` request from `response`'s complete body of `content_type`:
+/// 206 with the slice for one range, 206 `multipart/byteranges` for several, 416 when
+/// none overlaps. Advertises `Accept-Ranges` either way and keeps other headers.
+/// Returns whether the response became partial or unsatisfiable.
+pub fn apply(request: &Request, response: &mut Response, content_type: &str) -> bool {
+    if !matches!(request.method, Method::GET | Method::HEAD) {
+        return false;
+    }
+    response
+        .headers
+        .set(header_names::ACCEPT_RANGES.to_string(), "bytes".to_string());
+    let Some(value) = request.range() else {
+        return false;
+    };
+
+    let total = response.body.len() as u64;
+    match parse_range(value, total) {
+        RangeRequest::Ignore => false,
+        RangeRequest::Unsatisfiable => {
+            response.status = StatusCode::RANGE_NOT_SATISFIABLE;
+            response.headers.set(
+                header_names::CONTENT_RANGE.to_string(),
+                format!("bytes */{}", total),
+            );
+            response.set_content_type("text/plain");
+            response.set_body_str("Range Not Satisfiable");
+            true
+        }
+        RangeRequest::Satisfiable(ranges) => {
+            response.status = StatusCode::PARTIAL_CONTENT;
+            let content = std::mem::take(&mut response.body);
+            if let [single] = ranges.as_slice() {
+                response.headers.set(
+                    header_names::CONTENT_RANGE.to_string(),
+                    single.content_range(total),
+                );
+                response.set_content_type(content_type);
+                response.set_body(content[single.start as usize..=single.end as usize].to_vec());
+                return true;
+            }
+
+            let boundary = generate_boundary();
+            response.set_content_type(&format!("multipart/byteranges; boundary={}", boundary));
+            response.set_body(multipart_byteranges(
+                &content,
+                &ranges,
+                content_type,
+                &boundary,
+            ));
+            true
+        }
+    }
+}
+
+/// Boundary for a `multipart/byteranges` body, unique per call
+pub fn generate_boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!(
+        "localhost_{:x}_{:x}",
+        nanos,
+        BOUNDARY_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Build a `multipart/byteranges` body: one part per range, each with its own
+/// `Content-Type` and `Content-Range`, closed by the final boundary.
+pub fn multipart_byteranges(
+    content: &[u8],
+    ranges: &[ByteRange],
+    content_type: &str,
+    boundary: &str,
+) -> Vec<u8> {
+    let total = content.len() as u64;
+    let mut body = Vec::new();
+    for range in ranges {
+        body.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                boundary,
+                content_type,
+                range.content_range(total)
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&content[range.start as usize..=range.end as usize]);
+    }
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> ByteRange {
+        ByteRange { start, end }
+    }
+
+    #[test]
+    fn test_parse_multiple_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-99,200-299", 1000),
+            RangeRequest::Satisfiable(vec![range(0, 99), range(200, 299)])
+        );
+        assert_eq!(
+            parse_range("bytes=500-599, 0-9", 1000),
+            RangeRequest::Satisfiable(vec![range(0, 9), range(500, 599)])
+        );
+    }
+
+    #[test]
+    fn test_parse_merges_overlapping_and_adjacent_ranges() {
+        assert_eq!(
+            parse_range("bytes=900-, -50", 1000),
+            RangeRequest::Satisfiable(vec![range(900, 999)])
+        );
+        assert_eq!(
+            parse_range("bytes=10-19,0-9,30-39,35-50", 1000),
+            RangeRequest::Satisfiable(vec![range(0, 19), range(30, 50)])
+        );
+        assert_eq!(
+            parse_range("bytes=0-,0-,0-", 1000),
+            RangeRequest::Satisfiable(vec![range(0, 999)])
+        );
+    }
+
+    #[test]
+    fn test_parse_ignores_too_many_ranges() {
+        let many = vec!["0-"; MAX_BYTE_RANGES + 1].join(",");
+        assert_eq!(
+            parse_range(&format!("bytes={}", many), 1000),
+            RangeRequest::Ignore
+        );
+        let allowed = vec!["0-"; MAX_BYTE_RANGES].join(",");
+        assert_eq!(
+            parse_range(&format!("bytes={}", allowed), 1000),
+            RangeRequest::Satisfiable(vec![range(0, 999)])
+        );
+    }
+
+    #[test]
+    fn test_parse_clamps_and_drops_out_of_bounds() {
+        assert_eq!(
+            parse_range("bytes=0-5000,2000-3000", 1000),
+            RangeRequest::Satisfiable(vec![range(0, 999)])
+        );
+        assert_eq!(
+            parse_range("bytes=1000-,2000-2001", 1000),
+            RangeRequest::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn test_parse_ignores_malformed_headers() {
+        for value in ["items=0-5", "bytes=abc", "bytes=5-1", "bytes=0-x", "0-5"] {
+            assert_eq!(parse_range(value, 100), RangeRequest::Ignore, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_multipart_byteranges_layout() {
+        let content: Vec<u8> = (0u8..=255).collect();
+        let body = multipart_byteranges(&content, &[range(0, 3), range(10, 11)], "image/png", "B");
+
+        let mut expected =
+            b"\r\n--B\r\nContent-Type: image/png\r\nContent-Range: bytes 0-3/256\r\n\r\n".to_vec();
+        expected.extend_from_slice(&[0, 1, 2, 3]);
+        expected.extend_from_slice(
+            b"\r\n--B\r\nContent-Type: image/png\r\nContent-Range: bytes 10-11/256\r\n\r\n",
+        );
+        expected.extend_from_slice(&[10, 11]);
+        expected.extend_from_slice(b"\r\n--B--\r\n");
+        assert_eq!(body, expected);
+    }
+
+    fn ranged(method: Method, value: &str) -> (bool, Response) {
+        let mut request = Request::new(
+            method,
+            "/".to_string(),
+            crate::http::version::Version::Http11,
+        );
+        request.headers.set("Range".to_string(), value.to_string());
+        let mut response = Response::ok(request.version);
+        response.set_content_type("text/csv");
+        response.set_body_str("0123456789");
+        let applied = apply(&request, &mut response, "text/csv");
+        (applied, response)
+    }
+
+    #[test]
+    fn test_apply_slices_single_and_multiple_ranges() {
+        let (applied, response) = ranged(Method::GET, "bytes=2-4");
+        assert!(applied);
+        assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body, b"234");
+        assert_eq!(
+            response.headers.get(header_names::CONTENT_RANGE).unwrap(),
+            "bytes 2-4/10"
+        );
+        assert_eq!(
+            response.headers.get(header_names::CONTENT_TYPE).unwrap(),
+            "text/csv"
+        );
+
+        let (_, response) = ranged(Method::HEAD, "bytes=0-0,8-");
+        let content_type = response.headers.get(header_names::CONTENT_TYPE).unwrap();
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("Content-Type: text/csv\r\nContent-Range: bytes 8-9/10\r\n\r\n89"));
+    }
+
+    #[test]
+    fn test_apply_answers_416_and_skips_other_methods() {
+        let (applied, response) = ranged(Method::GET, "bytes=50-");
+        assert!(applied);
+        assert_eq!(response.status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers.get(header_names::CONTENT_RANGE).unwrap(),
+            "bytes */10"
+        );
+
+        let (applied, response) = ranged(Method::POST, "bytes=2-4");
+        assert!(!applied);
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, b"0123456789");
+    }
+
+    #[test]
+    fn test_boundaries_are_unique() {
+        assert_ne!(generate_boundary(), generate_boundary());
+    }
+}
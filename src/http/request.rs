@@ -1,8 +1,11 @@
-use crate::http::cookie::parse_cookie_header;
+use crate::common::constants::{DEFAULT_MAX_COOKIES, DEFAULT_MAX_COOKIE_HEADER_SIZE};
+use crate::http::cookie::parse_cookie_header_limited;
 use crate::http::headers::Headers;
 use crate::http::method::Method;
+use crate::http::multipart::StreamedPart;
 use crate::http::version::Version;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// HTTP request structure
 #[derive(Debug, Clone)]
@@ -22,8 +25,20 @@ pub struct Request {
     /// Request body
     pub body: Vec<u8>,
 
+    /// File parts of a large multipart body that was streamed to disk instead of `body`
+    pub streamed_parts: Vec<StreamedPart>,
+
+    /// Temporary file holding a large body spooled to disk instead of `body`
+    pub body_file: Option<PathBuf>,
+
     /// Parsed query parameters
     pub query_params: HashMap<String, String>,
+
+    /// Most cookies `cookies()` reads from the Cookie header
+    pub max_cookies: usize,
+
+    /// Most bytes the Cookie headers may take before `has_oversized_cookie_header` holds
+    pub max_cookie_header_size: usize,
 }
 
 impl Request {
@@ -35,7 +50,11 @@ impl Request {
             version,
             headers: Headers::new(),
             body: Vec::new(),
+            streamed_parts: Vec::new(),
+            body_file: None,
             query_params: HashMap::new(),
+            max_cookies: DEFAULT_MAX_COOKIES,
+            max_cookie_header_size: DEFAULT_MAX_COOKIE_HEADER_SIZE,
         }
     }
 
@@ -48,6 +67,25 @@ impl Request {
         }
     }
 
+    /// Collapse repeated slashes in the path, leaving the query string untouched
+    pub fn collapse_slashes(&mut self) {
+        let path = crate::common::path_utils::collapse_slashes(self.path());
+        if path.len() != self.path().len() {
+            let query = self.query_string().map(|q| format!("?{}", q));
+            self.target = path + query.as_deref().unwrap_or("");
+        }
+    }
+
+    /// Decode `%XX` escapes in the path, leaving the query string untouched. A decoded
+    /// `?` is re-escaped as `%3F` so it cannot be mistaken for the start of the query.
+    pub fn decode_path(&mut self) {
+        let path = crate::common::path_utils::percent_decode(self.path()).replace('?', "%3F");
+        if path != self.path() {
+            let query = self.query_string().map(|q| format!("?{}", q));
+            self.target = path + query.as_deref().unwrap_or("");
+        }
+    }
+
     /// Get the query string part
     pub fn query_string(&self) -> Option<&str> {
         self.target.find('?').map(|pos| &self.target[pos + 1..])
@@ -56,24 +94,25 @@ impl Request {
     /// Parse query parameters from target
     pub fn parse_query_params(&mut self) {
         if let Some(query_pos) = self.target.find('?') {
-            let query = self.target[query_pos + 1..].to_string();
-            let mut params = Vec::new();
-
-            for pair in query.split('&') {
-                if let Some(equal_pos) = pair.find('=') {
-                    let key = url_decode(&pair[..equal_pos]);
-                    let value = url_decode(&pair[equal_pos + 1..]);
-                    params.push((key, value));
-                } else if !pair.is_empty() {
-                    let key = url_decode(pair);
-                    params.push((key, String::new()));
-                }
-            }
+            let params = parse_urlencoded(&self.target[query_pos + 1..]);
+            self.query_params.extend(params);
+        }
+    }
 
-            for (key, value) in params {
-                self.query_params.insert(key, value);
-            }
+    /// Fields of an `application/x-www-form-urlencoded` body; empty for any other
+    /// content type (including a body streamed to disk)
+    pub fn form_params(&self) -> HashMap<String, String> {
+        let is_form = self.content_type().is_some_and(|ct| {
+            ct.split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        });
+        if !is_form {
+            return HashMap::new();
         }
+        parse_urlencoded(&String::from_utf8_lossy(&self.body))
     }
 
     /// Get Content-Length header value
@@ -106,6 +145,44 @@ impl Request {
         }
     }
 
+    /// Check if the client asks to upgrade to cleartext HTTP/2 (`Upgrade: h2c`)
+    pub fn is_h2c_upgrade(&self) -> bool {
+        self.headers
+            .get("Upgrade")
+            .map(|v| v.split(',').any(|p| p.trim().eq_ignore_ascii_case("h2c")))
+            .unwrap_or(false)
+    }
+
+    /// Drop an h2c upgrade offer so the request is served as plain HTTP/1.1.
+    /// Removes `Upgrade`/`HTTP2-Settings` and their tokens from `Connection`
+    /// (leaving e.g. `close` intact). Returns true if an offer was dropped.
+    pub fn ignore_h2c_upgrade(&mut self) -> bool {
+        if !self.is_h2c_upgrade() {
+            return false;
+        }
+
+        self.headers.remove("Upgrade");
+        self.headers.remove("HTTP2-Settings");
+        if let Some(connection) = self.connection().cloned() {
+            let remaining: Vec<&str> = connection
+                .split(',')
+                .map(|t| t.trim())
+                .filter(|t| {
+                    !t.is_empty()
+                        && !t.eq_ignore_ascii_case("upgrade")
+                        && !t.eq_ignore_ascii_case("http2-settings")
+                })
+                .collect();
+            if remaining.is_empty() {
+                self.headers.remove("Connection");
+            } else {
+                self.headers
+                    .set("Connection".to_string(), remaining.join(", "));
+            }
+        }
+        true
+    }
+
     /// Get Transfer-Encoding header value
     pub fn transfer_encoding(&self) -> Option<&String> {
         self.headers.get("Transfer-Encoding")
@@ -123,20 +200,147 @@ impl Request {
         self.headers.get("Content-Type")
     }
 
+    /// Get Range header value
+    pub fn range(&self) -> Option<&String> {
+        self.headers.get("Range")
+    }
+
+    /// Get Expect header value
+    pub fn expect(&self) -> Option<&String> {
+        self.headers.get("Expect")
+    }
+
+    /// Check if the client waits for `100 Continue` before sending its body
+    pub fn expects_continue(&self) -> bool {
+        self.expect()
+            .map(|v| v.trim().eq_ignore_ascii_case("100-continue"))
+            .unwrap_or(false)
+    }
+
+    /// Check if the request carries an Expect value other than 100-continue,
+    /// which must be answered with 417 Expectation Failed
+    pub fn has_unsupported_expectation(&self) -> bool {
+        self.expect()
+            .map(|v| !v.trim().eq_ignore_ascii_case("100-continue"))
+            .unwrap_or(false)
+    }
+
+    /// Get TE header value
+    pub fn te(&self) -> Option<&String> {
+        self.headers.get("TE")
+    }
+
+    /// First coding the TE header asks for that the server can't send: anything but
+    /// `trailers` and `chunked`, ignoring codings refused with q=0
+    pub fn unsupported_te_coding(&self) -> Option<String> {
+        self.te()?.split(',').find_map(|entry| {
+            let mut params = entry.split(';');
+            let coding = params.next().unwrap_or("").trim().to_ascii_lowercase();
+            let refused = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            let supported = coding.is_empty() || coding == "trailers" || coding == "chunked";
+            (!supported && !refused).then_some(coding)
+        })
+    }
+
+    /// Languages from the Accept-Language header, lowercased and ordered by
+    /// descending q-value (languages with q=0, the `*` wildcard and anything that isn't
+    /// a plain `[A-Za-z0-9-]{1,35}` tag are dropped)
+    pub fn accept_languages(&self) -> Vec<String> {
+        let mut languages: Vec<(String, f32)> = self
+            .headers
+            .get("Accept-Language")
+            .map(|header| {
+                header
+                    .split(',')
+                    .filter_map(|item| {
+                        let mut parts = item.split(';');
+                        let tag = parts.next()?.trim().to_ascii_lowercase();
+                        let q = parts
+                            .filter_map(|p| p.trim().strip_prefix("q="))
+                            .find_map(|v| v.trim().parse::<f32>().ok())
+                            .unwrap_or(1.0);
+                        // Tags end up in file names, so nothing but letters, digits and '-'
+                        let valid = (1..=35).contains(&tag.len())
+                            && tag.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+                        (valid && q > 0.0).then_some((tag, q))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Stable sort keeps header order for equal q-values
+        languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        languages.into_iter().map(|(tag, _)| tag).collect()
+    }
+
+    /// Media ranges from the Accept header with their q-values, lowercased and in header
+    /// order. Entries with q=0 are kept since they exclude a type.
+    pub fn accept_media_ranges(&self) -> Vec<(String, f32)> {
+        self.headers
+            .get("Accept")
+            .map(|header| {
+                header
+                    .split(',')
+                    .filter_map(|item| {
+                        let mut parts = item.split(';');
+                        let range = parts.next()?.trim().to_ascii_lowercase();
+                        let q = parts
+                            .filter_map(|p| p.trim().strip_prefix("q="))
+                            .find_map(|v| v.trim().parse::<f32>().ok())
+                            .unwrap_or(1.0);
+                        (!range.is_empty()).then_some((range, q))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Get all cookies from Cookie header
     pub fn cookies(&self) -> HashMap<String, String> {
         self.headers
             .get("Cookie")
-            .map(|header| parse_cookie_header(header))
+            .map(|header| parse_cookie_header_limited(header, self.max_cookies))
             .unwrap_or_default()
     }
 
+    /// Check if the Cookie header(s) exceed `max_cookie_header_size` bytes in total,
+    /// which is answered with 400 rather than parsed
+    pub fn has_oversized_cookie_header(&self) -> bool {
+        self.headers
+            .get_all("Cookie")
+            .map(|values| values.iter().map(String::len).sum::<usize>())
+            .unwrap_or(0)
+            > self.max_cookie_header_size
+    }
+
     /// Get a specific cookie value by name
     pub fn cookie(&self, name: &str) -> Option<String> {
         self.cookies().get(name).cloned()
     }
 }
 
+/// Decode `key=value&key2=value2` pairs; a key without `=` gets an empty value and a
+/// repeated key keeps its last value
+fn parse_urlencoded(s: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    for pair in s.split('&') {
+        if let Some(equal_pos) = pair.find('=') {
+            let key = url_decode(&pair[..equal_pos]);
+            let value = url_decode(&pair[equal_pos + 1..]);
+            params.insert(key, value);
+        } else if !pair.is_empty() {
+            params.insert(url_decode(pair), String::new());
+        }
+    }
+    params
+}
+
 /// URL decode function
 fn url_decode(s: &str) -> String {
     let mut result = String::new();
@@ -177,6 +381,18 @@ mod tests {
         assert_eq!(req.path(), "/path");
     }
 
+    #[test]
+    fn test_decode_path_keeps_the_query_string() {
+        let mut req = Request::new(
+            Method::GET,
+            "/my%20file%3F.txt?q=%20".to_string(),
+            Version::Http11,
+        );
+        req.decode_path();
+        assert_eq!(req.path(), "/my file%3F.txt");
+        assert_eq!(req.query_string(), Some("q=%20"));
+    }
+
     #[test]
     fn test_query_string() {
         let req = Request::new(Method::GET, "/path?key=value".to_string(), Version::Http11);
@@ -195,6 +411,30 @@ mod tests {
         assert_eq!(req.query_params.get("key2"), Some(&"value2".to_string()));
     }
 
+    #[test]
+    fn test_form_params_from_urlencoded_body() {
+        let mut req = Request::new(Method::POST, "/submit?page=2".to_string(), Version::Http11);
+        req.body = b"name=Jane+Doe&email=jane%40example.com&note=50%25+off&flag&empty=".to_vec();
+        assert!(req.form_params().is_empty());
+
+        req.headers.set(
+            "Content-Type".to_string(),
+            "Application/X-WWW-Form-Urlencoded; charset=UTF-8".to_string(),
+        );
+        let form = req.form_params();
+        assert_eq!(form.get("name"), Some(&"Jane Doe".to_string()));
+        assert_eq!(form.get("email"), Some(&"jane@example.com".to_string()));
+        assert_eq!(form.get("note"), Some(&"50% off".to_string()));
+        assert_eq!(form.get("flag"), Some(&String::new()));
+        assert_eq!(form.get("empty"), Some(&String::new()));
+        // The query string stays in query_params
+        assert_eq!(form.get("page"), None);
+
+        req.headers
+            .set("Content-Type".to_string(), "text/plain".to_string());
+        assert!(req.form_params().is_empty());
+    }
+
     #[test]
     fn test_keep_alive() {
         let mut req = Request::new(Method::GET, "/".to_string(), Version::Http11);
@@ -204,4 +444,119 @@ mod tests {
             .set("Connection".to_string(), "close".to_string());
         assert!(!req.should_keep_alive());
     }
+
+    #[test]
+    fn test_unsupported_te_coding() {
+        let mut req = Request::new(Method::GET, "/".to_string(), Version::Http11);
+        assert_eq!(req.unsupported_te_coding(), None);
+
+        for (te, unsupported) in [
+            ("trailers", None),
+            ("Trailers, chunked", None),
+            ("gzip;q=0, trailers", None),
+            ("trailers, gzip;q=0.5", Some("gzip")),
+            ("deflate", Some("deflate")),
+        ] {
+            req.headers.set("TE".to_string(), te.to_string());
+            assert_eq!(
+                req.unsupported_te_coding().as_deref(),
+                unsupported,
+                "{}",
+                te
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsupported_expectation() {
+        let mut req = Request::new(Method::POST, "/".to_string(), Version::Http11);
+        assert!(!req.has_unsupported_expectation());
+
+        req.headers
+            .set("Expect".to_string(), "100-Continue".to_string());
+        assert!(!req.has_unsupported_expectation());
+
+        req.headers.set("Expect".to_string(), "200-ok".to_string());
+        assert!(req.has_unsupported_expectation());
+    }
+
+    #[test]
+    fn test_accept_languages_ordered_by_q_value() {
+        let mut req = Request::new(Method::GET, "/".to_string(), Version::Http11);
+        assert!(req.accept_languages().is_empty());
+
+        req.headers.set(
+            "Accept-Language".to_string(),
+            "en;q=0.5, fr-CA, de;q=0, *;q=0.1, FR;q=0.9".to_string(),
+        );
+        assert_eq!(req.accept_languages(), vec!["fr-ca", "fr", "en"]);
+
+        req.headers.set(
+            "Accept-Language".to_string(),
+            "0/../../secret, ..;q=0.9, en\\x, de.at, x-abcdefghijklmnopqrstuvwxyz0123456789, it;q=0.5"
+                .to_string(),
+        );
+        assert_eq!(req.accept_languages(), vec!["it"]);
+    }
+
+    #[test]
+    fn test_accept_media_ranges() {
+        let mut req = Request::new(Method::GET, "/".to_string(), Version::Http11);
+        assert!(req.accept_media_ranges().is_empty());
+
+        req.headers.set(
+            "Accept".to_string(),
+            "Text/HTML, application/json;q=0.5, image/*;q=0".to_string(),
+        );
+        assert_eq!(
+            req.accept_media_ranges(),
+            vec![
+                ("text/html".to_string(), 1.0),
+                ("application/json".to_string(), 0.5),
+                ("image/*".to_string(), 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignore_h2c_upgrade() {
+        let mut req = Request::new(Method::GET, "/".to_string(), Version::Http11);
+        assert!(!req.ignore_h2c_upgrade());
+
+        req.headers.set("Upgrade".to_string(), "h2c".to_string());
+        req.headers.set(
+            "Connection".to_string(),
+            "Upgrade, HTTP2-Settings".to_string(),
+        );
+        req.headers.set(
+            "HTTP2-Settings".to_string(),
+            "AAMAAABkAARAAAAAAAIAAAAA".to_string(),
+        );
+        assert!(req.ignore_h2c_upgrade());
+        assert!(req.headers.get("Upgrade").is_none());
+        assert!(req.headers.get("HTTP2-Settings").is_none());
+        assert!(req.connection().is_none());
+        assert!(req.should_keep_alive());
+
+        req.headers.set("Upgrade".to_string(), "h2c".to_string());
+        req.headers
+            .set("Connection".to_string(), "Upgrade, close".to_string());
+        assert!(req.ignore_h2c_upgrade());
+        assert_eq!(req.connection(), Some(&"close".to_string()));
+    }
+
+    #[test]
+    fn test_oversized_cookie_header() {
+        let mut request = Request::new(Method::GET, "/".to_string(), Version::Http11);
+        assert!(!request.has_oversized_cookie_header());
+
+        request
+            .headers
+            .set("Cookie".to_string(), "a=1; b=2".to_string());
+        assert!(!request.has_oversized_cookie_header());
+
+        let big = format!("big={}", "x".repeat(DEFAULT_MAX_COOKIE_HEADER_SIZE));
+        request.headers.set("Cookie".to_string(), big);
+        assert!(request.has_oversized_cookie_header());
+    }
 }
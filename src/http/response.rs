@@ -1,3 +1,4 @@
+use crate::common::time::format_http_date;
 use crate::http::cookie::Cookie;
 use crate::http::headers::{names as header_names, Headers};
 use crate::http::status::StatusCode;
@@ -118,6 +119,13 @@ impl Response {
         response
     }
 
+    /// Create a 502 Bad Gateway response with message
+    pub fn bad_gateway_with_message(version: Version, message: &str) -> Self {
+        let mut response = Self::new(version, StatusCode::BAD_GATEWAY);
+        response.set_body_str(message);
+        response
+    }
+
     /// Create a 504 Gateway Timeout response with message
     pub fn gateway_timeout_with_message(version: Version, message: &str) -> Self {
         let mut response = Self::new(version, StatusCode::GATEWAY_TIMEOUT);
@@ -136,10 +144,10 @@ impl Response {
         );
 
         // Set Date header
-        if let Ok(duration) = SystemTime::UNIX_EPOCH.elapsed() {
-            let date = format_http_date(duration.as_secs());
-            self.headers.set(header_names::DATE.to_string(), date);
-        }
+        self.headers.set(
+            header_names::DATE.to_string(),
+            format_http_date(SystemTime::now()),
+        );
     }
 
     /// Set Content-Type header
@@ -168,6 +176,22 @@ impl Response {
             .set(header_names::CONNECTION.to_string(), connection.to_string());
     }
 
+    /// Add a request header name to the Vary header, keeping existing entries
+    pub fn add_vary(&mut self, header: &str) {
+        let value = match self.headers.get(header_names::VARY) {
+            Some(existing)
+                if existing
+                    .split(',')
+                    .any(|h| h.trim().eq_ignore_ascii_case(header)) =>
+            {
+                return;
+            }
+            Some(existing) => format!("{}, {}", existing, header),
+            None => header.to_string(),
+        };
+        self.headers.set(header_names::VARY.to_string(), value);
+    }
+
     /// Set body and update Content-Length
     pub fn set_body(&mut self, body: Vec<u8>) {
         self.body = body;
@@ -191,11 +215,38 @@ impl Response {
         self.headers.remove(header_names::CONTENT_LENGTH);
     }
 
+    /// Drop the body of a response to HEAD, keeping the headers (`Content-Length`
+    /// included) that the same GET would have been sent
+    pub fn omit_body(&mut self) {
+        // A body set without its length still reports the length it would have had
+        if !self.chunked && !self.headers.contains(header_names::CONTENT_LENGTH) {
+            self.set_content_length(self.body.len());
+        }
+        self.body.clear();
+        // Transfer-Encoding stays advertised, but no chunk framing is sent
+        self.chunked = false;
+    }
+
     /// Check if response has body
     pub fn has_body(&self) -> bool {
         self.status.allows_body() && !self.body.is_empty()
     }
 
+    /// Whether the client can find the end of the body without the connection closing
+    pub fn has_definite_length(&self) -> bool {
+        !self.status.allows_body()
+            || self.chunked
+            || self.headers.contains(header_names::CONTENT_LENGTH)
+    }
+
+    /// Whether the response carries `Connection: close`
+    pub fn closes_connection(&self) -> bool {
+        self.headers
+            .get(header_names::CONNECTION)
+            .map(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case("close")))
+            .unwrap_or(false)
+    }
+
     /// Get Content-Length
     pub fn content_length(&self) -> Option<usize> {
         if self.chunked {
@@ -226,42 +277,35 @@ impl Response {
     }
 }
 
-/// Format HTTP date (RFC 7231)
-/// Returns date in format: Wed, 21 Oct 2015 07:28:00 GMT
-/// Note: This is a simplified implementation. For production, use chrono crate.
-fn format_http_date(_timestamp: u64) -> String {
-    // For now, return current date in HTTP format
-    // In production, this should use chrono::Utc::now().format("%a, %d %b %Y %H:%M:%S GMT")
-    // Simplified version - returns a valid HTTP date format
-    use std::time::SystemTime;
-
-    // Get current time
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    // Simple date calculation (not accurate, but functional)
-    // Proper implementation would use chrono or similar
-    let days_since_epoch = now / 86400;
-    let day_of_week = (days_since_epoch + 4) % 7; // Jan 1, 1970 was Thursday
-    let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
-
-    // Calculate approximate date (simplified)
-    let year = 1970 + (days_since_epoch / 365);
-    let day = (days_since_epoch % 365) + 1;
-    let month = "Jan"; // Simplified - always Jan for now
-
-    format!(
-        "{}, {:02} {} {} 12:00:00 GMT",
-        days[day_of_week as usize], day, month, year
-    )
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_date_header_is_current_time() {
+        let before = format_http_date(SystemTime::now());
+        let response = Response::ok(Version::Http11);
+        let after = format_http_date(SystemTime::now());
+
+        let date = response.headers.get("Date").unwrap();
+        assert!(*date == before || *date == after, "{}", date);
+    }
+
+    #[test]
+    fn test_omit_body_keeps_the_get_length() {
+        let mut resp = Response::ok(Version::Http11);
+        resp.set_body_str("Hello, World!");
+        resp.omit_body();
+        assert!(resp.body.is_empty());
+        assert_eq!(resp.headers.get("Content-Length"), Some(&"13".to_string()));
+
+        // Assigned directly, the body has no Content-Length until the HEAD strips it
+        let mut resp = Response::ok(Version::Http11);
+        resp.body = b"listing".to_vec();
+        resp.omit_body();
+        assert_eq!(resp.headers.get("Content-Length"), Some(&"7".to_string()));
+    }
+
     #[test]
     fn test_response_creation() {
         let resp = Response::ok(Version::Http11);
@@ -1,8 +1,27 @@
-use crate::common::constants::CRLF;
+use crate::common::constants::{CRLF, DEFAULT_RESPONSE_CHUNK_SIZE};
 use crate::common::error::{Result, ServerError};
+use crate::http::headers::{names as header_names, Headers};
 use crate::http::response::Response;
 use std::io::Write;
 
+/// Wire-format choices applied when serializing a response
+#[derive(Debug, Clone, Copy)]
+pub struct SerializeOptions {
+    /// Largest chunk for chunked bodies (0 = the whole body as one chunk)
+    pub chunk_size: usize,
+    /// Write header names in canonical casing (`content-type` -> `Content-Type`)
+    pub canonical_header_names: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_RESPONSE_CHUNK_SIZE,
+            canonical_header_names: false,
+        }
+    }
+}
+
 /// Serialize HTTP response to bytes
 pub struct ResponseSerializer;
 
@@ -21,16 +40,39 @@ impl ResponseSerializer {
         Ok(())
     }
 
+    /// Write headers to buffer. Statuses that can't carry a body (1xx, 204, 304)
+    /// never advertise a length or transfer coding.
+    fn write_headers(buffer: &mut Vec<u8>, response: &Response, options: &SerializeOptions) {
+        let serialize = |headers: &Headers| {
+            if options.canonical_header_names {
+                headers.to_canonical_string()
+            } else {
+                headers.to_string()
+            }
+        };
+        if response.status.allows_body() {
+            buffer.extend_from_slice(serialize(&response.headers).as_bytes());
+        } else {
+            let mut headers = response.headers.clone();
+            headers.remove(header_names::CONTENT_LENGTH);
+            headers.remove(header_names::TRANSFER_ENCODING);
+            buffer.extend_from_slice(serialize(&headers).as_bytes());
+        }
+    }
+
     /// Serialize response to bytes
     pub fn serialize(response: &Response) -> Result<Vec<u8>> {
+        Self::serialize_plain(response, &SerializeOptions::default())
+    }
+
+    fn serialize_plain(response: &Response, options: &SerializeOptions) -> Result<Vec<u8>> {
         let mut buffer = Vec::new();
 
         // Status line
         Self::write_status_line(&mut buffer, response)?;
 
         // Headers
-        let headers_str = response.headers.to_string();
-        buffer.extend_from_slice(headers_str.as_bytes());
+        Self::write_headers(&mut buffer, response, options);
 
         // Empty line after headers
         buffer.extend_from_slice(CRLF.as_bytes());
@@ -43,27 +85,40 @@ impl ResponseSerializer {
         Ok(buffer)
     }
 
-    /// Serialize response with chunked encoding
+    /// Serialize response with chunked encoding, in chunks of the default size
     pub fn serialize_chunked(response: &Response) -> Result<Vec<u8>> {
+        Self::serialize_chunked_with(response, &SerializeOptions::default())
+    }
+
+    /// Serialize response with chunked encoding, framing the body as chunks of at most
+    /// `options.chunk_size` bytes
+    pub fn serialize_chunked_with(
+        response: &Response,
+        options: &SerializeOptions,
+    ) -> Result<Vec<u8>> {
         let mut buffer = Vec::new();
 
         // Status line
         Self::write_status_line(&mut buffer, response)?;
 
         // Headers
-        let headers_str = response.headers.to_string();
-        buffer.extend_from_slice(headers_str.as_bytes());
+        Self::write_headers(&mut buffer, response, options);
 
         // Empty line after headers
         buffer.extend_from_slice(CRLF.as_bytes());
 
         // Chunked body
-        if !response.body.is_empty() {
+        let chunk_size = if options.chunk_size == 0 {
+            response.body.len().max(1)
+        } else {
+            options.chunk_size
+        };
+        for chunk in response.body.chunks(chunk_size) {
             // Write chunk size and data
-            write!(buffer, "{:x}{}", response.body.len(), CRLF).map_err(|e| {
+            write!(buffer, "{:x}{}", chunk.len(), CRLF).map_err(|e| {
                 ServerError::HttpError(format!("Failed to write chunk size: {}", e))
             })?;
-            buffer.extend_from_slice(&response.body);
+            buffer.extend_from_slice(chunk);
             buffer.extend_from_slice(CRLF.as_bytes());
         }
 
@@ -77,10 +132,16 @@ impl ResponseSerializer {
 
     /// Serialize response (automatically chooses chunked or regular)
     pub fn serialize_auto(response: &Response) -> Result<Vec<u8>> {
-        if response.chunked {
-            Self::serialize_chunked(response)
+        Self::serialize_with(response, &SerializeOptions::default())
+    }
+
+    /// Like `serialize_auto`, with explicit wire-format options
+    pub fn serialize_with(response: &Response, options: &SerializeOptions) -> Result<Vec<u8>> {
+        // Bodyless statuses get no chunk framing either
+        if response.chunked && response.status.allows_body() {
+            Self::serialize_chunked_with(response, options)
         } else {
-            Self::serialize(response)
+            Self::serialize_plain(response, options)
         }
     }
 }
@@ -88,6 +149,7 @@ impl ResponseSerializer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::status::StatusCode;
     use crate::http::version::Version;
 
     #[test]
@@ -110,4 +172,98 @@ mod tests {
         assert!(text.contains("200 OK"));
         assert!(text.contains("Transfer-Encoding: chunked"));
     }
+
+    #[test]
+    fn test_serialize_304_has_validators_but_no_length_or_body() {
+        let mut response = Response::new(Version::Http11, StatusCode::NOT_MODIFIED);
+        response
+            .headers
+            .set("ETag".to_string(), "\"abc123\"".to_string());
+        // A handler mistakenly setting a body must not leak it onto the wire
+        response.set_body_str("stale body");
+
+        let text =
+            String::from_utf8(ResponseSerializer::serialize_auto(&response).unwrap()).unwrap();
+        assert!(text.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert!(text.contains("ETag: \"abc123\"\r\n"));
+        assert!(!text.contains("Content-Length"));
+        assert!(text.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_serialize_204_chunked_has_no_framing() {
+        let mut response = Response::new(Version::Http11, StatusCode::NO_CONTENT);
+        response.set_chunked();
+        response.set_body_str("ignored");
+
+        let text =
+            String::from_utf8(ResponseSerializer::serialize_auto(&response).unwrap()).unwrap();
+        assert!(!text.contains("Transfer-Encoding"));
+        assert!(!text.contains("Content-Length"));
+        assert!(text.ends_with("\r\n\r\n"));
+        assert!(!text.contains("ignored"));
+    }
+
+    #[test]
+    fn test_serialize_chunked_splits_large_body() {
+        let mut response = Response::ok(Version::Http11);
+        response.set_chunked();
+        response.set_body(vec![b'a'; 40 * 1024]);
+
+        let options = SerializeOptions {
+            chunk_size: 16 * 1024,
+            ..SerializeOptions::default()
+        };
+        let bytes = ResponseSerializer::serialize_chunked_with(&response, &options).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        let body = &text[text.find("\r\n\r\n").unwrap() + 4..];
+        let expected = format!(
+            "4000\r\n{a16}\r\n4000\r\n{a16}\r\n2000\r\n{a8}\r\n0\r\n\r\n",
+            a16 = "a".repeat(16 * 1024),
+            a8 = "a".repeat(8 * 1024)
+        );
+        assert_eq!(body, expected);
+
+        // The default size applies too, and 0 keeps the whole body in one chunk
+        let default =
+            String::from_utf8(ResponseSerializer::serialize_chunked(&response).unwrap()).unwrap();
+        assert_eq!(default.matches("\r\n4000\r\n").count(), 2);
+        let single = String::from_utf8(
+            ResponseSerializer::serialize_chunked_with(
+                &response,
+                &SerializeOptions {
+                    chunk_size: 0,
+                    ..SerializeOptions::default()
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(single.contains("\r\na000\r\n"));
+    }
+
+    #[test]
+    fn test_canonical_header_names_option() {
+        let mut response = Response::ok(Version::Http11);
+        response
+            .headers
+            .set("content-type".to_string(), "text/plain".to_string());
+        response
+            .headers
+            .set("x-cgi-extra".to_string(), "1".to_string());
+
+        let options = SerializeOptions {
+            canonical_header_names: true,
+            ..SerializeOptions::default()
+        };
+        let text =
+            String::from_utf8(ResponseSerializer::serialize_with(&response, &options).unwrap())
+                .unwrap();
+        assert!(text.contains("\r\nContent-Type: text/plain\r\n"));
+        assert!(text.contains("\r\nX-Cgi-Extra: 1\r\n"));
+
+        let raw =
+            String::from_utf8(ResponseSerializer::serialize_auto(&response).unwrap()).unwrap();
+        assert!(raw.contains("\r\ncontent-type: text/plain\r\n"));
+    }
 }
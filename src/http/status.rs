@@ -53,9 +53,11 @@ impl StatusCode {
     /// Get reason phrase for common status codes
     pub fn reason_phrase(&self) -> &'static str {
         match self.0 {
+            100 => "Continue",
             200 => "OK",
             201 => "Created",
             204 => "No Content",
+            206 => "Partial Content",
             301 => "Moved Permanently",
             302 => "Found",
             304 => "Not Modified",
@@ -63,7 +65,14 @@ impl StatusCode {
             403 => "Forbidden",
             404 => "Not Found",
             405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            408 => "Request Timeout",
             413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            421 => "Misdirected Request",
+            431 => "Request Header Fields Too Large",
             500 => "Internal Server Error",
             501 => "Not Implemented",
             502 => "Bad Gateway",
@@ -82,9 +91,11 @@ impl fmt::Display for StatusCode {
 
 // Common status codes
 impl StatusCode {
+    pub const CONTINUE: StatusCode = StatusCode(100);
     pub const OK: StatusCode = StatusCode(200);
     pub const CREATED: StatusCode = StatusCode(201);
     pub const NO_CONTENT: StatusCode = StatusCode(204);
+    pub const PARTIAL_CONTENT: StatusCode = StatusCode(206);
     pub const MOVED_PERMANENTLY: StatusCode = StatusCode(301);
     pub const FOUND: StatusCode = StatusCode(302);
     pub const NOT_MODIFIED: StatusCode = StatusCode(304);
@@ -92,7 +103,14 @@ impl StatusCode {
     pub const FORBIDDEN: StatusCode = StatusCode(403);
     pub const NOT_FOUND: StatusCode = StatusCode(404);
     pub const METHOD_NOT_ALLOWED: StatusCode = StatusCode(405);
+    pub const NOT_ACCEPTABLE: StatusCode = StatusCode(406);
+    pub const REQUEST_TIMEOUT: StatusCode = StatusCode(408);
     pub const PAYLOAD_TOO_LARGE: StatusCode = StatusCode(413);
+    pub const UNSUPPORTED_MEDIA_TYPE: StatusCode = StatusCode(415);
+    pub const RANGE_NOT_SATISFIABLE: StatusCode = StatusCode(416);
+    pub const EXPECTATION_FAILED: StatusCode = StatusCode(417);
+    pub const MISDIRECTED_REQUEST: StatusCode = StatusCode(421);
+    pub const REQUEST_HEADER_FIELDS_TOO_LARGE: StatusCode = StatusCode(431);
     pub const INTERNAL_SERVER_ERROR: StatusCode = StatusCode(500);
     pub const NOT_IMPLEMENTED: StatusCode = StatusCode(501);
     pub const BAD_GATEWAY: StatusCode = StatusCode(502);
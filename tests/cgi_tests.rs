@@ -635,3 +635,45 @@ sys.stdout.write(f'MD5 hash: {data_hash}\n')
     assert!(body1.contains("Data length: 43"));
     assert!(body2.contains("Data length: 43"));
 }
+
+#[test]
+fn test_cgi_streaming_echo_with_large_body_does_not_deadlock() {
+    // Echoes each block as soon as it is read, so output fills the stdout pipe long
+    // before the input is consumed
+    let script_content = r#"#!/usr/bin/env python3
+import sys
+
+sys.stdout.buffer.write(b'Content-Type: application/octet-stream\r\n\r\n')
+while True:
+    block = sys.stdin.buffer.read1(4096)
+    if not block:
+        break
+    sys.stdout.buffer.write(block)
+    sys.stdout.buffer.flush()
+"#;
+
+    let script_path = create_test_script("test_streaming_echo.py", script_content);
+
+    let body: Vec<u8> = (0..4 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let mut request = Request::new(Method::POST, "/test".to_string(), Version::Http11);
+    request.body = body.clone();
+    request
+        .headers
+        .add("Content-Length".to_string(), body.len().to_string());
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let path = script_path.clone();
+    std::thread::spawn(move || {
+        let executor = CgiExecutor::new(30);
+        let _ = tx.send(executor.execute(path, Some("python3"), &request, "localhost", 8080));
+    });
+    let response = rx.recv_timeout(std::time::Duration::from_secs(30));
+
+    cleanup_script(&script_path);
+
+    let response = response
+        .expect("CGI execution hung")
+        .expect("CGI execution should succeed");
+    assert_eq!(response.status.as_u16(), 200);
+    assert!(response.body == body, "echoed body should match the input");
+}
@@ -17,15 +17,9 @@ pub fn create_test_config(port: u16, body_size: usize) -> Config {
     routes.insert(
         "/".to_string(),
         RouteConfig {
-            methods: vec![],
-            filename: None,
-            directory: None,
-            redirect: None,
-            redirect_type: None,
             default_file: Some("index.html".to_string()),
-            cgi_extension: None,
             directory_listing: true,
-            upload_dir: None,
+            ..Default::default()
         },
     );
 
@@ -38,11 +32,9 @@ pub fn create_test_config(port: u16, body_size: usize) -> Config {
             ports: vec![port],
             root: test_root.to_string_lossy().to_string(),
             routes,
-            errors: std::collections::HashMap::new(),
-            cgi_handlers: std::collections::HashMap::new(),
-            admin_access: false,
+            ..Default::default()
         }],
-        admin: None,
+        ..Default::default()
     }
 }
 
@@ -533,3 +533,133 @@ client_max_body_size = 1048576
         "config without any servers must be rejected"
     );
 }
+
+#[test]
+fn test_invalid_banner_mode() {
+    let root = make_temp_root("banner");
+    let toml = format!(
+        r#"
+banner = "yaml"
+
+[[servers]]
+server_name = "test"
+server_address = "127.0.0.1"
+ports = [8080]
+root = "{r}"
+"#,
+        r = root.to_string_lossy()
+    );
+
+    let path = write_temp_toml("banner", &toml);
+    let result = ConfigLoader::load(path.to_str().unwrap());
+    assert!(result.is_err(), "an unknown banner mode must be rejected");
+}
+
+#[test]
+fn test_invalid_route_count_over_max_routes() {
+    let root = make_temp_root("max_routes");
+    let toml = |max_routes: usize| {
+        format!(
+            r#"
+max_routes = {m}
+
+[[servers]]
+server_name = "test"
+server_address = "127.0.0.1"
+ports = [8080]
+root = "{r}"
+
+[servers.routes."/"]
+methods = ["GET"]
+
+[servers.routes."/a"]
+methods = ["GET"]
+
+[servers.routes."/b"]
+methods = ["GET"]
+"#,
+            m = max_routes,
+            r = root.to_string_lossy()
+        )
+    };
+
+    let path = write_temp_toml("max_routes_ok", &toml(3));
+    assert!(ConfigLoader::load(path.to_str().unwrap()).is_ok());
+
+    let path = write_temp_toml("max_routes_over", &toml(2));
+    let result = ConfigLoader::load(path.to_str().unwrap());
+    assert!(
+        result.is_err(),
+        "a server with more routes than max_routes must be rejected"
+    );
+}
+
+#[test]
+fn test_missing_cgi_working_dir() {
+    let root = make_temp_root("cgi_workdir");
+    fs::create_dir_all(root.join("data")).unwrap();
+    let toml = |working_dir: &str| {
+        format!(
+            r#"
+[[servers]]
+server_name = "test"
+server_address = "127.0.0.1"
+ports = [8080]
+root = "{r}"
+
+[servers.routes."/cgi-bin"]
+methods = ["GET"]
+cgi_extension = "py"
+cgi_working_dir = "{w}"
+"#,
+            r = root.to_string_lossy(),
+            w = working_dir
+        )
+    };
+
+    let path = write_temp_toml("cgi_workdir_ok", &toml("./data"));
+    assert!(ConfigLoader::load(path.to_str().unwrap()).is_ok());
+
+    let path = write_temp_toml("cgi_workdir_missing", &toml("./missing"));
+    let result = ConfigLoader::load(path.to_str().unwrap());
+    assert!(
+        result.is_err(),
+        "a cgi_working_dir that doesn't exist must be rejected"
+    );
+}
+
+#[test]
+fn test_invalid_redirect_type() {
+    let root = make_temp_root("redirect_type");
+    let toml = |redirect_type: &str| {
+        format!(
+            r#"
+[[servers]]
+server_name = "test"
+server_address = "127.0.0.1"
+ports = [8080]
+root = "{r}"
+
+[servers.routes."/old"]
+methods = ["GET"]
+redirect = "/new"
+redirect_type = "{t}"
+"#,
+            r = root.to_string_lossy(),
+            t = redirect_type
+        )
+    };
+
+    for valid in ["permanent", "temporary", "301", "302"] {
+        let path = write_temp_toml("redirect_type_ok", &toml(valid));
+        assert!(
+            ConfigLoader::load(path.to_str().unwrap()).is_ok(),
+            "{}",
+            valid
+        );
+    }
+
+    let path = write_temp_toml("redirect_type_bad", &toml("307"));
+    let result = ConfigLoader::load(path.to_str().unwrap());
+    assert!(result.is_err(), "an unknown redirect_type must be rejected");
+}
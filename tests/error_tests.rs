@@ -34,8 +34,8 @@ fn test_invalid_http_method() {
     let request = "INVALIDMETHOD / HTTP/1.1\r\nHost: localhost\r\n\r\n";
     let response = send_request(port, request);
 
-    // Should return 400, 405, or simply close connection
-    assert!(response.is_empty() || response.contains("400") || response.contains("405"));
+    // Unknown methods are answered with 501 Not Implemented
+    assert!(response.starts_with("HTTP/1.1 501"));
 }
 
 #[test]
@@ -165,3 +165,46 @@ fn test_invalid_path() {
     // Should return 404, 403, or safely drop connection to protect against directory traversal
     assert!(response.is_empty() || response.contains("404") || response.contains("403"));
 }
+
+#[test]
+#[ignore]
+fn test_lowercase_method_gets_clean_error() {
+    let port = 9009;
+    let _server_thread = start_test_server(port, 1024);
+    thread::sleep(Duration::from_millis(500));
+
+    // Methods are case-sensitive, so `get` is not GET - but it must still be answered
+    let response = send_request(port, "get / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 501"), "{}", response);
+
+    // A method that isn't even a valid token is a bad request
+    let response = send_request(port, "G(T / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+}
+
+#[test]
+#[ignore]
+fn test_declared_oversized_body_rejected_before_upload() {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    let port = 9010;
+    let _server_thread = start_test_server(port, 1024);
+    thread::sleep(Duration::from_millis(500));
+
+    // Only the headers are sent; the server must answer without waiting for the body
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    stream
+        .write_all(
+            b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10737418240\r\n\r\n",
+        )
+        .unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let response = String::from_utf8_lossy(&response);
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+}
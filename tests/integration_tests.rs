@@ -7,6 +7,7 @@ use std::thread;
 use std::time::Duration;
 
 use localhost::application::config::models::RouteConfig;
+use localhost::application::server::server_manager::ServerManager;
 
 mod common;
 use common::{create_test_config, send_request, start_test_server_with_config};
@@ -37,23 +38,21 @@ fn test_single_server_single_port() {
 }
 
 #[test]
-#[ignore]
 fn test_get_request() {
-    let port = 8081;
-    let config = create_test_config(port, 1024 * 1024);
+    let mut config = create_test_config(8081, 1024 * 1024);
+    // Served in-process: let the OS pick the listener port so nothing collides
+    config.servers[0].ports = vec![0];
 
     let test_root = PathBuf::from(&config.servers[0].root);
     let test_file = test_root.join("test.txt");
     fs::write(&test_file, "Hello, World!").unwrap();
 
-    let _server_thread = start_test_server_with_config(config.clone());
-    thread::sleep(Duration::from_millis(500));
-
+    let mut server = ServerManager::new(config).unwrap();
     let request = "GET /test.txt HTTP/1.1\r\nHost: localhost\r\n\r\n";
-    let response = send_request(port, request);
+    let response = String::from_utf8(server.handle_request_bytes(request.as_bytes())).unwrap();
 
-    assert!(response.contains("200"));
-    assert!(response.contains("Hello, World!"));
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with("Hello, World!"));
 }
 
 #[test]
@@ -224,15 +223,8 @@ fn test_redirect() {
     config.servers[0].routes.insert(
         "/old".to_string(),
         RouteConfig {
-            methods: vec![],
-            filename: None,
-            directory: None,
             redirect: Some("/new".to_string()),
-            redirect_type: None,
-            default_file: None,
-            cgi_extension: None,
-            directory_listing: false,
-            upload_dir: None,
+            ..Default::default()
         },
     );
 
@@ -245,3 +237,540 @@ fn test_redirect() {
     assert!(response.contains("302") || response.contains("301"));
     assert!(response.contains("/new"));
 }
+
+#[test]
+#[ignore]
+fn test_unsupported_expect_returns_417() {
+    let port = 8090;
+    let config = create_test_config(port, 1024 * 1024);
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nExpect: 200-ok\r\n\r\n";
+    let response = send_request(port, request);
+
+    assert!(response.starts_with("HTTP/1.1 417"));
+}
+
+#[test]
+#[ignore]
+fn test_cgi_enabled_route_flag() {
+    let port = 8091;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.servers[0]
+        .cgi_handlers
+        .insert(".py".to_string(), "python3".to_string());
+    config.servers[0].routes.insert(
+        "/source".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            directory: Some("scripts".to_string()),
+            cgi_enabled: Some(false),
+            ..Default::default()
+        },
+    );
+    config.servers[0].routes.insert(
+        "/cgi".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            directory: Some("scripts".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    let scripts = test_root.join("scripts");
+    fs::create_dir_all(&scripts).unwrap();
+    let script = scripts.join("hello.py");
+    fs::write(
+        &script,
+        "#!/usr/bin/env python3\nprint('Content-Type: text/plain\\r\\n\\r\\n', end='')\nprint('executed', end='')\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /source/hello.py HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.contains("200"));
+    assert!(response.contains("Content-Type: text/plain"));
+    assert!(response.contains("print('executed'"));
+
+    let response = send_request(
+        port,
+        "GET /cgi/hello.py HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.contains("200"));
+    assert!(response.contains("executed"));
+    assert!(!response.contains("print("));
+}
+
+#[test]
+#[ignore]
+fn test_accept_language_selects_variant() {
+    let port = 8092;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.servers[0].routes.insert(
+        "/intl".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            directory: Some("intl".to_string()),
+            default_file: Some("index.html".to_string()),
+            default_language: Some("en".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    let intl = test_root.join("intl");
+    fs::create_dir_all(&intl).unwrap();
+    fs::write(intl.join("index.en.html"), "hello").unwrap();
+    fs::write(intl.join("index.fr.html"), "bonjour").unwrap();
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /intl/index.html HTTP/1.1\r\nHost: localhost\r\nAccept-Language: fr-CH, en;q=0.5\r\n\r\n",
+    );
+    assert!(response.contains("200"));
+    assert!(response.contains("Content-Language: fr"));
+    assert!(response.ends_with("bonjour"));
+
+    // Unsupported language falls back to the configured default
+    let response = send_request(
+        port,
+        "GET /intl HTTP/1.1\r\nHost: localhost\r\nAccept-Language: de\r\n\r\n",
+    );
+    assert!(response.contains("200"));
+    assert!(response.contains("Content-Language: en"));
+    assert!(response.ends_with("hello"));
+}
+
+#[test]
+#[ignore]
+fn test_h2c_upgrade_is_ignored() {
+    let port = 8093;
+    let config = create_test_config(port, 1024 * 1024);
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    fs::create_dir_all(&test_root).unwrap();
+    fs::write(test_root.join("h2c.html"), "plain http/1.1").unwrap();
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let request = "GET /h2c.html HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: AAMAAABkAARAAAAAAAIAAAAA\r\n\r\n";
+    let response = send_request(port, request);
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(!response.contains("101 Switching Protocols"));
+    assert!(!response.contains("Upgrade:"));
+    assert!(response.ends_with("plain http/1.1"));
+}
+
+#[test]
+#[ignore]
+fn test_cgi_default_content_type() {
+    let port = 8094;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.servers[0].cgi_default_content_type = "text/plain; charset=utf-8".to_string();
+    config.servers[0].routes.insert(
+        "/cgi".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            directory: Some("cgi-default".to_string()),
+            cgi_extension: Some("py".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    let scripts = test_root.join("cgi-default");
+    fs::create_dir_all(&scripts).unwrap();
+    let script = scripts.join("untyped.py");
+    fs::write(
+        &script,
+        "#!/usr/bin/env python3\nprint('X-Script: yes\\r\\n\\r\\n', end='')\nprint('no type', end='')\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /cgi/untyped.py HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("Content-Type: text/plain; charset=utf-8"));
+    assert!(response.contains("Content-Length: 7"));
+    assert!(response.ends_with("no type"));
+}
+
+#[test]
+#[ignore]
+fn test_default_file_takes_precedence_over_listing() {
+    let port = 8095;
+    let config = create_test_config(port, 1024 * 1024);
+    assert!(config.servers[0].routes["/"].directory_listing);
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    let docs = test_root.join("docs");
+    fs::create_dir_all(&docs).unwrap();
+    fs::write(docs.join("index.html"), "<html>Docs Index</html>").unwrap();
+    fs::write(docs.join("other.txt"), "other").unwrap();
+    let empty = test_root.join("empty");
+    fs::create_dir_all(&empty).unwrap();
+    fs::write(empty.join("listed.txt"), "listed").unwrap();
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    // index.html present: served instead of a listing
+    let response = send_request(port, "GET /docs/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("Docs Index"));
+    assert!(!response.contains("other.txt"));
+
+    // No index: fall back to the listing
+    let response = send_request(port, "GET /empty/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("listed.txt"));
+}
+
+#[test]
+#[ignore]
+fn test_multipart_byteranges_response() {
+    let port = 8096;
+    let config = create_test_config(port, 1024 * 1024);
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    let content: String = (0..1000)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+    fs::write(test_root.join("ranges.txt"), &content).unwrap();
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /ranges.txt HTTP/1.1\r\nHost: localhost\r\nRange: bytes=0-99,200-299\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 206"));
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let boundary = head
+        .lines()
+        .find_map(|l| l.strip_prefix("Content-Type: multipart/byteranges; boundary="))
+        .expect("multipart/byteranges content type")
+        .trim();
+
+    // Split the body into parts and check each carries the right slice and headers
+    let delimiter = format!("\r\n--{}", boundary);
+    let parts: Vec<&str> = body
+        .split(delimiter.as_str())
+        .skip(1)
+        .filter(|p| !p.starts_with("--"))
+        .collect();
+    assert_eq!(parts.len(), 2);
+
+    for (part, (start, end)) in parts.iter().zip([(0usize, 99usize), (200, 299)]) {
+        let (part_head, part_body) = part
+            .trim_start_matches("\r\n")
+            .split_once("\r\n\r\n")
+            .unwrap();
+        assert!(part_head.contains("Content-Type: text/plain"));
+        assert!(part_head.contains(&format!("Content-Range: bytes {}-{}/1000", start, end)));
+        assert_eq!(part_body, &content[start..=end]);
+    }
+    assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+}
+
+#[test]
+#[ignore]
+fn test_proxy_pass_round_trip() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    // Tiny upstream: answers one request, echoing the target it received
+    let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+    let upstream_port = upstream.local_addr().unwrap().port();
+    let upstream_thread = thread::spawn(move || {
+        let (mut stream, _) = upstream.accept().unwrap();
+        let mut received = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).unwrap();
+            received.extend_from_slice(&chunk[..n]);
+        }
+        let request = String::from_utf8_lossy(&received).to_string();
+        let target = request.split_whitespace().nth(1).unwrap().to_string();
+        let body = format!("upstream saw {}", target);
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-Upstream: yes\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        request
+    });
+
+    let port = 8097;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.worker_threads = 1;
+    config.servers[0].routes.insert(
+        "/api".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            proxy_pass: Some(format!("http://127.0.0.1:{}/v1", upstream_port)),
+            ..Default::default()
+        },
+    );
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /api/items?id=7 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("X-Upstream: yes"));
+    assert!(response.ends_with("upstream saw /v1/items?id=7"));
+
+    let forwarded = upstream_thread.join().unwrap();
+    assert!(forwarded.contains(&format!("Host: 127.0.0.1:{}", upstream_port)));
+    assert!(forwarded.contains("X-Forwarded-Host: localhost"));
+}
+
+#[test]
+#[ignore]
+fn test_cgi_internal_redirect_serves_file() {
+    let port = 8098;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.servers[0].cgi_internal_redirect_header = Some("X-Accel-Redirect".to_string());
+    config.servers[0].routes.insert(
+        "/cgi".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            directory: Some("cgi-accel".to_string()),
+            cgi_extension: Some("py".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let test_root = PathBuf::from(&config.servers[0].root);
+    let private = test_root.join("private");
+    fs::create_dir_all(&private).unwrap();
+    fs::write(private.join("report.txt"), "secret report").unwrap();
+    let scripts = test_root.join("cgi-accel");
+    fs::create_dir_all(&scripts).unwrap();
+    let script = scripts.join("download.py");
+    fs::write(
+        &script,
+        "#!/usr/bin/env python3\nprint('X-Accel-Redirect: /private/report.txt\\r\\n\\r\\n', end='')\nprint('script body', end='')\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /cgi/download.py HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with("secret report"));
+    assert!(!response.contains("X-Accel-Redirect"));
+    assert!(!response.contains("script body"));
+}
+
+#[test]
+#[ignore]
+fn test_acme_challenge_served_before_routing() {
+    let port = 8099;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.servers[0].acme_challenge_dir = Some("acme".to_string());
+    // Even a route that would redirect everything must not catch challenges
+    config.servers[0].routes.insert(
+        "/.well-known".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            redirect: Some("/elsewhere".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let acme_dir = PathBuf::from(&config.servers[0].root).join("acme");
+    fs::create_dir_all(&acme_dir).unwrap();
+    fs::write(acme_dir.join("Xy-9_token"), "Xy-9_token.key-auth").unwrap();
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let response = send_request(
+        port,
+        "GET /.well-known/acme-challenge/Xy-9_token HTTP/1.1\r\nHost: example.org\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("Content-Type: text/plain"));
+    assert!(response.ends_with("Xy-9_token.key-auth"));
+
+    let response = send_request(
+        port,
+        "GET /.well-known/acme-challenge/unknown HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+#[ignore]
+fn test_expect_continue_timeout_sends_408() {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Instant;
+
+    let port = 8100;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.expect_continue_timeout_secs = 1;
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream
+        .write_all(
+            b"POST /upload.txt HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\nContent-Length: 10\r\n\r\n",
+        )
+        .unwrap();
+
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"HTTP/1.1 100 Continue\r\n\r\n");
+
+    // Stall without sending the body
+    let started = Instant::now();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let response = String::from_utf8_lossy(&response);
+    assert!(response.starts_with("HTTP/1.1 408"), "{}", response);
+    assert!(started.elapsed() >= Duration::from_millis(900));
+}
+
+#[test]
+#[ignore]
+fn test_cgi_response_without_length_is_framed() {
+    let port = 8101;
+    let mut config = create_test_config(port, 1024 * 1024);
+    config.servers[0].routes.insert(
+        "/cgi".to_string(),
+        RouteConfig {
+            methods: vec!["GET".to_string()],
+            directory: Some("cgi-framing".to_string()),
+            cgi_extension: Some("py".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let scripts = PathBuf::from(&config.servers[0].root).join("cgi-framing");
+    fs::create_dir_all(&scripts).unwrap();
+    let script = scripts.join("nolength.py");
+    fs::write(
+        &script,
+        "#!/usr/bin/env python3\nprint('Content-Type: text/plain\\r\\n\\r\\n', end='')\nprint('twelve bytes', end='')\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    // The script sends no Content-Length; the buffered output is framed by the server
+    let response = send_request(
+        port,
+        "GET /cgi/nolength.py HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("Content-Length: 12\r\n"));
+    assert!(response.ends_with("twelve bytes"));
+}
+
+#[test]
+#[ignore]
+fn test_large_multipart_upload_streams_to_disk() {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    let port = 8102;
+    let mut config = create_test_config(port, 16 * 1024 * 1024);
+    config.servers[0].routes.insert(
+        "/upload".to_string(),
+        RouteConfig {
+            methods: vec!["POST".to_string()],
+            upload_dir: Some("streamed-uploads".to_string()),
+            ..Default::default()
+        },
+    );
+    let upload_dir = PathBuf::from(&config.servers[0].root).join("streamed-uploads");
+    let _ = fs::remove_dir_all(&upload_dir);
+
+    let _server_thread = start_test_server_with_config(config.clone());
+    thread::sleep(Duration::from_millis(500));
+
+    // 6MB of binary content, well past the streaming threshold
+    let file: Vec<u8> = (0..6 * 1024 * 1024u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+    let mut body = b"--bndry\r\nContent-Disposition: form-data; name=\"file\"; filename=\"big.zip\"\r\nContent-Type: application/zip\r\n\r\n".to_vec();
+    body.extend_from_slice(&file);
+    body.extend_from_slice(b"\r\n--bndry--\r\n");
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let head = format!(
+        "POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Type: multipart/form-data; boundary=bndry\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(&body).unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+
+    assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+    assert!(response.contains("\"filename\": \"big.zip\""));
+    assert_eq!(fs::read(upload_dir.join("big.zip")).unwrap(), file);
+    // The temporary part file was moved into place, not left behind
+    assert_eq!(fs::read_dir(&upload_dir).unwrap().count(), 1);
+}
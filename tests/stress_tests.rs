@@ -31,7 +31,7 @@ pub fn send_concurrent_requests(
     port: u16,
     num_requests: usize,
 ) -> Vec<Result<String, std::io::Error>> {
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
     let mut handles = Vec::new();
 
     for _ in 0..num_requests {
@@ -77,7 +77,7 @@ fn test_rapid_requests() {
     thread::sleep(Duration::from_millis(500));
 
     let num_requests = 1000;
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
 
     let start = Instant::now();
     let mut successful = 0;
@@ -128,7 +128,7 @@ fn test_connection_cleanup() {
     thread::sleep(Duration::from_millis(1000));
 
     // Server should still accept new connections
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
     let result = send_request_with_timeout(port, request);
 
     assert!(result.is_ok());
@@ -136,7 +136,7 @@ fn test_connection_cleanup() {
 
 /// Helper to measure server response time
 pub fn measure_response_time(port: u16, num_samples: usize) -> Duration {
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
     let mut total_time = Duration::new(0, 0);
 
     for _ in 0..num_samples {